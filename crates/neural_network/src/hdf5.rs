@@ -0,0 +1,590 @@
+//! Minimal reader for the subset of HDF5 written by Keras/h5py: version 0 superblocks, version 1
+//! object headers, symbol table or compact link groups and contiguous or compact datasets.
+
+use std::{fs::read, path::Path};
+
+use anyhow::{anyhow, bail, Context, Result};
+
+const SIGNATURE: &[u8] = b"\x89HDF\r\n\x1a\n";
+
+const MESSAGE_DATASPACE: u16 = 0x0001;
+const MESSAGE_LINK: u16 = 0x0006;
+const MESSAGE_DATATYPE: u16 = 0x0003;
+const MESSAGE_DATA_LAYOUT: u16 = 0x0008;
+const MESSAGE_ATTRIBUTE: u16 = 0x000c;
+const MESSAGE_CONTINUATION: u16 = 0x0010;
+const MESSAGE_SYMBOL_TABLE: u16 = 0x0011;
+
+pub struct File {
+    data: Vec<u8>,
+    size_of_offsets: usize,
+    size_of_lengths: usize,
+    root_object_header_address: usize,
+}
+
+impl File {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let data = read(path.as_ref())
+            .with_context(|| format!("Failed to read {}", path.as_ref().display()))?;
+        Self::from_bytes(data)
+    }
+
+    pub fn from_bytes(data: Vec<u8>) -> Result<Self> {
+        if !data.starts_with(SIGNATURE) {
+            bail!("Missing HDF5 signature");
+        }
+        let mut cursor = Cursor::new(&data, SIGNATURE.len(), 8, 8);
+        let superblock_version = cursor.u8()?;
+        if superblock_version != 0 {
+            bail!("Unsupported superblock version {superblock_version}");
+        }
+        cursor.skip(4)?; // free-space, root group and shared header message versions, reserved
+        let size_of_offsets = cursor.u8()? as usize;
+        let size_of_lengths = cursor.u8()? as usize;
+        if ![2, 4, 8].contains(&size_of_offsets) || ![2, 4, 8].contains(&size_of_lengths) {
+            bail!("Unsupported offset size {size_of_offsets} or length size {size_of_lengths}");
+        }
+        let mut cursor = Cursor::new(&data, cursor.position, size_of_offsets, size_of_lengths);
+        cursor.skip(1 + 2 + 2 + 4)?; // reserved, group K values, file consistency flags
+        let base_address = cursor.offset()?;
+        if base_address != 0 {
+            bail!("Unsupported base address {base_address}");
+        }
+        cursor.skip(3 * size_of_offsets)?; // free-space, end of file and driver info addresses
+        cursor.skip(size_of_offsets)?; // link name offset of the root symbol table entry
+        let root_object_header_address = cursor.offset()?;
+
+        Ok(Self {
+            data,
+            size_of_offsets,
+            size_of_lengths,
+            root_object_header_address,
+        })
+    }
+
+    pub fn root(&self) -> Result<Object<'_>> {
+        Object::parse(self, self.root_object_header_address)
+    }
+
+    /// Resolves a slash separated path starting at the root group
+    pub fn object(&self, path: &str) -> Result<Object<'_>> {
+        path.split('/')
+            .filter(|name| !name.is_empty())
+            .try_fold(self.root()?, |object, name| object.child(name))
+            .with_context(|| format!("Failed to resolve {path}"))
+    }
+
+    fn cursor(&self, position: usize) -> Cursor<'_> {
+        Cursor::new(
+            &self.data,
+            position,
+            self.size_of_offsets,
+            self.size_of_lengths,
+        )
+    }
+}
+
+struct Message<'file> {
+    kind: u16,
+    data: &'file [u8],
+}
+
+pub struct Object<'file> {
+    file: &'file File,
+    messages: Vec<Message<'file>>,
+}
+
+impl<'file> Object<'file> {
+    fn parse(file: &'file File, address: usize) -> Result<Self> {
+        let mut cursor = file.cursor(address);
+        let version = cursor.u8()?;
+        if version != 1 {
+            bail!("Unsupported object header version {version} at {address}");
+        }
+        cursor.skip(1)?;
+        let number_of_messages = cursor.u16()? as usize;
+        cursor.skip(4)?; // object reference count
+        let header_size = cursor.u32()? as usize;
+        cursor.skip(4)?; // alignment of the first message to 8 bytes
+
+        let mut blocks = vec![(cursor.position, header_size)];
+        let mut messages = vec![];
+        while let Some((start, size)) = blocks.pop() {
+            let mut cursor = file.cursor(start);
+            while cursor.position + 8 <= start + size && messages.len() < number_of_messages {
+                let kind = cursor.u16()?;
+                let message_size = cursor.u16()? as usize;
+                cursor.skip(4)?; // flags and reserved
+                let data = cursor.bytes(message_size)?;
+                if kind == MESSAGE_CONTINUATION {
+                    let mut continuation = file.cursor(0);
+                    continuation.data = data;
+                    let block_address = continuation.offset()?;
+                    let block_size = continuation.length()?;
+                    blocks.push((block_address, block_size));
+                }
+                messages.push(Message { kind, data });
+            }
+        }
+
+        Ok(Self { file, messages })
+    }
+
+    fn message(&self, kind: u16) -> Option<&Message<'file>> {
+        self.messages.iter().find(|message| message.kind == kind)
+    }
+
+    fn message_cursor(&self, message: &Message<'file>) -> Cursor<'file> {
+        let mut cursor = self.file.cursor(0);
+        cursor.data = message.data;
+        cursor
+    }
+
+    pub fn child(&self, name: &str) -> Result<Object<'file>> {
+        let address = self
+            .children()?
+            .into_iter()
+            .find_map(|(child_name, address)| (child_name == name).then_some(address))
+            .ok_or_else(|| anyhow!("No child named {name}"))?;
+        Object::parse(self.file, address)
+    }
+
+    /// Resolves a slash separated path relative to this group
+    pub fn descendant(&self, path: &str) -> Result<Object<'file>> {
+        let mut names = path.split('/').filter(|name| !name.is_empty());
+        let first = names
+            .next()
+            .ok_or_else(|| anyhow!("Empty path"))
+            .and_then(|name| self.child(name))?;
+        names
+            .try_fold(first, |object, name| object.child(name))
+            .with_context(|| format!("Failed to resolve {path}"))
+    }
+
+    /// Returns names and object header addresses of all members of this group
+    fn children(&self) -> Result<Vec<(String, usize)>> {
+        if let Some(symbol_table) = self.message(MESSAGE_SYMBOL_TABLE) {
+            let mut cursor = self.message_cursor(symbol_table);
+            let b_tree_address = cursor.offset()?;
+            let local_heap_address = cursor.offset()?;
+            let heap_data_address = self.local_heap_data_address(local_heap_address)?;
+            let mut children = vec![];
+            self.collect_b_tree_children(b_tree_address, heap_data_address, &mut children)?;
+            return Ok(children);
+        }
+        self.messages
+            .iter()
+            .filter(|message| message.kind == MESSAGE_LINK)
+            .map(|message| self.parse_link(message))
+            .collect()
+    }
+
+    fn local_heap_data_address(&self, address: usize) -> Result<usize> {
+        let mut cursor = self.file.cursor(address);
+        if cursor.bytes(4)? != b"HEAP" {
+            bail!("Missing local heap signature at {address}");
+        }
+        cursor.skip(4)?; // version and reserved
+        cursor.length()?; // data segment size
+        cursor.length()?; // offset to head of free-list
+        cursor.offset()
+    }
+
+    fn collect_b_tree_children(
+        &self,
+        address: usize,
+        heap_data_address: usize,
+        children: &mut Vec<(String, usize)>,
+    ) -> Result<()> {
+        let mut cursor = self.file.cursor(address);
+        if cursor.bytes(4)? != b"TREE" {
+            bail!("Missing B-tree signature at {address}");
+        }
+        let node_type = cursor.u8()?;
+        if node_type != 0 {
+            bail!("Expected group B-tree node at {address}, got type {node_type}");
+        }
+        let level = cursor.u8()?;
+        let entries_used = cursor.u16()? as usize;
+        cursor.offset()?; // left sibling
+        cursor.offset()?; // right sibling
+        for _ in 0..entries_used {
+            cursor.length()?; // key
+            let child_address = cursor.offset()?;
+            if level > 0 {
+                self.collect_b_tree_children(child_address, heap_data_address, children)?;
+            } else {
+                self.collect_symbol_table_node(child_address, heap_data_address, children)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn collect_symbol_table_node(
+        &self,
+        address: usize,
+        heap_data_address: usize,
+        children: &mut Vec<(String, usize)>,
+    ) -> Result<()> {
+        let mut cursor = self.file.cursor(address);
+        if cursor.bytes(4)? != b"SNOD" {
+            bail!("Missing symbol table node signature at {address}");
+        }
+        cursor.skip(2)?; // version and reserved
+        let number_of_symbols = cursor.u16()? as usize;
+        for _ in 0..number_of_symbols {
+            let name_offset = cursor.offset()?;
+            let object_header_address = cursor.offset()?;
+            cursor.skip(4 + 4 + 16)?; // cache type, reserved and scratch-pad
+            let name = self
+                .file
+                .cursor(heap_data_address + name_offset)
+                .null_terminated_string()?;
+            children.push((name, object_header_address));
+        }
+        Ok(())
+    }
+
+    fn parse_link(&self, message: &Message<'file>) -> Result<(String, usize)> {
+        let mut cursor = self.message_cursor(message);
+        let version = cursor.u8()?;
+        if version != 1 {
+            bail!("Unsupported link message version {version}");
+        }
+        let flags = cursor.u8()?;
+        let link_type = if flags & 0x08 != 0 { cursor.u8()? } else { 0 };
+        if link_type != 0 {
+            bail!("Unsupported link type {link_type}");
+        }
+        if flags & 0x04 != 0 {
+            cursor.skip(8)?; // creation order
+        }
+        if flags & 0x10 != 0 {
+            cursor.skip(1)?; // character set
+        }
+        let name_length = match flags & 0x03 {
+            0 => cursor.u8()? as usize,
+            1 => cursor.u16()? as usize,
+            2 => cursor.u32()? as usize,
+            _ => cursor.u64()? as usize,
+        };
+        let name = String::from_utf8_lossy(cursor.bytes(name_length)?).into_owned();
+        Ok((name, cursor.offset()?))
+    }
+
+    pub fn attribute(&self, name: &str) -> Result<Attribute<'file>> {
+        self.messages
+            .iter()
+            .filter(|message| message.kind == MESSAGE_ATTRIBUTE)
+            .map(|message| self.parse_attribute(message))
+            .find(|attribute| {
+                attribute
+                    .as_ref()
+                    .map_or(true, |attribute| attribute.name == name)
+            })
+            .ok_or_else(|| anyhow!("No attribute named {name}"))?
+    }
+
+    fn parse_attribute(&self, message: &Message<'file>) -> Result<Attribute<'file>> {
+        let mut cursor = self.message_cursor(message);
+        let version = cursor.u8()?;
+        if !(1..=3).contains(&version) {
+            bail!("Unsupported attribute message version {version}");
+        }
+        cursor.skip(1)?; // reserved or flags
+        let name_size = cursor.u16()? as usize;
+        let datatype_size = cursor.u16()? as usize;
+        let dataspace_size = cursor.u16()? as usize;
+        if version == 3 {
+            cursor.skip(1)?; // name character set encoding
+        }
+        let padded = |size: usize| {
+            if version == 1 {
+                (size + 7) & !7
+            } else {
+                size
+            }
+        };
+        let name_bytes = cursor.bytes(padded(name_size))?;
+        let name = String::from_utf8_lossy(&name_bytes[..name_size.saturating_sub(1)]).into_owned();
+        let datatype = Datatype::parse(cursor.bytes(padded(datatype_size))?)?;
+        let mut dataspace_cursor = self.file.cursor(0);
+        dataspace_cursor.data = cursor.bytes(padded(dataspace_size))?;
+        let shape = parse_dataspace(&mut dataspace_cursor)?;
+        let data = &cursor.data[cursor.position..];
+        Ok(Attribute {
+            file: self.file,
+            name,
+            datatype,
+            shape,
+            data,
+        })
+    }
+
+    pub fn shape(&self) -> Result<Vec<usize>> {
+        let dataspace = self
+            .message(MESSAGE_DATASPACE)
+            .ok_or_else(|| anyhow!("Object is no dataset, missing dataspace"))?;
+        parse_dataspace(&mut self.message_cursor(dataspace))
+    }
+
+    /// Reads a dataset of little endian 32 bit floats
+    pub fn read_f32(&self) -> Result<Vec<f32>> {
+        let datatype = self
+            .message(MESSAGE_DATATYPE)
+            .ok_or_else(|| anyhow!("Object is no dataset, missing datatype"))?;
+        match Datatype::parse(datatype.data)? {
+            Datatype::Float {
+                size: 4,
+                little_endian: true,
+            } => {}
+            datatype => bail!("Unsupported dataset datatype {datatype:?}"),
+        }
+        let number_of_elements = self.shape()?.iter().product::<usize>();
+        let raw_data = self.raw_data(number_of_elements * 4)?;
+        Ok(raw_data
+            .chunks_exact(4)
+            .map(|bytes| f32::from_le_bytes(bytes.try_into().unwrap()))
+            .collect())
+    }
+
+    fn raw_data(&self, size: usize) -> Result<&'file [u8]> {
+        let layout = self
+            .message(MESSAGE_DATA_LAYOUT)
+            .ok_or_else(|| anyhow!("Object is no dataset, missing data layout"))?;
+        let mut cursor = self.message_cursor(layout);
+        let version = cursor.u8()?;
+        let (layout_class, address) = match version {
+            1 | 2 => {
+                let dimensionality = cursor.u8()? as usize;
+                let layout_class = cursor.u8()?;
+                cursor.skip(5)?;
+                let address = if layout_class == 0 {
+                    cursor.skip(dimensionality * 4 + 4)?; // dimensions and compact data size
+                    None
+                } else {
+                    Some(cursor.offset()?)
+                };
+                (layout_class, address)
+            }
+            3 => {
+                let layout_class = cursor.u8()?;
+                let address = match layout_class {
+                    0 => {
+                        cursor.skip(2)?; // compact data size
+                        None
+                    }
+                    _ => Some(cursor.offset()?),
+                };
+                (layout_class, address)
+            }
+            _ => bail!("Unsupported data layout version {version}"),
+        };
+        match (layout_class, address) {
+            (0, _) => cursor.bytes(size),
+            (1, Some(address)) => self.file.cursor(address).bytes(size),
+            _ => bail!("Unsupported data layout class {layout_class}"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Datatype {
+    Float { size: usize, little_endian: bool },
+    FixedString { size: usize },
+    VariableLengthString,
+    Other,
+}
+
+impl Datatype {
+    fn parse(data: &[u8]) -> Result<Self> {
+        if data.len() < 8 {
+            bail!("Datatype message too short");
+        }
+        let class = data[0] & 0x0f;
+        let class_bits = data[1];
+        let size = u32::from_le_bytes(data[4..8].try_into().unwrap()) as usize;
+        Ok(match class {
+            1 => Datatype::Float {
+                size,
+                little_endian: class_bits & 0x01 == 0,
+            },
+            3 => Datatype::FixedString { size },
+            9 if class_bits & 0x0f == 1 => Datatype::VariableLengthString,
+            _ => Datatype::Other,
+        })
+    }
+}
+
+fn parse_dataspace(cursor: &mut Cursor<'_>) -> Result<Vec<usize>> {
+    let version = cursor.u8()?;
+    let dimensionality = cursor.u8()? as usize;
+    cursor.skip(1)?; // flags
+    match version {
+        1 => cursor.skip(5)?,
+        2 => cursor.skip(1)?,
+        _ => bail!("Unsupported dataspace version {version}"),
+    }
+    (0..dimensionality)
+        .map(|_| cursor.length())
+        .collect::<Result<_>>()
+}
+
+pub struct Attribute<'file> {
+    file: &'file File,
+    name: String,
+    datatype: Datatype,
+    shape: Vec<usize>,
+    data: &'file [u8],
+}
+
+impl<'file> Attribute<'file> {
+    /// Reads all elements of a fixed or variable length string attribute
+    pub fn strings(&self) -> Result<Vec<String>> {
+        let number_of_elements = self.shape.iter().product::<usize>();
+        if number_of_elements == 0 {
+            // h5py stores empty lists as empty float arrays
+            return Ok(vec![]);
+        }
+        match self.datatype {
+            Datatype::FixedString { size } => self
+                .data
+                .chunks_exact(size)
+                .take(number_of_elements)
+                .map(|bytes| {
+                    let end = bytes.iter().position(|&byte| byte == 0).unwrap_or(size);
+                    Ok(String::from_utf8_lossy(&bytes[..end]).into_owned())
+                })
+                .collect(),
+            Datatype::VariableLengthString => {
+                let mut cursor = self.file.cursor(0);
+                cursor.data = self.data;
+                (0..number_of_elements)
+                    .map(|_| {
+                        let length = cursor.u32()? as usize;
+                        let collection_address = cursor.offset()?;
+                        let object_index = cursor.u32()? as usize;
+                        let bytes =
+                            self.global_heap_object(collection_address, object_index, length)?;
+                        Ok(String::from_utf8_lossy(bytes).into_owned())
+                    })
+                    .collect()
+            }
+            datatype => bail!("Attribute {} is no string but {datatype:?}", self.name),
+        }
+    }
+
+    pub fn string(&self) -> Result<String> {
+        self.strings()?
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("Attribute {} is empty", self.name))
+    }
+
+    fn global_heap_object(
+        &self,
+        collection_address: usize,
+        object_index: usize,
+        length: usize,
+    ) -> Result<&'file [u8]> {
+        let mut cursor = self.file.cursor(collection_address);
+        if cursor.bytes(4)? != b"GCOL" {
+            bail!("Missing global heap signature at {collection_address}");
+        }
+        cursor.skip(4)?; // version and reserved
+        let collection_end = collection_address + cursor.length()?;
+        while cursor.position < collection_end {
+            let index = cursor.u16()? as usize;
+            cursor.skip(2 + 4)?; // reference count and reserved
+            let size = cursor.length()?;
+            if index == 0 {
+                break;
+            }
+            let object = cursor.bytes(size)?;
+            if index == object_index {
+                return Ok(&object[..length.min(size)]);
+            }
+            cursor.skip((8 - size % 8) % 8)?;
+        }
+        bail!("Global heap object {object_index} not found in {collection_address}")
+    }
+}
+
+struct Cursor<'data> {
+    data: &'data [u8],
+    position: usize,
+    size_of_offsets: usize,
+    size_of_lengths: usize,
+}
+
+impl<'data> Cursor<'data> {
+    fn new(
+        data: &'data [u8],
+        position: usize,
+        size_of_offsets: usize,
+        size_of_lengths: usize,
+    ) -> Self {
+        Self {
+            data,
+            position,
+            size_of_offsets,
+            size_of_lengths,
+        }
+    }
+
+    fn bytes(&mut self, length: usize) -> Result<&'data [u8]> {
+        let end = self.position + length;
+        let bytes = self
+            .data
+            .get(self.position..end)
+            .ok_or_else(|| anyhow!("Unexpected end of data at {}", self.position))?;
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn skip(&mut self, length: usize) -> Result<()> {
+        self.bytes(length).map(|_| ())
+    }
+
+    fn unsigned(&mut self, size: usize) -> Result<u64> {
+        Ok(self
+            .bytes(size)?
+            .iter()
+            .rev()
+            .fold(0, |value, &byte| value << 8 | byte as u64))
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        Ok(self.unsigned(2)? as u16)
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(self.unsigned(4)? as u32)
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        self.unsigned(8)
+    }
+
+    fn offset(&mut self) -> Result<usize> {
+        Ok(self.unsigned(self.size_of_offsets)? as usize)
+    }
+
+    fn length(&mut self) -> Result<usize> {
+        Ok(self.unsigned(self.size_of_lengths)? as usize)
+    }
+
+    fn null_terminated_string(&mut self) -> Result<String> {
+        let remaining = &self.data[self.position.min(self.data.len())..];
+        let end = remaining
+            .iter()
+            .position(|&byte| byte == 0)
+            .ok_or_else(|| anyhow!("Unterminated string at {}", self.position))?;
+        self.position += end + 1;
+        Ok(String::from_utf8_lossy(&remaining[..end]).into_owned())
+    }
+}
@@ -0,0 +1,584 @@
+use anyhow::{bail, Result};
+
+/// Activations in height × width × channels layout, stored row-major like Keras' channels_last
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Tensor {
+    pub height: usize,
+    pub width: usize,
+    pub channels: usize,
+    pub data: Vec<f32>,
+}
+
+impl Tensor {
+    pub fn zeros(height: usize, width: usize, channels: usize) -> Self {
+        Self {
+            height,
+            width,
+            channels,
+            data: vec![0.0; height * width * channels],
+        }
+    }
+
+    fn index(&self, y: usize, x: usize, channel: usize) -> usize {
+        (y * self.width + x) * self.channels + channel
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Padding {
+    Same,
+    Valid,
+}
+
+impl Padding {
+    /// Returns output size and padding before the first element along one axis
+    fn output_size_and_offset(
+        self,
+        input_size: usize,
+        window_size: usize,
+        stride: usize,
+    ) -> (usize, usize) {
+        match self {
+            Padding::Same => {
+                let output_size = (input_size as f32 / stride as f32).ceil() as usize;
+                let total_padding =
+                    ((output_size - 1) * stride + window_size).saturating_sub(input_size);
+                (output_size, total_padding / 2)
+            }
+            Padding::Valid => ((input_size.saturating_sub(window_size)) / stride + 1, 0),
+        }
+    }
+
+    /// Returns output height and width for a two dimensional window
+    fn output_size_2d(
+        self,
+        input: &Tensor,
+        window_size: (usize, usize),
+        strides: (usize, usize),
+    ) -> (usize, usize) {
+        (
+            self.output_size_and_offset(input.height, window_size.0, strides.0)
+                .0,
+            self.output_size_and_offset(input.width, window_size.1, strides.1)
+                .0,
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Activation {
+    Linear,
+    Relu,
+    Elu,
+    Selu,
+    Tanh,
+    Sigmoid,
+    Softmax,
+}
+
+impl Activation {
+    pub fn from_name(name: &str) -> Result<Self> {
+        Ok(match name {
+            "linear" => Activation::Linear,
+            "relu" => Activation::Relu,
+            "elu" => Activation::Elu,
+            "selu" => Activation::Selu,
+            "tanh" => Activation::Tanh,
+            "sigmoid" => Activation::Sigmoid,
+            "softmax" => Activation::Softmax,
+            _ => bail!("Unsupported activation {name}"),
+        })
+    }
+
+    pub fn apply(self, values: &mut [f32]) {
+        const SELU_ALPHA: f32 = 1.673_263_2;
+        const SELU_SCALE: f32 = 1.050_701;
+        match self {
+            Activation::Linear => {}
+            Activation::Relu => values.iter_mut().for_each(|value| *value = value.max(0.0)),
+            Activation::Elu => values.iter_mut().for_each(|value| {
+                if *value < 0.0 {
+                    *value = value.exp_m1();
+                }
+            }),
+            Activation::Selu => values.iter_mut().for_each(|value| {
+                *value = if *value < 0.0 {
+                    SELU_SCALE * SELU_ALPHA * value.exp_m1()
+                } else {
+                    SELU_SCALE * *value
+                }
+            }),
+            Activation::Tanh => values.iter_mut().for_each(|value| *value = value.tanh()),
+            Activation::Sigmoid => values
+                .iter_mut()
+                .for_each(|value| *value = 1.0 / (1.0 + (-*value).exp())),
+            Activation::Softmax => {
+                let maximum = values.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+                values
+                    .iter_mut()
+                    .for_each(|value| *value = (*value - maximum).exp());
+                let sum: f32 = values.iter().sum();
+                values.iter_mut().for_each(|value| *value /= sum);
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PoolingKind {
+    Maximum,
+    Average,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Layer {
+    /// Kernel layout is kernel height × kernel width × input channels × filters
+    Convolution {
+        kernel: Vec<f32>,
+        kernel_size: (usize, usize),
+        strides: (usize, usize),
+        padding: Padding,
+        filters: usize,
+        bias: Option<Vec<f32>>,
+        activation: Activation,
+    },
+    /// Depthwise kernel layout is kernel height × kernel width × input channels × depth multiplier
+    DepthwiseConvolution {
+        kernel: Vec<f32>,
+        kernel_size: (usize, usize),
+        strides: (usize, usize),
+        padding: Padding,
+        depth_multiplier: usize,
+    },
+    /// Kernel layout is inputs × units
+    Dense {
+        kernel: Vec<f32>,
+        units: usize,
+        bias: Option<Vec<f32>>,
+        activation: Activation,
+    },
+    /// Batch normalization folded into a per channel scale and offset
+    ChannelwiseAffine {
+        scale: Vec<f32>,
+        offset: Vec<f32>,
+    },
+    Activation(Activation),
+    Pooling {
+        kind: PoolingKind,
+        pool_size: (usize, usize),
+        strides: (usize, usize),
+        padding: Padding,
+    },
+    Flatten,
+}
+
+impl Layer {
+    pub fn batch_normalization(
+        gamma: Option<&[f32]>,
+        beta: Option<&[f32]>,
+        moving_mean: &[f32],
+        moving_variance: &[f32],
+        epsilon: f32,
+    ) -> Self {
+        let scale: Vec<f32> = moving_variance
+            .iter()
+            .enumerate()
+            .map(|(channel, variance)| {
+                gamma.map_or(1.0, |gamma| gamma[channel]) / (variance + epsilon).sqrt()
+            })
+            .collect();
+        let offset = moving_mean
+            .iter()
+            .zip(scale.iter())
+            .enumerate()
+            .map(|(channel, (mean, scale))| beta.map_or(0.0, |beta| beta[channel]) - mean * scale)
+            .collect();
+        Layer::ChannelwiseAffine { scale, offset }
+    }
+
+    pub fn apply(&self, input: &Tensor) -> Result<Tensor> {
+        match self {
+            Layer::Convolution {
+                kernel,
+                kernel_size,
+                strides,
+                padding,
+                filters,
+                bias,
+                activation,
+            } => {
+                let expected_kernel_size = kernel_size.0 * kernel_size.1 * input.channels * filters;
+                if kernel.len() != expected_kernel_size {
+                    bail!(
+                        "Convolution kernel has {} weights, expected {expected_kernel_size}",
+                        kernel.len()
+                    );
+                }
+                let mut output =
+                    convolve(input, kernel, *kernel_size, *strides, *padding, *filters);
+                add_bias(&mut output.data, bias.as_deref());
+                activation.apply(&mut output.data);
+                Ok(output)
+            }
+            Layer::DepthwiseConvolution {
+                kernel,
+                kernel_size,
+                strides,
+                padding,
+                depth_multiplier,
+            } => {
+                let expected_kernel_size =
+                    kernel_size.0 * kernel_size.1 * input.channels * depth_multiplier;
+                if kernel.len() != expected_kernel_size {
+                    bail!(
+                        "Depthwise kernel has {} weights, expected {expected_kernel_size}",
+                        kernel.len()
+                    );
+                }
+                Ok(convolve_depthwise(
+                    input,
+                    kernel,
+                    *kernel_size,
+                    *strides,
+                    *padding,
+                    *depth_multiplier,
+                ))
+            }
+            Layer::Dense {
+                kernel,
+                units,
+                bias,
+                activation,
+            } => {
+                if kernel.len() != input.data.len() * units {
+                    bail!(
+                        "Dense kernel has {} weights, expected {}",
+                        kernel.len(),
+                        input.data.len() * units
+                    );
+                }
+                let mut output = Tensor::zeros(1, 1, *units);
+                for (input_value, kernel_row) in input.data.iter().zip(kernel.chunks_exact(*units))
+                {
+                    for (output_value, weight) in output.data.iter_mut().zip(kernel_row) {
+                        *output_value += input_value * weight;
+                    }
+                }
+                add_bias(&mut output.data, bias.as_deref());
+                activation.apply(&mut output.data);
+                Ok(output)
+            }
+            Layer::ChannelwiseAffine { scale, offset } => {
+                if scale.len() != input.channels {
+                    bail!(
+                        "Normalization has {} channels, input has {}",
+                        scale.len(),
+                        input.channels
+                    );
+                }
+                let mut output = input.clone();
+                for values in output.data.chunks_exact_mut(input.channels) {
+                    for ((value, scale), offset) in values.iter_mut().zip(scale).zip(offset) {
+                        *value = *value * scale + offset;
+                    }
+                }
+                Ok(output)
+            }
+            Layer::Activation(activation) => {
+                let mut output = input.clone();
+                activation.apply(&mut output.data);
+                Ok(output)
+            }
+            Layer::Pooling {
+                kind,
+                pool_size,
+                strides,
+                padding,
+            } => Ok(pool(input, *kind, *pool_size, *strides, *padding)),
+            Layer::Flatten => Ok(Tensor {
+                height: 1,
+                width: 1,
+                channels: input.data.len(),
+                data: input.data.clone(),
+            }),
+        }
+    }
+}
+
+fn add_bias(values: &mut [f32], bias: Option<&[f32]>) {
+    if let Some(bias) = bias {
+        for channel_values in values.chunks_exact_mut(bias.len()) {
+            for (value, bias) in channel_values.iter_mut().zip(bias) {
+                *value += bias;
+            }
+        }
+    }
+}
+
+/// Calls `visit` for every combination of output position and kernel position that overlaps the
+/// input, returning the output height and width
+fn for_each_window_position(
+    input: &Tensor,
+    window_size: (usize, usize),
+    strides: (usize, usize),
+    padding: Padding,
+    mut visit: impl FnMut((usize, usize), (usize, usize), (usize, usize)),
+) -> (usize, usize) {
+    let (output_height, offset_y) =
+        padding.output_size_and_offset(input.height, window_size.0, strides.0);
+    let (output_width, offset_x) =
+        padding.output_size_and_offset(input.width, window_size.1, strides.1);
+    for output_y in 0..output_height {
+        for output_x in 0..output_width {
+            for window_y in 0..window_size.0 {
+                let input_y = match (output_y * strides.0 + window_y).checked_sub(offset_y) {
+                    Some(input_y) if input_y < input.height => input_y,
+                    _ => continue,
+                };
+                for window_x in 0..window_size.1 {
+                    let input_x = match (output_x * strides.1 + window_x).checked_sub(offset_x) {
+                        Some(input_x) if input_x < input.width => input_x,
+                        _ => continue,
+                    };
+                    visit(
+                        (output_y, output_x),
+                        (window_y, window_x),
+                        (input_y, input_x),
+                    );
+                }
+            }
+        }
+    }
+    (output_height, output_width)
+}
+
+fn convolve(
+    input: &Tensor,
+    kernel: &[f32],
+    kernel_size: (usize, usize),
+    strides: (usize, usize),
+    padding: Padding,
+    filters: usize,
+) -> Tensor {
+    let (output_height, output_width) = padding.output_size_2d(input, kernel_size, strides);
+    let mut output = Tensor::zeros(output_height, output_width, filters);
+    for_each_window_position(
+        input,
+        kernel_size,
+        strides,
+        padding,
+        |(output_y, output_x), (kernel_y, kernel_x), (input_y, input_x)| {
+            let output_start = output.index(output_y, output_x, 0);
+            let input_start = input.index(input_y, input_x, 0);
+            let kernel_start = (kernel_y * kernel_size.1 + kernel_x) * input.channels * filters;
+            for input_channel in 0..input.channels {
+                let input_value = input.data[input_start + input_channel];
+                let weights = &kernel[kernel_start + input_channel * filters..][..filters];
+                for (output_value, weight) in output.data[output_start..][..filters]
+                    .iter_mut()
+                    .zip(weights)
+                {
+                    *output_value += input_value * weight;
+                }
+            }
+        },
+    );
+    output
+}
+
+fn convolve_depthwise(
+    input: &Tensor,
+    kernel: &[f32],
+    kernel_size: (usize, usize),
+    strides: (usize, usize),
+    padding: Padding,
+    depth_multiplier: usize,
+) -> Tensor {
+    let (output_height, output_width) = padding.output_size_2d(input, kernel_size, strides);
+    let output_channels = input.channels * depth_multiplier;
+    let mut output = Tensor::zeros(output_height, output_width, output_channels);
+    for_each_window_position(
+        input,
+        kernel_size,
+        strides,
+        padding,
+        |(output_y, output_x), (kernel_y, kernel_x), (input_y, input_x)| {
+            let output_start = output.index(output_y, output_x, 0);
+            let input_start = input.index(input_y, input_x, 0);
+            let kernel_start = (kernel_y * kernel_size.1 + kernel_x) * output_channels;
+            for output_channel in 0..output_channels {
+                output.data[output_start + output_channel] += input.data
+                    [input_start + output_channel / depth_multiplier]
+                    * kernel[kernel_start + output_channel];
+            }
+        },
+    );
+    output
+}
+
+fn pool(
+    input: &Tensor,
+    kind: PoolingKind,
+    pool_size: (usize, usize),
+    strides: (usize, usize),
+    padding: Padding,
+) -> Tensor {
+    let (output_height, output_width) = padding.output_size_2d(input, pool_size, strides);
+    let initial_value = match kind {
+        PoolingKind::Maximum => f32::NEG_INFINITY,
+        PoolingKind::Average => 0.0,
+    };
+    let mut output = Tensor {
+        height: output_height,
+        width: output_width,
+        channels: input.channels,
+        data: vec![initial_value; output_height * output_width * input.channels],
+    };
+    let mut window_sizes = vec![0; output_height * output_width];
+    for_each_window_position(
+        input,
+        pool_size,
+        strides,
+        padding,
+        |(output_y, output_x), _, (input_y, input_x)| {
+            window_sizes[output_y * output_width + output_x] += 1;
+            let output_start = output.index(output_y, output_x, 0);
+            let input_start = input.index(input_y, input_x, 0);
+            for channel in 0..input.channels {
+                let output_value = &mut output.data[output_start + channel];
+                let input_value = input.data[input_start + channel];
+                match kind {
+                    PoolingKind::Maximum => *output_value = output_value.max(input_value),
+                    PoolingKind::Average => *output_value += input_value,
+                }
+            }
+        },
+    );
+    if kind == PoolingKind::Average {
+        for (values, window_size) in output
+            .data
+            .chunks_exact_mut(input.channels)
+            .zip(window_sizes)
+        {
+            values
+                .iter_mut()
+                .for_each(|value| *value /= window_size as f32);
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+
+    use super::*;
+
+    fn ramp(height: usize, width: usize, channels: usize) -> Tensor {
+        Tensor {
+            height,
+            width,
+            channels,
+            data: (0..height * width * channels)
+                .map(|value| value as f32)
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn same_padding_matches_keras_output_sizes() {
+        assert_eq!(Padding::Same.output_size_and_offset(32, 5, 2), (16, 1));
+        assert_eq!(Padding::Same.output_size_and_offset(16, 3, 1), (16, 1));
+        assert_eq!(Padding::Same.output_size_and_offset(5, 2, 2), (3, 0));
+        assert_eq!(Padding::Valid.output_size_and_offset(16, 2, 2), (8, 0));
+        assert_eq!(Padding::Valid.output_size_and_offset(5, 3, 1), (3, 0));
+    }
+
+    #[test]
+    fn box_filter_convolution_sums_neighborhood() {
+        let layer = Layer::Convolution {
+            kernel: vec![1.0; 9],
+            kernel_size: (3, 3),
+            strides: (1, 1),
+            padding: Padding::Same,
+            filters: 1,
+            bias: Some(vec![0.5]),
+            activation: Activation::Linear,
+        };
+        let output = layer.apply(&ramp(3, 3, 1)).unwrap();
+        assert_eq!((output.height, output.width, output.channels), (3, 3, 1));
+        // corner covers 0, 1, 3, 4 and center covers all values 0..9
+        assert_relative_eq!(output.data[0], 8.5);
+        assert_relative_eq!(output.data[4], 36.5);
+    }
+
+    #[test]
+    fn convolution_mixes_input_channels_into_filters() {
+        // 1×1 kernel mapping (a, b) to (a + b, a - b)
+        let layer = Layer::Convolution {
+            kernel: vec![1.0, 1.0, 1.0, -1.0],
+            kernel_size: (1, 1),
+            strides: (1, 1),
+            padding: Padding::Valid,
+            filters: 2,
+            bias: None,
+            activation: Activation::Linear,
+        };
+        let output = layer.apply(&ramp(1, 2, 2)).unwrap();
+        assert_eq!(output.data, vec![1.0, -1.0, 5.0, -1.0]);
+    }
+
+    #[test]
+    fn depthwise_convolution_keeps_channels_separate() {
+        let layer = Layer::DepthwiseConvolution {
+            kernel: vec![1.0, 10.0, 1.0, 10.0],
+            kernel_size: (1, 2),
+            strides: (1, 1),
+            padding: Padding::Valid,
+            depth_multiplier: 1,
+        };
+        let output = layer.apply(&ramp(1, 2, 2)).unwrap();
+        assert_eq!((output.width, output.channels), (1, 2));
+        assert_eq!(output.data, vec![2.0, 40.0]);
+    }
+
+    #[test]
+    fn pooling_reduces_windows() {
+        let input = ramp(4, 4, 1);
+        let maximum = pool(&input, PoolingKind::Maximum, (2, 2), (2, 2), Padding::Valid);
+        assert_eq!(maximum.data, vec![5.0, 7.0, 13.0, 15.0]);
+        let average = pool(&input, PoolingKind::Average, (2, 2), (2, 2), Padding::Valid);
+        assert_eq!(average.data, vec![2.5, 4.5, 10.5, 12.5]);
+    }
+
+    #[test]
+    fn batch_normalization_is_folded_into_affine_transform() {
+        let layer =
+            Layer::batch_normalization(Some(&[2.0]), Some(&[1.0]), &[3.0], &[4.0 - 0.001], 0.001);
+        let output = layer.apply(&ramp(1, 2, 1)).unwrap();
+        assert_relative_eq!(
+            output.data[0],
+            2.0 * (0.0 - 3.0) / 2.0 + 1.0,
+            epsilon = 1e-5
+        );
+        assert_relative_eq!(
+            output.data[1],
+            2.0 * (1.0 - 3.0) / 2.0 + 1.0,
+            epsilon = 1e-5
+        );
+    }
+
+    #[test]
+    fn activations_match_definitions() {
+        let mut values = [-1.0, 0.0, 2.0];
+        Activation::Elu.apply(&mut values);
+        assert_relative_eq!(values[0], (-1.0f32).exp() - 1.0);
+        assert_relative_eq!(values[2], 2.0);
+        let mut values = [0.0];
+        Activation::Sigmoid.apply(&mut values);
+        assert_relative_eq!(values[0], 0.5);
+        let mut values = [1.0, 1.0];
+        Activation::Softmax.apply(&mut values);
+        assert_relative_eq!(values[0], 0.5);
+    }
+}
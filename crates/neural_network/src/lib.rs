@@ -0,0 +1,10 @@
+//! Portable CPU inference for Keras Sequential models stored as `.hdf5`
+//!
+//! Used as fallback for `compiled_nn` on platforms where its JIT compiler is not available.
+
+mod hdf5;
+mod layer;
+mod network;
+
+pub use layer::{Activation, Layer, Padding, PoolingKind, Tensor};
+pub use network::Network;
@@ -0,0 +1,328 @@
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde_json::Value;
+
+use crate::{
+    hdf5::{File, Object},
+    layer::{Activation, Layer, Padding, PoolingKind, Tensor},
+};
+
+/// Keras Sequential model evaluated on the CPU
+///
+/// Loads the same `.hdf5` files as `compiled_nn::CompiledNN` and mirrors its buffer based
+/// interface: fill `input()`, call `apply()` and read `output()`.
+#[derive(Clone, Debug, Default)]
+pub struct Network {
+    layers: Vec<Layer>,
+    input: Tensor,
+    output: Vec<f32>,
+}
+
+impl Network {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path)?;
+        Self::from_file(&file).with_context(|| format!("Failed to load {}", path.display()))
+    }
+
+    fn from_file(file: &File) -> Result<Self> {
+        let model_config: Value = serde_json::from_str(
+            &file
+                .root()?
+                .attribute("model_config")?
+                .string()
+                .context("Failed to read model configuration")?,
+        )?;
+        if model_config["class_name"] != "Sequential" {
+            bail!(
+                "Only Sequential models are supported, got {}",
+                model_config["class_name"]
+            );
+        }
+        let layer_configs = match &model_config["config"] {
+            Value::Array(layers) => layers,
+            config => config["layers"]
+                .as_array()
+                .ok_or_else(|| anyhow!("Model configuration contains no layers"))?,
+        };
+        let model_weights = file.object("model_weights")?;
+
+        let mut input_shape = None;
+        let mut layers = vec![];
+        for layer_config in layer_configs {
+            let class_name = layer_config["class_name"]
+                .as_str()
+                .ok_or_else(|| anyhow!("Layer without class name"))?;
+            let config = &layer_config["config"];
+            if input_shape.is_none() {
+                input_shape = parse_input_shape(config);
+            }
+            if class_name == "InputLayer" {
+                continue;
+            }
+            let name = config["name"]
+                .as_str()
+                .ok_or_else(|| anyhow!("{class_name} layer without name"))?;
+            let weights = LayerWeights::load(&model_weights, name)?;
+            layers.extend(
+                parse_layer(class_name, config, &weights)
+                    .with_context(|| format!("Failed to parse layer {name}"))?,
+            );
+        }
+        let (height, width, channels) =
+            input_shape.ok_or_else(|| anyhow!("Model configuration contains no input shape"))?;
+
+        let mut network = Self {
+            layers,
+            input: Tensor::zeros(height, width, channels),
+            output: vec![],
+        };
+        network.apply()?;
+        Ok(network)
+    }
+
+    pub fn input(&mut self) -> &mut [f32] {
+        &mut self.input.data
+    }
+
+    pub fn output(&self) -> &[f32] {
+        &self.output
+    }
+
+    pub fn apply(&mut self) -> Result<()> {
+        let mut activations = self.input.clone();
+        for layer in &self.layers {
+            activations = layer.apply(&activations)?;
+        }
+        self.output = activations.data;
+        Ok(())
+    }
+}
+
+fn parse_input_shape(config: &Value) -> Option<(usize, usize, usize)> {
+    let shape: Vec<_> = config["batch_input_shape"]
+        .as_array()?
+        .iter()
+        .skip(1)
+        .map(|dimension| dimension.as_u64().map(|dimension| dimension as usize))
+        .collect::<Option<_>>()?;
+    match shape.as_slice() {
+        [height, width, channels] => Some((*height, *width, *channels)),
+        [height, width] => Some((*height, *width, 1)),
+        [length] => Some((1, 1, *length)),
+        _ => None,
+    }
+}
+
+/// Weights of one layer, stored as `model_weights/<layer>/<weight_name>` and listed in the
+/// `weight_names` attribute of the layer group
+struct LayerWeights {
+    weights: Vec<(String, Vec<f32>)>,
+}
+
+impl LayerWeights {
+    fn load(model_weights: &Object, layer_name: &str) -> Result<Self> {
+        let layer_group = match model_weights.child(layer_name) {
+            Ok(layer_group) => layer_group,
+            Err(_) => return Ok(Self { weights: vec![] }),
+        };
+        let weight_names = layer_group.attribute("weight_names")?.strings()?;
+        let weights = weight_names
+            .into_iter()
+            .map(|weight_name| {
+                let dataset = layer_group.descendant(&weight_name)?;
+                let short_name = weight_name
+                    .rsplit('/')
+                    .next()
+                    .unwrap_or(&weight_name)
+                    .trim_end_matches(":0")
+                    .to_string();
+                Ok((short_name, dataset.read_f32()?))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { weights })
+    }
+
+    fn get(&self, name: &str) -> Option<&[f32]> {
+        self.weights
+            .iter()
+            .find_map(|(weight_name, values)| (weight_name == name).then_some(values.as_slice()))
+    }
+
+    fn require(&self, name: &str) -> Result<Vec<f32>> {
+        self.get(name)
+            .map(|values| values.to_vec())
+            .ok_or_else(|| anyhow!("Missing weight {name}"))
+    }
+}
+
+fn parse_layer(class_name: &str, config: &Value, weights: &LayerWeights) -> Result<Vec<Layer>> {
+    if let Some(data_format) = config["data_format"].as_str() {
+        if data_format != "channels_last" {
+            bail!("Unsupported data format {data_format}");
+        }
+    }
+    Ok(match class_name {
+        "Conv2D" => {
+            check_dilation(config)?;
+            vec![Layer::Convolution {
+                kernel: weights.require("kernel")?,
+                kernel_size: parse_pair(config, "kernel_size")?,
+                strides: parse_pair(config, "strides")?,
+                padding: parse_padding(config)?,
+                filters: parse_usize(config, "filters")?,
+                bias: parse_bias(config, weights)?,
+                activation: parse_activation(config)?,
+            }]
+        }
+        "SeparableConv2D" => {
+            check_dilation(config)?;
+            vec![
+                Layer::DepthwiseConvolution {
+                    kernel: weights.require("depthwise_kernel")?,
+                    kernel_size: parse_pair(config, "kernel_size")?,
+                    strides: parse_pair(config, "strides")?,
+                    padding: parse_padding(config)?,
+                    depth_multiplier: parse_usize(config, "depth_multiplier")?,
+                },
+                Layer::Convolution {
+                    kernel: weights.require("pointwise_kernel")?,
+                    kernel_size: (1, 1),
+                    strides: (1, 1),
+                    padding: Padding::Valid,
+                    filters: parse_usize(config, "filters")?,
+                    bias: parse_bias(config, weights)?,
+                    activation: parse_activation(config)?,
+                },
+            ]
+        }
+        "Dense" => vec![Layer::Dense {
+            kernel: weights.require("kernel")?,
+            units: parse_usize(config, "units")?,
+            bias: parse_bias(config, weights)?,
+            activation: parse_activation(config)?,
+        }],
+        "BatchNormalization" => {
+            let epsilon = config["epsilon"]
+                .as_f64()
+                .ok_or_else(|| anyhow!("Missing epsilon"))? as f32;
+            vec![Layer::batch_normalization(
+                weights.get("gamma"),
+                weights.get("beta"),
+                &weights.require("moving_mean")?,
+                &weights.require("moving_variance")?,
+                epsilon,
+            )]
+        }
+        "Activation" => vec![Layer::Activation(parse_activation(config)?)],
+        "MaxPooling2D" | "AveragePooling2D" => vec![Layer::Pooling {
+            kind: if class_name == "MaxPooling2D" {
+                PoolingKind::Maximum
+            } else {
+                PoolingKind::Average
+            },
+            pool_size: parse_pair(config, "pool_size")?,
+            strides: parse_pair(config, "strides").or_else(|_| parse_pair(config, "pool_size"))?,
+            padding: parse_padding(config)?,
+        }],
+        "Flatten" => vec![Layer::Flatten],
+        "Dropout" => vec![],
+        _ => bail!("Unsupported layer {class_name}"),
+    })
+}
+
+fn parse_usize(config: &Value, key: &str) -> Result<usize> {
+    config[key]
+        .as_u64()
+        .map(|value| value as usize)
+        .ok_or_else(|| anyhow!("Missing {key}"))
+}
+
+fn parse_pair(config: &Value, key: &str) -> Result<(usize, usize)> {
+    match config[key].as_array().map(|values| values.as_slice()) {
+        Some([first, second]) => Ok((
+            first.as_u64().ok_or_else(|| anyhow!("Invalid {key}"))? as usize,
+            second.as_u64().ok_or_else(|| anyhow!("Invalid {key}"))? as usize,
+        )),
+        _ => bail!("Missing {key}"),
+    }
+}
+
+fn parse_padding(config: &Value) -> Result<Padding> {
+    match config["padding"].as_str() {
+        Some("same") => Ok(Padding::Same),
+        Some("valid") => Ok(Padding::Valid),
+        padding => bail!("Unsupported padding {padding:?}"),
+    }
+}
+
+fn parse_activation(config: &Value) -> Result<Activation> {
+    Activation::from_name(
+        config["activation"]
+            .as_str()
+            .ok_or_else(|| anyhow!("Missing activation"))?,
+    )
+}
+
+fn parse_bias(config: &Value, weights: &LayerWeights) -> Result<Option<Vec<f32>>> {
+    match config["use_bias"].as_bool() {
+        Some(false) => Ok(None),
+        _ => weights.require("bias").map(Some),
+    }
+}
+
+fn check_dilation(config: &Value) -> Result<()> {
+    match parse_pair(config, "dilation_rate") {
+        Ok((1, 1)) | Err(_) => Ok(()),
+        Ok(dilation_rate) => bail!("Unsupported dilation rate {dilation_rate:?}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+
+    use super::*;
+
+    const NEURAL_NETWORKS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../etc/neural_networks");
+    /// 32x32 grayscale sample of `tests/data/ball_sample.png` as fed by the ball detection
+    const BALL_SAMPLE: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../../tests/data/ball_sample_grayscale.json"
+    );
+
+    #[test]
+    fn ball_detection_networks_load_with_expected_shapes() {
+        for (name, number_of_outputs) in
+            [("preclassifier", 1), ("classifier", 1), ("positioner", 3)]
+        {
+            let mut network = Network::load(format!("{NEURAL_NETWORKS}/{name}.hdf5")).unwrap();
+            assert_eq!(network.input().len(), 32 * 32, "{name}");
+            network.input().fill(0.5);
+            network.apply().unwrap();
+            assert_eq!(network.output().len(), number_of_outputs, "{name}");
+            assert!(
+                network.output().iter().all(|value| value.is_finite()),
+                "{name}"
+            );
+        }
+    }
+
+    #[test]
+    fn ball_detection_networks_match_reference_outputs() {
+        let sample: Vec<f32> =
+            serde_json::from_str(&std::fs::read_to_string(BALL_SAMPLE).unwrap()).unwrap();
+        // recorded with CompiledNN, which approximates exp in elu and tanh
+        for (name, expected_output) in [
+            ("preclassifier", &[1.0][..]),
+            ("classifier", &[1.0][..]),
+            ("positioner", &[0.488, 0.514, 0.6311][..]),
+        ] {
+            let mut network = Network::load(format!("{NEURAL_NETWORKS}/{name}.hdf5")).unwrap();
+            network.input().copy_from_slice(&sample);
+            network.apply().unwrap();
+            assert_relative_eq!(network.output(), expected_output, epsilon = 0.01);
+        }
+    }
+}
@@ -224,6 +224,25 @@ impl Repository {
             .context("Failed to write configuration")
     }
 
+    pub async fn set_extrinsic_rotations(
+        &self,
+        head_id: &str,
+        cycler: &str,
+        extrinsic_rotations: [f32; 3],
+    ) -> Result<()> {
+        let mut configuration = self
+            .read_configuration(head_id)
+            .await
+            .context("Failed to read configuration")?;
+
+        configuration[cycler]["camera_matrix_parameters"]["extrinsic_rotations"] =
+            to_value(extrinsic_rotations).context("Failed to serialize extrinsic rotations")?;
+
+        self.write_configuration(head_id, &configuration)
+            .await
+            .context("Failed to write configuration")
+    }
+
     pub async fn set_communication(&self, head_id: &str, enable: bool) -> Result<()> {
         let mut configuration = self
             .read_configuration(head_id)
@@ -0,0 +1,12 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use spl_network::GameControllerReturnMessage;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(message) = GameControllerReturnMessage::try_from(data) {
+        let bytes: Vec<u8> = message.into();
+        GameControllerReturnMessage::try_from(bytes.as_slice())
+            .expect("serialized message must be parsable");
+    }
+});
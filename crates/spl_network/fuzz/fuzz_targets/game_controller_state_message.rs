@@ -0,0 +1,15 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use spl_network::GameControllerStateMessage;
+
+// The first byte selects the HULKs team number, the rest is the packet
+fuzz_target!(|data: &[u8]| {
+    if let Some((&hulks_team_number, buffer)) = data.split_first() {
+        if let Ok(message) = GameControllerStateMessage::try_from_bytes(buffer, hulks_team_number) {
+            let bytes = message.to_bytes(0);
+            GameControllerStateMessage::try_from_bytes(&bytes, hulks_team_number)
+                .expect("serialized message must be parsable");
+        }
+    }
+});
@@ -0,0 +1,11 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use spl_network::SplMessage;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(message) = SplMessage::try_from(data) {
+        let bytes: Vec<u8> = message.into();
+        SplMessage::try_from(bytes.as_slice()).expect("serialized message must be parsable");
+    }
+});
@@ -0,0 +1,109 @@
+use std::ops::Range;
+
+use anyhow::bail;
+
+/// Appends values with an arbitrary number of bits, least significant bit first
+#[derive(Default)]
+pub struct BitWriter {
+    bytes: Vec<u8>,
+    number_of_bits: usize,
+}
+
+impl BitWriter {
+    pub fn write_bits(&mut self, value: u32, number_of_bits: usize) {
+        for bit_index in 0..number_of_bits {
+            if self.number_of_bits == self.bytes.len() * 8 {
+                self.bytes.push(0);
+            }
+            if value >> bit_index & 1 == 1 {
+                *self.bytes.last_mut().unwrap() |= 1 << (self.number_of_bits % 8);
+            }
+            self.number_of_bits += 1;
+        }
+    }
+
+    pub fn write_bool(&mut self, value: bool) {
+        self.write_bits(u32::from(value), 1);
+    }
+
+    /// Clamps the value into the range and maps it onto `number_of_bits` evenly spaced steps
+    pub fn write_quantized(&mut self, value: f32, range: Range<f32>, number_of_bits: usize) {
+        let maximum_step = (1u32 << number_of_bits) - 1;
+        let normalized = ((value - range.start) / (range.end - range.start)).clamp(0.0, 1.0);
+        self.write_bits(
+            (normalized * maximum_step as f32).round() as u32,
+            number_of_bits,
+        );
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+/// Reads values written by the [`BitWriter`]
+pub struct BitReader<'buffer> {
+    bytes: &'buffer [u8],
+    bit_offset: usize,
+}
+
+impl<'buffer> BitReader<'buffer> {
+    pub fn new(bytes: &'buffer [u8]) -> Self {
+        Self {
+            bytes,
+            bit_offset: 0,
+        }
+    }
+
+    pub fn read_bits(&mut self, number_of_bits: usize) -> anyhow::Result<u32> {
+        if self.bit_offset + number_of_bits > self.bytes.len() * 8 {
+            bail!("Unexpected end of buffer");
+        }
+        let mut value = 0;
+        for bit_index in 0..number_of_bits {
+            let byte = self.bytes[self.bit_offset / 8];
+            if byte >> (self.bit_offset % 8) & 1 == 1 {
+                value |= 1 << bit_index;
+            }
+            self.bit_offset += 1;
+        }
+        Ok(value)
+    }
+
+    pub fn read_bool(&mut self) -> anyhow::Result<bool> {
+        Ok(self.read_bits(1)? == 1)
+    }
+
+    pub fn read_quantized(
+        &mut self,
+        range: Range<f32>,
+        number_of_bits: usize,
+    ) -> anyhow::Result<f32> {
+        let maximum_step = (1u32 << number_of_bits) - 1;
+        let step = self.read_bits(number_of_bits)?;
+        Ok(range.start + step as f32 / maximum_step as f32 * (range.end - range.start))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn values_spanning_bytes_are_read_back() {
+        let mut writer = BitWriter::default();
+        writer.write_bits(0b101, 3);
+        writer.write_bool(true);
+        writer.write_bits(0x1234, 13);
+        writer.write_quantized(0.25, -1.0..1.0, 10);
+        let bytes = writer.into_bytes();
+        assert_eq!(bytes.len(), 4);
+
+        let mut reader = BitReader::new(&bytes);
+        assert_eq!(reader.read_bits(3).unwrap(), 0b101);
+        assert!(reader.read_bool().unwrap());
+        assert_eq!(reader.read_bits(13).unwrap(), 0x1234);
+        assert!((reader.read_quantized(-1.0..1.0, 10).unwrap() - 0.25).abs() < 1.0 / 1023.0);
+        assert!(reader.read_bits(8).is_err());
+    }
+}
@@ -1,6 +1,7 @@
-use std::{mem::size_of, slice::from_raw_parts};
+use std::convert::{TryFrom, TryInto};
 
-use nalgebra::Isometry2;
+use anyhow::bail;
+use nalgebra::{point, vector, Isometry2};
 use serde::{Deserialize, Serialize};
 
 use crate::{
@@ -8,11 +9,15 @@ use crate::{
         RoboCupGameControlReturnData, GAMECONTROLLER_RETURN_STRUCT_HEADER,
         GAMECONTROLLER_RETURN_STRUCT_VERSION,
     },
-    BallPosition, PlayerNumber, HULKS_TEAM_NUMBER,
+    wire_format::{
+        duration_from_seconds, header_matches, read_finite_f32s, WireReader, WireWriter,
+    },
+    BallPosition, PlayerNumber,
 };
 
 #[derive(Clone, Copy, Debug, Deserialize, Serialize)]
 pub struct GameControllerReturnMessage {
+    pub team_number: u8,
     pub player_number: PlayerNumber,
     pub fallen: bool,
     pub robot_to_field: Isometry2<f32>,
@@ -21,14 +26,83 @@ pub struct GameControllerReturnMessage {
 
 impl From<GameControllerReturnMessage> for Vec<u8> {
     fn from(message: GameControllerReturnMessage) -> Self {
-        let message = message.into();
-        unsafe {
-            from_raw_parts(
-                &message as *const RoboCupGameControlReturnData as *const u8,
-                size_of::<RoboCupGameControlReturnData>(),
-            )
+        let message: RoboCupGameControlReturnData = message.into();
+        let mut writer = WireWriter::default();
+        writer.write_header(&message.header);
+        writer.write_u8(message.version);
+        writer.write_u8(message.playerNum);
+        writer.write_u8(message.teamNum);
+        writer.write_u8(message.fallen);
+        for value in message
+            .pose
+            .iter()
+            .chain([&message.ballAge])
+            .chain(&message.ball)
+        {
+            writer.write_f32(*value);
         }
-        .to_vec()
+        writer.into_bytes()
+    }
+}
+
+impl TryFrom<&[u8]> for GameControllerReturnMessage {
+    type Error = anyhow::Error;
+
+    fn try_from(buffer: &[u8]) -> anyhow::Result<Self> {
+        let mut reader = WireReader::new(buffer);
+        let message = RoboCupGameControlReturnData {
+            header: reader.read_header(GAMECONTROLLER_RETURN_STRUCT_HEADER)?,
+            version: reader.read_version(GAMECONTROLLER_RETURN_STRUCT_VERSION)?,
+            playerNum: reader.read_u8()?,
+            teamNum: reader.read_u8()?,
+            fallen: reader.read_u8()?,
+            pose: read_finite_f32s(&mut reader)?,
+            ballAge: read_finite_f32s::<1>(&mut reader)?[0],
+            ball: read_finite_f32s(&mut reader)?,
+        };
+        reader.expect_end()?;
+        message.try_into()
+    }
+}
+
+impl TryFrom<RoboCupGameControlReturnData> for GameControllerReturnMessage {
+    type Error = anyhow::Error;
+
+    fn try_from(message: RoboCupGameControlReturnData) -> anyhow::Result<Self> {
+        if !header_matches(&message.header, GAMECONTROLLER_RETURN_STRUCT_HEADER) {
+            bail!("Unexpected header");
+        }
+        if message.version != GAMECONTROLLER_RETURN_STRUCT_VERSION {
+            bail!("Unexpected version");
+        }
+        Ok(Self {
+            team_number: message.teamNum,
+            player_number: match message.playerNum {
+                1 => PlayerNumber::One,
+                2 => PlayerNumber::Two,
+                3 => PlayerNumber::Three,
+                4 => PlayerNumber::Four,
+                5 => PlayerNumber::Five,
+                _ => bail!("Unexpected player number {}", message.playerNum),
+            },
+            fallen: match message.fallen {
+                1 => true,
+                0 => false,
+                _ => bail!("Unexpected fallen state"),
+            },
+            robot_to_field: Isometry2::new(
+                vector![message.pose[0] / 1000.0, message.pose[1] / 1000.0],
+                message.pose[2],
+            ),
+            ball_position: if message.ballAge < 0.0 {
+                None
+            } else {
+                Some(BallPosition {
+                    relative_position: point![message.ball[0] / 1000.0, message.ball[1] / 1000.0],
+                    age: duration_from_seconds(message.ballAge)?,
+                })
+            },
+        })
     }
 }
 
@@ -59,7 +133,7 @@ impl From<GameControllerReturnMessage> for RoboCupGameControlReturnData {
                 PlayerNumber::Four => 4,
                 PlayerNumber::Five => 5,
             },
-            teamNum: HULKS_TEAM_NUMBER,
+            teamNum: message.team_number,
             fallen: u8::from(message.fallen),
             pose: [
                 message.robot_to_field.translation.vector.x * 1000.0,
@@ -74,16 +148,21 @@ impl From<GameControllerReturnMessage> for RoboCupGameControlReturnData {
 
 #[cfg(test)]
 mod test {
-    use std::f32::consts::{FRAC_PI_2, FRAC_PI_4, SQRT_2};
+    use std::{
+        f32::consts::{FRAC_PI_2, FRAC_PI_4, PI, SQRT_2},
+        time::Duration,
+    };
 
     use approx::assert_relative_eq;
     use nalgebra::{point, vector};
+    use proptest::{collection::vec, option, prelude::*};
 
     use super::*;
 
     #[test]
     fn zero_isometry() {
         let input_message = GameControllerReturnMessage {
+            team_number: 24,
             player_number: PlayerNumber::One,
             fallen: false,
             robot_to_field: Isometry2::default(),
@@ -99,6 +178,7 @@ mod test {
     #[test]
     fn one_to_the_left_isometry() {
         let input_message = GameControllerReturnMessage {
+            team_number: 24,
             player_number: PlayerNumber::One,
             fallen: false,
             robot_to_field: Isometry2::new(vector![0.0, 1.0], FRAC_PI_2),
@@ -114,6 +194,7 @@ mod test {
     #[test]
     fn one_schräg_to_the_top_right_isometry() {
         let input_message = GameControllerReturnMessage {
+            team_number: 24,
             player_number: PlayerNumber::One,
             fallen: false,
             robot_to_field: Isometry2::new(vector![1.0, 1.0], FRAC_PI_4),
@@ -136,4 +217,160 @@ mod test {
         assert_relative_eq!(output_message.pose[1], 1000.0, epsilon = 0.001);
         assert_relative_eq!(output_message.pose[2], FRAC_PI_4, epsilon = 0.001);
     }
+
+    #[test]
+    fn serialized_message_is_parsed_again() {
+        let input_message = GameControllerReturnMessage {
+            team_number: 5,
+            player_number: PlayerNumber::Four,
+            fallen: true,
+            robot_to_field: Isometry2::new(vector![-1.5, 2.0], FRAC_PI_2),
+            ball_position: Some(BallPosition {
+                relative_position: point![0.5, -0.25],
+                age: Duration::from_millis(500),
+            }),
+        };
+        let bytes: Vec<u8> = input_message.into();
+        let output_message = GameControllerReturnMessage::try_from(bytes.as_slice()).unwrap();
+
+        assert_eq!(output_message.team_number, 5);
+        assert_eq!(output_message.player_number, PlayerNumber::Four);
+        assert!(output_message.fallen);
+        assert_relative_eq!(
+            output_message.robot_to_field,
+            input_message.robot_to_field,
+            epsilon = 0.001
+        );
+        let ball_position = output_message.ball_position.unwrap();
+        assert_relative_eq!(ball_position.relative_position, point![0.5, -0.25]);
+        assert_eq!(ball_position.age, Duration::from_millis(500));
+        assert!(GameControllerReturnMessage::try_from(&bytes[..10]).is_err());
+    }
+
+    const MESSAGE_SIZE: usize = 32;
+
+    fn player_number() -> impl Strategy<Value = PlayerNumber> {
+        prop_oneof![
+            Just(PlayerNumber::One),
+            Just(PlayerNumber::Two),
+            Just(PlayerNumber::Three),
+            Just(PlayerNumber::Four),
+            Just(PlayerNumber::Five),
+        ]
+    }
+
+    prop_compose! {
+        fn return_message()(
+            team_number in any::<u8>(),
+            player_number in player_number(),
+            fallen in any::<bool>(),
+            translation in (-5.0_f32..5.0, -3.5_f32..3.5),
+            rotation in -PI..PI,
+            ball_position in option::of((-9.0_f32..9.0, -6.0_f32..6.0, 0_u64..60_000)),
+        ) -> GameControllerReturnMessage {
+            GameControllerReturnMessage {
+                team_number,
+                player_number,
+                fallen,
+                robot_to_field: Isometry2::new(vector![translation.0, translation.1], rotation),
+                ball_position: ball_position.map(|(x, y, age)| BallPosition {
+                    relative_position: point![x, y],
+                    age: Duration::from_millis(age),
+                }),
+            }
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn messages_round_trip(input_message in return_message()) {
+            let bytes: Vec<u8> = input_message.into();
+            prop_assert_eq!(bytes.len(), MESSAGE_SIZE);
+            let output_message = GameControllerReturnMessage::try_from(bytes.as_slice()).unwrap();
+
+            prop_assert_eq!(output_message.team_number, input_message.team_number);
+            prop_assert_eq!(output_message.player_number, input_message.player_number);
+            prop_assert_eq!(output_message.fallen, input_message.fallen);
+            assert_relative_eq!(
+                output_message.robot_to_field,
+                input_message.robot_to_field,
+                epsilon = 1e-4
+            );
+            prop_assert_eq!(
+                output_message.ball_position.is_some(),
+                input_message.ball_position.is_some()
+            );
+            if let (Some(output_ball), Some(input_ball)) =
+                (output_message.ball_position, input_message.ball_position)
+            {
+                assert_relative_eq!(
+                    output_ball.relative_position,
+                    input_ball.relative_position,
+                    epsilon = 1e-4
+                );
+                prop_assert!(
+                    (output_ball.age.as_secs_f32() - input_ball.age.as_secs_f32()).abs() < 1e-3
+                );
+            }
+        }
+
+        #[test]
+        fn arbitrary_bytes_do_not_panic(bytes in vec(any::<u8>(), 0..2 * MESSAGE_SIZE)) {
+            let _ = GameControllerReturnMessage::try_from(bytes.as_slice());
+        }
+
+        #[test]
+        fn mutated_messages_do_not_panic(
+            input_message in return_message(),
+            mutations in vec((0..MESSAGE_SIZE, any::<u8>()), 1..8),
+        ) {
+            let mut bytes: Vec<u8> = input_message.into();
+            for (index, value) in mutations {
+                bytes[index] = value;
+            }
+            if let Ok(message) = GameControllerReturnMessage::try_from(bytes.as_slice()) {
+                prop_assert!(message
+                    .robot_to_field
+                    .translation
+                    .vector
+                    .iter()
+                    .all(|value| value.is_finite()));
+            }
+        }
+
+        #[test]
+        fn truncated_and_extended_messages_are_rejected(
+            input_message in return_message(),
+            length in 0..MESSAGE_SIZE,
+            extension in vec(any::<u8>(), 1..16),
+        ) {
+            let bytes: Vec<u8> = input_message.into();
+            prop_assert!(GameControllerReturnMessage::try_from(&bytes[..length]).is_err());
+
+            let mut extended = bytes;
+            extended.extend(extension);
+            prop_assert!(GameControllerReturnMessage::try_from(extended.as_slice()).is_err());
+        }
+
+        #[test]
+        fn messages_with_wrong_header_or_version_are_rejected(
+            input_message in return_message(),
+            index in 0_usize..5,
+            flipped_bits in 1_u8..=u8::MAX,
+        ) {
+            let mut bytes: Vec<u8> = input_message.into();
+            bytes[index] ^= flipped_bits;
+            prop_assert!(GameControllerReturnMessage::try_from(bytes.as_slice()).is_err());
+        }
+
+        #[test]
+        fn messages_with_invalid_player_number_are_rejected(
+            input_message in return_message(),
+            player_number in prop_oneof![Just(0_u8), 6_u8..],
+        ) {
+            let mut bytes: Vec<u8> = input_message.into();
+            bytes[5] = player_number;
+            prop_assert!(GameControllerReturnMessage::try_from(bytes.as_slice()).is_err());
+        }
+    }
 }
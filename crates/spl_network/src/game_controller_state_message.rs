@@ -1,29 +1,21 @@
-use std::{
-    convert::{TryFrom, TryInto},
-    mem::size_of,
-    ptr::read,
-    time::Duration,
-};
+use std::{convert::TryInto, time::Duration};
 
 use anyhow::bail;
 use serde::{Deserialize, Serialize};
 
-use crate::{
-    bindings::{
-        RoboCupGameControlData, RobotInfo, GAMECONTROLLER_STRUCT_HEADER,
-        GAMECONTROLLER_STRUCT_VERSION, GAME_PHASE_NORMAL, GAME_PHASE_OVERTIME,
-        GAME_PHASE_PENALTYSHOOT, GAME_PHASE_TIMEOUT, MAX_NUM_PLAYERS, PENALTY_MANUAL, PENALTY_NONE,
-        PENALTY_SPL_ILLEGAL_BALL_CONTACT, PENALTY_SPL_ILLEGAL_MOTION_IN_SET,
-        PENALTY_SPL_ILLEGAL_POSITION, PENALTY_SPL_ILLEGAL_POSITION_IN_SET,
-        PENALTY_SPL_INACTIVE_PLAYER, PENALTY_SPL_LEAVING_THE_FIELD, PENALTY_SPL_LOCAL_GAME_STUCK,
-        PENALTY_SPL_PLAYER_PUSHING, PENALTY_SPL_REQUEST_FOR_PICKUP, PENALTY_SUBSTITUTE,
-        SET_PLAY_CORNER_KICK, SET_PLAY_GOAL_KICK, SET_PLAY_KICK_IN, SET_PLAY_NONE,
-        SET_PLAY_PENALTY_KICK, SET_PLAY_PUSHING_FREE_KICK, STATE_FINISHED, STATE_INITIAL,
-        STATE_PLAYING, STATE_READY, STATE_SET, TEAM_BLACK, TEAM_BLUE, TEAM_BROWN, TEAM_GRAY,
-        TEAM_GREEN, TEAM_ORANGE, TEAM_PURPLE, TEAM_RED, TEAM_WHITE, TEAM_YELLOW,
-    },
-    HULKS_TEAM_NUMBER,
+use crate::bindings::{
+    RoboCupGameControlData, RobotInfo, TeamInfo, GAMECONTROLLER_STRUCT_HEADER,
+    GAMECONTROLLER_STRUCT_VERSION, GAME_PHASE_NORMAL, GAME_PHASE_OVERTIME, GAME_PHASE_PENALTYSHOOT,
+    GAME_PHASE_TIMEOUT, MAX_NUM_PLAYERS, PENALTY_MANUAL, PENALTY_NONE,
+    PENALTY_SPL_ILLEGAL_BALL_CONTACT, PENALTY_SPL_ILLEGAL_MOTION_IN_SET,
+    PENALTY_SPL_ILLEGAL_POSITION, PENALTY_SPL_ILLEGAL_POSITION_IN_SET, PENALTY_SPL_INACTIVE_PLAYER,
+    PENALTY_SPL_LEAVING_THE_FIELD, PENALTY_SPL_LOCAL_GAME_STUCK, PENALTY_SPL_PLAYER_PUSHING,
+    PENALTY_SPL_REQUEST_FOR_PICKUP, PENALTY_SUBSTITUTE, SET_PLAY_CORNER_KICK, SET_PLAY_GOAL_KICK,
+    SET_PLAY_KICK_IN, SET_PLAY_NONE, SET_PLAY_PENALTY_KICK, SET_PLAY_PUSHING_FREE_KICK,
+    STATE_FINISHED, STATE_INITIAL, STATE_PLAYING, STATE_READY, STATE_SET, TEAM_BLACK, TEAM_BLUE,
+    TEAM_BROWN, TEAM_GRAY, TEAM_GREEN, TEAM_ORANGE, TEAM_PURPLE, TEAM_RED, TEAM_WHITE, TEAM_YELLOW,
 };
+use crate::wire_format::{header_matches, WireReader, WireWriter};
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct GameControllerStateMessage {
@@ -38,27 +30,64 @@ pub struct GameControllerStateMessage {
     pub kicking_team: Team,
 }
 
-impl TryFrom<&[u8]> for GameControllerStateMessage {
-    type Error = anyhow::Error;
+impl GameControllerStateMessage {
+    /// Parses a `RoboCupGameControlData` packet, `hulks_team_number` selects which team is ours
+    pub fn try_from_bytes(buffer: &[u8], hulks_team_number: u8) -> anyhow::Result<Self> {
+        let message = read_game_control_data(buffer)?;
+        Self::try_from_game_control_data(message, hulks_team_number)
+    }
 
-    fn try_from(buffer: &[u8]) -> anyhow::Result<Self> {
-        if buffer.len() < size_of::<RoboCupGameControlData>() {
-            bail!("Buffer too small");
-        }
-        let message = unsafe { read(buffer.as_ptr() as *const RoboCupGameControlData) };
-        message.try_into()
+    /// Serializes the message as `RoboCupGameControlData` with the HULKs team listed first, e.g.
+    /// for a local GameController stand-in
+    pub fn to_bytes(&self, packet_number: u8) -> Vec<u8> {
+        write_game_control_data(&self.to_game_control_data(packet_number))
     }
-}
 
-impl TryFrom<RoboCupGameControlData> for GameControllerStateMessage {
-    type Error = anyhow::Error;
+    fn to_game_control_data(&self, packet_number: u8) -> RoboCupGameControlData {
+        let team_number = |team: Team| match team {
+            Team::Hulks => self.hulks_team.team_number,
+            Team::Opponent => self.opponent_team.team_number,
+            Team::Uncertain => UNCERTAIN_TEAM_NUMBER,
+        };
+        let (game_phase, kicking_team) = match self.game_phase {
+            GamePhase::Normal => (GAME_PHASE_NORMAL, self.kicking_team),
+            GamePhase::PenaltyShootout { kicking_team } => (GAME_PHASE_PENALTYSHOOT, kicking_team),
+            GamePhase::Overtime => (GAME_PHASE_OVERTIME, self.kicking_team),
+            GamePhase::Timeout => (GAME_PHASE_TIMEOUT, self.kicking_team),
+        };
+        RoboCupGameControlData {
+            header: [
+                GAMECONTROLLER_STRUCT_HEADER[0] as i8,
+                GAMECONTROLLER_STRUCT_HEADER[1] as i8,
+                GAMECONTROLLER_STRUCT_HEADER[2] as i8,
+                GAMECONTROLLER_STRUCT_HEADER[3] as i8,
+            ],
+            version: GAMECONTROLLER_STRUCT_VERSION,
+            packetNumber: packet_number,
+            playersPerTeam: self
+                .hulks_team
+                .players
+                .len()
+                .max(self.opponent_team.players.len())
+                .min(MAX_NUM_PLAYERS as usize - 1) as u8,
+            competitionPhase: 0,
+            competitionType: 0,
+            gamePhase: game_phase,
+            state: self.game_state.into(),
+            setPlay: self.set_play.map_or(SET_PLAY_NONE, u8::from),
+            firstHalf: self.half.into(),
+            kickingTeam: team_number(kicking_team),
+            secsRemaining: self.remaining_time_in_half.as_secs().min(i16::MAX as u64) as i16,
+            secondaryTime: self.secondary_time.as_secs().min(i16::MAX as u64) as i16,
+            teams: [(&self.hulks_team).into(), (&self.opponent_team).into()],
+        }
+    }
 
-    fn try_from(message: RoboCupGameControlData) -> anyhow::Result<Self> {
-        if message.header[0] != GAMECONTROLLER_STRUCT_HEADER[0] as i8
-            && message.header[1] != GAMECONTROLLER_STRUCT_HEADER[1] as i8
-            && message.header[2] != GAMECONTROLLER_STRUCT_HEADER[2] as i8
-            && message.header[3] != GAMECONTROLLER_STRUCT_HEADER[3] as i8
-        {
+    fn try_from_game_control_data(
+        message: RoboCupGameControlData,
+        hulks_team_number: u8,
+    ) -> anyhow::Result<Self> {
+        if !header_matches(&message.header, GAMECONTROLLER_STRUCT_HEADER) {
             bail!("Unexpected header");
         }
         if message.version != GAMECONTROLLER_STRUCT_VERSION {
@@ -66,9 +95,9 @@ impl TryFrom<RoboCupGameControlData> for GameControllerStateMessage {
         }
         let (hulks_team_index, opponent_team_index) =
             match (message.teams[0].teamNumber, message.teams[1].teamNumber) {
-                (HULKS_TEAM_NUMBER, _) => (0, 1),
-                (_, HULKS_TEAM_NUMBER) => (1, 0),
-                _ => bail!("Failed to find HULKs team"),
+                (team_number, _) if team_number == hulks_team_number => (0, 1),
+                (_, team_number) if team_number == hulks_team_number => (1, 0),
+                _ => bail!("Failed to find HULKs team with team number {hulks_team_number}"),
             };
         const MAXIMUM_NUMBER_OF_PENALTY_SHOOTS: u8 = 16;
         if message.teams[hulks_team_index].penaltyShot >= MAXIMUM_NUMBER_OF_PENALTY_SHOOTS {
@@ -109,7 +138,11 @@ impl TryFrom<RoboCupGameControlData> for GameControllerStateMessage {
             })
             .collect::<anyhow::Result<Vec<_>>>()?;
         Ok(GameControllerStateMessage {
-            game_phase: GamePhase::try_from(message.gamePhase, message.kickingTeam)?,
+            game_phase: GamePhase::try_from(
+                message.gamePhase,
+                message.kickingTeam,
+                hulks_team_number,
+            )?,
             game_state: GameState::try_from(message.state)?,
             set_play: SetPlay::try_from(message.setPlay)?,
             half: message.firstHalf.try_into()?,
@@ -133,11 +166,83 @@ impl TryFrom<RoboCupGameControlData> for GameControllerStateMessage {
                 remaining_amount_of_messages: message.teams[opponent_team_index].messageBudget,
                 players: opponent_players,
             },
-            kicking_team: Team::try_from(message.kickingTeam)?,
+            kicking_team: Team::try_from(message.kickingTeam, hulks_team_number)?,
         })
     }
 }
 
+fn read_game_control_data(buffer: &[u8]) -> anyhow::Result<RoboCupGameControlData> {
+    let mut reader = WireReader::new(buffer);
+    let message = RoboCupGameControlData {
+        header: reader.read_header(GAMECONTROLLER_STRUCT_HEADER)?,
+        version: reader.read_version(GAMECONTROLLER_STRUCT_VERSION)?,
+        packetNumber: reader.read_u8()?,
+        playersPerTeam: reader.read_u8()?,
+        competitionPhase: reader.read_u8()?,
+        competitionType: reader.read_u8()?,
+        gamePhase: reader.read_u8()?,
+        state: reader.read_u8()?,
+        setPlay: reader.read_u8()?,
+        firstHalf: reader.read_u8()?,
+        kickingTeam: reader.read_u8()?,
+        secsRemaining: reader.read_i16()?,
+        secondaryTime: reader.read_i16()?,
+        teams: [read_team_info(&mut reader)?, read_team_info(&mut reader)?],
+    };
+    reader.expect_end()?;
+    Ok(message)
+}
+
+fn read_team_info(reader: &mut WireReader) -> anyhow::Result<TeamInfo> {
+    let mut team = TeamInfo {
+        teamNumber: reader.read_u8()?,
+        teamColour: reader.read_u8()?,
+        score: reader.read_u8()?,
+        penaltyShot: reader.read_u8()?,
+        singleShots: reader.read_u16()?,
+        messageBudget: reader.read_u16()?,
+        players: [RobotInfo {
+            penalty: PENALTY_NONE,
+            secsTillUnpenalised: 0,
+        }; MAX_NUM_PLAYERS as usize],
+    };
+    for player in team.players.iter_mut() {
+        player.penalty = reader.read_u8()?;
+        player.secsTillUnpenalised = reader.read_u8()?;
+    }
+    Ok(team)
+}
+
+fn write_game_control_data(message: &RoboCupGameControlData) -> Vec<u8> {
+    let mut writer = WireWriter::default();
+    writer.write_header(&message.header);
+    writer.write_u8(message.version);
+    writer.write_u8(message.packetNumber);
+    writer.write_u8(message.playersPerTeam);
+    writer.write_u8(message.competitionPhase);
+    writer.write_u8(message.competitionType);
+    writer.write_u8(message.gamePhase);
+    writer.write_u8(message.state);
+    writer.write_u8(message.setPlay);
+    writer.write_u8(message.firstHalf);
+    writer.write_u8(message.kickingTeam);
+    writer.write_i16(message.secsRemaining);
+    writer.write_i16(message.secondaryTime);
+    for team in &message.teams {
+        writer.write_u8(team.teamNumber);
+        writer.write_u8(team.teamColour);
+        writer.write_u8(team.score);
+        writer.write_u8(team.penaltyShot);
+        writer.write_u16(team.singleShots);
+        writer.write_u16(team.messageBudget);
+        for player in &team.players {
+            writer.write_u8(player.penalty);
+            writer.write_u8(player.secsTillUnpenalised);
+        }
+    }
+    writer.into_bytes()
+}
+
 #[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
 pub enum GamePhase {
     #[default]
@@ -149,13 +254,12 @@ pub enum GamePhase {
     Timeout,
 }
 
+/// Team number the GameController sends if no team is kicking, e.g. during a dropped ball
+const UNCERTAIN_TEAM_NUMBER: u8 = 255;
+
 impl GamePhase {
-    fn try_from(game_phase: u8, kicking_team: u8) -> anyhow::Result<Self> {
-        let team = if kicking_team == HULKS_TEAM_NUMBER {
-            Team::Hulks
-        } else {
-            Team::Opponent
-        };
+    fn try_from(game_phase: u8, kicking_team: u8, hulks_team_number: u8) -> anyhow::Result<Self> {
+        let team = Team::try_from(kicking_team, hulks_team_number)?;
         match game_phase {
             GAME_PHASE_NORMAL => Ok(GamePhase::Normal),
             GAME_PHASE_PENALTYSHOOT => Ok(GamePhase::PenaltyShootout { kicking_team: team }),
@@ -175,6 +279,18 @@ pub enum GameState {
     Finished,
 }
 
+impl From<GameState> for u8 {
+    fn from(game_state: GameState) -> Self {
+        match game_state {
+            GameState::Initial => STATE_INITIAL,
+            GameState::Ready => STATE_READY,
+            GameState::Set => STATE_SET,
+            GameState::Playing => STATE_PLAYING,
+            GameState::Finished => STATE_FINISHED,
+        }
+    }
+}
+
 impl GameState {
     fn try_from(game_state: u8) -> anyhow::Result<Self> {
         match game_state {
@@ -202,8 +318,8 @@ impl Default for Team {
 }
 
 impl Team {
-    fn try_from(team_number: u8) -> anyhow::Result<Self> {
-        let team = if team_number == HULKS_TEAM_NUMBER {
+    fn try_from(team_number: u8, hulks_team_number: u8) -> anyhow::Result<Self> {
+        let team = if team_number == hulks_team_number {
             Team::Hulks
         } else {
             Team::Opponent
@@ -212,6 +328,18 @@ impl Team {
     }
 }
 
+impl From<SetPlay> for u8 {
+    fn from(set_play: SetPlay) -> Self {
+        match set_play {
+            SetPlay::GoalKick => SET_PLAY_GOAL_KICK,
+            SetPlay::PushingFreeKick => SET_PLAY_PUSHING_FREE_KICK,
+            SetPlay::CornerKick => SET_PLAY_CORNER_KICK,
+            SetPlay::KickIn => SET_PLAY_KICK_IN,
+            SetPlay::PenaltyKick => SET_PLAY_PENALTY_KICK,
+        }
+    }
+}
+
 impl SetPlay {
     fn try_from(set_play: u8) -> anyhow::Result<Option<Self>> {
         match set_play {
@@ -241,7 +369,7 @@ impl Default for SetPlay {
     }
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub enum Half {
     First,
     Second,
@@ -259,6 +387,15 @@ impl TryFrom<u8> for Half {
     }
 }
 
+impl From<Half> for u8 {
+    fn from(half: Half) -> Self {
+        match half {
+            Half::First => 1,
+            Half::Second => 0,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct TeamState {
     pub team_number: u8,
@@ -270,7 +407,33 @@ pub struct TeamState {
     pub players: Vec<Player>,
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+impl From<&TeamState> for TeamInfo {
+    fn from(team_state: &TeamState) -> Self {
+        let mut players = [RobotInfo {
+            penalty: PENALTY_NONE,
+            secsTillUnpenalised: 0,
+        }; MAX_NUM_PLAYERS as usize];
+        for (robot_info, player) in players.iter_mut().zip(team_state.players.iter()) {
+            *robot_info = player.into();
+        }
+        Self {
+            teamNumber: team_state.team_number,
+            teamColour: team_state.color.into(),
+            score: team_state.score,
+            penaltyShot: team_state.penalty_shoot_index,
+            singleShots: team_state
+                .penalty_shoots
+                .iter()
+                .enumerate()
+                .filter(|(_, shoot)| matches!(shoot, PenaltyShoot::Successful))
+                .fold(0, |single_shots, (index, _)| single_shots | (1 << index)),
+            messageBudget: team_state.remaining_amount_of_messages,
+            players,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub enum TeamColor {
     Blue,
     Red,
@@ -304,6 +467,23 @@ impl TryFrom<u8> for TeamColor {
     }
 }
 
+impl From<TeamColor> for u8 {
+    fn from(team_color: TeamColor) -> Self {
+        match team_color {
+            TeamColor::Blue => TEAM_BLUE,
+            TeamColor::Red => TEAM_RED,
+            TeamColor::Yellow => TEAM_YELLOW,
+            TeamColor::Black => TEAM_BLACK,
+            TeamColor::White => TEAM_WHITE,
+            TeamColor::Green => TEAM_GREEN,
+            TeamColor::Orange => TEAM_ORANGE,
+            TeamColor::Purple => TEAM_PURPLE,
+            TeamColor::Brown => TEAM_BROWN,
+            TeamColor::Gray => TEAM_GRAY,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub enum PenaltyShoot {
     Successful,
@@ -326,6 +506,43 @@ impl TryFrom<RobotInfo> for Player {
     }
 }
 
+impl From<&Player> for RobotInfo {
+    fn from(player: &Player) -> Self {
+        let (penalty, remaining) = match player.penalty {
+            None => (PENALTY_NONE, Duration::ZERO),
+            Some(Penalty::IllegalBallContact { remaining }) => {
+                (PENALTY_SPL_ILLEGAL_BALL_CONTACT, remaining)
+            }
+            Some(Penalty::PlayerPushing { remaining }) => (PENALTY_SPL_PLAYER_PUSHING, remaining),
+            Some(Penalty::IllegalMotionInSet { remaining }) => {
+                (PENALTY_SPL_ILLEGAL_MOTION_IN_SET, remaining)
+            }
+            Some(Penalty::InactivePlayer { remaining }) => (PENALTY_SPL_INACTIVE_PLAYER, remaining),
+            Some(Penalty::IllegalPosition { remaining }) => {
+                (PENALTY_SPL_ILLEGAL_POSITION, remaining)
+            }
+            Some(Penalty::LeavingTheField { remaining }) => {
+                (PENALTY_SPL_LEAVING_THE_FIELD, remaining)
+            }
+            Some(Penalty::RequestForPickup { remaining }) => {
+                (PENALTY_SPL_REQUEST_FOR_PICKUP, remaining)
+            }
+            Some(Penalty::LocalGameStuck { remaining }) => {
+                (PENALTY_SPL_LOCAL_GAME_STUCK, remaining)
+            }
+            Some(Penalty::IllegalPositionInSet { remaining }) => {
+                (PENALTY_SPL_ILLEGAL_POSITION_IN_SET, remaining)
+            }
+            Some(Penalty::Substitute { remaining }) => (PENALTY_SUBSTITUTE, remaining),
+            Some(Penalty::Manual { remaining }) => (PENALTY_MANUAL, remaining),
+        };
+        Self {
+            penalty,
+            secsTillUnpenalised: remaining.as_secs().min(u8::MAX as u64) as u8,
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, Deserialize, Serialize)]
 pub enum Penalty {
     IllegalBallContact { remaining: Duration },
@@ -364,3 +581,303 @@ impl Penalty {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use proptest::{array::uniform7, collection::vec, prelude::*, sample::select};
+
+    use super::*;
+
+    const MESSAGE_SIZE: usize = 62;
+
+    fn game_control_data(first_team_number: u8, second_team_number: u8) -> RoboCupGameControlData {
+        let mut bytes = vec![0; MESSAGE_SIZE];
+        bytes[..4].copy_from_slice(&GAMECONTROLLER_STRUCT_HEADER[..4]);
+        bytes[4] = GAMECONTROLLER_STRUCT_VERSION;
+        let mut message = read_game_control_data(&bytes).unwrap();
+        message.playersPerTeam = 5;
+        message.gamePhase = GAME_PHASE_PENALTYSHOOT;
+        message.kickingTeam = second_team_number;
+        message.teams[0].teamNumber = first_team_number;
+        message.teams[0].score = 1;
+        message.teams[1].teamNumber = second_team_number;
+        message.teams[1].score = 2;
+        message
+    }
+
+    #[test]
+    fn own_team_is_selected_by_team_number() {
+        let message = game_control_data(24, 5);
+
+        let as_team_24 =
+            GameControllerStateMessage::try_from_game_control_data(message, 24).unwrap();
+        assert_eq!(as_team_24.hulks_team.team_number, 24);
+        assert_eq!(as_team_24.hulks_team.score, 1);
+        assert_eq!(as_team_24.opponent_team.score, 2);
+        assert_eq!(as_team_24.kicking_team, Team::Opponent);
+
+        let as_team_5 = GameControllerStateMessage::try_from_game_control_data(message, 5).unwrap();
+        assert_eq!(as_team_5.hulks_team.team_number, 5);
+        assert_eq!(as_team_5.hulks_team.score, 2);
+        assert_eq!(as_team_5.kicking_team, Team::Hulks);
+        assert!(matches!(
+            as_team_5.game_phase,
+            GamePhase::PenaltyShootout {
+                kicking_team: Team::Hulks
+            }
+        ));
+    }
+
+    #[test]
+    fn serialized_message_is_parsed_again() {
+        let message = GameControllerStateMessage {
+            game_phase: GamePhase::Normal,
+            game_state: GameState::Playing,
+            set_play: Some(SetPlay::CornerKick),
+            half: Half::Second,
+            remaining_time_in_half: Duration::from_secs(312),
+            secondary_time: Duration::from_secs(17),
+            hulks_team: TeamState {
+                team_number: 24,
+                color: TeamColor::Black,
+                score: 3,
+                penalty_shoot_index: 0,
+                penalty_shoots: vec![],
+                remaining_amount_of_messages: 1000,
+                players: vec![
+                    Player { penalty: None },
+                    Player {
+                        penalty: Some(Penalty::PlayerPushing {
+                            remaining: Duration::from_secs(30),
+                        }),
+                    },
+                ],
+            },
+            opponent_team: TeamState {
+                team_number: 5,
+                color: TeamColor::Yellow,
+                score: 1,
+                penalty_shoot_index: 2,
+                penalty_shoots: vec![PenaltyShoot::Unsuccessful, PenaltyShoot::Successful],
+                remaining_amount_of_messages: 800,
+                players: vec![Player { penalty: None }, Player { penalty: None }],
+            },
+            kicking_team: Team::Opponent,
+        };
+
+        let bytes = message.to_bytes(42);
+        let parsed = GameControllerStateMessage::try_from_bytes(&bytes, 24).unwrap();
+
+        assert_eq!(parsed.game_state, GameState::Playing);
+        assert!(matches!(parsed.set_play, Some(SetPlay::CornerKick)));
+        assert_eq!(parsed.half, Half::Second);
+        assert_eq!(parsed.remaining_time_in_half, Duration::from_secs(312));
+        assert_eq!(parsed.secondary_time, Duration::from_secs(17));
+        assert_eq!(parsed.kicking_team, Team::Opponent);
+        assert_eq!(parsed.hulks_team.color, TeamColor::Black);
+        assert_eq!(parsed.hulks_team.score, 3);
+        assert!(matches!(
+            parsed.hulks_team.players[1].penalty,
+            Some(Penalty::PlayerPushing { remaining }) if remaining == Duration::from_secs(30)
+        ));
+        assert_eq!(parsed.opponent_team.remaining_amount_of_messages, 800);
+        assert!(matches!(
+            parsed.opponent_team.penalty_shoots.as_slice(),
+            [PenaltyShoot::Unsuccessful, PenaltyShoot::Successful]
+        ));
+    }
+
+    #[test]
+    fn message_without_own_team_is_rejected() {
+        let message = game_control_data(24, 5);
+        assert!(GameControllerStateMessage::try_from_game_control_data(message, 7).is_err());
+    }
+
+    #[test]
+    fn header_with_a_single_matching_byte_is_rejected() {
+        let mut bytes = game_control_data(24, 5);
+        bytes.header[1] = b'X' as i8;
+        assert!(GameControllerStateMessage::try_from_game_control_data(bytes, 24).is_err());
+
+        let mut bytes = write_game_control_data(&game_control_data(24, 5));
+        bytes[0] = b'X';
+        assert!(GameControllerStateMessage::try_from_bytes(&bytes, 24).is_err());
+    }
+
+    #[test]
+    fn buffers_of_wrong_length_are_rejected() {
+        let bytes = write_game_control_data(&game_control_data(24, 5));
+        assert_eq!(bytes.len(), MESSAGE_SIZE);
+        assert!(GameControllerStateMessage::try_from_bytes(&bytes, 24).is_ok());
+        assert!(
+            GameControllerStateMessage::try_from_bytes(&bytes[..MESSAGE_SIZE - 1], 24).is_err()
+        );
+        let mut too_long = bytes;
+        too_long.push(0);
+        assert!(GameControllerStateMessage::try_from_bytes(&too_long, 24).is_err());
+    }
+
+    prop_compose! {
+        fn team_info(team_number: u8)(
+            team_colour in select(vec![
+                TEAM_BLUE, TEAM_RED, TEAM_YELLOW, TEAM_BLACK, TEAM_WHITE,
+                TEAM_GREEN, TEAM_ORANGE, TEAM_PURPLE, TEAM_BROWN, TEAM_GRAY,
+            ]),
+            score in any::<u8>(),
+            (penalty_shot, single_shots) in (0_u8..16)
+                .prop_flat_map(|penalty_shot| (Just(penalty_shot), 0..1_u16 << penalty_shot)),
+            message_budget in any::<u16>(),
+            players in uniform7((
+                select(vec![
+                    PENALTY_NONE, PENALTY_SPL_ILLEGAL_BALL_CONTACT, PENALTY_SPL_PLAYER_PUSHING,
+                    PENALTY_SPL_ILLEGAL_MOTION_IN_SET, PENALTY_SPL_INACTIVE_PLAYER,
+                    PENALTY_SPL_ILLEGAL_POSITION, PENALTY_SPL_LEAVING_THE_FIELD,
+                    PENALTY_SPL_REQUEST_FOR_PICKUP, PENALTY_SPL_LOCAL_GAME_STUCK,
+                    PENALTY_SPL_ILLEGAL_POSITION_IN_SET, PENALTY_SUBSTITUTE, PENALTY_MANUAL,
+                ]),
+                any::<u8>(),
+            )),
+        ) -> TeamInfo {
+            TeamInfo {
+                teamNumber: team_number,
+                teamColour: team_colour,
+                score,
+                penaltyShot: penalty_shot,
+                singleShots: single_shots,
+                messageBudget: message_budget,
+                players: players.map(|(penalty, secs_till_unpenalised)| RobotInfo {
+                    penalty,
+                    secsTillUnpenalised: if penalty == PENALTY_NONE {
+                        0
+                    } else {
+                        secs_till_unpenalised
+                    },
+                }),
+            }
+        }
+    }
+
+    prop_compose! {
+        /// Valid data with the HULKs team (24) listed first as written by `to_bytes()`
+        fn valid_game_control_data()(opponent_team_number in any::<u8>().prop_filter(
+            "Opponent must not share the HULKs team number",
+            |team_number| *team_number != 24,
+        ))(
+            packet_number in any::<u8>(),
+            players_per_team in 0..MAX_NUM_PLAYERS,
+            game_phase in select(vec![
+                GAME_PHASE_NORMAL, GAME_PHASE_PENALTYSHOOT, GAME_PHASE_OVERTIME, GAME_PHASE_TIMEOUT,
+            ]),
+            state in select(vec![
+                STATE_INITIAL, STATE_READY, STATE_SET, STATE_PLAYING, STATE_FINISHED,
+            ]),
+            set_play in select(vec![
+                SET_PLAY_NONE, SET_PLAY_GOAL_KICK, SET_PLAY_PUSHING_FREE_KICK,
+                SET_PLAY_CORNER_KICK, SET_PLAY_KICK_IN, SET_PLAY_PENALTY_KICK,
+            ]),
+            first_half in 0_u8..=1,
+            kicking_team in select(vec![24, opponent_team_number]),
+            secs_remaining in 0..=i16::MAX,
+            secondary_time in 0..=i16::MAX,
+            hulks_team in team_info(24),
+            opponent_team in team_info(opponent_team_number),
+        ) -> RoboCupGameControlData {
+            let mut message = game_control_data(24, opponent_team.teamNumber);
+            message.packetNumber = packet_number;
+            message.playersPerTeam = players_per_team;
+            message.gamePhase = game_phase;
+            message.state = state;
+            message.setPlay = set_play;
+            message.firstHalf = first_half;
+            message.kickingTeam = kicking_team;
+            message.secsRemaining = secs_remaining;
+            message.secondaryTime = secondary_time;
+            message.teams = [hulks_team, opponent_team];
+            for team in message.teams.iter_mut() {
+                for player in team.players[players_per_team as usize..].iter_mut() {
+                    *player = RobotInfo {
+                        penalty: PENALTY_NONE,
+                        secsTillUnpenalised: 0,
+                    };
+                }
+            }
+            message
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn valid_messages_round_trip(message in valid_game_control_data()) {
+            let bytes = write_game_control_data(&message);
+            let parsed = GameControllerStateMessage::try_from_bytes(&bytes, 24).unwrap();
+            prop_assert_eq!(parsed.to_bytes(message.packetNumber), bytes);
+        }
+
+        #[test]
+        fn accepted_buffers_are_parsed_again_after_serialization(
+            bytes in vec(any::<u8>(), MESSAGE_SIZE..=MESSAGE_SIZE),
+            packet_number in any::<u8>(),
+        ) {
+            if let Ok(message) = GameControllerStateMessage::try_from_bytes(&bytes, 24) {
+                let serialized = message.to_bytes(packet_number);
+                let parsed = GameControllerStateMessage::try_from_bytes(&serialized, 24).unwrap();
+                prop_assert_eq!(parsed.to_bytes(packet_number), serialized);
+            }
+        }
+
+        #[test]
+        fn arbitrary_bytes_do_not_panic(bytes in vec(any::<u8>(), 0..2 * MESSAGE_SIZE)) {
+            let _ = GameControllerStateMessage::try_from_bytes(&bytes, 24);
+        }
+
+        #[test]
+        fn mutated_messages_do_not_panic(
+            message in valid_game_control_data(),
+            mutations in vec((4..MESSAGE_SIZE, any::<u8>()), 1..8),
+        ) {
+            let mut bytes = write_game_control_data(&message);
+            for (index, value) in mutations {
+                bytes[index] = value;
+            }
+            if let Ok(message) = GameControllerStateMessage::try_from_bytes(&bytes, 24) {
+                prop_assert!(message.hulks_team.players.len() < MAX_NUM_PLAYERS as usize);
+                prop_assert!(message.hulks_team.penalty_shoots.len() < 16);
+            }
+        }
+
+        #[test]
+        fn truncated_and_extended_messages_are_rejected(
+            message in valid_game_control_data(),
+            length in 0..MESSAGE_SIZE,
+            extension in vec(any::<u8>(), 1..16),
+        ) {
+            let bytes = write_game_control_data(&message);
+            prop_assert!(GameControllerStateMessage::try_from_bytes(&bytes[..length], 24).is_err());
+
+            let mut extended = bytes;
+            extended.extend(extension);
+            prop_assert!(GameControllerStateMessage::try_from_bytes(&extended, 24).is_err());
+        }
+
+        #[test]
+        fn messages_with_wrong_header_or_version_are_rejected(
+            message in valid_game_control_data(),
+            index in 0_usize..5,
+            flipped_bits in 1_u8..=u8::MAX,
+        ) {
+            let mut bytes = write_game_control_data(&message);
+            bytes[index] ^= flipped_bits;
+            prop_assert!(GameControllerStateMessage::try_from_bytes(&bytes, 24).is_err());
+        }
+
+        #[test]
+        fn messages_without_own_team_are_rejected(
+            message in valid_game_control_data(),
+            team_number in any::<u8>(),
+        ) {
+            prop_assume!(team_number != 24 && team_number != message.teams[1].teamNumber);
+            let bytes = write_game_control_data(&message);
+            prop_assert!(GameControllerStateMessage::try_from_bytes(&bytes, team_number).is_err());
+        }
+    }
+}
@@ -1,19 +1,29 @@
 mod bindings;
+mod bit_packing;
 mod game_controller_return_message;
 mod game_controller_state_message;
+mod packet_capture;
+mod roles;
 mod spl_message;
+mod wire_format;
 
 use std::time::Duration;
 
 use nalgebra::Point2;
 use serde::{Deserialize, Serialize};
 
+pub use bindings::{GAMECONTROLLER_DATA_PORT, GAMECONTROLLER_RETURN_PORT};
 pub use game_controller_return_message::GameControllerReturnMessage;
 pub use game_controller_state_message::{
     GameControllerStateMessage, GamePhase, GameState, Half, Penalty, PenaltyShoot, Player, SetPlay,
     Team, TeamColor, TeamState,
 };
-pub use spl_message::SplMessage;
+pub use packet_capture::{read_pcap, CaptureRecord, CapturedPacket, DecodedMessage, PcapWriter};
+pub use roles::Role;
+pub use spl_message::{
+    SplMessage, TeamCommunicationPayload, MAXIMUM_NUMBER_OF_PAYLOAD_OBSTACLES,
+    TEAM_COMMUNICATION_PAYLOAD_VERSION,
+};
 
 #[derive(Clone, Copy, Debug, Deserialize, Serialize)]
 pub struct BallPosition {
@@ -21,8 +31,6 @@ pub struct BallPosition {
     pub age: Duration,
 }
 
-pub const HULKS_TEAM_NUMBER: u8 = 24;
-
 #[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize, Hash)]
 pub enum PlayerNumber {
     One,
@@ -0,0 +1,329 @@
+use std::{
+    convert::TryFrom,
+    io::Write,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    ops::RangeInclusive,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{bail, Context};
+use byteorder::{BigEndian, ByteOrder, LittleEndian, WriteBytesExt};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    GameControllerReturnMessage, GameControllerStateMessage, SplMessage, GAMECONTROLLER_DATA_PORT,
+    GAMECONTROLLER_RETURN_PORT,
+};
+
+const PCAP_MAGIC_MICROSECONDS: u32 = 0xa1b2c3d4;
+const PCAP_MAGIC_NANOSECONDS: u32 = 0xa1b23c4d;
+const LINKTYPE_ETHERNET: u32 = 1;
+const LINKTYPE_RAW: u32 = 101;
+const LINKTYPE_IPV4: u32 = 228;
+const ETHERNET_HEADER_LENGTH: usize = 14;
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const IPV4_HEADER_LENGTH: usize = 20;
+const UDP_HEADER_LENGTH: usize = 8;
+const UDP_PROTOCOL: u8 = 17;
+const SPL_MESSAGE_PORTS: RangeInclusive<u16> = 10000..=10255;
+
+/// UDP datagram sent or received by the robot
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CapturedPacket {
+    pub time: SystemTime,
+    pub source: SocketAddr,
+    pub destination: SocketAddr,
+    pub payload: Vec<u8>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum DecodedMessage {
+    GameControllerState(GameControllerStateMessage),
+    GameControllerReturn(GameControllerReturnMessage),
+    Spl(SplMessage),
+}
+
+impl CapturedPacket {
+    /// Decodes the payload depending on the destination port
+    pub fn decode(&self, hulks_team_number: u8) -> anyhow::Result<DecodedMessage> {
+        match self.destination.port() {
+            GAMECONTROLLER_DATA_PORT => Ok(DecodedMessage::GameControllerState(
+                GameControllerStateMessage::try_from_bytes(&self.payload, hulks_team_number)?,
+            )),
+            GAMECONTROLLER_RETURN_PORT => Ok(DecodedMessage::GameControllerReturn(
+                GameControllerReturnMessage::try_from(self.payload.as_slice())?,
+            )),
+            port if SPL_MESSAGE_PORTS.contains(&port) => Ok(DecodedMessage::Spl(
+                SplMessage::try_from(self.payload.as_slice())?,
+            )),
+            port => bail!("Unexpected destination port {port}"),
+        }
+    }
+}
+
+/// One line of a JSONL capture, the payload is kept if it could not be decoded
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CaptureRecord {
+    pub time: SystemTime,
+    pub source: SocketAddr,
+    pub destination: SocketAddr,
+    pub message: Option<DecodedMessage>,
+    pub error: Option<String>,
+    pub payload: Option<Vec<u8>>,
+}
+
+impl CaptureRecord {
+    pub fn new(packet: &CapturedPacket, hulks_team_number: u8) -> Self {
+        let (message, error, payload) = match packet.decode(hulks_team_number) {
+            Ok(message) => (Some(message), None, None),
+            Err(error) => (
+                None,
+                Some(format!("{error:#}")),
+                Some(packet.payload.clone()),
+            ),
+        };
+        Self {
+            time: packet.time,
+            source: packet.source,
+            destination: packet.destination,
+            message,
+            error,
+            payload,
+        }
+    }
+}
+
+/// Writes packets as raw IPv4 UDP datagrams into a pcap file, e.g. for Wireshark
+///
+/// IPv6 addresses are written as unspecified IPv4 addresses.
+pub struct PcapWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> PcapWriter<W> {
+    pub fn new(mut writer: W) -> anyhow::Result<Self> {
+        writer.write_u32::<LittleEndian>(PCAP_MAGIC_MICROSECONDS)?;
+        writer.write_u16::<LittleEndian>(2)?;
+        writer.write_u16::<LittleEndian>(4)?;
+        writer.write_i32::<LittleEndian>(0)?;
+        writer.write_u32::<LittleEndian>(0)?;
+        writer.write_u32::<LittleEndian>(u16::MAX as u32)?;
+        writer.write_u32::<LittleEndian>(LINKTYPE_RAW)?;
+        Ok(Self { writer })
+    }
+
+    pub fn write(&mut self, packet: &CapturedPacket) -> anyhow::Result<()> {
+        let datagram = encode_ipv4_udp_datagram(packet)?;
+        let since_epoch = packet
+            .time
+            .duration_since(UNIX_EPOCH)
+            .context("Packet time is before the UNIX epoch")?;
+        self.writer
+            .write_u32::<LittleEndian>(since_epoch.as_secs() as u32)?;
+        self.writer
+            .write_u32::<LittleEndian>(since_epoch.subsec_micros())?;
+        self.writer
+            .write_u32::<LittleEndian>(datagram.len() as u32)?;
+        self.writer
+            .write_u32::<LittleEndian>(datagram.len() as u32)?;
+        self.writer.write_all(&datagram)?;
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> anyhow::Result<()> {
+        Ok(self.writer.flush()?)
+    }
+}
+
+fn to_ipv4(address: IpAddr) -> Ipv4Addr {
+    match address {
+        IpAddr::V4(address) => address,
+        IpAddr::V6(address) => address.to_ipv4_mapped().unwrap_or(Ipv4Addr::UNSPECIFIED),
+    }
+}
+
+fn encode_ipv4_udp_datagram(packet: &CapturedPacket) -> anyhow::Result<Vec<u8>> {
+    let total_length = IPV4_HEADER_LENGTH + UDP_HEADER_LENGTH + packet.payload.len();
+    let total_length =
+        u16::try_from(total_length).context("Payload does not fit into an IPv4 datagram")?;
+    let mut datagram = vec![0; total_length as usize];
+    {
+        let header = &mut datagram[..IPV4_HEADER_LENGTH];
+        header[0] = 0x45;
+        BigEndian::write_u16(&mut header[2..4], total_length);
+        header[8] = 64;
+        header[9] = UDP_PROTOCOL;
+        header[12..16].copy_from_slice(&to_ipv4(packet.source.ip()).octets());
+        header[16..20].copy_from_slice(&to_ipv4(packet.destination.ip()).octets());
+        let checksum = ipv4_header_checksum(header);
+        BigEndian::write_u16(&mut header[10..12], checksum);
+    }
+    {
+        let header = &mut datagram[IPV4_HEADER_LENGTH..IPV4_HEADER_LENGTH + UDP_HEADER_LENGTH];
+        BigEndian::write_u16(&mut header[0..2], packet.source.port());
+        BigEndian::write_u16(&mut header[2..4], packet.destination.port());
+        BigEndian::write_u16(
+            &mut header[4..6],
+            (UDP_HEADER_LENGTH + packet.payload.len()) as u16,
+        );
+    }
+    datagram[IPV4_HEADER_LENGTH + UDP_HEADER_LENGTH..].copy_from_slice(&packet.payload);
+    Ok(datagram)
+}
+
+fn ipv4_header_checksum(header: &[u8]) -> u16 {
+    let mut sum = header
+        .chunks(2)
+        .map(|word| BigEndian::read_u16(word) as u32)
+        .sum::<u32>();
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+/// Reads all UDP datagrams over IPv4 from a pcap file with raw IP or Ethernet link type, other
+/// packets are skipped
+pub fn read_pcap(bytes: &[u8]) -> anyhow::Result<Vec<CapturedPacket>> {
+    if bytes.len() < 24 {
+        bail!("File is too short for a pcap header");
+    }
+    let (read_u32, subsecond_unit): (fn(&[u8]) -> u32, Duration) =
+        match LittleEndian::read_u32(&bytes[0..4]) {
+            PCAP_MAGIC_MICROSECONDS => (LittleEndian::read_u32, Duration::from_micros(1)),
+            PCAP_MAGIC_NANOSECONDS => (LittleEndian::read_u32, Duration::from_nanos(1)),
+            _ => match BigEndian::read_u32(&bytes[0..4]) {
+                PCAP_MAGIC_MICROSECONDS => (BigEndian::read_u32, Duration::from_micros(1)),
+                PCAP_MAGIC_NANOSECONDS => (BigEndian::read_u32, Duration::from_nanos(1)),
+                magic => bail!("Unexpected pcap magic number {magic:#x}"),
+            },
+        };
+    let link_type = read_u32(&bytes[20..24]);
+    let link_header_length = match link_type {
+        LINKTYPE_RAW | LINKTYPE_IPV4 => 0,
+        LINKTYPE_ETHERNET => ETHERNET_HEADER_LENGTH,
+        _ => bail!("Unsupported link type {link_type}"),
+    };
+
+    let mut packets = Vec::new();
+    let mut offset = 24;
+    while offset < bytes.len() {
+        if offset + 16 > bytes.len() {
+            bail!("Truncated packet header at byte {offset}");
+        }
+        let seconds = read_u32(&bytes[offset..offset + 4]);
+        let subseconds = read_u32(&bytes[offset + 4..offset + 8]);
+        let captured_length = read_u32(&bytes[offset + 8..offset + 12]) as usize;
+        let data = bytes
+            .get(offset + 16..offset + 16 + captured_length)
+            .with_context(|| format!("Truncated packet data at byte {offset}"))?;
+        offset += 16 + captured_length;
+
+        if link_type == LINKTYPE_ETHERNET
+            && (data.len() < ETHERNET_HEADER_LENGTH
+                || BigEndian::read_u16(&data[12..14]) != ETHERTYPE_IPV4)
+        {
+            continue;
+        }
+        let time = UNIX_EPOCH + Duration::from_secs(seconds as u64) + subsecond_unit * subseconds;
+        if let Some(packet) = decode_ipv4_udp_datagram(&data[link_header_length..], time) {
+            packets.push(packet);
+        }
+    }
+    Ok(packets)
+}
+
+fn decode_ipv4_udp_datagram(datagram: &[u8], time: SystemTime) -> Option<CapturedPacket> {
+    if datagram.len() < IPV4_HEADER_LENGTH || datagram[0] >> 4 != 4 {
+        return None;
+    }
+    let ip_header_length = (datagram[0] & 0x0f) as usize * 4;
+    if datagram[9] != UDP_PROTOCOL || datagram.len() < ip_header_length + UDP_HEADER_LENGTH {
+        return None;
+    }
+    let source_address = Ipv4Addr::new(datagram[12], datagram[13], datagram[14], datagram[15]);
+    let destination_address = Ipv4Addr::new(datagram[16], datagram[17], datagram[18], datagram[19]);
+    let udp = &datagram[ip_header_length..];
+    let udp_length = BigEndian::read_u16(&udp[4..6]) as usize;
+    let payload = udp.get(UDP_HEADER_LENGTH..udp_length)?;
+    Some(CapturedPacket {
+        time,
+        source: SocketAddr::new(source_address.into(), BigEndian::read_u16(&udp[0..2])),
+        destination: SocketAddr::new(destination_address.into(), BigEndian::read_u16(&udp[2..4])),
+        payload: payload.to_vec(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use nalgebra::Isometry2;
+
+    use crate::PlayerNumber;
+
+    use super::*;
+
+    fn return_message_packet() -> CapturedPacket {
+        let message = GameControllerReturnMessage {
+            team_number: 24,
+            player_number: PlayerNumber::Three,
+            fallen: false,
+            robot_to_field: Isometry2::new(nalgebra::vector![1.0, -2.0], 0.5),
+            ball_position: None,
+        };
+        CapturedPacket {
+            time: UNIX_EPOCH + Duration::from_micros(1_650_000_000_123_456),
+            source: "10.0.24.23:3838".parse().unwrap(),
+            destination: "10.0.0.1:3939".parse().unwrap(),
+            payload: message.into(),
+        }
+    }
+
+    #[test]
+    fn written_packets_are_read_back() {
+        let packets = vec![
+            return_message_packet(),
+            CapturedPacket {
+                time: UNIX_EPOCH + Duration::from_secs(1_650_000_001),
+                source: "10.0.0.1:4000".parse().unwrap(),
+                destination: "255.255.255.255:3838".parse().unwrap(),
+                payload: vec![1, 2, 3],
+            },
+        ];
+        let mut writer = PcapWriter::new(Vec::new()).unwrap();
+        for packet in &packets {
+            writer.write(packet).unwrap();
+        }
+        let bytes = writer.writer;
+
+        assert_eq!(read_pcap(&bytes).unwrap(), packets);
+    }
+
+    #[test]
+    fn ipv4_header_checksum_verifies() {
+        let datagram = encode_ipv4_udp_datagram(&return_message_packet()).unwrap();
+        assert_eq!(ipv4_header_checksum(&datagram[..IPV4_HEADER_LENGTH]), 0);
+    }
+
+    #[test]
+    fn packets_are_decoded_by_destination_port() {
+        let packet = return_message_packet();
+        match packet.decode(24).unwrap() {
+            DecodedMessage::GameControllerReturn(message) => {
+                assert_eq!(message.player_number, PlayerNumber::Three)
+            }
+            message => panic!("Unexpected message {message:?}"),
+        }
+
+        let record = CaptureRecord::new(
+            &CapturedPacket {
+                destination: "255.255.255.255:10024".parse().unwrap(),
+                payload: vec![0; 4],
+                ..packet
+            },
+            24,
+        );
+        assert!(record.message.is_none());
+        assert!(record.error.is_some());
+        assert_eq!(record.payload, Some(vec![0; 4]));
+    }
+}
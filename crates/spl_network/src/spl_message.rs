@@ -1,14 +1,12 @@
 use std::{
     convert::{TryFrom, TryInto},
-    mem::size_of,
-    ptr::read,
-    slice::from_raw_parts,
-    time::Duration,
+    f32::consts::PI,
+    ops::Range,
 };
 
 use anyhow::bail;
-use byteorder::{ByteOrder, NativeEndian};
-use nalgebra::{point, vector, Isometry2};
+use log::warn;
+use nalgebra::{matrix, point, vector, Isometry2, Matrix3, Point2, Vector2};
 use serde::{Deserialize, Serialize};
 
 use crate::{
@@ -16,34 +14,263 @@ use crate::{
         SPLStandardMessage, SPL_STANDARD_MESSAGE_DATA_SIZE, SPL_STANDARD_MESSAGE_STRUCT_HEADER,
         SPL_STANDARD_MESSAGE_STRUCT_VERSION,
     },
-    BallPosition, PlayerNumber, HULKS_TEAM_NUMBER,
+    bit_packing::{BitReader, BitWriter},
+    wire_format::{
+        duration_from_seconds, header_matches, read_finite_f32s, WireReader, WireWriter,
+    },
+    BallPosition, PlayerNumber, Role,
 };
 
-#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct SplMessage {
+    pub team_number: u8,
     pub player_number: PlayerNumber,
     pub fallen: bool,
     pub robot_to_field: Isometry2<f32>,
     pub ball_position: Option<BallPosition>,
+    /// Additional team communication transmitted in the user data, `None` if the message has none
+    /// or it cannot be decoded, e.g. because it was sent by another team or payload version
+    pub payload: Option<TeamCommunicationPayload>,
+}
+
+/// Incremented whenever the bit layout of the [`TeamCommunicationPayload`] changes
+pub const TEAM_COMMUNICATION_PAYLOAD_VERSION: u8 = 1;
+pub const MAXIMUM_NUMBER_OF_PAYLOAD_OBSTACLES: usize = 7;
+
+const POSITION_RANGE: Range<f32> = -8.192..8.192;
+const POSITION_BITS: usize = 12;
+const ANGLE_BITS: usize = 8;
+const POSITION_STANDARD_DEVIATION_RANGE: Range<f32> = 0.0..2.55;
+const STANDARD_DEVIATION_BITS: usize = 8;
+const CORRELATION_BITS: usize = 7;
+const BALL_VELOCITY_RANGE: Range<f32> = -10.24..10.24;
+const BALL_VELOCITY_BITS: usize = 11;
+
+/// Bit-packed team communication in the user data of the SPL standard message
+///
+/// Values are quantized: positions to 4 mm, angles to 2.5°, standard deviations to 1 cm and ball
+/// velocities to 1 cm/s.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct TeamCommunicationPayload {
+    pub role: Role,
+    /// Pose the robot is walking to in field coordinates
+    pub target_pose: Option<Isometry2<f32>>,
+    /// Teammate the robot is about to pass the ball to
+    pub pass_target: Option<PlayerNumber>,
+    pub robot_to_field_covariance: Matrix3<f32>,
+    /// Closest obstacles in robot coordinates, at most [`MAXIMUM_NUMBER_OF_PAYLOAD_OBSTACLES`]
+    pub obstacles: Vec<Point2<f32>>,
+    /// Ball velocity in robot coordinates
+    pub ball_velocity: Vector2<f32>,
+}
+
+impl TeamCommunicationPayload {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut writer = BitWriter::default();
+        writer.write_bits(TEAM_COMMUNICATION_PAYLOAD_VERSION as u32, 8);
+        writer.write_bits(role_to_index(self.role), 3);
+
+        writer.write_bool(self.target_pose.is_some());
+        if let Some(target_pose) = self.target_pose {
+            write_position(&mut writer, target_pose.translation.vector);
+            writer.write_quantized(target_pose.rotation.angle(), -PI..PI, ANGLE_BITS);
+        }
+        writer.write_bits(
+            self.pass_target
+                .map_or(0, |player_number| player_number_to_index(player_number) + 1),
+            3,
+        );
+
+        let covariance = &self.robot_to_field_covariance;
+        let standard_deviations = covariance
+            .diagonal()
+            .map(|variance| variance.max(0.0).sqrt());
+        writer.write_quantized(
+            standard_deviations.x,
+            POSITION_STANDARD_DEVIATION_RANGE,
+            STANDARD_DEVIATION_BITS,
+        );
+        writer.write_quantized(
+            standard_deviations.y,
+            POSITION_STANDARD_DEVIATION_RANGE,
+            STANDARD_DEVIATION_BITS,
+        );
+        writer.write_quantized(standard_deviations.z, 0.0..PI, STANDARD_DEVIATION_BITS);
+        for (row, column) in [(0, 1), (0, 2), (1, 2)] {
+            let scale = standard_deviations[row] * standard_deviations[column];
+            let correlation = if scale > f32::EPSILON {
+                covariance[(row, column)] / scale
+            } else {
+                0.0
+            };
+            writer.write_quantized(correlation, -1.0..1.0, CORRELATION_BITS);
+        }
+
+        let number_of_obstacles = self
+            .obstacles
+            .len()
+            .min(MAXIMUM_NUMBER_OF_PAYLOAD_OBSTACLES);
+        writer.write_bits(number_of_obstacles as u32, 3);
+        for obstacle in &self.obstacles[..number_of_obstacles] {
+            write_position(&mut writer, obstacle.coords);
+        }
+
+        writer.write_quantized(
+            self.ball_velocity.x,
+            BALL_VELOCITY_RANGE,
+            BALL_VELOCITY_BITS,
+        );
+        writer.write_quantized(
+            self.ball_velocity.y,
+            BALL_VELOCITY_RANGE,
+            BALL_VELOCITY_BITS,
+        );
+        writer.into_bytes()
+    }
+
+    pub fn decode(bytes: &[u8]) -> anyhow::Result<Self> {
+        let mut reader = BitReader::new(bytes);
+        let version = reader.read_bits(8)? as u8;
+        if version != TEAM_COMMUNICATION_PAYLOAD_VERSION {
+            bail!("Unexpected payload version {version} != {TEAM_COMMUNICATION_PAYLOAD_VERSION}");
+        }
+        let role = index_to_role(reader.read_bits(3)?)?;
+
+        let target_pose = if reader.read_bool()? {
+            let translation = read_position(&mut reader)?;
+            let angle = reader.read_quantized(-PI..PI, ANGLE_BITS)?;
+            Some(Isometry2::new(translation, angle))
+        } else {
+            None
+        };
+        let pass_target = match reader.read_bits(3)? {
+            0 => None,
+            index => Some(index_to_player_number(index - 1)?),
+        };
+
+        let standard_deviations = vector![
+            reader.read_quantized(POSITION_STANDARD_DEVIATION_RANGE, STANDARD_DEVIATION_BITS)?,
+            reader.read_quantized(POSITION_STANDARD_DEVIATION_RANGE, STANDARD_DEVIATION_BITS)?,
+            reader.read_quantized(0.0..PI, STANDARD_DEVIATION_BITS)?
+        ];
+        let correlation_xy = reader.read_quantized(-1.0..1.0, CORRELATION_BITS)?;
+        let correlation_xangle = reader.read_quantized(-1.0..1.0, CORRELATION_BITS)?;
+        let correlation_yangle = reader.read_quantized(-1.0..1.0, CORRELATION_BITS)?;
+        let correlations = matrix![
+            1.0, correlation_xy, correlation_xangle;
+            correlation_xy, 1.0, correlation_yangle;
+            correlation_xangle, correlation_yangle, 1.0
+        ];
+        let robot_to_field_covariance = Matrix3::from_diagonal(&standard_deviations)
+            * correlations
+            * Matrix3::from_diagonal(&standard_deviations);
+
+        let number_of_obstacles = reader.read_bits(3)?;
+        let obstacles = (0..number_of_obstacles)
+            .map(|_| Ok(Point2::from(read_position(&mut reader)?)))
+            .collect::<anyhow::Result<_>>()?;
+
+        let ball_velocity = vector![
+            reader.read_quantized(BALL_VELOCITY_RANGE, BALL_VELOCITY_BITS)?,
+            reader.read_quantized(BALL_VELOCITY_RANGE, BALL_VELOCITY_BITS)?
+        ];
+        Ok(Self {
+            role,
+            target_pose,
+            pass_target,
+            robot_to_field_covariance,
+            obstacles,
+            ball_velocity,
+        })
+    }
+}
+
+fn write_position(writer: &mut BitWriter, position: Vector2<f32>) {
+    writer.write_quantized(position.x, POSITION_RANGE, POSITION_BITS);
+    writer.write_quantized(position.y, POSITION_RANGE, POSITION_BITS);
+}
+
+fn read_position(reader: &mut BitReader) -> anyhow::Result<Vector2<f32>> {
+    Ok(vector![
+        reader.read_quantized(POSITION_RANGE, POSITION_BITS)?,
+        reader.read_quantized(POSITION_RANGE, POSITION_BITS)?
+    ])
+}
+
+fn role_to_index(role: Role) -> u32 {
+    match role {
+        Role::DefenderLeft => 0,
+        Role::DefenderRight => 1,
+        Role::Keeper => 2,
+        Role::Loser => 3,
+        Role::ReplacementKeeper => 4,
+        Role::Searcher => 5,
+        Role::Striker => 6,
+        Role::StrikerSupporter => 7,
+    }
+}
+
+fn index_to_role(index: u32) -> anyhow::Result<Role> {
+    Ok(match index {
+        0 => Role::DefenderLeft,
+        1 => Role::DefenderRight,
+        2 => Role::Keeper,
+        3 => Role::Loser,
+        4 => Role::ReplacementKeeper,
+        5 => Role::Searcher,
+        6 => Role::Striker,
+        7 => Role::StrikerSupporter,
+        _ => bail!("Unexpected role {index}"),
+    })
+}
+
+fn player_number_to_index(player_number: PlayerNumber) -> u32 {
+    match player_number {
+        PlayerNumber::One => 0,
+        PlayerNumber::Two => 1,
+        PlayerNumber::Three => 2,
+        PlayerNumber::Four => 3,
+        PlayerNumber::Five => 4,
+    }
+}
+
+fn index_to_player_number(index: u32) -> anyhow::Result<PlayerNumber> {
+    Ok(match index {
+        0 => PlayerNumber::One,
+        1 => PlayerNumber::Two,
+        2 => PlayerNumber::Three,
+        3 => PlayerNumber::Four,
+        4 => PlayerNumber::Five,
+        _ => bail!("Unexpected player number {}", index + 1),
+    })
 }
 
 impl TryFrom<&[u8]> for SplMessage {
     type Error = anyhow::Error;
 
     fn try_from(buffer: &[u8]) -> anyhow::Result<Self> {
-        let buffer_offset_of_user_data =
-            size_of::<SPLStandardMessage>() - (SPL_STANDARD_MESSAGE_DATA_SIZE as usize);
-        if buffer.len() < buffer_offset_of_user_data {
-            bail!("Buffer too small");
-        }
-        let number_of_bytes_of_user_data = NativeEndian::read_u16(
-            &buffer[buffer_offset_of_user_data - 2..buffer_offset_of_user_data],
-        );
-        let additional_number_of_bytes_in_message = buffer.len() - buffer_offset_of_user_data;
-        if number_of_bytes_of_user_data as usize != additional_number_of_bytes_in_message {
-            bail!("Buffer size mismatch: numOfDataBytes != length of message remainder");
+        let mut reader = WireReader::new(buffer);
+        let mut message = SPLStandardMessage {
+            header: reader.read_header(SPL_STANDARD_MESSAGE_STRUCT_HEADER)?,
+            version: reader.read_version(SPL_STANDARD_MESSAGE_STRUCT_VERSION)?,
+            playerNum: reader.read_u8()?,
+            teamNum: reader.read_u8()?,
+            fallen: reader.read_u8()?,
+            pose: read_finite_f32s(&mut reader)?,
+            ballAge: read_finite_f32s::<1>(&mut reader)?[0],
+            ball: read_finite_f32s(&mut reader)?,
+            numOfDataBytes: reader.read_u16()?,
+            data: [0; SPL_STANDARD_MESSAGE_DATA_SIZE as usize],
+        };
+        let number_of_data_bytes = message.numOfDataBytes as usize;
+        if number_of_data_bytes > message.data.len() || number_of_data_bytes != reader.remaining() {
+            bail!(
+                "Buffer size mismatch: numOfDataBytes {number_of_data_bytes} != length of message remainder {}",
+                reader.remaining()
+            );
         }
-        let message = unsafe { read(buffer.as_ptr() as *const SPLStandardMessage) };
+        message.data[..number_of_data_bytes]
+            .copy_from_slice(reader.read_bytes(number_of_data_bytes)?);
         message.try_into()
     }
 }
@@ -52,20 +279,14 @@ impl TryFrom<SPLStandardMessage> for SplMessage {
     type Error = anyhow::Error;
 
     fn try_from(message: SPLStandardMessage) -> anyhow::Result<Self> {
-        if message.header[0] != SPL_STANDARD_MESSAGE_STRUCT_HEADER[0] as i8
-            && message.header[1] != SPL_STANDARD_MESSAGE_STRUCT_HEADER[1] as i8
-            && message.header[2] != SPL_STANDARD_MESSAGE_STRUCT_HEADER[2] as i8
-            && message.header[3] != SPL_STANDARD_MESSAGE_STRUCT_HEADER[3] as i8
-        {
+        if !header_matches(&message.header, SPL_STANDARD_MESSAGE_STRUCT_HEADER) {
             bail!("Unexpected header");
         }
         if message.version != SPL_STANDARD_MESSAGE_STRUCT_VERSION {
             bail!("Unexpected version");
         }
-        if message.teamNum != HULKS_TEAM_NUMBER {
-            bail!("Unexpected team number != {}", HULKS_TEAM_NUMBER);
-        }
         Ok(Self {
+            team_number: message.teamNum,
             player_number: match message.playerNum {
                 1 => PlayerNumber::One,
                 2 => PlayerNumber::Two,
@@ -88,9 +309,22 @@ impl TryFrom<SPLStandardMessage> for SplMessage {
             } else {
                 Some(BallPosition {
                     relative_position: point![message.ball[0] / 1000.0, message.ball[1] / 1000.0],
-                    age: Duration::from_secs_f32(message.ballAge),
+                    age: duration_from_seconds(message.ballAge)?,
                 })
             },
+            payload: match message.numOfDataBytes as usize {
+                0 => None,
+                number_of_bytes if number_of_bytes <= message.data.len() => {
+                    match TeamCommunicationPayload::decode(&message.data[..number_of_bytes]) {
+                        Ok(payload) => Some(payload),
+                        Err(error) => {
+                            warn!("Ignoring team communication payload: {error:#}");
+                            None
+                        }
+                    }
+                }
+                number_of_bytes => bail!("Unexpected number of data bytes {number_of_bytes}"),
+            },
         })
     }
 }
@@ -98,14 +332,23 @@ impl TryFrom<SPLStandardMessage> for SplMessage {
 impl From<SplMessage> for Vec<u8> {
     fn from(message: SplMessage) -> Self {
         let message: SPLStandardMessage = message.into();
-        unsafe {
-            from_raw_parts(
-                &message as *const SPLStandardMessage as *const u8,
-                size_of::<SPLStandardMessage>() - (SPL_STANDARD_MESSAGE_DATA_SIZE as usize)
-                    + (message.numOfDataBytes as usize),
-            )
+        let mut writer = WireWriter::default();
+        writer.write_header(&message.header);
+        writer.write_u8(message.version);
+        writer.write_u8(message.playerNum);
+        writer.write_u8(message.teamNum);
+        writer.write_u8(message.fallen);
+        for value in message
+            .pose
+            .iter()
+            .chain([&message.ballAge])
+            .chain(&message.ball)
+        {
+            writer.write_f32(*value);
         }
-        .to_vec()
+        writer.write_u16(message.numOfDataBytes);
+        writer.write_bytes(&message.data[..message.numOfDataBytes as usize]);
+        writer.into_bytes()
     }
 }
 
@@ -121,6 +364,15 @@ impl From<SplMessage> for SPLStandardMessage {
             ),
             None => ([0.0; 2], -1.0),
         };
+        let mut data = [0; SPL_STANDARD_MESSAGE_DATA_SIZE as usize];
+        let number_of_data_bytes = match &message.payload {
+            Some(payload) => {
+                let bytes = payload.encode();
+                data[..bytes.len()].copy_from_slice(&bytes);
+                bytes.len()
+            }
+            None => 0,
+        };
         Self {
             header: [
                 SPL_STANDARD_MESSAGE_STRUCT_HEADER[0] as i8,
@@ -136,7 +388,7 @@ impl From<SplMessage> for SPLStandardMessage {
                 PlayerNumber::Four => 4,
                 PlayerNumber::Five => 5,
             },
-            teamNum: HULKS_TEAM_NUMBER,
+            teamNum: message.team_number,
             fallen: u8::from(message.fallen),
             pose: [
                 message.robot_to_field.translation.vector.x * 1000.0,
@@ -145,8 +397,8 @@ impl From<SplMessage> for SPLStandardMessage {
             ],
             ballAge: ball_age,
             ball: ball_position,
-            numOfDataBytes: 0,
-            data: [0; SPL_STANDARD_MESSAGE_DATA_SIZE as usize],
+            numOfDataBytes: number_of_data_bytes as u16,
+            data,
         }
     }
 }
@@ -155,18 +407,22 @@ impl From<SplMessage> for SPLStandardMessage {
 mod test {
     use std::f32::consts::{FRAC_PI_2, FRAC_PI_4, SQRT_2};
 
+    use std::time::Duration;
+
     use approx::assert_relative_eq;
-    use nalgebra::vector;
+    use proptest::{collection::vec, option, prelude::*, sample::Index};
 
     use super::*;
 
     #[test]
     fn zero_isometry() {
         let input_message = SplMessage {
+            team_number: 24,
             player_number: PlayerNumber::One,
             fallen: false,
             robot_to_field: Isometry2::default(),
             ball_position: None,
+            payload: None,
         };
         let output_message: SPLStandardMessage = input_message.into();
 
@@ -182,10 +438,12 @@ mod test {
     #[test]
     fn one_to_the_left_isometry() {
         let input_message = SplMessage {
+            team_number: 24,
             player_number: PlayerNumber::One,
             fallen: false,
             robot_to_field: Isometry2::new(vector![0.0, 1.0], FRAC_PI_2),
             ball_position: None,
+            payload: None,
         };
         let output_message: SPLStandardMessage = input_message.into();
 
@@ -205,12 +463,14 @@ mod test {
     #[test]
     fn one_schräg_to_the_top_right_isometry() {
         let input_message = SplMessage {
+            team_number: 24,
             player_number: PlayerNumber::One,
             fallen: false,
             robot_to_field: Isometry2::new(vector![1.0, 1.0], FRAC_PI_4),
             ball_position: None,
+            payload: None,
         };
-        let output_message: SPLStandardMessage = input_message.into();
+        let output_message: SPLStandardMessage = input_message.clone().into();
 
         assert_relative_eq!(
             input_message.robot_to_field * point![1.0 / SQRT_2, -1.0 / SQRT_2],
@@ -235,4 +495,292 @@ mod test {
             epsilon = 0.001
         );
     }
+
+    fn payload() -> TeamCommunicationPayload {
+        TeamCommunicationPayload {
+            role: Role::StrikerSupporter,
+            target_pose: Some(Isometry2::new(vector![-2.5, 1.75], -FRAC_PI_2)),
+            pass_target: Some(PlayerNumber::Four),
+            robot_to_field_covariance: matrix![
+                0.04, 0.01, 0.0;
+                0.01, 0.09, -0.003;
+                0.0, -0.003, 0.01
+            ],
+            obstacles: vec![point![1.0, -0.5], point![-3.2, 4.1]],
+            ball_velocity: vector![1.5, -0.25],
+        }
+    }
+
+    #[test]
+    fn payload_round_trip() {
+        let payload = payload();
+        let bytes = payload.encode();
+        assert!(bytes.len() <= 24, "payload has {} bytes", bytes.len());
+
+        let decoded = TeamCommunicationPayload::decode(&bytes).unwrap();
+        assert_eq!(decoded.role, payload.role);
+        assert_eq!(decoded.pass_target, payload.pass_target);
+        assert_relative_eq!(
+            decoded.target_pose.unwrap(),
+            payload.target_pose.unwrap(),
+            epsilon = 0.03
+        );
+        assert_relative_eq!(
+            decoded.robot_to_field_covariance,
+            payload.robot_to_field_covariance,
+            epsilon = 0.003
+        );
+        assert_eq!(decoded.obstacles.len(), 2);
+        for (decoded, original) in decoded.obstacles.iter().zip(payload.obstacles.iter()) {
+            assert_relative_eq!(decoded, original, epsilon = 0.003);
+        }
+        assert_relative_eq!(decoded.ball_velocity, payload.ball_velocity, epsilon = 0.01);
+    }
+
+    #[test]
+    fn empty_payload_round_trip() {
+        let payload = TeamCommunicationPayload {
+            obstacles: vec![point![0.5, 0.5]; 10],
+            ..Default::default()
+        };
+        let decoded = TeamCommunicationPayload::decode(&payload.encode()).unwrap();
+
+        assert_eq!(decoded.role, Role::default());
+        assert_eq!(decoded.target_pose, None);
+        assert_eq!(decoded.pass_target, None);
+        assert_relative_eq!(decoded.robot_to_field_covariance, Matrix3::zeros());
+        assert_eq!(decoded.obstacles.len(), MAXIMUM_NUMBER_OF_PAYLOAD_OBSTACLES);
+    }
+
+    #[test]
+    fn payload_with_other_version_is_rejected() {
+        let mut bytes = payload().encode();
+        bytes[0] = TEAM_COMMUNICATION_PAYLOAD_VERSION + 1;
+        assert!(TeamCommunicationPayload::decode(&bytes).is_err());
+        assert!(TeamCommunicationPayload::decode(&bytes[..3]).is_err());
+    }
+
+    #[test]
+    fn payload_is_transmitted_in_user_data() {
+        let input_message = SplMessage {
+            team_number: 24,
+            player_number: PlayerNumber::Three,
+            fallen: false,
+            robot_to_field: Isometry2::new(vector![1.0, 1.0], FRAC_PI_4),
+            ball_position: None,
+            payload: Some(payload()),
+        };
+        let bytes: Vec<u8> = input_message.into();
+        let output_message = SplMessage::try_from(bytes.as_slice()).unwrap();
+
+        let payload = output_message.payload.unwrap();
+        assert_eq!(payload.role, Role::StrikerSupporter);
+        assert_eq!(payload.pass_target, Some(PlayerNumber::Four));
+    }
+
+    #[test]
+    fn message_with_foreign_payload_keeps_standard_fields() {
+        let mut message: SPLStandardMessage = SplMessage {
+            team_number: 24,
+            player_number: PlayerNumber::Four,
+            fallen: false,
+            robot_to_field: Isometry2::new(vector![-1.0, 2.0], FRAC_PI_2),
+            ball_position: Some(BallPosition {
+                relative_position: point![0.5, -0.25],
+                age: Duration::from_millis(1500),
+            }),
+            payload: None,
+        }
+        .into();
+        let foreign_user_data = [
+            TEAM_COMMUNICATION_PAYLOAD_VERSION + 1,
+            0xde,
+            0xad,
+            0xbe,
+            0xef,
+        ];
+        message.data[..foreign_user_data.len()].copy_from_slice(&foreign_user_data);
+        message.numOfDataBytes = foreign_user_data.len() as u16;
+
+        let decoded = SplMessage::try_from(message).unwrap();
+
+        assert_eq!(decoded.team_number, 24);
+        assert_eq!(decoded.player_number, PlayerNumber::Four);
+        assert!(!decoded.fallen);
+        assert_relative_eq!(
+            decoded.robot_to_field,
+            Isometry2::new(vector![-1.0, 2.0], FRAC_PI_2),
+            epsilon = 0.001
+        );
+        assert_relative_eq!(
+            decoded.ball_position.unwrap().relative_position,
+            point![0.5, -0.25],
+            epsilon = 0.001
+        );
+        assert!(decoded.payload.is_none());
+    }
+
+    fn valid_message_bytes() -> Vec<u8> {
+        SplMessage {
+            team_number: 24,
+            player_number: PlayerNumber::Two,
+            fallen: true,
+            robot_to_field: Isometry2::new(vector![-2.0, 1.5], FRAC_PI_2),
+            ball_position: Some(BallPosition {
+                relative_position: point![0.5, -0.25],
+                age: Duration::from_millis(1500),
+            }),
+            payload: Some(payload()),
+        }
+        .into()
+    }
+
+    #[test]
+    fn header_with_any_wrong_byte_is_rejected() {
+        for index in 0..4 {
+            let mut bytes = valid_message_bytes();
+            bytes[index] ^= 1;
+            assert!(SplMessage::try_from(bytes.as_slice()).is_err());
+        }
+        assert!(SplMessage::try_from(valid_message_bytes().as_slice()).is_ok());
+    }
+
+    #[test]
+    fn negative_or_non_finite_ball_age_is_rejected() {
+        let ball_age_offset = 20;
+        for ball_age in [-2.0_f32, f32::NAN, f32::INFINITY, f32::MAX] {
+            let mut bytes = valid_message_bytes();
+            bytes[ball_age_offset..ball_age_offset + 4].copy_from_slice(&ball_age.to_le_bytes());
+            assert!(SplMessage::try_from(bytes.as_slice()).is_err());
+        }
+    }
+
+    #[test]
+    fn truncated_and_extended_buffers_are_rejected() {
+        let bytes = valid_message_bytes();
+        for length in 0..bytes.len() {
+            assert!(SplMessage::try_from(&bytes[..length]).is_err());
+        }
+        let mut extended = bytes;
+        extended.push(0);
+        assert!(SplMessage::try_from(extended.as_slice()).is_err());
+    }
+
+    fn player_number() -> impl Strategy<Value = PlayerNumber> {
+        prop_oneof![
+            Just(PlayerNumber::One),
+            Just(PlayerNumber::Two),
+            Just(PlayerNumber::Three),
+            Just(PlayerNumber::Four),
+            Just(PlayerNumber::Five),
+        ]
+    }
+
+    prop_compose! {
+        fn spl_message()(
+            team_number in any::<u8>(),
+            player_number in player_number(),
+            fallen in any::<bool>(),
+            translation in (-5.0_f32..5.0, -3.5_f32..3.5),
+            rotation in -PI..PI,
+            ball_position in option::of((-9.0_f32..9.0, -6.0_f32..6.0, 0_u64..60_000)),
+            payload in option::of(Just(payload())),
+        ) -> SplMessage {
+            SplMessage {
+                team_number,
+                player_number,
+                fallen,
+                robot_to_field: Isometry2::new(vector![translation.0, translation.1], rotation),
+                ball_position: ball_position.map(|(x, y, age)| BallPosition {
+                    relative_position: point![x, y],
+                    age: Duration::from_millis(age),
+                }),
+                payload,
+            }
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn messages_round_trip(input_message in spl_message()) {
+            let bytes: Vec<u8> = input_message.clone().into();
+            let output_message = SplMessage::try_from(bytes.as_slice()).unwrap();
+
+            prop_assert_eq!(output_message.team_number, input_message.team_number);
+            prop_assert_eq!(output_message.player_number, input_message.player_number);
+            prop_assert_eq!(output_message.fallen, input_message.fallen);
+            assert_relative_eq!(
+                output_message.robot_to_field,
+                input_message.robot_to_field,
+                epsilon = 1e-4
+            );
+            prop_assert_eq!(
+                output_message.ball_position.is_some(),
+                input_message.ball_position.is_some()
+            );
+            if let (Some(output_ball), Some(input_ball)) =
+                (output_message.ball_position, input_message.ball_position)
+            {
+                assert_relative_eq!(
+                    output_ball.relative_position,
+                    input_ball.relative_position,
+                    epsilon = 1e-4
+                );
+                prop_assert!(
+                    (output_ball.age.as_secs_f32() - input_ball.age.as_secs_f32()).abs() < 1e-3
+                );
+            }
+            prop_assert_eq!(
+                output_message.payload.is_some(),
+                input_message.payload.is_some()
+            );
+        }
+
+        #[test]
+        fn accepted_buffers_are_encoded_again_unchanged(bytes in vec(any::<u8>(), 0..600)) {
+            if let Ok(message) = SplMessage::try_from(bytes.as_slice()) {
+                let encoded: Vec<u8> = message.into();
+                prop_assert!(SplMessage::try_from(encoded.as_slice()).is_ok());
+            }
+        }
+
+        #[test]
+        fn mutated_messages_do_not_panic(
+            input_message in spl_message(),
+            mutations in vec((any::<Index>(), any::<u8>()), 1..8),
+        ) {
+            let mut bytes: Vec<u8> = input_message.into();
+            for (index, value) in mutations {
+                let index = index.index(bytes.len());
+                bytes[index] = value;
+            }
+            let _ = SplMessage::try_from(bytes.as_slice());
+        }
+
+        #[test]
+        fn truncated_and_extended_messages_are_rejected(
+            input_message in spl_message(),
+            length in any::<Index>(),
+            extension in vec(any::<u8>(), 1..16),
+        ) {
+            let bytes: Vec<u8> = input_message.into();
+            let length = length.index(bytes.len());
+            prop_assert!(SplMessage::try_from(&bytes[..length]).is_err());
+
+            let mut extended = bytes;
+            extended.extend(extension);
+            prop_assert!(SplMessage::try_from(extended.as_slice()).is_err());
+        }
+
+        #[test]
+        fn messages_with_wrong_header_are_rejected(
+            input_message in spl_message(),
+            index in 0_usize..4,
+            flipped_bits in 1_u8..=u8::MAX,
+        ) {
+            let mut bytes: Vec<u8> = input_message.into();
+            bytes[index] ^= flipped_bits;
+            prop_assert!(SplMessage::try_from(bytes.as_slice()).is_err());
+        }
+    }
 }
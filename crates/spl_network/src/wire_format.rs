@@ -0,0 +1,190 @@
+use std::time::Duration;
+
+use anyhow::bail;
+use byteorder::{ByteOrder, LittleEndian};
+
+/// Reads the little-endian fields of the C structs one by one, failing instead of reading past
+/// the end of the buffer
+pub struct WireReader<'buffer> {
+    buffer: &'buffer [u8],
+    offset: usize,
+}
+
+impl<'buffer> WireReader<'buffer> {
+    pub fn new(buffer: &'buffer [u8]) -> Self {
+        Self { buffer, offset: 0 }
+    }
+
+    pub fn read_bytes(&mut self, number_of_bytes: usize) -> anyhow::Result<&'buffer [u8]> {
+        if self.remaining() < number_of_bytes {
+            bail!(
+                "Buffer too small: expected {number_of_bytes} more bytes at offset {}, got {}",
+                self.offset,
+                self.remaining()
+            );
+        }
+        let bytes = &self.buffer[self.offset..self.offset + number_of_bytes];
+        self.offset += number_of_bytes;
+        Ok(bytes)
+    }
+
+    /// Checks the first four bytes against a null-terminated header constant of the bindings
+    pub fn read_header(&mut self, expected: &[u8; 5]) -> anyhow::Result<[i8; 4]> {
+        let header = self.read_bytes(4)?;
+        if header != &expected[..4] {
+            bail!("Unexpected header {header:?}");
+        }
+        Ok([
+            header[0] as i8,
+            header[1] as i8,
+            header[2] as i8,
+            header[3] as i8,
+        ])
+    }
+
+    pub fn read_version(&mut self, expected: u8) -> anyhow::Result<u8> {
+        let version = self.read_u8()?;
+        if version != expected {
+            bail!("Unexpected version {version}, expected {expected}");
+        }
+        Ok(version)
+    }
+
+    pub fn read_u8(&mut self) -> anyhow::Result<u8> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    pub fn read_u16(&mut self) -> anyhow::Result<u16> {
+        Ok(LittleEndian::read_u16(self.read_bytes(2)?))
+    }
+
+    pub fn read_i16(&mut self) -> anyhow::Result<i16> {
+        Ok(LittleEndian::read_i16(self.read_bytes(2)?))
+    }
+
+    pub fn read_f32(&mut self) -> anyhow::Result<f32> {
+        Ok(LittleEndian::read_f32(self.read_bytes(4)?))
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.buffer.len() - self.offset
+    }
+
+    pub fn expect_end(&self) -> anyhow::Result<()> {
+        if self.remaining() != 0 {
+            bail!("Unexpected {} trailing bytes", self.remaining());
+        }
+        Ok(())
+    }
+}
+
+/// Counterpart of the [`WireReader`]
+#[derive(Default)]
+pub struct WireWriter {
+    bytes: Vec<u8>,
+}
+
+impl WireWriter {
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.bytes.extend_from_slice(bytes);
+    }
+
+    pub fn write_header(&mut self, header: &[i8; 4]) {
+        self.bytes.extend(header.iter().map(|&byte| byte as u8));
+    }
+
+    pub fn write_u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    pub fn write_u16(&mut self, value: u16) {
+        let mut bytes = [0; 2];
+        LittleEndian::write_u16(&mut bytes, value);
+        self.write_bytes(&bytes);
+    }
+
+    pub fn write_i16(&mut self, value: i16) {
+        let mut bytes = [0; 2];
+        LittleEndian::write_i16(&mut bytes, value);
+        self.write_bytes(&bytes);
+    }
+
+    pub fn write_f32(&mut self, value: f32) {
+        let mut bytes = [0; 4];
+        LittleEndian::write_f32(&mut bytes, value);
+        self.write_bytes(&bytes);
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+pub fn header_matches(header: &[i8; 4], expected: &[u8; 5]) -> bool {
+    header
+        .iter()
+        .zip(expected)
+        .all(|(&received, &expected)| received as u8 == expected)
+}
+
+/// Reads the pose and ball of the SPL message and the GameController return message, rejecting
+/// values which are not finite
+pub fn read_finite_f32s<const N: usize>(reader: &mut WireReader) -> anyhow::Result<[f32; N]> {
+    let mut values = [0.0; N];
+    for value in values.iter_mut() {
+        *value = reader.read_f32()?;
+        if !value.is_finite() {
+            bail!("Unexpected non-finite value {value}");
+        }
+    }
+    Ok(values)
+}
+
+/// Converts the ball age of the C structs without panicking on invalid values
+pub fn duration_from_seconds(seconds: f32) -> anyhow::Result<Duration> {
+    if !(0.0..=u32::MAX as f32).contains(&seconds) {
+        bail!("Unexpected duration of {seconds} seconds");
+    }
+    Ok(Duration::from_secs_f32(seconds))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn fields_are_little_endian() {
+        let mut writer = WireWriter::default();
+        writer.write_header(&[b'R' as i8, b'G' as i8, b'm' as i8, b'e' as i8]);
+        writer.write_u8(14);
+        writer.write_u16(0x1234);
+        writer.write_i16(-2);
+        writer.write_f32(1.0);
+        let bytes = writer.into_bytes();
+        assert_eq!(
+            bytes,
+            [b'R', b'G', b'm', b'e', 14, 0x34, 0x12, 0xfe, 0xff, 0, 0, 0x80, 0x3f]
+        );
+
+        let mut reader = WireReader::new(&bytes);
+        reader.read_header(b"RGme\0").unwrap();
+        reader.read_version(14).unwrap();
+        assert_eq!(reader.read_u16().unwrap(), 0x1234);
+        assert_eq!(reader.read_i16().unwrap(), -2);
+        assert_eq!(reader.read_f32().unwrap(), 1.0);
+        reader.expect_end().unwrap();
+        assert!(reader.read_u8().is_err());
+    }
+
+    #[test]
+    fn header_with_any_wrong_byte_is_rejected() {
+        for index in 0..4 {
+            let mut bytes = *b"RGme";
+            bytes[index] ^= 1;
+            assert!(WireReader::new(&bytes).read_header(b"RGme\0").is_err());
+            let header = bytes.map(|byte| byte as i8);
+            assert!(!header_matches(&header, b"RGme\0"));
+        }
+        assert!(header_matches(&b"RGme".map(|byte| byte as i8), b"RGme\0"));
+    }
+}
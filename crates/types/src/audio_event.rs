@@ -0,0 +1,16 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serialize_hierarchy::SerializeHierarchy;
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
+pub struct AudioEvent {
+    /// Name of the configured detector which reported this event
+    pub name: String,
+    pub is_active: bool,
+    pub started_this_cycle: bool,
+    /// Time the frequency band of the detector has continuously stood out of the noise floor
+    pub active_duration: Duration,
+    /// Largest signal to noise ratio over all channels in dB
+    pub signal_to_noise_ratio: f32,
+}
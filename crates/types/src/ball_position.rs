@@ -1,12 +1,18 @@
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use nalgebra::Point2;
+use nalgebra::{Matrix4, Point2, Vector2};
 use serde::{Deserialize, Serialize};
 use serialize_hierarchy::SerializeHierarchy;
 
+use crate::Line2;
+
 #[derive(Clone, Copy, Serialize, Deserialize, SerializeHierarchy, Debug)]
 pub struct BallPosition {
     pub position: Point2<f32>,
+    pub velocity: Vector2<f32>,
+    /// Covariance of the filter state `[x, y, velocity_x, velocity_y]`
+    #[leaf]
+    pub covariance: Matrix4<f32>,
     #[leaf]
     pub last_seen: SystemTime,
 }
@@ -15,7 +21,150 @@ impl Default for BallPosition {
     fn default() -> Self {
         Self {
             position: Default::default(),
+            velocity: Vector2::zeros(),
+            covariance: Matrix4::zeros(),
             last_seen: UNIX_EPOCH,
         }
     }
 }
+
+impl BallPosition {
+    pub fn predicted_stop_position(&self, deceleration: f32) -> Point2<f32> {
+        predict_stop_position(self.position, self.velocity, deceleration)
+    }
+
+    pub fn predicted_line_crossing(
+        &self,
+        line: Line2,
+        deceleration: f32,
+    ) -> Option<BallLineCrossing> {
+        predict_line_crossing(self.position, self.velocity, line, deceleration)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BallLineCrossing {
+    pub position: Point2<f32>,
+    pub time_to_crossing: Duration,
+}
+
+/// Position where a ball rolling with constant deceleration due to friction comes to rest
+pub fn predict_stop_position(
+    position: Point2<f32>,
+    velocity: Vector2<f32>,
+    deceleration: f32,
+) -> Point2<f32> {
+    match velocity.try_normalize(f32::EPSILON) {
+        Some(direction) => position + direction * stopping_distance(velocity.norm(), deceleration),
+        None => position,
+    }
+}
+
+/// Point and time at which a ball rolling with constant deceleration crosses the line segment,
+/// `None` if it stops before reaching it or rolls past it
+pub fn predict_line_crossing(
+    position: Point2<f32>,
+    velocity: Vector2<f32>,
+    line: Line2,
+    deceleration: f32,
+) -> Option<BallLineCrossing> {
+    let speed = velocity.norm();
+    let direction = velocity.try_normalize(f32::EPSILON)?;
+    let segment = line.1 - line.0;
+    let denominator = cross(direction, segment);
+    if denominator.abs() < f32::EPSILON {
+        return None;
+    }
+    let ball_to_line = line.0 - position;
+    let distance_along_path = cross(ball_to_line, segment) / denominator;
+    let parameter_on_segment = cross(ball_to_line, direction) / denominator;
+    if distance_along_path < 0.0
+        || distance_along_path > stopping_distance(speed, deceleration)
+        || !(0.0..=1.0).contains(&parameter_on_segment)
+    {
+        return None;
+    }
+    let time_to_crossing = if deceleration > 0.0 {
+        let discriminant = (speed.powi(2) - 2.0 * deceleration * distance_along_path).max(0.0);
+        (speed - discriminant.sqrt()) / deceleration
+    } else {
+        distance_along_path / speed
+    };
+    Some(BallLineCrossing {
+        position: position + direction * distance_along_path,
+        time_to_crossing: Duration::from_secs_f32(time_to_crossing),
+    })
+}
+
+fn stopping_distance(speed: f32, deceleration: f32) -> f32 {
+    if deceleration > 0.0 {
+        speed.powi(2) / (2.0 * deceleration)
+    } else {
+        f32::INFINITY
+    }
+}
+
+fn cross(left: Vector2<f32>, right: Vector2<f32>) -> f32 {
+    left.x * right.y - left.y * right.x
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+    use nalgebra::{point, vector};
+
+    use crate::Line;
+
+    use super::*;
+
+    #[test]
+    fn resting_ball_stops_where_it_is() {
+        assert_relative_eq!(
+            predict_stop_position(point![1.0, 2.0], Vector2::zeros(), 0.5),
+            point![1.0, 2.0]
+        );
+    }
+
+    #[test]
+    fn rolling_ball_stops_after_braking_distance() {
+        assert_relative_eq!(
+            predict_stop_position(point![0.0, 0.0], vector![0.0, -2.0], 0.5),
+            point![0.0, -4.0]
+        );
+    }
+
+    #[test]
+    fn rolling_ball_crosses_line_in_its_path() {
+        let line = Line(point![-2.0, -1.0], point![-2.0, 1.0]);
+        let crossing =
+            predict_line_crossing(point![0.0, 0.0], vector![-2.0, 0.5], line, 0.5).unwrap();
+
+        assert_relative_eq!(crossing.position, point![-2.0, 0.5], epsilon = 1e-5);
+        let speed = vector![-2.0_f32, 0.5].norm();
+        let distance = crossing.position.coords.norm();
+        let expected_time = (speed - (speed.powi(2) - 2.0 * 0.5 * distance).sqrt()) / 0.5;
+        assert_relative_eq!(
+            crossing.time_to_crossing.as_secs_f32(),
+            expected_time,
+            epsilon = 1e-5
+        );
+    }
+
+    #[test]
+    fn ball_stopping_short_or_missing_the_segment_does_not_cross() {
+        let line = Line(point![-2.0, -1.0], point![-2.0, 1.0]);
+
+        assert_eq!(
+            predict_line_crossing(point![0.0, 0.0], vector![-1.0, 0.0], line, 0.5),
+            None
+        );
+        assert_eq!(
+            predict_line_crossing(point![0.0, 0.0], vector![-2.0, 2.0], line, 0.0),
+            None
+        );
+        assert_eq!(
+            predict_line_crossing(point![0.0, 0.0], vector![2.0, 0.0], line, 0.5),
+            None
+        );
+    }
+}
@@ -89,6 +89,34 @@ impl CameraMatrix {
         })
     }
 
+    /// Replaces the camera to head transformation while keeping the head and robot pose, e.g. to
+    /// evaluate different extrinsic calibrations.
+    pub fn with_camera_to_head(&self, camera_to_head: Isometry3<f32>) -> Self {
+        let head_to_robot = self.camera_to_robot * self.camera_to_head.inverse();
+        let robot_to_ground = self.camera_to_ground * self.robot_to_camera;
+        let camera_to_robot = head_to_robot * camera_to_head;
+        let camera_to_ground = robot_to_ground * camera_to_robot;
+        let image_width = 2.0 * self.focal_length.x * (self.field_of_view.x / 2.0).tan();
+        let horizon = Horizon::from_parameters(
+            camera_to_ground,
+            self.focal_length,
+            self.optical_center,
+            image_width,
+        );
+
+        Self {
+            camera_to_head,
+            camera_to_ground,
+            ground_to_camera: camera_to_ground.inverse(),
+            camera_to_robot,
+            robot_to_camera: camera_to_robot.inverse(),
+            focal_length: self.focal_length,
+            optical_center: self.optical_center,
+            field_of_view: self.field_of_view,
+            horizon,
+        }
+    }
+
     pub fn pixel_to_camera(&self, pixel_coordinates: &Point2<f32>) -> Vector3<f32> {
         vector![
             1.0,
@@ -565,4 +593,50 @@ mod tests {
         );
         Ok(())
     }
+
+    #[test]
+    fn replacing_camera_to_head_matches_full_construction() {
+        let head_to_robot = Isometry3::from_parts(
+            Translation::from(point![0.0, 0.0, 0.2]),
+            UnitQuaternion::from_euler_angles(0.0, 0.1, 0.2),
+        );
+        let robot_to_ground = Isometry3::from_parts(
+            Translation::from(point![0.0, 0.0, 0.3]),
+            UnitQuaternion::from_euler_angles(0.02, -0.01, 0.0),
+        );
+        let construct = |camera_to_head| {
+            CameraMatrix::from_normalized_focal_and_center(
+                vector![0.95, 1.27],
+                point![0.5, 0.5],
+                vector![640.0, 480.0],
+                camera_to_head,
+                head_to_robot,
+                robot_to_ground,
+            )
+        };
+        let original = construct(Isometry3::identity());
+        let camera_to_head = Isometry3::from_parts(
+            Translation::from(point![0.05, 0.0, 0.06]),
+            UnitQuaternion::from_euler_angles(0.01, 0.3, -0.02),
+        );
+
+        let replaced = original.with_camera_to_head(camera_to_head);
+        let expected = construct(camera_to_head);
+
+        assert_relative_eq!(
+            replaced.camera_to_ground,
+            expected.camera_to_ground,
+            epsilon = 1e-5
+        );
+        assert_relative_eq!(
+            replaced.horizon.left_horizon_y,
+            expected.horizon.left_horizon_y,
+            epsilon = 1e-2
+        );
+        assert_relative_eq!(
+            replaced.horizon.right_horizon_y,
+            expected.horizon.right_horizon_y,
+            epsilon = 1e-2
+        );
+    }
 }
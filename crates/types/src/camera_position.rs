@@ -1,5 +1,34 @@
+use nalgebra::{Isometry3, UnitQuaternion, Vector3};
+
+use crate::RobotDimensions;
+
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub enum CameraPosition {
     Top,
     Bottom,
 }
+
+/// Transformation from the camera to the head frame, `extrinsic_rotation` holds the calibrated
+/// roll, pitch and yaw correction in degrees
+pub fn camera_to_head(
+    camera_position: CameraPosition,
+    extrinsic_rotation: Vector3<f32>,
+) -> Isometry3<f32> {
+    let extrinsic_angles_in_radians = extrinsic_rotation.map(|a: f32| a.to_radians());
+    let extrinsic_rotation = UnitQuaternion::from_euler_angles(
+        extrinsic_angles_in_radians.x,
+        extrinsic_angles_in_radians.y,
+        extrinsic_angles_in_radians.z,
+    );
+    let neck_to_camera = match camera_position {
+        CameraPosition::Top => RobotDimensions::NECK_TO_TOP_CAMERA,
+        CameraPosition::Bottom => RobotDimensions::NECK_TO_BOTTOM_CAMERA,
+    };
+    let camera_pitch = match camera_position {
+        CameraPosition::Top => 1.2f32.to_radians(),
+        CameraPosition::Bottom => 39.7f32.to_radians(),
+    };
+    Isometry3::from(neck_to_camera)
+        * Isometry3::rotation(Vector3::y() * camera_pitch)
+        * extrinsic_rotation
+}
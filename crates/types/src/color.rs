@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
 use serialize_hierarchy::SerializeHierarchy;
+use spl_network::TeamColor;
 
 #[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
 #[repr(C)]
@@ -146,6 +147,24 @@ impl Rgb {
     }
 }
 
+impl From<TeamColor> for Rgb {
+    /// Nominal jersey color of a team as seen by the cameras
+    fn from(team_color: TeamColor) -> Self {
+        match team_color {
+            TeamColor::Blue => Rgb::new(0, 60, 170),
+            TeamColor::Red => Rgb::new(190, 20, 30),
+            TeamColor::Yellow => Rgb::new(230, 200, 0),
+            TeamColor::Black => Rgb::new(20, 20, 20),
+            TeamColor::White => Rgb::new(230, 230, 230),
+            TeamColor::Green => Rgb::new(20, 140, 40),
+            TeamColor::Orange => Rgb::new(240, 120, 0),
+            TeamColor::Purple => Rgb::new(110, 30, 150),
+            TeamColor::Brown => Rgb::new(100, 60, 30),
+            TeamColor::Gray => Rgb::new(120, 120, 120),
+        }
+    }
+}
+
 impl From<YCbCr422> for Rgb {
     fn from(ycbcr422: YCbCr422) -> Self {
         let y = ycbcr422.averaged_y();
@@ -1,10 +1,26 @@
 use nalgebra::{vector, Point2, Vector2};
 use serde::{Deserialize, Serialize};
 use serialize_hierarchy::SerializeHierarchy;
+use spl_network::TeamColor;
+
+use crate::Circle;
 
 #[derive(Default, Clone, Debug, Deserialize, Serialize, SerializeHierarchy)]
 pub struct DetectedRobots {
-    pub robot_positions: Vec<ScoredCluster>,
+    pub robots: Vec<DetectedRobot>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize, SerializeHierarchy)]
+pub struct DetectedRobot {
+    /// Position of the feet on the ground in robot coordinates
+    pub position: Point2<f32>,
+    /// Circle around the feet in image coordinates
+    pub image_location: Circle,
+    /// Confidence of the network, the heuristic detector always reports 1.0
+    pub confidence: f32,
+    /// Jersey color of the detected robot, `None` if it could not be classified
+    #[leaf]
+    pub jersey_color: Option<TeamColor>,
 }
 
 #[derive(Default, Clone, Debug, Deserialize, Serialize, SerializeHierarchy)]
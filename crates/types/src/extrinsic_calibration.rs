@@ -0,0 +1,17 @@
+use nalgebra::Vector3;
+use serde::{Deserialize, Serialize};
+use serialize_hierarchy::SerializeHierarchy;
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
+pub struct ExtrinsicCalibration {
+    pub number_of_collected_frames: usize,
+    pub number_of_used_points: usize,
+    /// Root mean square of the distances of the projected line points to their field marks, each
+    /// relative to the distance of the point to the robot, before calibration
+    pub initial_error: f32,
+    /// Same as `initial_error` but after calibration
+    pub error: f32,
+    /// Calibrated extrinsic rotations in degrees, in the format of the camera matrix parameters
+    pub extrinsic_rotations: Vector3<f32>,
+    pub is_finished: bool,
+}
@@ -0,0 +1,56 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use serialize_hierarchy::SerializeHierarchy;
+
+use super::{FallDirection, MotionType};
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
+pub struct FallStatistics {
+    pub number_of_falls: usize,
+    pub falls_per_direction: FallsPerDirection,
+    pub falls: Vec<FallRecord>,
+}
+
+impl FallStatistics {
+    pub fn record(&mut self, record: FallRecord, maximum_number_of_recorded_falls: usize) {
+        self.number_of_falls += 1;
+        match record.direction {
+            FallDirection::Forward => self.falls_per_direction.forward += 1,
+            FallDirection::Backward => self.falls_per_direction.backward += 1,
+            FallDirection::Left => self.falls_per_direction.left += 1,
+            FallDirection::Right => self.falls_per_direction.right += 1,
+        }
+        self.falls.push(record);
+        let number_of_dropped_records = self
+            .falls
+            .len()
+            .saturating_sub(maximum_number_of_recorded_falls);
+        self.falls.drain(..number_of_dropped_records);
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
+pub struct FallsPerDirection {
+    pub forward: usize,
+    pub backward: usize,
+    pub left: usize,
+    pub right: usize,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct FallRecord {
+    pub direction: FallDirection,
+    pub cause_motion: MotionType,
+    pub time: SystemTime,
+}
+
+impl Default for FallRecord {
+    fn default() -> Self {
+        Self {
+            direction: FallDirection::Forward,
+            cause_motion: MotionType::default(),
+            time: UNIX_EPOCH,
+        }
+    }
+}
@@ -31,3 +31,11 @@ impl FieldColor {
         }
     }
 }
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
+pub struct FieldColorHistogram {
+    pub number_of_samples: usize,
+    pub number_of_field_samples: usize,
+    pub green_chromaticity: Vec<usize>,
+    pub green_luminance: Vec<usize>,
+}
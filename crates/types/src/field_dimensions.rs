@@ -1,4 +1,4 @@
-use nalgebra::Point2;
+use nalgebra::{point, Point2};
 use serde::{Deserialize, Serialize};
 use serialize_hierarchy::SerializeHierarchy;
 
@@ -30,4 +30,16 @@ impl FieldDimensions {
         position.x.abs() > self.length / 2.0 - self.goal_box_area_length
             && position.y.abs() < self.goal_box_area_width / 2.0
     }
+
+    pub fn goal_post_positions(&self) -> [Point2<f32>; 4] {
+        let radius = self.goal_post_diameter / 2.0;
+        let x = self.length / 2.0 + radius - self.line_width / 2.0;
+        let y = self.goal_inner_width / 2.0 + radius;
+        [point![-x, -y], point![-x, y], point![x, -y], point![x, y]]
+    }
+
+    pub fn penalty_mark_positions(&self) -> [Point2<f32>; 2] {
+        let x = self.length / 2.0 - self.penalty_marker_distance;
+        [point![-x, 0.0], point![x, 0.0]]
+    }
 }
@@ -2,7 +2,7 @@ use nalgebra::{distance, point, vector, Point2, Vector2};
 use ordered_float::NotNan;
 use serde::{Deserialize, Serialize};
 
-use super::{FieldDimensions, Line, Line2};
+use super::{line_intersections, FieldDimensions, Line, Line2, LineIntersection};
 
 #[derive(Clone, Copy, Debug, Deserialize, Serialize)]
 pub enum FieldMark {
@@ -409,3 +409,21 @@ pub fn field_marks_from_field_dimensions(field_dimensions: &FieldDimensions) ->
         },
     ]
 }
+
+/// Intersections of the field lines. The penalty marker is not part of the lines.
+pub fn line_intersections_from_field_dimensions(
+    field_dimensions: &FieldDimensions,
+) -> Vec<LineIntersection> {
+    let lines: Vec<_> = field_marks_from_field_dimensions(field_dimensions)
+        .into_iter()
+        .filter_map(|field_mark| match field_mark {
+            FieldMark::Line { line, .. }
+                if line.length() > field_dimensions.penalty_marker_size =>
+            {
+                Some(line)
+            }
+            _ => None,
+        })
+        .collect();
+    line_intersections(&lines, 0.01, field_dimensions.line_width)
+}
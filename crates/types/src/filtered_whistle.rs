@@ -9,4 +9,6 @@ pub struct FilteredWhistle {
     pub started_this_cycle: bool,
     #[leaf]
     pub last_detection: Option<SystemTime>,
+    /// Direction of the most supported whistle bearing hypothesis in robot coordinates
+    pub bearing: Option<f32>,
 }
@@ -0,0 +1,165 @@
+use nalgebra::{distance_squared, Point2};
+use serde::{Deserialize, Serialize};
+use serialize_hierarchy::SerializeHierarchy;
+
+use crate::Role;
+
+/// Target positions of the roles placed by a formation in field coordinates
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize, SerializeHierarchy)]
+pub struct FormationPositions {
+    pub defender_left: Point2<f32>,
+    pub defender_right: Point2<f32>,
+    pub striker_supporter: Point2<f32>,
+}
+
+impl FormationPositions {
+    pub fn position(&self, role: Role) -> Option<Point2<f32>> {
+        match role {
+            Role::DefenderLeft => Some(self.defender_left),
+            Role::DefenderRight => Some(self.defender_right),
+            Role::StrikerSupporter => Some(self.striker_supporter),
+            _ => None,
+        }
+    }
+
+    pub fn positions(&self) -> [(Role, Point2<f32>); 3] {
+        [
+            (Role::DefenderLeft, self.defender_left),
+            (Role::DefenderRight, self.defender_right),
+            (Role::StrikerSupporter, self.striker_supporter),
+        ]
+    }
+
+    pub fn map(&self, mut function: impl FnMut(Point2<f32>) -> Point2<f32>) -> Self {
+        Self {
+            defender_left: function(self.defender_left),
+            defender_right: function(self.defender_right),
+            striker_supporter: function(self.striker_supporter),
+        }
+    }
+
+    /// Linear interpolation towards `other`, `factor` 0.0 returns `self` and 1.0 returns `other`
+    pub fn interpolate(&self, other: &Self, factor: f32) -> Self {
+        let interpolate = |start: Point2<f32>, end: Point2<f32>| {
+            Point2::from(start.coords.lerp(&end.coords, factor))
+        };
+        Self {
+            defender_left: interpolate(self.defender_left, other.defender_left),
+            defender_right: interpolate(self.defender_right, other.defender_right),
+            striker_supporter: interpolate(self.striker_supporter, other.striker_supporter),
+        }
+    }
+}
+
+/// Positions of the roles for a single ball position
+#[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
+pub struct FormationAnchor {
+    pub ball_position: Point2<f32>,
+    pub positions: FormationPositions,
+}
+
+/// Formation as stored in the `etc/configuration/formation.<name>.json` files
+#[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
+pub struct Formation {
+    pub anchors: Vec<FormationAnchor>,
+}
+
+impl Formation {
+    /// Interpolates between the anchors weighted by the inverse squared distance of their ball
+    /// position to the given ball position
+    pub fn positions(&self, ball_position: Point2<f32>) -> Option<FormationPositions> {
+        let mut anchors = self.anchors.iter().map(|anchor| {
+            let weight = 1.0 / distance_squared(&anchor.ball_position, &ball_position);
+            (anchor, weight)
+        });
+        let (first_anchor, first_weight) = anchors.next()?;
+        let mut positions = first_anchor.positions;
+        let mut accumulated_weight = first_weight;
+        for (anchor, weight) in anchors {
+            if weight.is_infinite() {
+                return Some(anchor.positions);
+            }
+            if accumulated_weight.is_infinite() {
+                break;
+            }
+            accumulated_weight += weight;
+            positions = positions.interpolate(&anchor.positions, weight / accumulated_weight);
+        }
+        Some(positions)
+    }
+}
+
+/// State of the positioning in the current cycle for debugging
+#[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
+pub struct FormationState {
+    pub formation_name: String,
+    pub ball_position: Point2<f32>,
+    /// Progress of the transition from the previously selected formation in [0.0, 1.0]
+    pub transition_progress: f32,
+    pub anchors: Vec<FormationAnchor>,
+    pub positions: FormationPositions,
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+    use nalgebra::point;
+
+    use super::*;
+
+    fn uniform_positions(position: Point2<f32>) -> FormationPositions {
+        FormationPositions {
+            defender_left: position,
+            defender_right: position,
+            striker_supporter: position,
+        }
+    }
+
+    fn formation() -> Formation {
+        Formation {
+            anchors: vec![
+                FormationAnchor {
+                    ball_position: point![-2.0, 0.0],
+                    positions: uniform_positions(point![-3.0, 0.0]),
+                },
+                FormationAnchor {
+                    ball_position: point![2.0, 0.0],
+                    positions: uniform_positions(point![1.0, 0.0]),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn anchor_positions_are_reproduced_exactly() {
+        let formation = formation();
+        assert_eq!(
+            formation.positions(point![-2.0, 0.0]),
+            Some(uniform_positions(point![-3.0, 0.0]))
+        );
+        assert_eq!(
+            formation.positions(point![2.0, 0.0]),
+            Some(uniform_positions(point![1.0, 0.0]))
+        );
+    }
+
+    #[test]
+    fn positions_between_anchors_are_interpolated() {
+        let formation = formation();
+        let positions = formation.positions(point![0.0, 1.0]).unwrap();
+        assert_relative_eq!(positions.defender_left, point![-1.0, 0.0], epsilon = 1e-5);
+
+        let closer_to_second = formation.positions(point![1.0, 0.0]).unwrap();
+        // weights 1/9 and 1/1
+        assert_relative_eq!(
+            closer_to_second.striker_supporter,
+            point![(-3.0 / 9.0 + 1.0) / (1.0 / 9.0 + 1.0), 0.0],
+            epsilon = 1e-5
+        );
+    }
+
+    #[test]
+    fn empty_formation_has_no_positions() {
+        assert_eq!(Formation::default().positions(Point2::origin()), None);
+    }
+}
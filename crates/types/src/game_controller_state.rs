@@ -1,7 +1,7 @@
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
 
 use serde::{Deserialize, Serialize};
-use spl_network::{GamePhase, GameState, Penalty, SetPlay, Team};
+use spl_network::{GamePhase, GameState, Half, Penalty, SetPlay, Team, TeamColor};
 
 use super::Players;
 
@@ -14,4 +14,19 @@ pub struct GameControllerState {
     pub penalties: Players<Option<Penalty>>,
     pub remaining_amount_of_messages: u16,
     pub set_play: Option<SetPlay>,
+    pub hulks_team_color: TeamColor,
+    pub opponent_team_color: TeamColor,
+    pub half: Half,
+    pub remaining_time_in_half: Duration,
+    /// Remaining time of the current set play, the ready/set phase or the penalty shot
+    pub secondary_time: Duration,
+    pub hulks_score: u8,
+    pub opponent_score: u8,
+}
+
+impl GameControllerState {
+    /// Positive if the HULKs are leading
+    pub fn goal_difference(&self) -> i16 {
+        self.hulks_score as i16 - self.opponent_score as i16
+    }
 }
@@ -1,3 +1,4 @@
+mod audio_event;
 mod ball;
 mod ball_position;
 mod buttons;
@@ -6,7 +7,9 @@ mod camera_position;
 mod color;
 mod cycle_info;
 mod detected_robots;
+mod extrinsic_calibration;
 mod fall_state;
+mod fall_statistics;
 mod field_border;
 mod field_color;
 mod field_dimensions;
@@ -14,8 +17,10 @@ mod field_marks;
 mod filtered_game_state;
 mod filtered_segments;
 mod filtered_whistle;
+mod formation;
 mod game_controller_state;
 mod geometry;
+mod goal_post;
 mod image;
 mod image_segments;
 mod initial_pose;
@@ -26,12 +31,15 @@ mod led;
 mod limb;
 mod line;
 mod line_data;
+mod line_intersection;
 mod localization_update;
+mod message_budget;
 mod message_event;
 mod motion_command;
 mod motion_selection;
 mod obstacles;
 mod path_obstacles;
+mod penalty_mark;
 mod penalty_shot_direction;
 mod perspective_grid_candidates;
 mod planned_path;
@@ -40,7 +48,6 @@ mod primary_state;
 mod robot_dimensions;
 mod robot_kinematics;
 mod robot_masses;
-mod roles;
 mod sensor_data;
 mod sole_pressure;
 mod sonar_obstacle;
@@ -52,26 +59,36 @@ mod whistle;
 mod world_state;
 
 pub use self::image::Image422;
+pub use audio_event::AudioEvent;
 pub use ball::{Ball, CandidateEvaluation};
-pub use ball_position::BallPosition;
+pub use ball_position::{
+    predict_line_crossing, predict_stop_position, BallLineCrossing, BallPosition,
+};
 pub use buttons::Buttons;
 pub use camera_matrix::{CameraMatrices, CameraMatrix, Horizon, ProjectedFieldLines};
-pub use camera_position::CameraPosition;
+pub use camera_position::{camera_to_head, CameraPosition};
 pub use color::{Intensity, Rgb, RgbChannel, YCbCr422, YCbCr444};
 pub use cycle_info::CycleInfo;
-pub use detected_robots::{ClusterCone, DetectedRobots, ScoredCluster, ScoredClusterPoint};
+pub use detected_robots::{
+    ClusterCone, DetectedRobot, DetectedRobots, ScoredCluster, ScoredClusterPoint,
+};
+pub use extrinsic_calibration::ExtrinsicCalibration;
 pub use fall_state::FallState;
+pub use fall_statistics::{FallRecord, FallStatistics, FallsPerDirection};
 pub use field_border::FieldBorder;
-pub use field_color::FieldColor;
+pub use field_color::{FieldColor, FieldColorHistogram};
 pub use field_dimensions::FieldDimensions;
 pub use field_marks::{
-    field_marks_from_field_dimensions, CorrespondencePoints, Correspondences, Direction, FieldMark,
+    field_marks_from_field_dimensions, line_intersections_from_field_dimensions,
+    CorrespondencePoints, Correspondences, Direction, FieldMark,
 };
 pub use filtered_game_state::FilteredGameState;
 pub use filtered_segments::FilteredSegments;
 pub use filtered_whistle::FilteredWhistle;
+pub use formation::{Formation, FormationAnchor, FormationPositions, FormationState};
 pub use game_controller_state::GameControllerState;
 pub use geometry::{rotate_towards, Arc, Circle, LineSegment, Orientation, Rectangle};
+pub use goal_post::GoalPost;
 pub use image_segments::{EdgeType, ImageSegments, ScanGrid, ScanLine, Segment};
 pub use initial_pose::InitialPose;
 pub use joints::{
@@ -84,7 +101,11 @@ pub use led::{Ear, Eye, Leds};
 pub use limb::{is_above_limbs, Limb, ProjectedLimbs};
 pub use line::{Line, Line2};
 pub use line_data::{ImageLines, LineData};
+pub use line_intersection::{
+    classify_line_intersection, line_intersections, IntersectionKind, LineIntersection,
+};
 pub use localization_update::LocalizationUpdate;
+pub use message_budget::{MessageBudgetUsage, MessageReason};
 pub use message_event::MessageEvent;
 pub use motion_command::{
     ArmMotion, Facing, FallDirection, HeadMotion, JumpDirection, KickDirection, KickVariant,
@@ -93,6 +114,7 @@ pub use motion_command::{
 pub use motion_selection::{MotionSafeExits, MotionSelection, MotionType};
 pub use obstacles::{Obstacle, ObstacleKind};
 pub use path_obstacles::{PathObstacle, PathObstacleShape};
+pub use penalty_mark::PenaltyMark;
 pub use penalty_shot_direction::PenaltyShotDirection;
 pub use perspective_grid_candidates::PerspectiveGridCandidates;
 pub use planned_path::{direct_path, PathSegment, PlannedPath};
@@ -101,7 +123,6 @@ pub use primary_state::PrimaryState;
 pub use robot_dimensions::RobotDimensions;
 pub use robot_kinematics::RobotKinematics;
 pub use robot_masses::RobotMass;
-pub use roles::Role;
 pub use sensor_data::{
     Foot, ForceSensitiveResistors, InertialMeasurementUnitData, SensorData, SonarSensors,
     TouchSensors,
@@ -109,8 +130,9 @@ pub use sensor_data::{
 pub use sole_pressure::SolePressure;
 pub use sonar_obstacle::SonarObstacle;
 pub use sonar_values::SonarValues;
+pub use spl_network::Role;
 pub use step_plan::Step;
 pub use support_foot::{Side, SupportFoot};
 pub use walk_command::WalkCommand;
 pub use whistle::Whistle;
-pub use world_state::{BallState, RobotState, WorldState};
+pub use world_state::{BallState, RobotState, TeammateState, WorldState};
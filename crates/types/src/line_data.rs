@@ -4,13 +4,14 @@ use nalgebra::Point2;
 use serde::{Deserialize, Serialize};
 use serialize_hierarchy::SerializeHierarchy;
 
-use super::Line2;
+use super::{Line2, LineIntersection};
 
 #[derive(Clone, Default, Debug, Serialize, Deserialize, SerializeHierarchy)]
 pub struct LineData {
     pub lines_in_robot: Vec<Line2>,
     #[leaf]
     pub used_vertical_filtered_segments: HashSet<Point2<u16>>,
+    pub intersections_in_robot: Vec<LineIntersection>,
 }
 
 #[derive(Clone, Default, Debug, Serialize, Deserialize, SerializeHierarchy)]
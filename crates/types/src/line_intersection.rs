@@ -0,0 +1,183 @@
+use nalgebra::{distance, Point2, Vector2};
+use serde::{Deserialize, Serialize};
+
+use super::Line2;
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum IntersectionKind {
+    L,
+    T,
+    X,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct LineIntersection {
+    pub position: Point2<f32>,
+    pub kind: IntersectionKind,
+    /// Points into the corner of an L and along the stem of a T, zero for an X
+    pub direction: Vector2<f32>,
+}
+
+enum IntersectionLocation {
+    Endpoint {
+        away_from_intersection: Vector2<f32>,
+    },
+    Inside,
+}
+
+fn locate_on_segment(
+    line: &Line2,
+    intersection: Point2<f32>,
+    endpoint_tolerance: f32,
+) -> Option<IntersectionLocation> {
+    let distance_to_start = distance(&line.0, &intersection);
+    let distance_to_end = distance(&line.1, &intersection);
+    if distance_to_start <= endpoint_tolerance {
+        return Some(IntersectionLocation::Endpoint {
+            away_from_intersection: (line.1 - line.0).normalize(),
+        });
+    }
+    if distance_to_end <= endpoint_tolerance {
+        return Some(IntersectionLocation::Endpoint {
+            away_from_intersection: (line.0 - line.1).normalize(),
+        });
+    }
+    let is_inside = line.squared_distance_to_segment(intersection).sqrt() <= endpoint_tolerance;
+    is_inside.then_some(IntersectionLocation::Inside)
+}
+
+/// Classifies where two roughly orthogonal line segments meet. Segments meeting at their ends
+/// form an L, a segment ending on the other one forms a T and crossing segments form an X.
+pub fn classify_line_intersection(
+    first: &Line2,
+    second: &Line2,
+    angle_tolerance: f32,
+    endpoint_tolerance: f32,
+) -> Option<LineIntersection> {
+    if first.length() <= endpoint_tolerance
+        || second.length() <= endpoint_tolerance
+        || !first.is_orthogonal(second, angle_tolerance)
+    {
+        return None;
+    }
+    let position = first.intersection(second);
+    if !position.coords.iter().all(|value| value.is_finite()) {
+        return None;
+    }
+    let first_location = locate_on_segment(first, position, endpoint_tolerance)?;
+    let second_location = locate_on_segment(second, position, endpoint_tolerance)?;
+    let (kind, direction) = match (first_location, second_location) {
+        (
+            IntersectionLocation::Endpoint {
+                away_from_intersection: first_direction,
+            },
+            IntersectionLocation::Endpoint {
+                away_from_intersection: second_direction,
+            },
+        ) => (
+            IntersectionKind::L,
+            (first_direction + second_direction).normalize(),
+        ),
+        (
+            IntersectionLocation::Endpoint {
+                away_from_intersection,
+            },
+            IntersectionLocation::Inside,
+        )
+        | (
+            IntersectionLocation::Inside,
+            IntersectionLocation::Endpoint {
+                away_from_intersection,
+            },
+        ) => (IntersectionKind::T, away_from_intersection),
+        (IntersectionLocation::Inside, IntersectionLocation::Inside) => {
+            (IntersectionKind::X, Vector2::zeros())
+        }
+    };
+    Some(LineIntersection {
+        position,
+        kind,
+        direction,
+    })
+}
+
+/// Classifies all intersections between pairs of the given line segments.
+pub fn line_intersections(
+    lines: &[Line2],
+    angle_tolerance: f32,
+    endpoint_tolerance: f32,
+) -> Vec<LineIntersection> {
+    lines
+        .iter()
+        .enumerate()
+        .flat_map(|(index, first)| {
+            lines[index + 1..].iter().filter_map(move |second| {
+                classify_line_intersection(first, second, angle_tolerance, endpoint_tolerance)
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+    use nalgebra::{point, vector};
+
+    use crate::Line;
+
+    use super::*;
+
+    #[test]
+    fn lines_meeting_at_ends_form_l() {
+        let intersection = classify_line_intersection(
+            &Line(point![0.0, 0.0], point![1.0, 0.0]),
+            &Line(point![0.0, 1.0], point![0.0, 0.05]),
+            0.2,
+            0.1,
+        )
+        .unwrap();
+        assert_eq!(intersection.kind, IntersectionKind::L);
+        assert_relative_eq!(intersection.position, point![0.0, 0.0], epsilon = 1e-6);
+        assert_relative_eq!(
+            intersection.direction,
+            vector![1.0, 1.0].normalize(),
+            epsilon = 1e-6
+        );
+    }
+
+    #[test]
+    fn line_ending_on_other_line_forms_t() {
+        let intersection = classify_line_intersection(
+            &Line(point![-1.0, 0.0], point![1.0, 0.0]),
+            &Line(point![0.0, 0.0], point![0.0, -1.0]),
+            0.2,
+            0.1,
+        )
+        .unwrap();
+        assert_eq!(intersection.kind, IntersectionKind::T);
+        assert_relative_eq!(intersection.direction, vector![0.0, -1.0], epsilon = 1e-6);
+    }
+
+    #[test]
+    fn crossing_lines_form_x() {
+        let intersection = classify_line_intersection(
+            &Line(point![-1.0, 0.0], point![1.0, 0.0]),
+            &Line(point![0.0, 1.0], point![0.0, -1.0]),
+            0.2,
+            0.1,
+        )
+        .unwrap();
+        assert_eq!(intersection.kind, IntersectionKind::X);
+    }
+
+    #[test]
+    fn separated_lines_do_not_intersect() {
+        let intersection = classify_line_intersection(
+            &Line(point![-1.0, 0.0], point![1.0, 0.0]),
+            &Line(point![3.0, 1.0], point![3.0, -1.0]),
+            0.2,
+            0.1,
+        );
+        assert!(intersection.is_none());
+    }
+}
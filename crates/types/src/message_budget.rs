@@ -0,0 +1,40 @@
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+use serialize_hierarchy::SerializeHierarchy;
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub enum MessageReason {
+    BallFoundAgain,
+    Fallen,
+    RoleChanged,
+    PassAnnounced,
+    Periodic,
+}
+
+impl MessageReason {
+    pub fn is_event(&self) -> bool {
+        !matches!(self, MessageReason::Periodic)
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
+pub struct MessageBudgetUsage {
+    /// Last value of the GameController minus the messages this robot sent since
+    pub remaining_messages: u16,
+    /// Remaining messages of the team which may be spent on periodic messages
+    pub remaining_periodic_messages: u16,
+    pub remaining_time_in_game: Duration,
+    /// This robot and the teammates which recently sent messages
+    pub number_of_sending_players: usize,
+    /// Interval between periodic messages which spreads this robot's share of the budget over the
+    /// remaining time, `None` if only event messages may be sent
+    pub periodic_message_interval: Option<Duration>,
+    pub sent_event_messages: usize,
+    pub sent_periodic_messages: usize,
+    pub rejected_messages: usize,
+    #[leaf]
+    pub last_message_reason: Option<MessageReason>,
+    #[leaf]
+    pub last_message_time: Option<SystemTime>,
+}
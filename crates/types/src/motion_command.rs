@@ -62,6 +62,7 @@ impl MotionCommand {
 
 #[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
 pub enum HeadMotion {
+    ActiveVision,
     ZeroAngles,
     Center,
     LookAround,
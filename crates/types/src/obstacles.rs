@@ -1,6 +1,7 @@
 use nalgebra::Point2;
 use serde::{Deserialize, Serialize};
 use serialize_hierarchy::SerializeHierarchy;
+use spl_network::Team;
 
 #[derive(Debug, Clone, Copy, Deserialize, Serialize)]
 pub enum ObstacleKind {
@@ -17,6 +18,9 @@ pub struct Obstacle {
     pub position: Point2<f32>,
     pub radius_at_foot_height: f32,
     pub radius_at_hip_height: f32,
+    /// Team of robot obstacles, `Uncertain` for all other kinds
+    #[leaf]
+    pub team: Team,
 }
 
 impl Obstacle {
@@ -26,6 +30,7 @@ impl Obstacle {
             position,
             radius_at_foot_height: radius,
             radius_at_hip_height: radius,
+            team: Team::Uncertain,
         }
     }
 
@@ -33,12 +38,14 @@ impl Obstacle {
         position: Point2<f32>,
         radius_at_foot_height: f32,
         radius_at_hip_height: f32,
+        team: Team,
     ) -> Self {
         Self {
             kind: ObstacleKind::Robot,
             position,
             radius_at_foot_height,
             radius_at_hip_height,
+            team,
         }
     }
 
@@ -48,6 +55,7 @@ impl Obstacle {
             position,
             radius_at_foot_height: radius,
             radius_at_hip_height: radius,
+            team: Team::Uncertain,
         }
     }
 }
@@ -0,0 +1,8 @@
+use nalgebra::Point2;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct PenaltyMark {
+    pub position_in_robot: Point2<f32>,
+    pub center_in_image: Point2<f32>,
+}
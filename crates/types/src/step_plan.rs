@@ -1,4 +1,4 @@
-use std::ops::Sub;
+use std::ops::{Add, Sub};
 
 use serde::{Deserialize, Serialize};
 use serialize_hierarchy::SerializeHierarchy;
@@ -28,6 +28,18 @@ impl Step {
     }
 }
 
+impl Add<Step> for Step {
+    type Output = Step;
+
+    fn add(self, rhs: Step) -> Self::Output {
+        Self {
+            forward: self.forward + rhs.forward,
+            left: self.left + rhs.left,
+            turn: self.turn + rhs.turn,
+        }
+    }
+}
+
 impl Sub<Step> for Step {
     type Output = Step;
 
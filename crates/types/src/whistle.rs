@@ -5,4 +5,7 @@ use serialize_hierarchy::SerializeHierarchy;
 pub struct Whistle {
     #[leaf]
     pub is_detected: [bool; 4],
+    /// Direction of the whistle in head coordinates estimated from the time differences of
+    /// arrival between the microphones
+    pub bearing: Option<f32>,
 }
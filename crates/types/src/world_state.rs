@@ -1,9 +1,13 @@
-use nalgebra::{Isometry2, Point2};
+use std::time::SystemTime;
+
+use nalgebra::{Isometry2, Point2, Vector2};
 use serde::{Deserialize, Serialize};
 use serialize_hierarchy::SerializeHierarchy;
 use spl_network::PlayerNumber;
 
-use crate::GameControllerState;
+use crate::{
+    predict_line_crossing, predict_stop_position, BallLineCrossing, GameControllerState, Line2,
+};
 
 use crate::PenaltyShotDirection;
 
@@ -21,11 +25,13 @@ pub struct WorldState {
     pub game_phase: GamePhase,
     pub obstacles: Vec<Obstacle>,
     pub robot: RobotState,
+    pub teammates: Vec<TeammateState>,
 }
 
 #[derive(Clone, Copy, Debug, Serialize, Deserialize, SerializeHierarchy)]
 pub struct BallState {
     pub position: Point2<f32>,
+    pub velocity: Vector2<f32>,
     #[leaf]
     pub penalty_shot_direction: Option<PenaltyShotDirection>,
     #[leaf]
@@ -36,12 +42,27 @@ impl Default for BallState {
     fn default() -> Self {
         Self {
             position: Point2::origin(),
+            velocity: Vector2::zeros(),
             penalty_shot_direction: Default::default(),
             field_side: Side::Left,
         }
     }
 }
 
+impl BallState {
+    pub fn predicted_stop_position(&self, deceleration: f32) -> Point2<f32> {
+        predict_stop_position(self.position, self.velocity, deceleration)
+    }
+
+    pub fn predicted_line_crossing(
+        &self,
+        line: Line2,
+        deceleration: f32,
+    ) -> Option<BallLineCrossing> {
+        predict_line_crossing(self.position, self.velocity, line, deceleration)
+    }
+}
+
 #[derive(Clone, Default, Debug, Serialize, Deserialize, SerializeHierarchy)]
 pub struct RobotState {
     pub robot_to_field: Option<Isometry2<f32>>,
@@ -55,3 +76,19 @@ pub struct RobotState {
     #[leaf]
     pub player_number: PlayerNumber,
 }
+
+/// Latest state a teammate sent via team communication
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, SerializeHierarchy)]
+pub struct TeammateState {
+    #[leaf]
+    pub player_number: PlayerNumber,
+    pub robot_to_field: Isometry2<f32>,
+    #[leaf]
+    pub role: Role,
+    pub is_fallen: bool,
+    /// Teammate this robot announced to pass the ball to
+    #[leaf]
+    pub pass_target: Option<PlayerNumber>,
+    #[leaf]
+    pub last_message_time: SystemTime,
+}
@@ -1,6 +1,7 @@
 use std::{
     sync::Arc,
     thread::{Builder, JoinHandle},
+    time::Instant,
 };
 
 use anyhow::Result;
@@ -14,15 +15,18 @@ use crate::{
     framework::{
         buffer::{Reader, Writer},
         future_queue::Producer,
+        AdditionalOutput,
     },
     hardware::{HardwareInterface, NUMBER_OF_AUDIO_SAMPLES},
     CommunicationChannelsForCycler,
 };
 
 use super::{
-    database::MainOutputs,
-    microphone_recorder::record_microphone,
-    whistle_detection::{self, is_whistle_detected_in_buffer},
+    database::{MainOutputs, WhistleDetectionPerformance},
+    event_detection::AudioEventDetection,
+    microphone_recorder::{record_microphone, WavRecorder},
+    whistle_detection::{self, compute_spectra, is_whistle_detected_in_spectra},
+    whistle_direction::estimate_whistle_bearing,
     Database,
 };
 
@@ -37,6 +41,10 @@ where
     audio_producer: Producer<MainOutputs>,
     communication_channels: CommunicationChannelsForCycler<Database>,
     fft: Arc<dyn Fft<f32>>,
+    inverse_fft: Arc<dyn Fft<f32>>,
+    audio_event_detection: AudioEventDetection,
+    wav_recorder: WavRecorder,
+    whistle_detection_performance: WhistleDetectionPerformance,
 }
 
 impl<Hardware> Audio<Hardware>
@@ -52,6 +60,7 @@ where
     ) -> anyhow::Result<Self> {
         let mut planner = FftPlanner::new();
         let fft = planner.plan_fft_forward(NUMBER_OF_AUDIO_SAMPLES);
+        let inverse_fft = planner.plan_fft_inverse(NUMBER_OF_AUDIO_SAMPLES);
         Ok(Self {
             hardware_interface,
             control_reader,
@@ -59,6 +68,10 @@ where
             audio_producer,
             communication_channels,
             fft,
+            inverse_fft,
+            audio_event_detection: Default::default(),
+            wav_recorder: WavRecorder::new()?,
+            whistle_detection_performance: Default::default(),
         })
     }
 
@@ -91,25 +104,77 @@ where
                 .subscribed_additional_outputs
                 .next();
 
+            self.wav_recorder
+                .record(&configuration.audio.recording_path, &buffer.lock());
+
             // process
+            let whistle_detection = &configuration.audio.whistle_detection;
+            let processing_start = Instant::now();
+            let spectra = compute_spectra(self.fft.clone(), &buffer.lock());
+            let is_detected = is_whistle_detected_in_spectra(
+                &spectra,
+                &whistle_detection.detection_band,
+                whistle_detection.background_noise_scaling,
+                whistle_detection.whistle_scaling,
+                whistle_detection.number_of_chunks,
+                whistle_detection::AdditionalOutputs::new(
+                    &mut audio_database.additional_outputs,
+                    &subscribed_additional_outputs,
+                ),
+            )?;
+            let bearing_estimation = if is_detected.iter().any(|&is_detected| is_detected) {
+                estimate_whistle_bearing(
+                    self.inverse_fft.clone(),
+                    &spectra,
+                    &whistle_detection.detection_band,
+                    &whistle_detection.microphone_positions,
+                    whistle_detection.speed_of_sound,
+                )
+            } else {
+                None
+            };
+            AdditionalOutput::new(
+                subscribed_additional_outputs.contains("time_differences_of_arrival"),
+                &mut audio_database
+                    .additional_outputs
+                    .time_differences_of_arrival,
+            )
+            .fill_on_subscription(|| {
+                bearing_estimation
+                    .as_ref()
+                    .map(|(_, time_differences)| time_differences.clone())
+                    .unwrap_or_default()
+            });
+            update_whistle_detection_performance(
+                &mut self.whistle_detection_performance,
+                &is_detected,
+                bearing_estimation.is_some(),
+                processing_start,
+            );
+            AdditionalOutput::new(
+                subscribed_additional_outputs.contains("whistle_detection_performance"),
+                &mut audio_database
+                    .additional_outputs
+                    .whistle_detection_performance,
+            )
+            .fill_on_subscription(|| self.whistle_detection_performance.clone());
             audio_database.main_outputs.detected_whistle = Some(Whistle {
-                is_detected: is_whistle_detected_in_buffer(
-                    self.fft.clone(),
-                    &buffer.lock(),
-                    &configuration.audio.whistle_detection.detection_band,
-                    configuration
-                        .audio
-                        .whistle_detection
-                        .background_noise_scaling,
-                    configuration.audio.whistle_detection.whistle_scaling,
-                    configuration.audio.whistle_detection.number_of_chunks,
-                    whistle_detection::AdditionalOutputs::new(
-                        &mut audio_database.additional_outputs,
-                        &subscribed_additional_outputs,
-                    ),
-                )?,
+                is_detected,
+                bearing: bearing_estimation.map(|(bearing, _)| bearing),
             });
 
+            let (audio_events, signal_to_noise_ratios) = self
+                .audio_event_detection
+                .detect(&spectra, &configuration.audio.event_detectors);
+            AdditionalOutput::new(
+                subscribed_additional_outputs.contains("audio_event_signal_to_noise_ratios"),
+                &mut audio_database
+                    .additional_outputs
+                    .audio_event_signal_to_noise_ratios,
+            )
+            .fill_on_subscription(|| signal_to_noise_ratios);
+            audio_database.main_outputs.audio_events = Some(audio_events);
+
             self.audio_producer
                 .finalize(audio_database.main_outputs.clone());
         }
@@ -119,3 +184,27 @@ where
         Ok(())
     }
 }
+
+fn update_whistle_detection_performance(
+    performance: &mut WhistleDetectionPerformance,
+    is_detected: &[bool],
+    has_bearing: bool,
+    processing_start: Instant,
+) {
+    performance.processing_duration = processing_start.elapsed();
+    performance.number_of_cycles += 1;
+    for (number_of_detections, &is_detected) in
+        performance.number_of_detections.iter_mut().zip(is_detected)
+    {
+        if is_detected {
+            *number_of_detections += 1;
+        }
+    }
+    let number_of_detecting_channels = is_detected.iter().filter(|&&detected| detected).count();
+    if number_of_detecting_channels > 0 && number_of_detecting_channels < is_detected.len() {
+        performance.number_of_channel_disagreements += 1;
+    }
+    if has_bearing {
+        performance.number_of_bearing_estimations += 1;
+    }
+}
@@ -1,16 +1,24 @@
+use std::time::Duration;
+
 use serde::{Deserialize, Serialize};
 use serialize_hierarchy::SerializeHierarchy;
-use types::Whistle;
+use types::{AudioEvent, Whistle};
+
+use crate::hardware::NUMBER_OF_AUDIO_CHANNELS;
 
 #[derive(Clone, Debug, Default, SerializeHierarchy)]
 pub struct MainOutputs {
     pub detected_whistle: Option<Whistle>,
+    pub audio_events: Option<Vec<AudioEvent>>,
 }
 
 #[derive(Debug, Default, Clone, SerializeHierarchy)]
 pub struct AdditionalOutputs {
     pub audio_spectrums: Option<Vec<Vec<(f32, f32)>>>,
     pub detection_infos: Option<Vec<DetectionInfo>>,
+    pub time_differences_of_arrival: Option<Vec<f32>>,
+    pub audio_event_signal_to_noise_ratios: Option<Vec<Vec<f32>>>,
+    pub whistle_detection_performance: Option<WhistleDetectionPerformance>,
 }
 
 #[derive(Debug, Default, Clone, SerializeHierarchy, Serialize, Deserialize)]
@@ -31,6 +39,19 @@ pub struct DetectionInfo {
     pub upper_band_index: Option<usize>,
 }
 
+#[derive(Debug, Default, Clone, SerializeHierarchy, Serialize, Deserialize)]
+pub struct WhistleDetectionPerformance {
+    /// Time spent on the spectra, the detection and the bearing estimation in the last cycle
+    pub processing_duration: Duration,
+    pub number_of_cycles: usize,
+    /// Cycles in which the whistle was detected in the respective channel
+    #[leaf]
+    pub number_of_detections: [usize; NUMBER_OF_AUDIO_CHANNELS],
+    /// Cycles in which only some of the channels detected the whistle
+    pub number_of_channel_disagreements: usize,
+    pub number_of_bearing_estimations: usize,
+}
+
 #[derive(Debug, Default, Clone)]
 pub struct Database {
     pub main_outputs: MainOutputs,
@@ -0,0 +1,289 @@
+use std::{collections::HashSet, fs::File, ops::Range, path::Path};
+
+use anyhow::{bail, Context, Result};
+use rustfft::FftPlanner;
+use serde_json::Value;
+
+use crate::{
+    framework::configuration::WhistleDetection,
+    hardware::{AUDIO_SAMPLE_RATE, NUMBER_OF_AUDIO_CHANNELS, NUMBER_OF_AUDIO_SAMPLES},
+};
+
+use super::{
+    database,
+    wav::WavRecording,
+    whistle_detection::{self, compute_spectra, is_whistle_detected_in_spectra},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WhistleParameters {
+    pub background_noise_scaling: f32,
+    pub whistle_scaling: f32,
+    pub number_of_chunks: usize,
+}
+
+/// Reads the detection band and the whistle parameters from `audio.whistle_detection` of a
+/// configuration file
+pub fn load_whistle_parameters(
+    configuration_path: impl AsRef<Path>,
+) -> Result<(Range<f32>, WhistleParameters)> {
+    let configuration_path = configuration_path.as_ref();
+    let file = File::open(configuration_path)
+        .with_context(|| format!("Failed to open {}", configuration_path.display()))?;
+    let configuration: Value = serde_json::from_reader(file)
+        .with_context(|| format!("Failed to parse {}", configuration_path.display()))?;
+    let whistle_detection: WhistleDetection =
+        serde_json::from_value(configuration["audio"]["whistle_detection"].clone())
+            .context("Failed to read audio.whistle_detection")?;
+    Ok((
+        whistle_detection.detection_band,
+        WhistleParameters {
+            background_noise_scaling: whistle_detection.background_noise_scaling,
+            whistle_scaling: whistle_detection.whistle_scaling,
+            number_of_chunks: whistle_detection.number_of_chunks,
+        },
+    ))
+}
+
+/// Microphone recording split into the buffers processed by the audio cycler
+///
+/// Only the samples are kept, the spectra of a buffer are computed when it is evaluated.
+pub struct LabeledRecording {
+    channels: Vec<Vec<f32>>,
+    /// Whether the center of the respective buffer lies within a labeled whistle
+    labels: Vec<bool>,
+}
+
+impl LabeledRecording {
+    /// Loads a 4-channel WAV recording and its labels from a JSON file with the same stem
+    ///
+    /// The labels are the time intervals of all whistles in seconds, e.g.
+    /// `[{"start": 1.2, "end": 1.9}]`.
+    pub fn load(wav_path: impl AsRef<Path>) -> Result<Self> {
+        let wav_path = wav_path.as_ref();
+        let labels_path = wav_path.with_extension("json");
+        let labels_file = File::open(&labels_path)
+            .with_context(|| format!("Failed to open labels {}", labels_path.display()))?;
+        let whistles: Vec<Range<f32>> = serde_json::from_reader(labels_file)
+            .with_context(|| format!("Failed to parse labels {}", labels_path.display()))?;
+        let recording = WavRecording::load(wav_path)?;
+        if recording.sample_rate != AUDIO_SAMPLE_RATE {
+            bail!(
+                "{} has a sample rate of {} Hz, expected {AUDIO_SAMPLE_RATE} Hz",
+                wav_path.display(),
+                recording.sample_rate
+            );
+        }
+        Self::from_samples(recording.channels, &whistles)
+            .with_context(|| format!("Failed to prepare {}", wav_path.display()))
+    }
+
+    fn from_samples(channels: Vec<Vec<f32>>, whistles: &[Range<f32>]) -> Result<Self> {
+        if channels.len() != NUMBER_OF_AUDIO_CHANNELS {
+            bail!(
+                "Expected {NUMBER_OF_AUDIO_CHANNELS} channels, got {}",
+                channels.len()
+            );
+        }
+        let number_of_buffers = channels[0].len() / NUMBER_OF_AUDIO_SAMPLES;
+        let labels = (0..number_of_buffers)
+            .map(|buffer_index| {
+                let center = (buffer_index as f32 + 0.5) * NUMBER_OF_AUDIO_SAMPLES as f32
+                    / AUDIO_SAMPLE_RATE as f32;
+                whistles.iter().any(|whistle| whistle.contains(&center))
+            })
+            .collect();
+        Ok(Self { channels, labels })
+    }
+
+    fn buffers(
+        &self,
+        buffer_index: usize,
+    ) -> [[f32; NUMBER_OF_AUDIO_SAMPLES]; NUMBER_OF_AUDIO_CHANNELS] {
+        let samples =
+            buffer_index * NUMBER_OF_AUDIO_SAMPLES..(buffer_index + 1) * NUMBER_OF_AUDIO_SAMPLES;
+        let mut buffers = [[0.0; NUMBER_OF_AUDIO_SAMPLES]; NUMBER_OF_AUDIO_CHANNELS];
+        for (buffer, channel) in buffers.iter_mut().zip(&self.channels) {
+            buffer.copy_from_slice(&channel[samples.clone()]);
+        }
+        buffers
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Evaluation {
+    pub true_positives: usize,
+    pub false_positives: usize,
+    pub false_negatives: usize,
+    pub true_negatives: usize,
+}
+
+impl Evaluation {
+    pub fn precision(&self) -> f32 {
+        ratio(
+            self.true_positives,
+            self.true_positives + self.false_positives,
+        )
+    }
+
+    pub fn recall(&self) -> f32 {
+        ratio(
+            self.true_positives,
+            self.true_positives + self.false_negatives,
+        )
+    }
+
+    pub fn f1_score(&self) -> f32 {
+        let precision = self.precision();
+        let recall = self.recall();
+        if precision + recall > 0.0 {
+            2.0 * precision * recall / (precision + recall)
+        } else {
+            0.0
+        }
+    }
+}
+
+fn ratio(numerator: usize, denominator: usize) -> f32 {
+    if denominator == 0 {
+        0.0
+    } else {
+        numerator as f32 / denominator as f32
+    }
+}
+
+/// Runs the whistle detection on every buffer, a buffer counts as detection if any channel
+/// detected the whistle
+pub fn evaluate(
+    recordings: &[LabeledRecording],
+    detection_band: &Range<f32>,
+    parameters: WhistleParameters,
+) -> Result<Evaluation> {
+    let mut additional_outputs = database::AdditionalOutputs::default();
+    let subscribed_additional_outputs = HashSet::new();
+    let fft = FftPlanner::new().plan_fft_forward(NUMBER_OF_AUDIO_SAMPLES);
+    let mut evaluation = Evaluation::default();
+    for recording in recordings {
+        for (buffer_index, &is_whistle) in recording.labels.iter().enumerate() {
+            let spectra = compute_spectra(fft.clone(), &recording.buffers(buffer_index));
+            let is_detected = is_whistle_detected_in_spectra(
+                &spectra,
+                detection_band,
+                parameters.background_noise_scaling,
+                parameters.whistle_scaling,
+                parameters.number_of_chunks,
+                whistle_detection::AdditionalOutputs::new(
+                    &mut additional_outputs,
+                    &subscribed_additional_outputs,
+                ),
+            )?
+            .iter()
+            .any(|&is_detected| is_detected);
+            match (is_detected, is_whistle) {
+                (true, true) => evaluation.true_positives += 1,
+                (true, false) => evaluation.false_positives += 1,
+                (false, true) => evaluation.false_negatives += 1,
+                (false, false) => evaluation.true_negatives += 1,
+            }
+        }
+    }
+    Ok(evaluation)
+}
+
+/// Evaluates all combinations of the given parameters, sorted by descending F1 score
+pub fn grid_search(
+    recordings: &[LabeledRecording],
+    detection_band: &Range<f32>,
+    background_noise_scalings: &[f32],
+    whistle_scalings: &[f32],
+    numbers_of_chunks: &[usize],
+) -> Result<Vec<(WhistleParameters, Evaluation)>> {
+    let mut results = Vec::new();
+    for &background_noise_scaling in background_noise_scalings {
+        for &whistle_scaling in whistle_scalings {
+            for &number_of_chunks in numbers_of_chunks {
+                let parameters = WhistleParameters {
+                    background_noise_scaling,
+                    whistle_scaling,
+                    number_of_chunks,
+                };
+                results.push((
+                    parameters,
+                    evaluate(recordings, detection_band, parameters)?,
+                ));
+            }
+        }
+    }
+    results.sort_by(|(_, left), (_, right)| right.f1_score().total_cmp(&left.f1_score()));
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::TAU;
+
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::*;
+
+    const PARAMETERS: WhistleParameters = WhistleParameters {
+        background_noise_scaling: 1.6,
+        whistle_scaling: 3.8,
+        number_of_chunks: 16,
+    };
+
+    fn recording_with_whistle(whistle: Range<f32>, duration: f32) -> Vec<Vec<f32>> {
+        let mut random_number_generator = StdRng::seed_from_u64(42);
+        let number_of_samples = (duration * AUDIO_SAMPLE_RATE as f32) as usize;
+        (0..NUMBER_OF_AUDIO_CHANNELS)
+            .map(|_| {
+                (0..number_of_samples)
+                    .map(|index| {
+                        let time = index as f32 / AUDIO_SAMPLE_RATE as f32;
+                        let noise = random_number_generator.gen_range(-0.05..0.05);
+                        let tone = if whistle.contains(&time) {
+                            (TAU * 3000.0 * time).sin()
+                        } else {
+                            0.0
+                        };
+                        noise + tone
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn buffers_are_labeled_by_their_center() {
+        let channels = recording_with_whistle(0.5..1.0, 1.5);
+        let recording = LabeledRecording::from_samples(channels, &[0.5..1.0]).unwrap();
+
+        assert_eq!(recording.labels.len(), 32);
+        let number_of_positives = recording.labels.iter().filter(|&&label| label).count();
+        assert_eq!(number_of_positives, 11);
+    }
+
+    #[test]
+    fn synthetic_whistle_is_detected() {
+        let channels = recording_with_whistle(0.5..1.0, 1.5);
+        let recording = LabeledRecording::from_samples(channels, &[0.5..1.0]).unwrap();
+
+        let evaluation = evaluate(&[recording], &(2000.0..4000.0), PARAMETERS).unwrap();
+        assert!(evaluation.precision() > 0.8, "{evaluation:?}");
+        assert!(evaluation.recall() > 0.8, "{evaluation:?}");
+    }
+
+    #[test]
+    fn scores_are_computed_from_counts() {
+        let evaluation = Evaluation {
+            true_positives: 6,
+            false_positives: 2,
+            false_negatives: 4,
+            true_negatives: 10,
+        };
+        assert_eq!(evaluation.precision(), 0.75);
+        assert_eq!(evaluation.recall(), 0.6);
+        assert!((evaluation.f1_score() - 2.0 / 3.0).abs() < 1e-6);
+        assert_eq!(Evaluation::default().f1_score(), 0.0);
+    }
+}
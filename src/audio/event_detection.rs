@@ -0,0 +1,200 @@
+use std::{collections::HashMap, ops::Range, time::Duration};
+
+use rustfft::num_complex::Complex32;
+use types::AudioEvent;
+
+use crate::{
+    framework::configuration::AudioEventDetector,
+    hardware::{AUDIO_SAMPLE_RATE, NUMBER_OF_AUDIO_CHANNELS, NUMBER_OF_AUDIO_SAMPLES},
+};
+
+const NUMBER_OF_FREQUENCY_SAMPLES: usize = NUMBER_OF_AUDIO_SAMPLES / 2;
+
+/// Detects events whose energy is concentrated in a frequency band, e.g. whistles, referee tones
+/// or audio signals of other robots, as configured in `audio.event_detectors`
+#[derive(Default)]
+pub struct AudioEventDetection {
+    /// Keyed by detector name to keep the state when detectors are reordered in the configuration
+    active_durations: HashMap<String, Duration>,
+}
+
+impl AudioEventDetection {
+    /// Returns the events of all detectors and the signal to noise ratios per detector and channel
+    pub fn detect(
+        &mut self,
+        spectra: &[Vec<Complex32>; NUMBER_OF_AUDIO_CHANNELS],
+        detectors: &[AudioEventDetector],
+    ) -> (Vec<AudioEvent>, Vec<Vec<f32>>) {
+        let cycle_duration =
+            Duration::from_secs_f32(NUMBER_OF_AUDIO_SAMPLES as f32 / AUDIO_SAMPLE_RATE as f32);
+        let absolute_values: Vec<Vec<f32>> = spectra
+            .iter()
+            .map(|spectrum| {
+                spectrum
+                    .iter()
+                    .take(NUMBER_OF_FREQUENCY_SAMPLES)
+                    .map(|value| value.norm())
+                    .collect()
+            })
+            .collect();
+        let noise_floors: Vec<f32> = absolute_values
+            .iter()
+            .map(|values| median(values))
+            .collect();
+
+        self.active_durations
+            .retain(|name, _| detectors.iter().any(|detector| detector.name == *name));
+        detectors
+            .iter()
+            .map(|detector| {
+                let active_duration = self
+                    .active_durations
+                    .entry(detector.name.clone())
+                    .or_default();
+                let signal_to_noise_ratios: Vec<f32> = absolute_values
+                    .iter()
+                    .zip(noise_floors.iter())
+                    .map(|(values, &noise_floor)| {
+                        signal_to_noise_ratio(values, noise_floor, &detector.frequency_band)
+                    })
+                    .collect();
+                let number_of_channels_above_threshold = signal_to_noise_ratios
+                    .iter()
+                    .filter(|&&ratio| ratio >= detector.minimum_signal_to_noise_ratio)
+                    .count();
+                let was_active = *active_duration >= detector.minimum_duration;
+                if number_of_channels_above_threshold >= detector.minimum_number_of_channels {
+                    *active_duration += cycle_duration;
+                } else {
+                    *active_duration = Duration::ZERO;
+                }
+                let is_active = *active_duration >= detector.minimum_duration;
+                let event = AudioEvent {
+                    name: detector.name.clone(),
+                    is_active,
+                    started_this_cycle: is_active && !was_active,
+                    active_duration: *active_duration,
+                    signal_to_noise_ratio: signal_to_noise_ratios
+                        .iter()
+                        .copied()
+                        .fold(f32::NEG_INFINITY, f32::max),
+                };
+                (event, signal_to_noise_ratios)
+            })
+            .unzip()
+    }
+}
+
+/// Ratio of the mean magnitude in the band to the noise floor in dB
+fn signal_to_noise_ratio(absolute_values: &[f32], noise_floor: f32, band: &Range<f32>) -> f32 {
+    let frequency_resolution = AUDIO_SAMPLE_RATE as f32 / NUMBER_OF_AUDIO_SAMPLES as f32;
+    let minimum_index = (band.start / frequency_resolution).ceil() as usize;
+    let maximum_index =
+        ((band.end / frequency_resolution).ceil() as usize).min(absolute_values.len());
+    if minimum_index >= maximum_index || noise_floor <= 0.0 {
+        return f32::NEG_INFINITY;
+    }
+    let band_values = &absolute_values[minimum_index..maximum_index];
+    let band_mean = band_values.iter().sum::<f32>() / band_values.len() as f32;
+    20.0 * (band_mean / noise_floor).log10()
+}
+
+fn median(values: &[f32]) -> f32 {
+    let mut sorted_values = values.to_vec();
+    sorted_values.sort_by(f32::total_cmp);
+    sorted_values
+        .get(sorted_values.len() / 2)
+        .copied()
+        .unwrap_or(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spectra_with_band(band: Range<f32>, magnitude: f32) -> [Vec<Complex32>; 4] {
+        let frequency_resolution = AUDIO_SAMPLE_RATE as f32 / NUMBER_OF_AUDIO_SAMPLES as f32;
+        let spectrum: Vec<_> = (0..NUMBER_OF_AUDIO_SAMPLES)
+            .map(|index| {
+                let frequency = index as f32 * frequency_resolution;
+                let value = if band.contains(&frequency) {
+                    magnitude
+                } else {
+                    1.0
+                };
+                Complex32::new(value, 0.0)
+            })
+            .collect();
+        [
+            spectrum.clone(),
+            spectrum.clone(),
+            spectrum.clone(),
+            spectrum,
+        ]
+    }
+
+    fn detector() -> AudioEventDetector {
+        AudioEventDetector {
+            name: "tone".to_string(),
+            frequency_band: 1000.0..1200.0,
+            minimum_duration: Duration::from_millis(100),
+            minimum_signal_to_noise_ratio: 12.0,
+            minimum_number_of_channels: 2,
+        }
+    }
+
+    #[test]
+    fn event_becomes_active_after_minimum_duration() {
+        let mut detection = AudioEventDetection::default();
+        let spectra = spectra_with_band(1000.0..1200.0, 10.0);
+
+        let mut started_events = 0;
+        for cycle in 0..5 {
+            let (events, signal_to_noise_ratios) = detection.detect(&spectra, &[detector()]);
+            assert_eq!(signal_to_noise_ratios[0].len(), 4);
+            assert!((events[0].signal_to_noise_ratio - 20.0).abs() < 1e-3);
+            // one cycle lasts about 46 ms, so the event is reported in the third cycle
+            assert_eq!(events[0].is_active, cycle >= 2, "cycle {cycle}");
+            if events[0].started_this_cycle {
+                started_events += 1;
+            }
+        }
+        assert_eq!(started_events, 1);
+    }
+
+    #[test]
+    fn quiet_band_resets_event() {
+        let mut detection = AudioEventDetection::default();
+        let loud = spectra_with_band(1000.0..1200.0, 10.0);
+        let quiet = spectra_with_band(1000.0..1200.0, 2.0);
+
+        for _ in 0..3 {
+            detection.detect(&loud, &[detector()]);
+        }
+        let (events, _) = detection.detect(&quiet, &[detector()]);
+        assert!(!events[0].is_active);
+        assert_eq!(events[0].active_duration, Duration::ZERO);
+    }
+
+    #[test]
+    fn reordered_detectors_keep_their_state() {
+        let mut detection = AudioEventDetection::default();
+        let spectra = spectra_with_band(1000.0..1200.0, 10.0);
+        let tone = detector();
+        let silence = AudioEventDetector {
+            name: "silence".to_string(),
+            frequency_band: 3000.0..3200.0,
+            ..detector()
+        };
+
+        for _ in 0..3 {
+            detection.detect(&spectra, &[tone.clone(), silence.clone()]);
+        }
+        let (events, _) = detection.detect(&spectra, &[silence, tone]);
+        assert_eq!(events[0].name, "silence");
+        assert!(!events[0].is_active);
+        assert_eq!(events[1].name, "tone");
+        assert!(events[1].is_active);
+        assert!(!events[1].started_this_cycle);
+    }
+}
@@ -1,7 +1,18 @@
+use std::{
+    path::PathBuf,
+    sync::mpsc::{channel, Sender},
+    thread,
+};
+
 use anyhow::Context;
+use log::warn;
 use parking_lot::Mutex;
 
-use crate::hardware::{HardwareInterface, NUMBER_OF_AUDIO_CHANNELS, NUMBER_OF_AUDIO_SAMPLES};
+use crate::hardware::{
+    HardwareInterface, AUDIO_SAMPLE_RATE, NUMBER_OF_AUDIO_CHANNELS, NUMBER_OF_AUDIO_SAMPLES,
+};
+
+use super::wav::WavWriter;
 
 pub fn record_microphone<Hardware>(
     hardware_interface: &Hardware,
@@ -14,3 +25,91 @@ where
         .context("Failed to record from the microphone")?;
     Ok(hardware_interface.get_audio_buffer())
 }
+
+enum RecordingCommand {
+    Record {
+        recording_path: PathBuf,
+        buffers: Box<[[f32; NUMBER_OF_AUDIO_SAMPLES]; NUMBER_OF_AUDIO_CHANNELS]>,
+    },
+    Stop,
+}
+
+/// Writes the recorded buffers to a WAV file while `audio.recording_path` is set, e.g. for the
+/// offline evaluation with the `audio_evaluation` tool
+///
+/// Files are written by a separate thread to not block the audio cycler.
+pub struct WavRecorder {
+    command_sender: Sender<RecordingCommand>,
+    is_recording: bool,
+}
+
+impl WavRecorder {
+    pub fn new() -> anyhow::Result<Self> {
+        let (command_sender, command_receiver) = channel();
+        thread::Builder::new()
+            .name("wav_recorder".to_string())
+            .spawn(move || {
+                let mut writer: Option<(PathBuf, WavWriter)> = None;
+                while let Ok(command) = command_receiver.recv() {
+                    let (recording_path, buffers) = match command {
+                        RecordingCommand::Record {
+                            recording_path,
+                            buffers,
+                        } => (recording_path, buffers),
+                        RecordingCommand::Stop => {
+                            writer = None;
+                            continue;
+                        }
+                    };
+                    if let Err(error) = write_buffers(&mut writer, recording_path, &buffers) {
+                        warn!("Failed to record microphone buffers: {error:?}");
+                    }
+                }
+            })
+            .context("Failed to spawn WAV recorder thread")?;
+        Ok(Self {
+            command_sender,
+            is_recording: false,
+        })
+    }
+
+    pub fn record(
+        &mut self,
+        recording_path: &Option<PathBuf>,
+        buffers: &[[f32; NUMBER_OF_AUDIO_SAMPLES]; NUMBER_OF_AUDIO_CHANNELS],
+    ) {
+        let command = match recording_path {
+            Some(recording_path) => RecordingCommand::Record {
+                recording_path: recording_path.clone(),
+                buffers: Box::new(*buffers),
+            },
+            None if self.is_recording => RecordingCommand::Stop,
+            None => return,
+        };
+        self.is_recording = recording_path.is_some();
+        if self.command_sender.send(command).is_err() {
+            warn!("WAV recorder thread has stopped");
+        }
+    }
+}
+
+fn write_buffers(
+    writer: &mut Option<(PathBuf, WavWriter)>,
+    recording_path: PathBuf,
+    buffers: &[[f32; NUMBER_OF_AUDIO_SAMPLES]; NUMBER_OF_AUDIO_CHANNELS],
+) -> anyhow::Result<()> {
+    let is_recording_to_path = matches!(writer, Some((path, _)) if *path == recording_path);
+    if !is_recording_to_path {
+        *writer = None;
+        let wav_writer = WavWriter::create(
+            &recording_path,
+            AUDIO_SAMPLE_RATE,
+            NUMBER_OF_AUDIO_CHANNELS as u16,
+        )?;
+        *writer = Some((recording_path, wav_writer));
+    }
+    if let Some((_, writer)) = writer.as_mut() {
+        writer.write(buffers)?;
+    }
+    Ok(())
+}
@@ -1,7 +1,11 @@
 mod cycler;
 mod database;
+pub mod evaluation;
+mod event_detection;
 mod microphone_recorder;
+mod wav;
 mod whistle_detection;
+mod whistle_direction;
 
 pub use cycler::Audio;
 
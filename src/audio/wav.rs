@@ -0,0 +1,304 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use anyhow::{bail, Context, Result};
+use byteorder::{ByteOrder, LittleEndian, WriteBytesExt};
+use log::error;
+
+const FORMAT_PCM: u16 = 1;
+const FORMAT_IEEE_FLOAT: u16 = 3;
+const FORMAT_EXTENSIBLE: u16 = 0xfffe;
+const HEADER_SIZE: u32 = 44;
+
+/// Samples of a WAV file, one vector per channel
+#[derive(Clone, Debug, PartialEq)]
+pub struct WavRecording {
+    pub sample_rate: u32,
+    pub channels: Vec<Vec<f32>>,
+}
+
+impl WavRecording {
+    /// Reads 16 or 32 bit integer PCM as well as 32 bit float WAV files
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let mut bytes = Vec::new();
+        File::open(path)
+            .and_then(|mut file| file.read_to_end(&mut bytes))
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::parse(&bytes).with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    fn parse(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+            bail!("Missing RIFF/WAVE header");
+        }
+        let mut format = None;
+        let mut data = None;
+        let mut offset = 12;
+        while offset + 8 <= bytes.len() {
+            let chunk_id = &bytes[offset..offset + 4];
+            let chunk_size = LittleEndian::read_u32(&bytes[offset + 4..offset + 8]) as usize;
+            let chunk_start = offset + 8;
+            let chunk_end = (chunk_start + chunk_size).min(bytes.len());
+            let chunk = &bytes[chunk_start..chunk_end];
+            match chunk_id {
+                b"fmt " => {
+                    if chunk.len() < 16 {
+                        bail!("Format chunk too short");
+                    }
+                    let mut format_tag = LittleEndian::read_u16(&chunk[0..2]);
+                    if format_tag == FORMAT_EXTENSIBLE && chunk.len() >= 26 {
+                        format_tag = LittleEndian::read_u16(&chunk[24..26]);
+                    }
+                    format = Some((
+                        format_tag,
+                        LittleEndian::read_u16(&chunk[2..4]) as usize,
+                        LittleEndian::read_u32(&chunk[4..8]),
+                        LittleEndian::read_u16(&chunk[14..16]),
+                    ));
+                }
+                b"data" => data = Some(chunk),
+                _ => {}
+            }
+            offset = chunk_start + chunk_size + chunk_size % 2;
+        }
+        let (format_tag, number_of_channels, sample_rate, bits_per_sample) =
+            format.context("Missing format chunk")?;
+        let data = data.context("Missing data chunk")?;
+        if number_of_channels == 0 {
+            bail!("WAV file without channels");
+        }
+
+        let samples: Vec<f32> = match (format_tag, bits_per_sample) {
+            (FORMAT_PCM, 16) => data
+                .chunks_exact(2)
+                .map(|sample| LittleEndian::read_i16(sample) as f32 / i16::MAX as f32)
+                .collect(),
+            (FORMAT_PCM, 32) => data
+                .chunks_exact(4)
+                .map(|sample| LittleEndian::read_i32(sample) as f32 / i32::MAX as f32)
+                .collect(),
+            (FORMAT_IEEE_FLOAT, 32) => data.chunks_exact(4).map(LittleEndian::read_f32).collect(),
+            _ => bail!("Unsupported sample format {format_tag} with {bits_per_sample} bits"),
+        };
+        let mut channels = vec![Vec::new(); number_of_channels];
+        for frame in samples.chunks_exact(number_of_channels) {
+            for (channel, &sample) in channels.iter_mut().zip(frame) {
+                channel.push(sample);
+            }
+        }
+        Ok(Self {
+            sample_rate,
+            channels,
+        })
+    }
+}
+
+/// Appends multi-channel buffers as 32 bit float samples to a WAV file
+///
+/// The header is updated at most once per [`HEADER_UPDATE_INTERVAL`] and when the writer is
+/// dropped, so a killed process loses at most the sizes of the last interval. Before the data
+/// exceeds the 4 GiB limit of the format, recording continues in `<stem>_1.wav`, `<stem>_2.wav`,
+/// and so on.
+pub struct WavWriter {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    sample_rate: u32,
+    number_of_channels: u16,
+    number_of_data_bytes: u32,
+    maximum_number_of_data_bytes: u32,
+    file_index: usize,
+    last_header_update: Instant,
+}
+
+const HEADER_UPDATE_INTERVAL: Duration = Duration::from_secs(1);
+
+impl WavWriter {
+    pub fn create(
+        path: impl AsRef<Path>,
+        sample_rate: u32,
+        number_of_channels: u16,
+    ) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let writer = create_file(&path, sample_rate, number_of_channels)?;
+        Ok(Self {
+            path,
+            writer,
+            sample_rate,
+            number_of_channels,
+            number_of_data_bytes: 0,
+            maximum_number_of_data_bytes: u32::MAX - (HEADER_SIZE - 8),
+            file_index: 0,
+            last_header_update: Instant::now(),
+        })
+    }
+
+    pub fn write<const SAMPLES: usize>(&mut self, buffers: &[[f32; SAMPLES]]) -> Result<()> {
+        if buffers.len() != self.number_of_channels as usize {
+            bail!(
+                "Expected {} channels, got {}",
+                self.number_of_channels,
+                buffers.len()
+            );
+        }
+        let number_of_bytes = u32::try_from(SAMPLES * buffers.len() * 4)
+            .ok()
+            .filter(|&number_of_bytes| number_of_bytes <= self.maximum_number_of_data_bytes)
+            .context("Buffers do not fit into a WAV file")?;
+        let fits_into_file = self
+            .number_of_data_bytes
+            .checked_add(number_of_bytes)
+            .is_some_and(|number_of_data_bytes| {
+                number_of_data_bytes <= self.maximum_number_of_data_bytes
+            });
+        if !fits_into_file {
+            self.start_next_file()?;
+        }
+        for frame_index in 0..SAMPLES {
+            for buffer in buffers {
+                self.writer.write_f32::<LittleEndian>(buffer[frame_index])?;
+            }
+        }
+        self.number_of_data_bytes += number_of_bytes;
+        if self.last_header_update.elapsed() >= HEADER_UPDATE_INTERVAL {
+            self.update_header()?;
+        }
+        Ok(())
+    }
+
+    fn update_header(&mut self) -> Result<()> {
+        self.writer.seek(SeekFrom::Start(4))?;
+        self.writer
+            .write_u32::<LittleEndian>(HEADER_SIZE - 8 + self.number_of_data_bytes)?;
+        self.writer.seek(SeekFrom::Start(HEADER_SIZE as u64 - 4))?;
+        self.writer
+            .write_u32::<LittleEndian>(self.number_of_data_bytes)?;
+        self.writer.seek(SeekFrom::End(0))?;
+        self.writer.flush()?;
+        self.last_header_update = Instant::now();
+        Ok(())
+    }
+
+    fn start_next_file(&mut self) -> Result<()> {
+        self.update_header()?;
+        self.file_index += 1;
+        let stem = self.path.file_stem().unwrap_or_default().to_string_lossy();
+        let path = self
+            .path
+            .with_file_name(format!("{stem}_{}.wav", self.file_index));
+        self.writer = create_file(&path, self.sample_rate, self.number_of_channels)?;
+        self.number_of_data_bytes = 0;
+        Ok(())
+    }
+}
+
+impl Drop for WavWriter {
+    fn drop(&mut self) {
+        if let Err(error) = self.update_header() {
+            error!(
+                "Failed to update header of {}: {error:?}",
+                self.path.display()
+            );
+        }
+    }
+}
+
+fn create_file(path: &Path, sample_rate: u32, number_of_channels: u16) -> Result<BufWriter<File>> {
+    let file =
+        File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut writer = BufWriter::new(file);
+    let block_align = number_of_channels * 4;
+    writer.write_all(b"RIFF")?;
+    writer.write_u32::<LittleEndian>(HEADER_SIZE - 8)?;
+    writer.write_all(b"WAVEfmt ")?;
+    writer.write_u32::<LittleEndian>(16)?;
+    writer.write_u16::<LittleEndian>(FORMAT_IEEE_FLOAT)?;
+    writer.write_u16::<LittleEndian>(number_of_channels)?;
+    writer.write_u32::<LittleEndian>(sample_rate)?;
+    writer.write_u32::<LittleEndian>(sample_rate * block_align as u32)?;
+    writer.write_u16::<LittleEndian>(block_align)?;
+    writer.write_u16::<LittleEndian>(32)?;
+    writer.write_all(b"data")?;
+    writer.write_u32::<LittleEndian>(0)?;
+    Ok(writer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn written_buffers_are_read_back() {
+        let path = std::env::temp_dir().join(format!("recording_{}.wav", std::process::id()));
+        let first = [[0.1, 0.2, 0.3], [-0.1, -0.2, -0.3]];
+        let second = [[0.4, 0.5, 0.6], [-0.4, -0.5, -0.6]];
+
+        let mut writer = WavWriter::create(&path, 44100, 2).unwrap();
+        writer.write(&first).unwrap();
+        writer.write(&second).unwrap();
+        drop(writer);
+
+        let recording = WavRecording::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(recording.sample_rate, 44100);
+        assert_eq!(
+            recording.channels,
+            vec![
+                vec![0.1, 0.2, 0.3, 0.4, 0.5, 0.6],
+                vec![-0.1, -0.2, -0.3, -0.4, -0.5, -0.6]
+            ]
+        );
+    }
+
+    #[test]
+    fn recording_continues_in_next_file_before_size_limit() {
+        let directory = std::env::temp_dir();
+        let path = directory.join(format!("rollover_{}.wav", std::process::id()));
+        let next_path = directory.join(format!("rollover_{}_1.wav", std::process::id()));
+        let first = [[0.1, 0.2], [-0.1, -0.2]];
+        let second = [[0.3, 0.4], [-0.3, -0.4]];
+
+        let mut writer = WavWriter::create(&path, 16000, 2).unwrap();
+        writer.maximum_number_of_data_bytes = 24;
+        writer.write(&first).unwrap();
+        writer.write(&second).unwrap();
+        drop(writer);
+
+        let recording = WavRecording::load(&path).unwrap();
+        let next_recording = WavRecording::load(&next_path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(&next_path).unwrap();
+        assert_eq!(recording.channels, vec![vec![0.1, 0.2], vec![-0.1, -0.2]]);
+        assert_eq!(
+            next_recording.channels,
+            vec![vec![0.3, 0.4], vec![-0.3, -0.4]]
+        );
+    }
+
+    #[test]
+    fn integer_samples_are_normalized() {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(b"RIFF");
+        bytes.extend_from_slice(&40u32.to_le_bytes());
+        bytes.extend_from_slice(b"WAVEfmt ");
+        bytes.extend_from_slice(&16u32.to_le_bytes());
+        bytes.extend_from_slice(&FORMAT_PCM.to_le_bytes());
+        bytes.extend_from_slice(&1u16.to_le_bytes());
+        bytes.extend_from_slice(&16000u32.to_le_bytes());
+        bytes.extend_from_slice(&32000u32.to_le_bytes());
+        bytes.extend_from_slice(&2u16.to_le_bytes());
+        bytes.extend_from_slice(&16u16.to_le_bytes());
+        bytes.extend_from_slice(b"data");
+        bytes.extend_from_slice(&4u32.to_le_bytes());
+        bytes.extend_from_slice(&i16::MAX.to_le_bytes());
+        bytes.extend_from_slice(&0i16.to_le_bytes());
+
+        let recording = WavRecording::parse(&bytes).unwrap();
+        assert_eq!(recording.sample_rate, 16000);
+        assert_eq!(recording.channels, vec![vec![1.0, 0.0]]);
+    }
+}
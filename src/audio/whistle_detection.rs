@@ -36,20 +36,12 @@ impl<'a> AdditionalOutputs<'a> {
     }
 }
 
-pub fn is_whistle_detected_in_buffer(
+/// Hann windowed spectra of all channels, shared by the detection and the direction estimation
+pub fn compute_spectra(
     fft: Arc<dyn Fft<f32>>,
     buffers: &[[f32; NUMBER_OF_AUDIO_SAMPLES]; NUMBER_OF_AUDIO_CHANNELS],
-    detection_band: &Range<f32>,
-    background_noise_scaling: f32,
-    whistle_scaling: f32,
-    number_of_chunks: usize,
-    mut additional_outputs: AdditionalOutputs,
-) -> anyhow::Result<[bool; NUMBER_OF_AUDIO_CHANNELS]> {
-    let mut audio_spectrums = Vec::new();
-    let mut detection_infos = Vec::new();
-    let frequency_resolution = AUDIO_SAMPLE_RATE as f32 / NUMBER_OF_AUDIO_SAMPLES as f32;
-    let mut is_detected = [false; NUMBER_OF_AUDIO_CHANNELS];
-    for (channel, buffer) in buffers.iter().enumerate() {
+) -> [Vec<Complex32>; NUMBER_OF_AUDIO_CHANNELS] {
+    buffers.map(|buffer| {
         let mut buffer: Vec<_> = buffer
             .iter()
             .enumerate()
@@ -61,7 +53,24 @@ pub fn is_whistle_detected_in_buffer(
             })
             .collect();
         fft.process(&mut buffer);
-        let absolute_values: Vec<_> = buffer
+        buffer
+    })
+}
+
+pub fn is_whistle_detected_in_spectra(
+    spectra: &[Vec<Complex32>; NUMBER_OF_AUDIO_CHANNELS],
+    detection_band: &Range<f32>,
+    background_noise_scaling: f32,
+    whistle_scaling: f32,
+    number_of_chunks: usize,
+    mut additional_outputs: AdditionalOutputs,
+) -> anyhow::Result<[bool; NUMBER_OF_AUDIO_CHANNELS]> {
+    let mut audio_spectrums = Vec::new();
+    let mut detection_infos = Vec::new();
+    let frequency_resolution = AUDIO_SAMPLE_RATE as f32 / NUMBER_OF_AUDIO_SAMPLES as f32;
+    let mut is_detected = [false; NUMBER_OF_AUDIO_CHANNELS];
+    for (channel, spectrum) in spectra.iter().enumerate() {
+        let absolute_values: Vec<_> = spectrum
             .iter()
             .take(NUMBER_OF_FREQUENCY_SAMPLES)
             .map(|sample| {
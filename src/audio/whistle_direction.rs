@@ -0,0 +1,227 @@
+use std::{f32::consts::TAU, ops::Range, sync::Arc};
+
+use nalgebra::{vector, Point2, Vector2};
+use rustfft::{num_complex::Complex32, Fft};
+
+use crate::hardware::{AUDIO_SAMPLE_RATE, NUMBER_OF_AUDIO_CHANNELS, NUMBER_OF_AUDIO_SAMPLES};
+
+const NUMBER_OF_BEARING_STEPS: usize = 360;
+
+/// Estimates the bearing of the whistle in head coordinates from the time differences of arrival
+/// between all pairs of microphones
+///
+/// Time differences are measured with the phase transform weighted generalized cross correlation
+/// (GCC-PHAT) restricted to the detection band. The bearing is the far-field direction whose
+/// expected time differences match the measured ones best. Returns the bearing together with the
+/// measured time differences in seconds.
+pub fn estimate_whistle_bearing(
+    inverse_fft: Arc<dyn Fft<f32>>,
+    spectra: &[Vec<Complex32>; NUMBER_OF_AUDIO_CHANNELS],
+    detection_band: &Range<f32>,
+    microphone_positions: &[Point2<f32>],
+    speed_of_sound: f32,
+) -> Option<(f32, Vec<f32>)> {
+    if microphone_positions.len() != NUMBER_OF_AUDIO_CHANNELS {
+        return None;
+    }
+    let pairs = microphone_pairs();
+    let time_differences = pairs
+        .iter()
+        .map(|&(first, second)| {
+            let microphone_distance =
+                (microphone_positions[first] - microphone_positions[second]).norm();
+            let maximum_lag = (microphone_distance / speed_of_sound * AUDIO_SAMPLE_RATE as f32)
+                .ceil() as usize
+                + 1;
+            time_difference_of_arrival(
+                inverse_fft.clone(),
+                &spectra[first],
+                &spectra[second],
+                detection_band,
+                maximum_lag,
+            )
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    let bearing = (0..NUMBER_OF_BEARING_STEPS)
+        .map(|step| step as f32 / NUMBER_OF_BEARING_STEPS as f32 * TAU)
+        .min_by(|left, right| {
+            let left_error = bearing_error(
+                *left,
+                &pairs,
+                &time_differences,
+                microphone_positions,
+                speed_of_sound,
+            );
+            let right_error = bearing_error(
+                *right,
+                &pairs,
+                &time_differences,
+                microphone_positions,
+                speed_of_sound,
+            );
+            left_error.total_cmp(&right_error)
+        })?;
+    Some((bearing, time_differences))
+}
+
+fn microphone_pairs() -> Vec<(usize, usize)> {
+    (0..NUMBER_OF_AUDIO_CHANNELS)
+        .flat_map(|first| (first + 1..NUMBER_OF_AUDIO_CHANNELS).map(move |second| (first, second)))
+        .collect()
+}
+
+/// Time in seconds the signal arrives later at the first than at the second microphone
+fn time_difference_of_arrival(
+    inverse_fft: Arc<dyn Fft<f32>>,
+    first_spectrum: &[Complex32],
+    second_spectrum: &[Complex32],
+    detection_band: &Range<f32>,
+    maximum_lag: usize,
+) -> Option<f32> {
+    let frequency_resolution = AUDIO_SAMPLE_RATE as f32 / NUMBER_OF_AUDIO_SAMPLES as f32;
+    let minimum_frequency_index = (detection_band.start / frequency_resolution).ceil() as usize;
+    let maximum_frequency_index = ((detection_band.end / frequency_resolution).ceil() as usize)
+        .min(NUMBER_OF_AUDIO_SAMPLES / 2);
+
+    let mut cross_spectrum = vec![Complex32::new(0.0, 0.0); NUMBER_OF_AUDIO_SAMPLES];
+    for index in minimum_frequency_index.max(1)..maximum_frequency_index {
+        let cross_power = first_spectrum[index] * second_spectrum[index].conj();
+        let magnitude = cross_power.norm();
+        if magnitude > f32::EPSILON {
+            cross_spectrum[index] = cross_power / magnitude;
+            cross_spectrum[NUMBER_OF_AUDIO_SAMPLES - index] = (cross_power / magnitude).conj();
+        }
+    }
+    inverse_fft.process(&mut cross_spectrum);
+
+    let correlation_at =
+        |lag: isize| cross_spectrum[lag.rem_euclid(NUMBER_OF_AUDIO_SAMPLES as isize) as usize].re;
+    let maximum_lag = maximum_lag as isize;
+    let best_lag = (-maximum_lag..=maximum_lag)
+        .max_by(|&left, &right| correlation_at(left).total_cmp(&correlation_at(right)))?;
+    let previous = correlation_at(best_lag - 1);
+    let current = correlation_at(best_lag);
+    let next = correlation_at(best_lag + 1);
+    let curvature = previous - 2.0 * current + next;
+    let subsample_offset = if curvature.abs() > f32::EPSILON {
+        (0.5 * (previous - next) / curvature).clamp(-0.5, 0.5)
+    } else {
+        0.0
+    };
+    Some((best_lag as f32 + subsample_offset) / AUDIO_SAMPLE_RATE as f32)
+}
+
+fn bearing_error(
+    bearing: f32,
+    pairs: &[(usize, usize)],
+    time_differences: &[f32],
+    microphone_positions: &[Point2<f32>],
+    speed_of_sound: f32,
+) -> f32 {
+    let direction: Vector2<f32> = vector![bearing.cos(), bearing.sin()];
+    pairs
+        .iter()
+        .zip(time_differences)
+        .map(|(&(first, second), measured_time_difference)| {
+            let expected_time_difference =
+                (microphone_positions[second] - microphone_positions[first]).dot(&direction)
+                    / speed_of_sound;
+            (measured_time_difference - expected_time_difference).powi(2)
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::{FRAC_PI_3, PI};
+
+    use nalgebra::point;
+    use rustfft::FftPlanner;
+
+    use super::*;
+
+    const SPEED_OF_SOUND: f32 = 343.0;
+
+    fn microphone_positions() -> Vec<Point2<f32>> {
+        vec![
+            point![-0.0366, 0.0214],
+            point![-0.0366, -0.0214],
+            point![0.0366, 0.0214],
+            point![0.0366, -0.0214],
+        ]
+    }
+
+    fn spectra_of_distant_source(bearing: f32) -> [Vec<Complex32>; NUMBER_OF_AUDIO_CHANNELS] {
+        let mut planner = FftPlanner::new();
+        let fft = planner.plan_fft_forward(NUMBER_OF_AUDIO_SAMPLES);
+        let direction = vector![bearing.cos(), bearing.sin()];
+        let frequencies: Vec<_> = (0..40).map(|index| 2000.0 + index as f32 * 50.0).collect();
+        let positions = microphone_positions();
+        [0, 1, 2, 3].map(|channel| {
+            let delay = -positions[channel].coords.dot(&direction) / SPEED_OF_SOUND;
+            let mut samples: Vec<_> = (0..NUMBER_OF_AUDIO_SAMPLES)
+                .map(|index| {
+                    let time = index as f32 / AUDIO_SAMPLE_RATE as f32 - delay;
+                    let value: f32 = frequencies
+                        .iter()
+                        .enumerate()
+                        .map(|(phase, frequency)| (TAU * frequency * time + phase as f32).sin())
+                        .sum();
+                    let hann = (PI * index as f32 / NUMBER_OF_AUDIO_SAMPLES as f32)
+                        .sin()
+                        .powi(2);
+                    Complex32::new(hann * value, 0.0)
+                })
+                .collect();
+            fft.process(&mut samples);
+            samples
+        })
+    }
+
+    fn angle_difference(left: f32, right: f32) -> f32 {
+        ((left - right + PI).rem_euclid(TAU) - PI).abs()
+    }
+
+    #[test]
+    fn bearing_of_distant_source_is_estimated() {
+        let inverse_fft = FftPlanner::new().plan_fft_inverse(NUMBER_OF_AUDIO_SAMPLES);
+        for true_bearing in [0.0, FRAC_PI_3, 2.5, -2.0] {
+            let spectra = spectra_of_distant_source(true_bearing);
+            let (bearing, time_differences) = estimate_whistle_bearing(
+                inverse_fft.clone(),
+                &spectra,
+                &(2000.0..4000.0),
+                &microphone_positions(),
+                SPEED_OF_SOUND,
+            )
+            .unwrap();
+
+            assert_eq!(time_differences.len(), 6);
+            assert!(
+                angle_difference(bearing, true_bearing) < 0.15,
+                "estimated {bearing}, expected {true_bearing}"
+            );
+        }
+    }
+
+    #[test]
+    fn time_difference_of_delayed_channel_is_positive() {
+        let inverse_fft = FftPlanner::new().plan_fft_inverse(NUMBER_OF_AUDIO_SAMPLES);
+        let spectra = spectra_of_distant_source(0.0);
+        // the source is in front, so the rear microphone hears it after the front microphone
+        let time_difference = time_difference_of_arrival(
+            inverse_fft,
+            &spectra[0],
+            &spectra[2],
+            &(2000.0..4000.0),
+            12,
+        )
+        .unwrap();
+        let expected = 2.0 * 0.0366 / SPEED_OF_SOUND;
+        assert!(
+            (time_difference - expected).abs() < 0.5 / AUDIO_SAMPLE_RATE as f32,
+            "measured {time_difference}, expected {expected}"
+        );
+    }
+}
@@ -5,7 +5,7 @@ use mlua::Lua;
 use nalgebra::{Isometry2, Point2, Vector2};
 use serde::{Deserialize, Serialize};
 use serde_json::from_reader;
-use spl_network::SetPlay;
+use spl_network::{SetPlay, Team};
 use types::FilteredGameState;
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -66,6 +66,9 @@ pub enum Action {
     SetFilteredGameState {
         filtered_game_state: FilteredGameState,
     },
+    SetKickingTeam {
+        kicking_team: Team,
+    },
     SetPenalized {
         robot_index: usize,
         is_penalized: bool,
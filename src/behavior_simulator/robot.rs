@@ -92,7 +92,12 @@ impl Robot {
                 self.robot_to_field,
                 sensor_data,
                 primary_state,
-                state.broadcasted_spl_messages.clone(),
+                state
+                    .broadcasted_spl_messages
+                    .iter()
+                    .filter(|message| message.team_number == self.configuration.team_number)
+                    .cloned()
+                    .collect(),
                 game_controller_state,
                 has_ground_contact,
                 filtered_game_state,
@@ -182,6 +187,8 @@ impl Robot {
         )
         .map(|position| BallPosition {
             position,
+            velocity: self.robot_to_field.inverse() * state.ball_velocity,
+            covariance: Default::default(),
             last_seen: state.now,
         });
 
@@ -221,23 +228,13 @@ impl Robot {
             MotionCommand::Penalized => NextAction::DoNothing,
             MotionCommand::SitDown { .. } => NextAction::DoNothing,
             MotionCommand::Stand { head, .. } => {
-                let head_yaw = match head {
-                    HeadMotion::LookAt { target } => {
-                        UnitComplex::rotation_between(&Vector::x(), &target.coords)
-                    }
-                    _ => UnitComplex::identity(),
-                };
+                let head_yaw = head_yaw(head, database.main_outputs.ball_position.as_ref());
                 NextAction::Stand { head_yaw }
             }
             MotionCommand::StandUp { .. } => NextAction::DoNothing,
             MotionCommand::Unstiff => NextAction::DoNothing,
             MotionCommand::InWalkKick { head, .. } => {
-                let head_yaw = match head {
-                    HeadMotion::LookAt { target } => {
-                        UnitComplex::rotation_between(&Vector::x(), &target.coords)
-                    }
-                    _ => UnitComplex::identity(),
-                };
+                let head_yaw = head_yaw(head, database.main_outputs.ball_position.as_ref());
                 NextAction::WalkTo {
                     end_pose: Isometry2::translation(0.1, 0.0),
                     head_yaw,
@@ -249,12 +246,7 @@ impl Robot {
                 head,
                 ..
             } => {
-                let head_yaw = match head {
-                    HeadMotion::LookAt { target } => {
-                        UnitComplex::rotation_between(&Vector::x(), &target.coords)
-                    }
-                    _ => UnitComplex::identity(),
-                };
+                let head_yaw = head_yaw(head, database.main_outputs.ball_position.as_ref());
                 let max_step_size = 0.1;
 
                 let segment = path
@@ -351,6 +343,18 @@ impl Robot {
     }
 }
 
+fn head_yaw(head: &HeadMotion, ball_position: Option<&BallPosition>) -> UnitComplex<f32> {
+    match head {
+        HeadMotion::LookAt { target } => {
+            UnitComplex::rotation_between(&Vector::x(), &target.coords)
+        }
+        HeadMotion::ActiveVision => ball_position
+            .map(|ball| UnitComplex::rotation_between(&Vector::x(), &ball.position.coords))
+            .unwrap_or_else(UnitComplex::identity),
+        _ => UnitComplex::identity(),
+    }
+}
+
 fn limit_ball_visibility(
     head_yaw: UnitComplex<f32>,
     ball_position: Point2<f32>,
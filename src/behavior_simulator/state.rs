@@ -7,7 +7,7 @@ use anyhow::{bail, Context};
 use mlua::Lua;
 use nalgebra::{Point2, Vector2};
 use serde::Serialize;
-use spl_network::{GamePhase, GameState, Penalty, SplMessage, Team};
+use spl_network::{GamePhase, GameState, Half, Penalty, SplMessage, Team, TeamColor};
 use types::{FilteredGameState, GameControllerState, Players};
 
 use crate::control::Database;
@@ -52,6 +52,13 @@ impl TryFrom<SimulationConfiguration> for State {
                 },
                 remaining_amount_of_messages: 1200,
                 set_play: None,
+                hulks_team_color: TeamColor::Blue,
+                opponent_team_color: TeamColor::Red,
+                half: Half::First,
+                remaining_time_in_half: Duration::from_secs(600),
+                secondary_time: Duration::ZERO,
+                hulks_score: 0,
+                opponent_score: 0,
             },
             ball_is_free: true,
             ball_position: Point2::origin(),
@@ -132,6 +139,9 @@ impl State {
                         }
                     }
                 }
+                Action::SetKickingTeam { kicking_team } => {
+                    self.game_controller_state.kicking_team = kicking_team;
+                }
                 Action::SetPenalized {
                     robot_index,
                     is_penalized,
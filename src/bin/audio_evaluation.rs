@@ -0,0 +1,71 @@
+use std::path::PathBuf;
+
+use anyhow::Context;
+use hulk::{
+    audio_evaluation::{evaluate, grid_search, load_whistle_parameters, LabeledRecording},
+    setup_logger,
+};
+use log::info;
+use structopt::StructOpt;
+
+#[derive(StructOpt)]
+struct Arguments {
+    /// Paths to 4-channel WAV recordings, each labeled by a JSON file with the same stem
+    #[structopt(required = true)]
+    recordings: Vec<PathBuf>,
+    /// Path to configuration file containing the whistle detection parameters
+    #[structopt(long, default_value = "etc/configuration/default.json")]
+    configuration_path: PathBuf,
+    /// Search all combinations of the given parameters instead of evaluating the configuration
+    #[structopt(long)]
+    grid_search: bool,
+    #[structopt(long, use_delimiter = true, default_value = "1.2,1.4,1.6,1.8,2.0")]
+    background_noise_scalings: Vec<f32>,
+    #[structopt(long, use_delimiter = true, default_value = "3.0,3.4,3.8,4.2,4.6")]
+    whistle_scalings: Vec<f32>,
+    #[structopt(long, use_delimiter = true, default_value = "8,16,32")]
+    numbers_of_chunks: Vec<usize>,
+    /// Number of best parameter sets printed after the grid search
+    #[structopt(long, default_value = "10")]
+    number_of_results: usize,
+}
+
+fn main() -> anyhow::Result<()> {
+    setup_logger()?;
+    let arguments = Arguments::from_args();
+    let (detection_band, parameters) = load_whistle_parameters(&arguments.configuration_path)
+        .context("Failed to load whistle detection parameters")?;
+    let recordings = arguments
+        .recordings
+        .iter()
+        .map(LabeledRecording::load)
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    if arguments.grid_search {
+        let results = grid_search(
+            &recordings,
+            &detection_band,
+            &arguments.background_noise_scalings,
+            &arguments.whistle_scalings,
+            &arguments.numbers_of_chunks,
+        )?;
+        for (parameters, evaluation) in results.iter().take(arguments.number_of_results) {
+            info!(
+                "{parameters:?}: precision {:.3}, recall {:.3}, F1 {:.3}",
+                evaluation.precision(),
+                evaluation.recall(),
+                evaluation.f1_score()
+            );
+        }
+    } else {
+        let evaluation = evaluate(&recordings, &detection_band, parameters)?;
+        info!("{parameters:?}: {evaluation:?}");
+        info!(
+            "Precision {:.3}, recall {:.3}, F1 {:.3}",
+            evaluation.precision(),
+            evaluation.recall(),
+            evaluation.f1_score()
+        );
+    }
+    Ok(())
+}
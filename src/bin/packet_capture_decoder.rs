@@ -0,0 +1,68 @@
+use std::{
+    fs::{read, File},
+    io::{BufWriter, Write},
+    path::PathBuf,
+};
+
+use anyhow::Context;
+use hulk::setup_logger;
+use log::{info, warn};
+use spl_network::{read_pcap, CaptureRecord};
+use structopt::StructOpt;
+
+#[derive(StructOpt)]
+struct Arguments {
+    /// Path to a pcap file, e.g. written by the spl_network cycler or tcpdump
+    capture: PathBuf,
+    /// Team number of the HULKs, needed to decode GameController state messages
+    #[structopt(long, default_value = "24")]
+    team_number: u8,
+    /// Write the decoded packets as JSONL to this path instead of logging them
+    #[structopt(long)]
+    jsonl: Option<PathBuf>,
+}
+
+fn main() -> anyhow::Result<()> {
+    setup_logger()?;
+    let arguments = Arguments::from_args();
+    let bytes = read(&arguments.capture)
+        .with_context(|| format!("Failed to read {}", arguments.capture.display()))?;
+    let packets = read_pcap(&bytes)
+        .with_context(|| format!("Failed to parse {}", arguments.capture.display()))?;
+
+    match &arguments.jsonl {
+        Some(jsonl) => {
+            let mut writer = BufWriter::new(
+                File::create(jsonl)
+                    .with_context(|| format!("Failed to create {}", jsonl.display()))?,
+            );
+            for packet in &packets {
+                serde_json::to_writer(
+                    &mut writer,
+                    &CaptureRecord::new(packet, arguments.team_number),
+                )?;
+                writer.write_all(b"\n")?;
+            }
+            writer.flush()?;
+            info!("Wrote {} packets to {}", packets.len(), jsonl.display());
+        }
+        None => {
+            for packet in &packets {
+                match packet.decode(arguments.team_number) {
+                    Ok(message) => info!(
+                        "{:?} {} -> {}: {message:?}",
+                        packet.time, packet.source, packet.destination
+                    ),
+                    Err(error) => warn!(
+                        "{:?} {} -> {}: failed to decode {} bytes: {error:#}",
+                        packet.time,
+                        packet.source,
+                        packet.destination,
+                        packet.payload.len()
+                    ),
+                }
+            }
+        }
+    }
+    Ok(())
+}
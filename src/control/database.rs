@@ -1,14 +1,18 @@
-use nalgebra::{Isometry2, Isometry3, Point2, Point3, UnitComplex, Vector2, Vector3};
+use std::time::Duration;
+
+use nalgebra::{Isometry2, Isometry3, Matrix3, Point2, Point3, UnitComplex, Vector2, Vector3};
 use serde::{Deserialize, Serialize};
 use serialize_hierarchy::SerializeHierarchy;
+use spl_network::PlayerNumber;
 
 use types::{
-    BallPosition, BodyJointsCommand, Buttons, CameraMatrices, Circle, FallState, FilteredGameState,
-    FilteredWhistle, GameControllerState, HeadJoints, HeadJointsCommand, Joints, JointsCommand,
-    KickDecision, Leds, Line2, LocalizationUpdate, MotionCommand, MotionSafeExits, MotionSelection,
-    Obstacle, PathObstacle, PenaltyShotDirection, PrimaryState, ProjectedFieldLines,
-    ProjectedLimbs, RobotKinematics, Role, SensorData, SolePressure, SonarObstacle, SonarValues,
-    Step, SupportFoot, WalkCommand, WorldState,
+    BallPosition, BodyJointsCommand, Buttons, CameraMatrices, Circle, FallState, FallStatistics,
+    FilteredGameState, FilteredWhistle, FormationState, GameControllerState, HeadJoints,
+    HeadJointsCommand, Joints, JointsCommand, KickDecision, Leds, Line2, LocalizationUpdate,
+    MessageBudgetUsage, MotionCommand, MotionSafeExits, MotionSelection, Obstacle, PathObstacle,
+    PenaltyShotDirection, PrimaryState, ProjectedFieldLines, ProjectedLimbs, RobotKinematics, Role,
+    SensorData, SolePressure, SonarObstacle, SonarValues, Step, SupportFoot, TeammateState,
+    WalkCommand, WorldState,
 };
 
 use crate::spl_network::MessageReceivers;
@@ -16,16 +20,19 @@ use crate::spl_network::MessageReceivers;
 use super::{
     filtering::ScoredPoseFilter,
     modules::{
-        ball_filter::BallFilterHypothesis, motion::walking_engine::WalkingEngine,
+        ball_filter::BallFilterHypothesis,
+        motion::{active_vision::GazeTarget, walking_engine::WalkingEngine},
         obstacle_filter::ObstacleFilterHypothesis,
     },
 };
 
 #[derive(Clone, Debug, Default, Serialize, SerializeHierarchy)]
 pub struct MainOutputs {
+    pub active_vision: Option<HeadJoints>,
     pub arms_up_squat_joints_command: Option<JointsCommand>,
     pub accumulated_odometry: Option<Isometry2<f32>>,
     pub ball_position: Option<BallPosition>,
+    pub ball_filter_hypotheses: Option<Vec<BallFilterHypothesis>>,
     pub buttons: Option<Buttons>,
     pub camera_matrices: Option<CameraMatrices>,
     pub center_of_mass: Option<Point3<f32>>,
@@ -63,6 +70,7 @@ pub struct MainOutputs {
     #[leaf]
     pub robot_orientation: Option<UnitComplex<f32>>,
     pub robot_to_field: Option<Isometry2<f32>>,
+    pub robot_to_field_covariance: Option<Matrix3<f32>>,
     #[leaf]
     pub role: Option<Role>,
     pub sensor_data: Option<SensorData>,
@@ -75,6 +83,7 @@ pub struct MainOutputs {
     pub stiffnesses: Option<Joints>,
     pub support_foot: Option<SupportFoot>,
     pub team_ball: Option<BallPosition>,
+    pub teammates: Option<Vec<TeammateState>>,
     pub robot_to_ground: Option<Isometry3<f32>>,
     #[leaf]
     pub walk_command: Option<WalkCommand>,
@@ -88,10 +97,11 @@ pub struct MainOutputs {
 #[derive(Clone, Debug, Default, Serialize, SerializeHierarchy)]
 pub struct AdditionalOutputs {
     pub accumulated_odometry: Option<Isometry2<f32>>,
-    pub ball_filter_hypotheses: Option<Vec<BallFilterHypothesis>>,
     pub obstacle_filter_hypotheses: Option<Vec<ObstacleFilterHypothesis>>,
     pub walking_engine: Option<WalkingEngine>,
     pub step_adjustment: Option<StepAdjustment>,
+    pub walk_stabilization: Option<WalkStabilization>,
+    pub planned_steps: Option<Vec<Step>>,
     pub projected_field_lines: Option<ProjectedFieldLines>,
     pub localization: Localization,
     pub path_obstacles: Option<Vec<PathObstacle>>,
@@ -100,11 +110,16 @@ pub struct AdditionalOutputs {
     pub sonar_values: Option<SonarValues>,
     pub kick_decisions: Option<Vec<KickDecision>>,
     pub kick_targets: Option<Vec<Point2<f32>>>,
+    pub active_vision_targets: Option<Vec<GazeTarget>>,
     pub filtered_linear_acceleration: Option<Vector3<f32>>,
     pub filtered_angular_velocity: Option<Vector3<f32>>,
     pub filtered_roll_pitch: Option<Vector2<f32>>,
+    pub predicted_roll_pitch: Option<Vector2<f32>>,
+    pub fall_statistics: Option<FallStatistics>,
     pub backward_gravitational_difference: Option<f32>,
     pub forward_gravitational_difference: Option<f32>,
+    pub message_budget: Option<MessageBudgetUsage>,
+    pub formation: Option<FormationState>,
 }
 
 #[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
@@ -116,6 +131,18 @@ pub struct StepAdjustment {
     pub backward_balance_limit: f32,
 }
 
+#[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
+pub struct WalkStabilization {
+    pub center_of_mass: Point2<f32>,
+    pub center_of_mass_velocity: Vector2<f32>,
+    pub zero_moment_point: Option<Point2<f32>>,
+    pub capture_point: Point2<f32>,
+    pub predicted_capture_point: Point2<f32>,
+    pub capture_point_error: Vector2<f32>,
+    pub step_adjustment: Step,
+    pub step_duration: Duration,
+}
+
 #[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
 pub struct Localization {
     pub pose_hypotheses: Option<Vec<ScoredPoseFilter>>,
@@ -136,4 +163,6 @@ pub struct PersistentState {
     pub motion_safe_exits: MotionSafeExits,
     pub walk_return_offset: Step,
     pub robot_to_field: Isometry2<f32>,
+    /// Pass target chosen by the behavior, announced by the role assignment in the next cycle
+    pub pass_target: Option<PlayerNumber>,
 }
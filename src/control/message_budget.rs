@@ -0,0 +1,296 @@
+use std::time::{Duration, SystemTime};
+
+use spl_network::{Half, PlayerNumber};
+use types::{GameControllerState, MessageBudgetUsage, MessageReason, Players};
+
+use crate::framework::configuration::SplNetwork;
+
+/// Plans the SPL messages of this robot around the message budget of the team
+///
+/// The budget above the event reserve is shared by the robots that actually send, i.e. this robot
+/// and the teammates heard from within the striker message timeout, and this robot's share is
+/// spread evenly over the remaining time of the game for periodic messages. Event messages may
+/// additionally spend the reserve, only below `remaining_amount_of_messages_to_stop_sending`
+/// nothing is sent at all.
+#[derive(Default)]
+pub struct MessageBudget {
+    usage: MessageBudgetUsage,
+    last_game_controller_remaining_messages: Option<u16>,
+    last_teammate_messages: Players<Option<SystemTime>>,
+}
+
+impl MessageBudget {
+    pub fn update(
+        &mut self,
+        now: SystemTime,
+        game_controller_state: &GameControllerState,
+        spl_network: &SplNetwork,
+    ) {
+        // the GameController value only changes with its next packet, messages sent in between
+        // are accounted for locally
+        let remaining_amount_of_messages = game_controller_state.remaining_amount_of_messages;
+        if self.last_game_controller_remaining_messages != Some(remaining_amount_of_messages) {
+            self.last_game_controller_remaining_messages = Some(remaining_amount_of_messages);
+            self.usage.remaining_messages = remaining_amount_of_messages;
+        }
+        let remaining_time_in_game = game_controller_state.remaining_time_in_half
+            + match game_controller_state.half {
+                Half::First => spl_network.half_duration,
+                Half::Second => Duration::ZERO,
+            };
+        let sender_timeout = self
+            .striker_message_timeout(spl_network)
+            .unwrap_or(spl_network.spl_striker_message_receive_timeout);
+        let number_of_sending_players = 1 + self
+            .last_teammate_messages
+            .iter()
+            .filter(|(_, last_message_time)| {
+                last_message_time.is_some_and(|last_message_time| {
+                    now.duration_since(last_message_time)
+                        .map_or(true, |age| age <= sender_timeout)
+                })
+            })
+            .count();
+        let remaining_periodic_messages = self
+            .usage
+            .remaining_messages
+            .saturating_sub(spl_network.remaining_amount_of_messages_to_stop_sending)
+            .saturating_sub(spl_network.reserved_amount_of_event_messages);
+        let periodic_message_interval = (remaining_periodic_messages > 0).then(|| {
+            let messages_of_this_robot =
+                remaining_periodic_messages as f32 / number_of_sending_players as f32;
+            remaining_time_in_game
+                .div_f32(messages_of_this_robot)
+                .max(spl_network.spl_striker_message_send_interval)
+        });
+        self.usage = MessageBudgetUsage {
+            remaining_periodic_messages,
+            remaining_time_in_game,
+            number_of_sending_players,
+            periodic_message_interval,
+            ..self.usage
+        };
+    }
+
+    /// Marks the teammate as sending, its messages are spent from the same budget
+    pub fn record_teammate_message(&mut self, player_number: PlayerNumber, now: SystemTime) {
+        self.last_teammate_messages[player_number] = Some(now);
+    }
+
+    pub fn is_periodic_message_due(&self, now: SystemTime) -> bool {
+        match (
+            self.usage.last_message_time,
+            self.usage.periodic_message_interval,
+        ) {
+            (_, None) => false,
+            (None, Some(_)) => true,
+            (Some(last_message_time), Some(periodic_message_interval)) => now
+                .duration_since(last_message_time)
+                .map_or(true, |elapsed| elapsed >= periodic_message_interval),
+        }
+    }
+
+    /// Time without striker messages after which the striker is considered lost, extended by the
+    /// amount the periodic message interval exceeds the configured send interval
+    ///
+    /// `None` if no periodic messages are planned anymore, the roles then only change on events.
+    pub fn striker_message_timeout(&self, spl_network: &SplNetwork) -> Option<Duration> {
+        self.usage
+            .periodic_message_interval
+            .map(|periodic_message_interval| {
+                spl_network.spl_striker_message_receive_timeout
+                    + periodic_message_interval
+                        .saturating_sub(spl_network.spl_striker_message_send_interval)
+            })
+    }
+
+    /// Returns whether the message may be sent and accounts for it if so
+    pub fn try_spend(
+        &mut self,
+        now: SystemTime,
+        reason: MessageReason,
+        spl_network: &SplNetwork,
+    ) -> bool {
+        let is_allowed = if reason.is_event() {
+            self.usage.remaining_messages > spl_network.remaining_amount_of_messages_to_stop_sending
+        } else {
+            self.is_periodic_message_due(now)
+        };
+        if !is_allowed {
+            self.usage.rejected_messages += 1;
+            return false;
+        }
+        if reason.is_event() {
+            self.usage.sent_event_messages += 1;
+        } else {
+            self.usage.sent_periodic_messages += 1;
+        }
+        self.usage.remaining_messages = self.usage.remaining_messages.saturating_sub(1);
+        self.usage.last_message_reason = Some(reason);
+        self.usage.last_message_time = Some(now);
+        true
+    }
+
+    pub fn usage(&self) -> &MessageBudgetUsage {
+        &self.usage
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use spl_network::{GamePhase, GameState, Team, TeamColor};
+
+    use super::*;
+
+    fn spl_network() -> SplNetwork {
+        SplNetwork {
+            remaining_amount_of_messages_to_stop_sending: 20,
+            reserved_amount_of_event_messages: 80,
+            half_duration: Duration::from_secs(600),
+            spl_striker_message_receive_timeout: Duration::from_secs(3),
+            spl_striker_message_send_interval: Duration::from_secs(2),
+            ..Default::default()
+        }
+    }
+
+    fn game_controller_state(
+        remaining_amount_of_messages: u16,
+        half: Half,
+        remaining_time_in_half: Duration,
+    ) -> GameControllerState {
+        GameControllerState {
+            game_state: GameState::Playing,
+            game_phase: GamePhase::Normal,
+            kicking_team: Team::Hulks,
+            last_game_state_change: SystemTime::UNIX_EPOCH,
+            penalties: Players::default(),
+            remaining_amount_of_messages,
+            set_play: None,
+            hulks_team_color: TeamColor::Blue,
+            opponent_team_color: TeamColor::Red,
+            half,
+            remaining_time_in_half,
+            secondary_time: Duration::ZERO,
+            hulks_score: 0,
+            opponent_score: 0,
+        }
+    }
+
+    #[test]
+    fn periodic_messages_are_spread_over_the_remaining_game() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1000);
+        let mut budget = MessageBudget::default();
+        for player_number in [PlayerNumber::One, PlayerNumber::Two, PlayerNumber::Four] {
+            budget.record_teammate_message(player_number, now);
+        }
+        budget.update(
+            now,
+            &game_controller_state(1200, Half::First, Duration::from_secs(600)),
+            &spl_network(),
+        );
+        // 1100 messages for 4 sending players in 1200 s
+        assert_eq!(budget.usage().number_of_sending_players, 4);
+        let periodic_message_interval = budget.usage().periodic_message_interval.unwrap();
+        assert!((periodic_message_interval.as_secs_f32() - 1200.0 * 4.0 / 1100.0).abs() < 1e-3);
+
+        budget.update(
+            now,
+            &game_controller_state(420, Half::Second, Duration::from_secs(100)),
+            &spl_network(),
+        );
+        // 320 messages for 4 players in 100 s is limited by the configured send interval
+        assert_eq!(
+            budget.usage().periodic_message_interval,
+            Some(Duration::from_secs(2))
+        );
+        assert_eq!(
+            budget.striker_message_timeout(&spl_network()),
+            Some(Duration::from_secs(3))
+        );
+    }
+
+    #[test]
+    fn budget_is_shared_by_recently_sending_players_only() {
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1000);
+        let state = game_controller_state(1200, Half::First, Duration::from_secs(600));
+        let mut budget = MessageBudget::default();
+        budget.record_teammate_message(PlayerNumber::Two, start);
+        budget.record_teammate_message(PlayerNumber::Five, start);
+        budget.update(start, &state, &spl_network());
+        assert_eq!(budget.usage().number_of_sending_players, 3);
+        let periodic_message_interval = budget.usage().periodic_message_interval.unwrap();
+        assert!((periodic_message_interval.as_secs_f32() - 1200.0 * 3.0 / 1100.0).abs() < 1e-3);
+
+        let later = start + Duration::from_secs(10);
+        budget.record_teammate_message(PlayerNumber::Two, later);
+        budget.update(later, &state, &spl_network());
+        assert_eq!(budget.usage().number_of_sending_players, 2);
+        let periodic_message_interval = budget.usage().periodic_message_interval.unwrap();
+        assert!((periodic_message_interval.as_secs_f32() - 1200.0 * 2.0 / 1100.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn sent_messages_are_counted_until_the_game_controller_reports_a_new_value() {
+        let spl_network = spl_network();
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1000);
+        let mut budget = MessageBudget::default();
+        let state = game_controller_state(500, Half::Second, Duration::from_secs(300));
+        budget.update(now, &state, &spl_network);
+        assert!(budget.try_spend(now, MessageReason::Fallen, &spl_network));
+        assert!(budget.try_spend(now, MessageReason::RoleChanged, &spl_network));
+
+        budget.update(now, &state, &spl_network);
+        assert_eq!(budget.usage().remaining_messages, 498);
+        assert_eq!(budget.usage().remaining_periodic_messages, 398);
+
+        budget.update(
+            now,
+            &game_controller_state(490, Half::Second, Duration::from_secs(299)),
+            &spl_network,
+        );
+        assert_eq!(budget.usage().remaining_messages, 490);
+    }
+
+    #[test]
+    fn events_spend_the_reserve_and_periodic_messages_do_not() {
+        let spl_network = spl_network();
+        let mut budget = MessageBudget::default();
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1000);
+        budget.update(
+            now,
+            &game_controller_state(60, Half::Second, Duration::from_secs(300)),
+            &spl_network,
+        );
+        assert_eq!(budget.usage().periodic_message_interval, None);
+        assert_eq!(budget.striker_message_timeout(&spl_network), None);
+        assert!(!budget.try_spend(now, MessageReason::Periodic, &spl_network));
+        assert!(budget.try_spend(now, MessageReason::Fallen, &spl_network));
+        assert_eq!(budget.usage().remaining_messages, 59);
+        assert_eq!(budget.usage().sent_event_messages, 1);
+        assert_eq!(budget.usage().rejected_messages, 1);
+
+        budget.update(
+            now,
+            &game_controller_state(20, Half::Second, Duration::from_secs(300)),
+            &spl_network,
+        );
+        assert!(!budget.try_spend(now, MessageReason::RoleChanged, &spl_network));
+    }
+
+    #[test]
+    fn periodic_messages_wait_for_the_planned_interval() {
+        let spl_network = spl_network();
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1000);
+        let mut budget = MessageBudget::default();
+        budget.update(
+            start,
+            &game_controller_state(1200, Half::Second, Duration::from_secs(600)),
+            &spl_network,
+        );
+        let interval = budget.usage().periodic_message_interval.unwrap();
+        assert!(budget.try_spend(start, MessageReason::Periodic, &spl_network));
+        assert!(!budget.is_periodic_message_due(start + interval / 2));
+        assert!(budget.is_periodic_message_due(start + interval));
+        assert_eq!(budget.usage().sent_periodic_messages, 1);
+    }
+}
@@ -5,6 +5,7 @@ mod cycler;
 mod database;
 mod filtering;
 mod linear_interpolator;
+mod message_budget;
 mod modules;
 mod path_planner;
 mod sensor_data_receiver;
@@ -42,10 +42,10 @@ pub struct BallFilter {
 #[historic_input(path = current_odometry_to_last_odometry, data_type = Isometry2<f32>)]
 #[perception_input(name = balls_top, path = balls, data_type = Vec<Ball>, cycler = vision_top)]
 #[perception_input(name = balls_bottom, path = balls, data_type = Vec<Ball>, cycler = vision_bottom)]
-#[additional_output(path = ball_filter_hypotheses, data_type = Vec<BallFilterHypothesis>)]
 #[additional_output(path = filtered_balls_in_image_top, data_type = Vec<Circle>)]
 #[additional_output(path = filtered_balls_in_image_bottom, data_type = Vec<Circle>)]
 #[main_output(name = ball_position, data_type = BallPosition )]
+#[main_output(name = ball_filter_hypotheses, data_type = Vec<BallFilterHypothesis>)]
 impl BallFilter {}
 
 impl BallFilter {
@@ -110,11 +110,10 @@ impl BallFilter {
         let best_hypothesis = self.find_best_hypothesis();
         let ball_position = best_hypothesis.map(|hypothesis| BallPosition {
             position: Point2::from(hypothesis.filter.state().xy()),
+            velocity: hypothesis.filter.state().fixed_rows::<2>(2).into_owned(),
+            covariance: hypothesis.filter.covariance(),
             last_seen: hypothesis.last_update,
         });
-        context
-            .ball_filter_hypotheses
-            .fill_on_subscription(|| self.hypotheses.clone());
         if let Some(camera_matrices) = &context.camera_matrices.as_ref() {
             let ball_radius = context.field_dimensions.ball_radius;
             context
@@ -138,7 +137,10 @@ impl BallFilter {
                         .collect()
                 });
         }
-        Ok(MainOutputs { ball_position })
+        Ok(MainOutputs {
+            ball_position,
+            ball_filter_hypotheses: Some(self.hypotheses.clone()),
+        })
     }
 
     fn decay_hypotheses(
@@ -305,6 +307,21 @@ impl BallFilter {
 }
 
 impl BallFilterHypothesis {
+    pub fn position(&self) -> Point2<f32> {
+        Point2::from(self.filter.state().xy())
+    }
+
+    pub fn position_covariance(&self) -> Matrix2<f32> {
+        self.filter
+            .covariance()
+            .fixed_slice::<2, 2>(0, 0)
+            .into_owned()
+    }
+
+    pub fn validity(&self) -> f32 {
+        self.validity
+    }
+
     fn project_to_image(&self, camera_matrix: &CameraMatrix, ball_radius: f32) -> Option<Circle> {
         let pixel_position = camera_matrix
             .ground_with_z_to_pixel(&Point2::from(self.filter.state().xy()), ball_radius)
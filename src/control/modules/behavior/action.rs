@@ -11,8 +11,12 @@ pub enum Action {
     DefendKickOff,
     DefendLeft,
     DefendRight,
+    DefendSetPlay,
     Jump,
+    Pass,
     PrepareJump,
+    ReceivePass,
+    SupportSetPlay,
     SupportStriker,
     Search,
     SearchForLostBall,
@@ -1,19 +1,25 @@
 use std::ops::Range;
 
-use nalgebra::{distance, point, Isometry2, Point2};
+use nalgebra::{distance, point, Isometry2, Point2, Vector2};
 use spl_network::Team;
 use types::{
-    rotate_towards, BallState, FieldDimensions, Line, MotionCommand, PathObstacle, Side, WorldState,
+    rotate_towards, BallState, FieldDimensions, FormationPositions, Line, Line2, MotionCommand,
+    PathObstacle, Role, WorldState,
 };
 
-use crate::framework::{configuration::RolePositions, AdditionalOutput};
+use crate::framework::{
+    configuration::{InterceptBall, RolePositions},
+    AdditionalOutput,
+};
 
-use super::{head::LookAction, walk_to_pose::WalkAndStand};
+use super::{head::LookAction, positioning::keep_distance_to_ball, walk_to_pose::WalkAndStand};
 
 pub struct Defend<'cycle> {
     world_state: &'cycle WorldState,
     field_dimensions: &'cycle FieldDimensions,
     role_positions: &'cycle RolePositions,
+    intercept_ball: &'cycle InterceptBall,
+    formation_positions: Option<&'cycle FormationPositions>,
     walk_and_stand: &'cycle WalkAndStand<'cycle>,
     look_action: &'cycle LookAction<'cycle>,
 }
@@ -23,6 +29,8 @@ impl<'cycle> Defend<'cycle> {
         world_state: &'cycle WorldState,
         field_dimensions: &'cycle FieldDimensions,
         role_positions: &'cycle RolePositions,
+        intercept_ball: &'cycle InterceptBall,
+        formation_positions: Option<&'cycle FormationPositions>,
         walk_and_stand: &'cycle WalkAndStand,
         look_action: &'cycle LookAction,
     ) -> Self {
@@ -30,6 +38,8 @@ impl<'cycle> Defend<'cycle> {
             world_state,
             field_dimensions,
             role_positions,
+            intercept_ball,
+            formation_positions,
             walk_and_stand,
             look_action,
         }
@@ -48,7 +58,11 @@ impl<'cycle> Defend<'cycle> {
         &self,
         path_obstacles_output: &mut AdditionalOutput<Vec<PathObstacle>>,
     ) -> Option<MotionCommand> {
-        let pose = defend_left_pose(self.world_state, self.field_dimensions, self.role_positions)?;
+        let pose = defend_formation_pose(
+            self.world_state,
+            self.formation_positions?.defender_left,
+            self.intercept_ball,
+        )?;
         self.with_pose(pose, path_obstacles_output)
     }
 
@@ -56,7 +70,11 @@ impl<'cycle> Defend<'cycle> {
         &self,
         path_obstacles_output: &mut AdditionalOutput<Vec<PathObstacle>>,
     ) -> Option<MotionCommand> {
-        let pose = defend_right_pose(self.world_state, self.field_dimensions, self.role_positions)?;
+        let pose = defend_formation_pose(
+            self.world_state,
+            self.formation_positions?.defender_right,
+            self.intercept_ball,
+        )?;
         self.with_pose(pose, path_obstacles_output)
     }
 
@@ -64,7 +82,12 @@ impl<'cycle> Defend<'cycle> {
         &self,
         path_obstacles_output: &mut AdditionalOutput<Vec<PathObstacle>>,
     ) -> Option<MotionCommand> {
-        let pose = defend_goal_pose(self.world_state, self.field_dimensions, self.role_positions)?;
+        let pose = defend_goal_pose(
+            self.world_state,
+            self.field_dimensions,
+            self.role_positions,
+            self.intercept_ball,
+        )?;
         self.with_pose(pose, path_obstacles_output)
     }
 
@@ -76,63 +99,44 @@ impl<'cycle> Defend<'cycle> {
             defend_kick_off_pose(self.world_state, self.field_dimensions, self.role_positions)?;
         self.with_pose(pose, path_obstacles_output)
     }
-}
 
-fn defend_left_pose(
-    world_state: &WorldState,
-    field_dimensions: &FieldDimensions,
-    role_positions: &RolePositions,
-) -> Option<Isometry2<f32>> {
-    let robot_to_field = world_state.robot.robot_to_field?;
-    let ball = world_state
-        .ball
-        .map(|ball| BallState {
-            position: robot_to_field * ball.position,
-            field_side: ball.field_side,
-            penalty_shot_direction: Default::default(),
-        })
-        .unwrap_or_default();
-
-    let position_to_defend = point![
-        -field_dimensions.length / 2.0,
-        role_positions.defender_y_offset
-    ];
-    let distance_to_target = if ball.field_side == Side::Left {
-        role_positions.defender_aggressive_ring_radius
-    } else {
-        role_positions.defender_passive_ring_radius
-    };
-
-    let defend_pose = block_on_circle(ball.position, position_to_defend, distance_to_target);
-    Some(robot_to_field.inverse() * defend_pose)
+    pub fn set_play(
+        &self,
+        absolute_last_known_ball_position: Point2<f32>,
+        path_obstacles_output: &mut AdditionalOutput<Vec<PathObstacle>>,
+    ) -> Option<MotionCommand> {
+        let pose = defend_set_play_pose(
+            self.world_state,
+            self.field_dimensions,
+            self.role_positions,
+            self.formation_positions,
+            absolute_last_known_ball_position,
+        )?;
+        self.with_pose(pose, path_obstacles_output)
+    }
 }
 
-fn defend_right_pose(
+/// Stands at the position of the formation facing the ball, unless a rolling ball can be
+/// intercepted along the defense line through that position
+fn defend_formation_pose(
     world_state: &WorldState,
-    field_dimensions: &FieldDimensions,
-    role_positions: &RolePositions,
+    formation_position: Point2<f32>,
+    intercept_ball: &InterceptBall,
 ) -> Option<Isometry2<f32>> {
     let robot_to_field = world_state.robot.robot_to_field?;
-    let ball = world_state
-        .ball
-        .map(|ball| BallState {
-            position: robot_to_field * ball.position,
-            field_side: ball.field_side,
-            penalty_shot_direction: Default::default(),
-        })
-        .unwrap_or_default();
-
-    let position_to_defend = point![
-        -field_dimensions.length / 2.0,
-        -role_positions.defender_y_offset
-    ];
-    let distance_to_target = if ball.field_side == Side::Right {
-        role_positions.defender_aggressive_ring_radius
-    } else {
-        role_positions.defender_passive_ring_radius
-    };
+    let ball = ball_in_field(world_state, robot_to_field).unwrap_or_default();
 
-    let defend_pose = block_on_circle(ball.position, position_to_defend, distance_to_target);
+    let defend_pose = Isometry2::new(
+        formation_position.coords,
+        rotate_towards(formation_position, ball.position).angle(),
+    );
+    let defense_line = vertical_line(
+        formation_position.x,
+        formation_position.y - intercept_ball.defender_interception_range
+            ..formation_position.y + intercept_ball.defender_interception_range,
+    );
+    let defend_pose =
+        intercept_rolling_ball(&ball, defense_line, intercept_ball).unwrap_or(defend_pose);
     Some(robot_to_field.inverse() * defend_pose)
 }
 
@@ -140,16 +144,10 @@ fn defend_goal_pose(
     world_state: &WorldState,
     field_dimensions: &FieldDimensions,
     role_positions: &RolePositions,
+    intercept_ball: &InterceptBall,
 ) -> Option<Isometry2<f32>> {
     let robot_to_field = world_state.robot.robot_to_field?;
-    let ball = world_state
-        .ball
-        .map(|ball| BallState {
-            position: robot_to_field * ball.position,
-            field_side: ball.field_side,
-            penalty_shot_direction: Default::default(),
-        })
-        .unwrap_or_default();
+    let ball = ball_in_field(world_state, robot_to_field).unwrap_or_default();
 
     let keeper_x_offset = match world_state.game_phase {
         spl_network::GamePhase::PenaltyShootout {
@@ -159,12 +157,18 @@ fn defend_goal_pose(
     };
 
     let position_to_defend = point![-field_dimensions.length / 2.0 - 1.0, 0.0];
-    let defend_pose = block_on_line(
-        ball.position,
-        position_to_defend,
-        -field_dimensions.length / 2.0 + keeper_x_offset,
-        -0.7..0.7,
-    );
+    let defense_line_x = -field_dimensions.length / 2.0 + keeper_x_offset;
+    let defense_line_y_range = -0.7..0.7;
+    let defense_line = vertical_line(defense_line_x, defense_line_y_range.clone());
+    let defend_pose =
+        intercept_rolling_ball(&ball, defense_line, intercept_ball).unwrap_or_else(|| {
+            block_on_line(
+                ball.position,
+                position_to_defend,
+                defense_line_x,
+                defense_line_y_range,
+            )
+        });
     Some(robot_to_field.inverse() * defend_pose)
 }
 
@@ -189,6 +193,88 @@ fn defend_kick_off_pose(
     Some(robot_to_field.inverse() * defend_pose)
 }
 
+/// Keeps the distance the rules require to the ball during opponent set plays, the striker blocks
+/// the way to the own goal and all other field players stay at their formation position or where
+/// they are if they have none
+///
+/// Without a seen ball the last known ball position is kept away from.
+fn defend_set_play_pose(
+    world_state: &WorldState,
+    field_dimensions: &FieldDimensions,
+    role_positions: &RolePositions,
+    formation_positions: Option<&FormationPositions>,
+    absolute_last_known_ball_position: Point2<f32>,
+) -> Option<Isometry2<f32>> {
+    let robot_to_field = world_state.robot.robot_to_field?;
+    let ball_position = match world_state.ball {
+        Some(ball) => robot_to_field * ball.position,
+        None => absolute_last_known_ball_position,
+    };
+    let role = world_state.robot.role;
+    let position = if role == Role::Striker {
+        block_set_play_position(ball_position, field_dimensions, role_positions)
+    } else {
+        let position = formation_positions
+            .and_then(|formation_positions| formation_positions.position(role))
+            .unwrap_or_else(|| robot_to_field * Point2::origin());
+        keep_distance_to_ball(
+            position,
+            ball_position,
+            role_positions.distance_to_opponent_set_play_ball,
+        )
+    };
+    let defend_pose = Isometry2::new(
+        position.coords,
+        rotate_towards(position, ball_position).angle(),
+    );
+    Some(robot_to_field.inverse() * defend_pose)
+}
+
+/// Blocks the way from the ball to the own goal at the distance the rules require, staying inside
+/// the field when the ball lies on a line
+fn block_set_play_position(
+    absolute_ball_position: Point2<f32>,
+    field_dimensions: &FieldDimensions,
+    role_positions: &RolePositions,
+) -> Point2<f32> {
+    let own_goal_center = point![-field_dimensions.length / 2.0, 0.0];
+    let ball_to_own_goal = own_goal_center - absolute_ball_position;
+    let direction = if ball_to_own_goal.norm() > f32::EPSILON {
+        ball_to_own_goal.normalize()
+    } else {
+        Vector2::x()
+    };
+    let block_position =
+        absolute_ball_position + direction * role_positions.distance_to_opponent_set_play_ball;
+    point![
+        block_position.x.clamp(
+            -field_dimensions.length / 2.0,
+            field_dimensions.length / 2.0
+        ),
+        block_position
+            .y
+            .clamp(-field_dimensions.width / 2.0, field_dimensions.width / 2.0)
+    ]
+}
+
+/// Ball of the world state in field coordinates
+pub fn ball_in_field(
+    world_state: &WorldState,
+    robot_to_field: Isometry2<f32>,
+) -> Option<BallState> {
+    world_state.ball.map(|ball| BallState {
+        position: robot_to_field * ball.position,
+        velocity: robot_to_field * ball.velocity,
+        penalty_shot_direction: ball.penalty_shot_direction,
+        field_side: ball.field_side,
+    })
+}
+
+/// Line at a fixed x coordinate spanning the y range, e.g. the line a defender blocks on
+fn vertical_line(x: f32, y_range: Range<f32>) -> Line2 {
+    Line(point![x, y_range.start], point![x, y_range.end])
+}
+
 pub fn block_on_circle(
     ball_position: Point2<f32>,
     target: Point2<f32>,
@@ -202,6 +288,27 @@ pub fn block_on_circle(
     )
 }
 
+/// Returns the pose on the defense line where a rolling ball will cross it, if the ball gets there
+/// soon enough
+pub fn intercept_rolling_ball(
+    ball: &BallState,
+    defense_line: Line2,
+    intercept_ball: &InterceptBall,
+) -> Option<Isometry2<f32>> {
+    if ball.velocity.norm() < intercept_ball.minimum_ball_speed {
+        return None;
+    }
+    let crossing =
+        ball.predicted_line_crossing(defense_line, intercept_ball.rolling_deceleration)?;
+    if crossing.time_to_crossing > intercept_ball.maximum_time_to_interception {
+        return None;
+    }
+    Some(Isometry2::new(
+        crossing.position.coords,
+        rotate_towards(crossing.position, ball.position).angle(),
+    ))
+}
+
 fn block_on_line(
     ball_position: Point2<f32>,
     target: Point2<f32>,
@@ -210,10 +317,7 @@ fn block_on_line(
 ) -> Isometry2<f32> {
     let is_ball_in_front_of_defense_line = defense_line_x < ball_position.x;
     if is_ball_in_front_of_defense_line {
-        let defense_line = Line(
-            point![defense_line_x, defense_line_y_range.start],
-            point![defense_line_x, defense_line_y_range.end],
-        );
+        let defense_line = vertical_line(defense_line_x, defense_line_y_range.clone());
         let ball_target_line = Line(ball_position, target);
         let intersection_point = defense_line.intersection(&ball_target_line);
         let defense_position = point![
@@ -237,3 +341,137 @@ fn block_on_line(
         )
     }
 }
+
+#[cfg(test)]
+pub mod tests {
+    use std::{f32::consts::PI, time::Duration};
+
+    use approx::assert_relative_eq;
+    use nalgebra::vector;
+    use types::RobotState;
+
+    use super::*;
+
+    pub fn intercept_ball() -> InterceptBall {
+        InterceptBall {
+            rolling_deceleration: 0.4,
+            minimum_ball_speed: 0.3,
+            maximum_time_to_interception: Duration::from_secs(1),
+            defender_interception_range: 1.0,
+            keeper_jump_reach: 0.6,
+            keeper_jump_time: Duration::from_millis(800),
+            keeper_minimum_jump_offset: 0.15,
+        }
+    }
+
+    fn ball(position: Point2<f32>, velocity: Vector2<f32>) -> BallState {
+        BallState {
+            position,
+            velocity,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn rolling_ball_is_intercepted_where_it_crosses_the_defense_line() {
+        let defense_line = vertical_line(0.0, -1.0..1.0);
+        let pose = intercept_rolling_ball(
+            &ball(point![1.0, 0.5], vector![-2.0, -0.5]),
+            defense_line,
+            &intercept_ball(),
+        )
+        .unwrap();
+
+        assert_relative_eq!(pose.translation.vector, vector![0.0, 0.25], epsilon = 1e-5);
+        assert_relative_eq!(pose.rotation.angle(), 0.25_f32.atan2(1.0), epsilon = 1e-5);
+    }
+
+    #[test]
+    fn slow_missing_and_late_balls_are_not_intercepted() {
+        let defense_line = vertical_line(0.0, -1.0..1.0);
+        let intercept_ball = intercept_ball();
+
+        let lying_ball = ball(point![1.0, 0.0], vector![-0.2, 0.0]);
+        assert_eq!(
+            intercept_rolling_ball(&lying_ball, defense_line, &intercept_ball),
+            None
+        );
+        let passing_ball = ball(point![1.0, 0.0], vector![-2.0, 3.0]);
+        assert_eq!(
+            intercept_rolling_ball(&passing_ball, defense_line, &intercept_ball),
+            None
+        );
+        let distant_ball = ball(point![3.0, 0.0], vector![-2.0, 0.0]);
+        assert_eq!(
+            intercept_rolling_ball(&distant_ball, defense_line, &intercept_ball),
+            None
+        );
+    }
+
+    #[test]
+    fn defender_leaves_formation_position_only_for_rolling_balls() {
+        let robot_to_field = Isometry2::new(vector![-2.0, 0.0], 0.0);
+        let formation_position = point![-2.0, 1.0];
+        let mut world_state = WorldState {
+            ball: Some(ball(point![3.0, 1.0], Vector2::zeros())),
+            robot: RobotState {
+                robot_to_field: Some(robot_to_field),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let pose =
+            defend_formation_pose(&world_state, formation_position, &intercept_ball()).unwrap();
+        assert_relative_eq!(
+            robot_to_field * pose,
+            Isometry2::new(formation_position.coords, 0.0),
+            epsilon = 1e-5
+        );
+
+        world_state.ball = Some(ball(point![1.0, 1.5], vector![-2.0, -0.5]));
+        let pose =
+            defend_formation_pose(&world_state, formation_position, &intercept_ball()).unwrap();
+        assert_relative_eq!(
+            robot_to_field * pose,
+            Isometry2::new(vector![-2.0, 1.25], 0.25_f32.atan2(1.0)),
+            epsilon = 1e-5
+        );
+    }
+
+    #[test]
+    fn defenders_keep_set_play_distance_to_last_known_ball() {
+        let robot_to_field = Isometry2::new(vector![-3.0, 1.0], 0.0);
+        let formation_positions = FormationPositions {
+            defender_left: point![-3.0, 1.0],
+            defender_right: point![-3.0, -1.0],
+            striker_supporter: point![-1.0, 0.0],
+        };
+        let role_positions = RolePositions {
+            distance_to_opponent_set_play_ball: 0.95,
+            ..Default::default()
+        };
+        let world_state = WorldState {
+            robot: RobotState {
+                robot_to_field: Some(robot_to_field),
+                role: Role::DefenderLeft,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let pose = defend_set_play_pose(
+            &world_state,
+            &FieldDimensions::default(),
+            &role_positions,
+            Some(&formation_positions),
+            point![-3.5, 1.0],
+        )
+        .unwrap();
+        assert_relative_eq!(
+            robot_to_field * pose,
+            Isometry2::new(vector![-2.55, 1.0], PI),
+            epsilon = 1e-5
+        );
+    }
+}
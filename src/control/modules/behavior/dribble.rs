@@ -1,11 +1,11 @@
 use std::cmp::Ordering;
 
 use itertools::iproduct;
-use nalgebra::{point, vector, Isometry2, Point2, Rotation2, UnitComplex};
+use nalgebra::{distance, point, vector, Isometry2, Point2, Rotation2, UnitComplex, Vector2};
 use ordered_float::NotNan;
 use types::{
     rotate_towards, Circle, FieldDimensions, HeadMotion, KickDecision, KickVariant, LineSegment,
-    MotionCommand, Obstacle,
+    MotionCommand, Obstacle, ObstacleKind,
     OrientationMode::{self, AlignWithPath},
     PathObstacle, Side, WorldState,
 };
@@ -62,9 +62,6 @@ pub fn execute(
 ) -> Option<MotionCommand> {
     let robot_to_field = world_state.robot.robot_to_field?;
     let relative_ball_position = world_state.ball?.position;
-    let head = HeadMotion::LookAt {
-        target: relative_ball_position,
-    };
 
     let targets_to_kick_to = find_targets_to_kick_to(
         relative_ball_position,
@@ -75,6 +72,34 @@ pub fn execute(
     );
     kick_targets_output.fill_on_subscription(|| targets_to_kick_to.clone());
 
+    kick_to_targets(
+        world_state,
+        field_dimensions,
+        parameters,
+        walk_path_planner,
+        &targets_to_kick_to,
+        path_obstacles_output,
+        kick_decisions_output,
+    )
+}
+
+/// Kicks if a kick pose for any of the targets in robot coordinates is reached, otherwise walks
+/// to the best kick pose
+pub fn kick_to_targets(
+    world_state: &WorldState,
+    field_dimensions: &FieldDimensions,
+    parameters: &Dribbling,
+    walk_path_planner: &WalkPathPlanner,
+    targets_to_kick_to: &[Point2<f32>],
+    path_obstacles_output: &mut AdditionalOutput<Vec<PathObstacle>>,
+    kick_decisions_output: &mut AdditionalOutput<Vec<KickDecision>>,
+) -> Option<MotionCommand> {
+    let robot_to_field = world_state.robot.robot_to_field?;
+    let relative_ball_position = world_state.ball?.position;
+    let head = HeadMotion::LookAt {
+        target: relative_ball_position,
+    };
+
     let sides = [Side::Left, Side::Right];
     let mut kick_variants = Vec::new();
     if parameters.in_walk_kicks.forward.enabled {
@@ -91,7 +116,7 @@ pub fn execute(
     let kick_decisions: Vec<_> = iproduct!(sides, kick_variants)
         .filter_map(|(side, kick_variant)| {
             kick_decisions_from_targets(
-                &targets_to_kick_to,
+                targets_to_kick_to,
                 &parameters.in_walk_kicks,
                 kick_variant,
                 side,
@@ -199,38 +224,10 @@ fn find_targets_to_kick_to(
     parameters: &Dribbling,
 ) -> Vec<Point2<f32>> {
     let field_to_robot = robot_to_field.inverse();
-    let left_goal_half = field_to_robot
-        * point![
-            field_dimensions.length / 2.0,
-            field_dimensions.goal_inner_width / 4.0
-        ];
-    let right_goal_half = field_to_robot
-        * point![
-            field_dimensions.length / 2.0,
-            -field_dimensions.goal_inner_width / 4.0
-        ];
-    let obstacle_circles: Vec<_> = obstacles
-        .iter()
-        .map(|obstacle| {
-            let ball_to_obstacle = obstacle.position - ball_position;
-            let obstacle_radius =
-                obstacle.radius_at_foot_height + parameters.ball_radius_for_kick_target_selection;
-            let safety_radius = obstacle_radius / parameters.max_kick_around_obstacle_angle.sin();
-            let distance_to_obstacle = ball_to_obstacle.norm();
-            let center = if distance_to_obstacle < safety_radius {
-                obstacle.position
-                    + ball_to_obstacle.normalize() * (safety_radius - distance_to_obstacle)
-            } else {
-                obstacle.position
-            };
-            Circle {
-                center,
-                radius: obstacle_radius,
-            }
-        })
-        .collect();
+    let obstacle_circles = obstacle_circles(ball_position, obstacles, parameters);
 
-    let mut possible_kick_targets = vec![left_goal_half, right_goal_half];
+    let mut possible_kick_targets =
+        goal_kick_targets(field_to_robot, field_dimensions, obstacles, parameters).to_vec();
 
     let own_goal_center = field_to_robot
         * point![
@@ -278,6 +275,118 @@ fn find_targets_to_kick_to(
         .collect()
 }
 
+/// Whether the ball in robot coordinates can be kicked into a half of the opponent goal at most
+/// `maximum_shot_distance` away without kicking around an obstacle
+pub fn has_free_shot_on_goal(
+    ball_position: Point2<f32>,
+    robot_to_field: Isometry2<f32>,
+    field_dimensions: &FieldDimensions,
+    obstacles: &[Obstacle],
+    parameters: &Dribbling,
+    maximum_shot_distance: f32,
+) -> bool {
+    let obstacle_circles = obstacle_circles(ball_position, obstacles, parameters);
+    goal_kick_targets(
+        robot_to_field.inverse(),
+        field_dimensions,
+        obstacles,
+        parameters,
+    )
+    .into_iter()
+    .filter(|target| distance(&ball_position, target) <= maximum_shot_distance)
+    .any(|target| {
+        let ball_to_target = LineSegment(ball_position, target);
+        !obstacle_circles
+            .iter()
+            .any(|circle| circle.intersects_line_segment(&ball_to_target))
+    })
+}
+
+/// Centers of the left and right half of the opponent goal in robot coordinates
+fn goal_kick_targets(
+    field_to_robot: Isometry2<f32>,
+    field_dimensions: &FieldDimensions,
+    obstacles: &[Obstacle],
+    parameters: &Dribbling,
+) -> [Point2<f32>; 2] {
+    let goal_correction = opponent_goal_correction(
+        field_to_robot,
+        field_dimensions,
+        obstacles,
+        parameters.goal_post_matching_distance,
+    );
+    let left_goal_half = field_to_robot
+        * point![
+            field_dimensions.length / 2.0,
+            field_dimensions.goal_inner_width / 4.0
+        ]
+        + goal_correction;
+    let right_goal_half = field_to_robot
+        * point![
+            field_dimensions.length / 2.0,
+            -field_dimensions.goal_inner_width / 4.0
+        ]
+        + goal_correction;
+    [left_goal_half, right_goal_half]
+}
+
+/// Obstacles enlarged by the ball radius, obstacles close to the ball are pushed away so that the
+/// ball is not kicked around them at a steep angle
+fn obstacle_circles(
+    ball_position: Point2<f32>,
+    obstacles: &[Obstacle],
+    parameters: &Dribbling,
+) -> Vec<Circle> {
+    obstacles
+        .iter()
+        .map(|obstacle| {
+            let ball_to_obstacle = obstacle.position - ball_position;
+            let obstacle_radius =
+                obstacle.radius_at_foot_height + parameters.ball_radius_for_kick_target_selection;
+            let safety_radius = obstacle_radius / parameters.max_kick_around_obstacle_angle.sin();
+            let distance_to_obstacle = ball_to_obstacle.norm();
+            let center = if distance_to_obstacle < safety_radius {
+                obstacle.position
+                    + ball_to_obstacle.normalize() * (safety_radius - distance_to_obstacle)
+            } else {
+                obstacle.position
+            };
+            Circle {
+                center,
+                radius: obstacle_radius,
+            }
+        })
+        .collect()
+}
+
+/// Offset of the opponent goal posts as seen in the goal post obstacles relative to where the
+/// localization expects them. Kicking at the measured goal is robust against localization errors.
+fn opponent_goal_correction(
+    field_to_robot: Isometry2<f32>,
+    field_dimensions: &FieldDimensions,
+    obstacles: &[Obstacle],
+    goal_post_matching_distance: f32,
+) -> Vector2<f32> {
+    let corrections: Vec<_> = field_dimensions
+        .goal_post_positions()
+        .into_iter()
+        .filter(|goal_post| goal_post.x > 0.0)
+        .filter_map(|goal_post| {
+            let expected_goal_post = field_to_robot * goal_post;
+            obstacles
+                .iter()
+                .filter(|obstacle| matches!(obstacle.kind, ObstacleKind::GoalPost))
+                .map(|obstacle| obstacle.position - expected_goal_post)
+                .filter(|correction| correction.norm() < goal_post_matching_distance)
+                .min_by_key(|correction| NotNan::new(correction.norm()).unwrap())
+        })
+        .collect();
+    if corrections.is_empty() {
+        return Vector2::zeros();
+    }
+    corrections.iter().sum::<Vector2<f32>>() / corrections.len() as f32
+}
+
 fn is_inside_any_obstacle(
     kick_pose: Isometry2<f32>,
     obstacles: &[Obstacle],
@@ -27,6 +27,7 @@ impl<'cycle> LookAction<'cycle> {
 
     pub fn execute(&self) -> HeadMotion {
         match self.world_state.ball {
+            Some(_) if self.parameters.use_active_vision => HeadMotion::ActiveVision,
             Some(ball) => HeadMotion::LookAt {
                 target: ball.position,
             },
@@ -60,7 +61,7 @@ impl<'cycle> LookAction<'cycle> {
     }
 }
 
-fn generate_field_marks(field_dimensions: &FieldDimensions) -> Vec<Point2<f32>> {
+pub fn generate_field_marks(field_dimensions: &FieldDimensions) -> Vec<Point2<f32>> {
     let left_center_circle_junction = point![0.0, field_dimensions.center_circle_diameter / 2.0];
     let right_center_circle_junction = point![0.0, -field_dimensions.center_circle_diameter / 2.0];
     let left_center_t_junction = point![0.0, field_dimensions.width / 2.0];
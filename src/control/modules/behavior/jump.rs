@@ -1,15 +1,103 @@
-use types::{JumpDirection, MotionCommand, PenaltyShotDirection, WorldState};
+use nalgebra::point;
+use types::{JumpDirection, Line, MotionCommand, PenaltyShotDirection, WorldState};
 
-pub fn execute(world_state: &WorldState) -> Option<MotionCommand> {
-    world_state
-        .ball
-        .and_then(|ball| match ball.penalty_shot_direction {
-            Some(PenaltyShotDirection::Left) => Some(MotionCommand::Jump {
+use crate::framework::configuration::InterceptBall;
+
+pub fn execute(world_state: &WorldState, intercept_ball: &InterceptBall) -> Option<MotionCommand> {
+    let ball = world_state.ball?;
+    match ball.penalty_shot_direction {
+        Some(PenaltyShotDirection::Left) => {
+            return Some(MotionCommand::Jump {
                 direction: JumpDirection::Left,
-            }),
-            Some(PenaltyShotDirection::Right) => Some(MotionCommand::Jump {
+            })
+        }
+        Some(PenaltyShotDirection::Right) => {
+            return Some(MotionCommand::Jump {
                 direction: JumpDirection::Right,
+            })
+        }
+        Some(PenaltyShotDirection::NotMoving) | None => {}
+    }
+    if ball.velocity.norm() < intercept_ball.minimum_ball_speed {
+        return None;
+    }
+    let jump_reach = Line(
+        point![0.0, -intercept_ball.keeper_jump_reach],
+        point![0.0, intercept_ball.keeper_jump_reach],
+    );
+    let crossing = ball.predicted_line_crossing(jump_reach, intercept_ball.rolling_deceleration)?;
+    if crossing.time_to_crossing > intercept_ball.keeper_jump_time
+        || crossing.position.y.abs() < intercept_ball.keeper_minimum_jump_offset
+    {
+        return None;
+    }
+    let direction = if crossing.position.y > 0.0 {
+        JumpDirection::Left
+    } else {
+        JumpDirection::Right
+    };
+    Some(MotionCommand::Jump { direction })
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::{vector, Point2, Vector2};
+    use types::BallState;
+
+    use super::{super::defend::tests::intercept_ball, *};
+
+    fn world_state_with_ball(position: Point2<f32>, velocity: Vector2<f32>) -> WorldState {
+        WorldState {
+            ball: Some(BallState {
+                position,
+                velocity,
+                ..Default::default()
             }),
-            Some(PenaltyShotDirection::NotMoving) | None => None,
-        })
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn keeper_jumps_to_the_side_the_ball_crosses() {
+        let left_shot = world_state_with_ball(point![2.0, 0.4], vector![-3.0, 0.0]);
+        assert!(matches!(
+            execute(&left_shot, &intercept_ball()),
+            Some(MotionCommand::Jump {
+                direction: JumpDirection::Left
+            })
+        ));
+        let right_shot = world_state_with_ball(point![2.0, 0.0], vector![-3.0, -0.6]);
+        assert!(matches!(
+            execute(&right_shot, &intercept_ball()),
+            Some(MotionCommand::Jump {
+                direction: JumpDirection::Right
+            })
+        ));
+    }
+
+    #[test]
+    fn keeper_does_not_jump_for_central_missing_slow_or_distant_balls() {
+        let intercept_ball = intercept_ball();
+        for (position, velocity) in [
+            (point![2.0, 0.05], vector![-3.0, 0.0]),
+            (point![2.0, 1.0], vector![-3.0, 0.0]),
+            (point![1.0, 0.4], vector![-0.2, 0.0]),
+            (point![4.0, 0.4], vector![-3.0, 0.0]),
+        ] {
+            assert!(execute(&world_state_with_ball(position, velocity), &intercept_ball).is_none());
+        }
+    }
+
+    #[test]
+    fn keeper_jumps_to_the_estimated_penalty_shot_direction() {
+        let mut penalty_shot = world_state_with_ball(point![1.5, 0.2], vector![0.0, 0.0]);
+        penalty_shot.ball.as_mut().unwrap().penalty_shot_direction =
+            Some(PenaltyShotDirection::Right);
+        assert!(matches!(
+            execute(&penalty_shot, &intercept_ball()),
+            Some(MotionCommand::Jump {
+                direction: JumpDirection::Right
+            })
+        ));
+    }
 }
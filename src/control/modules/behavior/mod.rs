@@ -2,16 +2,20 @@ mod action;
 mod defend;
 mod dribble;
 mod fall_safely;
-mod head;
+pub mod head;
 mod jump;
 mod lost_ball;
 pub mod module;
+mod pass;
 mod penalize;
+mod positioning;
 mod prepare_jump;
+mod receive_pass;
 mod search;
 mod sit_down;
 mod stand;
 mod stand_up;
+mod support_set_play;
 mod support_striker;
 mod unstiff;
 mod walk_to_kick_off;
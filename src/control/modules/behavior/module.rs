@@ -1,10 +1,10 @@
 use anyhow::Result;
 use module_derive::module;
 use nalgebra::{point, Point2};
-use spl_network::{GamePhase, Team};
+use spl_network::{GamePhase, PlayerNumber, SetPlay, Team};
 use types::{
-    CameraMatrices, FieldDimensions, FilteredGameState, KickDecision, MotionCommand, PathObstacle,
-    ProjectedLimbs, Role, WorldState,
+    CameraMatrices, FieldDimensions, FilteredGameState, FormationState, KickDecision,
+    MotionCommand, PathObstacle, ProjectedLimbs, Role, SensorData, WorldState,
 };
 
 use crate::framework::configuration;
@@ -14,34 +14,49 @@ use super::{
     defend::Defend,
     dribble, fall_safely,
     head::LookAction,
-    jump, lost_ball, penalize, prepare_jump, search, sit_down, stand, stand_up, support_striker,
-    unstiff, walk_to_kick_off,
+    jump, lost_ball,
+    pass::{self, PassTarget},
+    penalize,
+    positioning::Positioning,
+    prepare_jump,
+    receive_pass::{self, PassReception},
+    search, sit_down, stand, stand_up, support_set_play, support_striker, unstiff,
+    walk_to_kick_off,
     walk_to_pose::{WalkAndStand, WalkPathPlanner},
 };
 
 pub struct Behavior {
     last_motion_command: MotionCommand,
     absolute_last_known_ball_position: Point2<f32>,
+    positioning: Positioning,
+    pass_target: Option<PassTarget>,
+    pass_reception: Option<PassReception>,
 }
 
 #[module(control)]
 #[input(path = world_state, data_type = WorldState, required)]
+#[input(path = sensor_data, data_type = SensorData, required)]
 #[input(path = camera_matrices, data_type = CameraMatrices)]
 #[input(path = projected_limbs, data_type = ProjectedLimbs)]
+#[persistent_state(path = pass_target, data_type = Option<PlayerNumber>)]
 #[parameter(path = control.behavior, data_type = configuration::Behavior)]
 #[parameter(path = field_dimensions, data_type = FieldDimensions)]
 #[parameter(path = control.behavior.lost_ball, data_type=configuration::LostBall, name=lost_ball_parameters)]
 #[additional_output(path = path_obstacles, data_type = Vec<PathObstacle>)]
 #[additional_output(path = kick_decisions, data_type = Vec<KickDecision>)]
 #[additional_output(path = kick_targets, data_type = Vec<Point2<f32>>)]
+#[additional_output(path = formation, data_type = FormationState)]
 #[main_output(data_type = MotionCommand)]
 impl Behavior {}
 
 impl Behavior {
-    fn new(_context: NewContext) -> anyhow::Result<Self> {
+    fn new(context: NewContext) -> anyhow::Result<Self> {
         Ok(Self {
             last_motion_command: MotionCommand::Unstiff,
             absolute_last_known_ball_position: point![0.0, 0.0],
+            positioning: Positioning::load(&context.behavior.positioning.tactic)?,
+            pass_target: None,
+            pass_reception: None,
         })
     }
 
@@ -49,6 +64,7 @@ impl Behavior {
         let world_state = context.world_state;
 
         if let Some(command) = &context.behavior.injected_motion_command {
+            *context.pass_target = None;
             return Ok(MainOutputs {
                 motion_command: Some(command.clone()),
             });
@@ -60,6 +76,49 @@ impl Behavior {
             self.absolute_last_known_ball_position = robot_to_field * ball_state.position;
         }
 
+        let formation = self.positioning.update(
+            context.sensor_data.cycle_info.start_time,
+            world_state,
+            self.absolute_last_known_ball_position,
+            &context.behavior.positioning,
+        );
+        context
+            .formation
+            .mutate_on_subscription(|output| *output = formation.clone());
+        let formation_positions = formation.as_ref().map(|formation| &formation.positions);
+
+        let set_play_kicking_team = set_play_kicking_team(world_state);
+        let passing = match set_play_kicking_team {
+            Some(Team::Hulks) => &context.behavior.set_play_passing,
+            _ => &context.behavior.passing,
+        };
+        let is_ball_free = matches!(
+            world_state.filtered_game_state,
+            None | Some(FilteredGameState::Playing { ball_is_free: true })
+        );
+        self.pass_target =
+            if passing.enabled && is_ball_free && world_state.robot.role == Role::Striker {
+                pass::select_pass_target(
+                    world_state,
+                    context.field_dimensions,
+                    passing,
+                    &context.behavior.dribbling,
+                    self.pass_target.map(|target| target.player_number),
+                )
+            } else {
+                None
+            };
+        self.pass_reception = if passing.enabled && is_ball_free {
+            receive_pass::update_reception(
+                world_state,
+                context.sensor_data.cycle_info.start_time,
+                passing,
+                self.pass_reception,
+            )
+        } else {
+            None
+        };
+
         let mut actions = vec![
             Action::Unstiff,
             Action::SitDown,
@@ -69,6 +128,24 @@ impl Behavior {
             Action::Stand,
         ];
 
+        let is_keeper = matches!(
+            world_state.robot.role,
+            Role::Keeper | Role::ReplacementKeeper
+        );
+        if !is_keeper && world_state.robot.role != Role::Striker {
+            actions.push(Action::ReceivePass);
+        }
+
+        match set_play_kicking_team {
+            Some(Team::Opponent) if !is_ball_free && !is_keeper => {
+                actions.push(Action::DefendSetPlay);
+            }
+            Some(Team::Hulks) if world_state.robot.role == Role::StrikerSupporter => {
+                actions.push(Action::SupportSetPlay);
+            }
+            _ => {}
+        }
+
         match world_state.robot.role {
             Role::DefenderLeft => actions.push(Action::DefendLeft),
             Role::DefenderRight => actions.push(Action::DefendRight),
@@ -84,6 +161,7 @@ impl Behavior {
             Role::Searcher => actions.push(Action::Search),
             Role::Striker => match world_state.filtered_game_state {
                 None | Some(FilteredGameState::Playing { ball_is_free: true }) => {
+                    actions.push(Action::Pass);
                     actions.push(Action::Dribble);
                 }
                 Some(FilteredGameState::Ready {
@@ -120,10 +198,13 @@ impl Behavior {
             world_state,
             context.field_dimensions,
             &context.behavior.role_positions,
+            &context.behavior.intercept_ball,
+            formation_positions,
             &walk_and_stand,
             &look_action,
         );
 
+        let mut is_passing = false;
         let motion_command = actions
             .iter()
             .find_map(|action| match action {
@@ -136,6 +217,10 @@ impl Behavior {
                 Action::DefendKickOff => defend.kick_off(&mut context.path_obstacles),
                 Action::DefendLeft => defend.left(&mut context.path_obstacles),
                 Action::DefendRight => defend.right(&mut context.path_obstacles),
+                Action::DefendSetPlay => defend.set_play(
+                    self.absolute_last_known_ball_position,
+                    &mut context.path_obstacles,
+                ),
                 Action::Stand => stand::execute(world_state),
                 Action::Dribble => dribble::execute(
                     world_state,
@@ -146,7 +231,29 @@ impl Behavior {
                     &mut context.kick_targets,
                     &mut context.kick_decisions,
                 ),
-                Action::Jump => jump::execute(world_state),
+                Action::Pass => {
+                    let command = pass::execute(
+                        world_state,
+                        context.field_dimensions,
+                        &context.behavior.dribbling,
+                        &walk_path_planner,
+                        self.pass_target,
+                        &mut context.path_obstacles,
+                        &mut context.kick_targets,
+                        &mut context.kick_decisions,
+                    );
+                    is_passing = command.is_some();
+                    command
+                }
+                Action::ReceivePass => receive_pass::execute(
+                    world_state,
+                    self.pass_reception,
+                    &context.behavior.intercept_ball,
+                    &walk_and_stand,
+                    &look_action,
+                    &mut context.path_obstacles,
+                ),
+                Action::Jump => jump::execute(world_state, &context.behavior.intercept_ball),
                 Action::PrepareJump => prepare_jump::execute(world_state),
                 Action::Search => search::execute(
                     world_state,
@@ -163,10 +270,17 @@ impl Behavior {
                     context.lost_ball_parameters,
                     &mut context.path_obstacles,
                 ),
-                Action::SupportStriker => support_striker::execute(
+                Action::SupportSetPlay => support_set_play::execute(
                     world_state,
                     context.field_dimensions,
-                    &context.behavior.role_positions,
+                    passing,
+                    &walk_and_stand,
+                    &look_action,
+                    &mut context.path_obstacles,
+                ),
+                Action::SupportStriker => support_striker::execute(
+                    world_state,
+                    formation_positions,
                     &walk_and_stand,
                     &look_action,
                     &mut context.path_obstacles,
@@ -186,9 +300,22 @@ impl Behavior {
             });
 
         self.last_motion_command = motion_command.clone();
+        *context.pass_target = self
+            .pass_target
+            .filter(|_| is_passing)
+            .map(|target| target.player_number);
 
         Ok(MainOutputs {
             motion_command: Some(motion_command),
         })
     }
 }
+
+/// Kicking team of the set play in progress, penalty kicks are handled like kick-offs
+fn set_play_kicking_team(world_state: &WorldState) -> Option<Team> {
+    let game_controller_state = world_state.game_controller_state.as_ref()?;
+    match game_controller_state.set_play? {
+        SetPlay::PenaltyKick => None,
+        _ => Some(game_controller_state.kicking_team),
+    }
+}
@@ -0,0 +1,417 @@
+use nalgebra::{distance, point, Point2};
+use ordered_float::NotNan;
+use spl_network::{PlayerNumber, Team};
+use types::{
+    FieldDimensions, KickDecision, LineSegment, MotionCommand, ObstacleKind, PathObstacle, Role,
+    TeammateState, WorldState,
+};
+
+use crate::framework::{
+    configuration::{Dribbling, Passing},
+    AdditionalOutput,
+};
+
+use super::{dribble, walk_to_pose::WalkPathPlanner};
+
+/// Teammate selected to receive a pass
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PassTarget {
+    pub player_number: PlayerNumber,
+    /// Position of the receiver in field coordinates
+    pub position: Point2<f32>,
+}
+
+/// Selects the teammate with the best combination of forward gain and obstacle clearance among
+/// those the ball can be kicked to, the current target is kept as long as it stays valid. No pass
+/// is played while the ball can be kicked into the opponent goal directly.
+pub fn select_pass_target(
+    world_state: &WorldState,
+    field_dimensions: &FieldDimensions,
+    parameters: &Passing,
+    dribbling: &Dribbling,
+    current_pass_target: Option<PlayerNumber>,
+) -> Option<PassTarget> {
+    let robot_to_field = world_state.robot.robot_to_field?;
+    let relative_ball_position = world_state.ball?.position;
+    if dribble::has_free_shot_on_goal(
+        relative_ball_position,
+        robot_to_field,
+        field_dimensions,
+        &world_state.obstacles,
+        dribbling,
+        parameters.maximum_shot_distance,
+    ) {
+        return None;
+    }
+    let ball_position = robot_to_field * relative_ball_position;
+    let teammate_positions: Vec<_> = world_state
+        .teammates
+        .iter()
+        .map(|teammate| teammate.robot_to_field * Point2::origin())
+        .collect();
+    let opponent_positions: Vec<_> = world_state
+        .obstacles
+        .iter()
+        .filter(|obstacle| {
+            matches!(obstacle.kind, ObstacleKind::Robot | ObstacleKind::Unknown)
+                && obstacle.team != Team::Hulks
+        })
+        .map(|obstacle| {
+            (
+                robot_to_field * obstacle.position,
+                obstacle.radius_at_hip_height,
+            )
+        })
+        .filter(|(position, radius)| {
+            !teammate_positions
+                .iter()
+                .any(|teammate_position| distance(position, teammate_position) < *radius)
+        })
+        .map(|(position, _)| position)
+        .collect();
+
+    let candidates: Vec<_> = world_state
+        .teammates
+        .iter()
+        .filter(|teammate| is_eligible_receiver(teammate, world_state))
+        .filter_map(|teammate| {
+            let position = teammate.robot_to_field * Point2::origin();
+            let score = rate_receiver(
+                ball_position,
+                position,
+                &opponent_positions,
+                field_dimensions,
+                parameters,
+            )?;
+            // scores of non-finite poses, e.g. received from teammates, cannot be ranked
+            let score = NotNan::new(score).ok()?;
+            Some((
+                PassTarget {
+                    player_number: teammate.player_number,
+                    position,
+                },
+                score,
+            ))
+        })
+        .collect();
+
+    if let Some((current, _)) = candidates
+        .iter()
+        .find(|(candidate, _)| Some(candidate.player_number) == current_pass_target)
+    {
+        return Some(*current);
+    }
+    candidates
+        .into_iter()
+        .max_by_key(|(_, score)| *score)
+        .map(|(candidate, _)| candidate)
+}
+
+fn is_eligible_receiver(teammate: &TeammateState, world_state: &WorldState) -> bool {
+    teammate.player_number != world_state.robot.player_number
+        && !teammate.is_fallen
+        && !matches!(teammate.role, Role::Keeper | Role::ReplacementKeeper)
+}
+
+/// Returns `None` if the receiver is not reachable or not worth a pass
+fn rate_receiver(
+    ball_position: Point2<f32>,
+    receiver_position: Point2<f32>,
+    opponent_positions: &[Point2<f32>],
+    field_dimensions: &FieldDimensions,
+    parameters: &Passing,
+) -> Option<f32> {
+    if !field_dimensions.is_inside_field(receiver_position) {
+        return None;
+    }
+    let pass_distance = distance(&ball_position, &receiver_position);
+    if pass_distance < parameters.minimum_pass_distance
+        || pass_distance > parameters.maximum_pass_distance
+    {
+        return None;
+    }
+    let opponent_goal = point![field_dimensions.length / 2.0, 0.0];
+    let forward_gain =
+        distance(&ball_position, &opponent_goal) - distance(&receiver_position, &opponent_goal);
+    if forward_gain < parameters.minimum_forward_gain {
+        return None;
+    }
+
+    let lane = LineSegment(ball_position, receiver_position);
+    let lane_clearance = opponent_positions
+        .iter()
+        .map(|opponent| lane.shortest_distance_to_point(*opponent))
+        .fold(f32::INFINITY, f32::min);
+    let receiver_clearance = opponent_positions
+        .iter()
+        .map(|opponent| distance(opponent, &receiver_position))
+        .fold(f32::INFINITY, f32::min);
+    if lane_clearance < parameters.lane_clearance
+        || receiver_clearance < parameters.receiver_clearance
+    {
+        return None;
+    }
+
+    let clearance = lane_clearance
+        .min(receiver_clearance)
+        .min(field_dimensions.width);
+    Some(forward_gain + parameters.clearance_weight * clearance)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn execute(
+    world_state: &WorldState,
+    field_dimensions: &FieldDimensions,
+    parameters: &Dribbling,
+    walk_path_planner: &WalkPathPlanner,
+    pass_target: Option<PassTarget>,
+    path_obstacles_output: &mut AdditionalOutput<Vec<PathObstacle>>,
+    kick_targets_output: &mut AdditionalOutput<Vec<Point2<f32>>>,
+    kick_decisions_output: &mut AdditionalOutput<Vec<KickDecision>>,
+) -> Option<MotionCommand> {
+    let robot_to_field = world_state.robot.robot_to_field?;
+    let target = robot_to_field.inverse() * pass_target?.position;
+    kick_targets_output.fill_on_subscription(|| vec![target]);
+
+    dribble::kick_to_targets(
+        world_state,
+        field_dimensions,
+        parameters,
+        walk_path_planner,
+        &[target],
+        path_obstacles_output,
+        kick_decisions_output,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::SystemTime;
+
+    use nalgebra::Isometry2;
+    use types::{BallState, Obstacle, RobotState};
+
+    use super::*;
+
+    fn field_dimensions() -> FieldDimensions {
+        FieldDimensions {
+            ball_radius: 0.05,
+            length: 9.0,
+            width: 6.0,
+            line_width: 0.05,
+            penalty_marker_size: 0.1,
+            goal_box_area_length: 0.6,
+            goal_box_area_width: 2.2,
+            penalty_area_length: 1.65,
+            penalty_area_width: 4.0,
+            penalty_marker_distance: 1.3,
+            center_circle_diameter: 1.5,
+            border_strip_width: 0.7,
+            goal_inner_width: 1.5,
+            goal_post_diameter: 0.1,
+            goal_depth: 0.5,
+        }
+    }
+
+    fn parameters() -> Passing {
+        Passing {
+            enabled: true,
+            minimum_pass_distance: 1.0,
+            maximum_pass_distance: 3.5,
+            minimum_forward_gain: 0.5,
+            lane_clearance: 0.5,
+            receiver_clearance: 0.7,
+            pass_announcement_timeout: Default::default(),
+            clearance_weight: 1.0,
+            maximum_shot_distance: 3.0,
+        }
+    }
+
+    fn dribbling() -> Dribbling {
+        Dribbling {
+            max_kick_around_obstacle_angle: 1.0,
+            ball_radius_for_kick_target_selection: 0.1,
+            goal_post_matching_distance: 0.5,
+            ..Default::default()
+        }
+    }
+
+    fn teammate(player_number: PlayerNumber, position: Point2<f32>) -> TeammateState {
+        TeammateState {
+            player_number,
+            robot_to_field: Isometry2::translation(position.x, position.y),
+            role: Role::StrikerSupporter,
+            is_fallen: false,
+            pass_target: None,
+            last_message_time: SystemTime::UNIX_EPOCH,
+        }
+    }
+
+    fn world_state(teammates: Vec<TeammateState>, opponents: &[Point2<f32>]) -> WorldState {
+        WorldState {
+            ball: Some(BallState {
+                position: point![0.2, 0.0],
+                ..Default::default()
+            }),
+            obstacles: opponents
+                .iter()
+                .map(|&position| Obstacle::robot(position, 0.2, 0.3, Team::Opponent))
+                .collect(),
+            robot: RobotState {
+                robot_to_field: Some(Isometry2::translation(-0.2, 0.0)),
+                player_number: PlayerNumber::Five,
+                ..Default::default()
+            },
+            teammates,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn receiver_ahead_with_free_lane_is_selected() {
+        let world_state = world_state(
+            vec![
+                teammate(PlayerNumber::Two, point![-2.0, 0.0]),
+                teammate(PlayerNumber::Three, point![2.0, 1.0]),
+            ],
+            &[],
+        );
+        let target = select_pass_target(
+            &world_state,
+            &field_dimensions(),
+            &parameters(),
+            &dribbling(),
+            None,
+        );
+        assert_eq!(
+            target.map(|target| target.player_number),
+            Some(PlayerNumber::Three)
+        );
+    }
+
+    #[test]
+    fn blocked_lanes_and_covered_receivers_are_rejected() {
+        let teammates = vec![teammate(PlayerNumber::Three, point![2.0, 0.0])];
+        let blocked_lane = world_state(teammates.clone(), &[point![1.0, 0.0]]);
+        assert_eq!(
+            select_pass_target(
+                &blocked_lane,
+                &field_dimensions(),
+                &parameters(),
+                &dribbling(),
+                None
+            ),
+            None
+        );
+        let covered_receiver = world_state(teammates, &[point![2.2, 0.5]]);
+        assert_eq!(
+            select_pass_target(
+                &covered_receiver,
+                &field_dimensions(),
+                &parameters(),
+                &dribbling(),
+                None
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn current_target_is_kept_while_valid() {
+        let world_state = world_state(
+            vec![
+                teammate(PlayerNumber::Three, point![1.5, 1.0]),
+                teammate(PlayerNumber::Four, point![3.0, 0.0]),
+            ],
+            &[],
+        );
+        let parameters = parameters();
+        let best = select_pass_target(
+            &world_state,
+            &field_dimensions(),
+            &parameters,
+            &dribbling(),
+            None,
+        );
+        assert_eq!(
+            best.map(|target| target.player_number),
+            Some(PlayerNumber::Four)
+        );
+        let kept = select_pass_target(
+            &world_state,
+            &field_dimensions(),
+            &parameters,
+            &dribbling(),
+            Some(PlayerNumber::Three),
+        );
+        assert_eq!(
+            kept.map(|target| target.player_number),
+            Some(PlayerNumber::Three)
+        );
+    }
+
+    #[test]
+    fn striker_shoots_instead_of_passing_with_a_free_goal() {
+        let mut world_state =
+            world_state(vec![teammate(PlayerNumber::Three, point![3.5, 1.0])], &[]);
+        world_state.robot.robot_to_field = Some(Isometry2::translation(2.3, 0.0));
+        assert_eq!(
+            select_pass_target(
+                &world_state,
+                &field_dimensions(),
+                &parameters(),
+                &dribbling(),
+                None
+            ),
+            None
+        );
+
+        world_state.obstacles = vec![Obstacle::robot(point![1.5, 0.0], 0.3, 0.3, Team::Opponent)];
+        assert_eq!(
+            select_pass_target(
+                &world_state,
+                &field_dimensions(),
+                &parameters(),
+                &dribbling(),
+                None
+            )
+            .map(|target| target.player_number),
+            Some(PlayerNumber::Three)
+        );
+    }
+
+    #[test]
+    fn non_finite_poses_are_not_selected() {
+        let world_state = world_state(
+            vec![
+                teammate(PlayerNumber::Two, point![f32::NAN, 0.0]),
+                teammate(PlayerNumber::Three, point![2.0, 1.0]),
+            ],
+            &[],
+        );
+        assert_eq!(
+            select_pass_target(
+                &world_state,
+                &field_dimensions(),
+                &parameters(),
+                &dribbling(),
+                None
+            )
+            .map(|target| target.player_number),
+            Some(PlayerNumber::Three)
+        );
+
+        let mut world_state = world_state;
+        world_state.ball.as_mut().unwrap().position = point![f32::NAN, 0.0];
+        assert_eq!(
+            select_pass_target(
+                &world_state,
+                &field_dimensions(),
+                &parameters(),
+                &dribbling(),
+                None
+            ),
+            None
+        );
+    }
+}
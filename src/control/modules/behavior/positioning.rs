@@ -0,0 +1,331 @@
+use std::{
+    collections::HashMap,
+    fs::{read_dir, File},
+    io::BufReader,
+    path::Path,
+    time::SystemTime,
+};
+
+use anyhow::{bail, Context};
+use log::warn;
+use nalgebra::{distance, Point2, Vector2};
+use serde_json::from_reader;
+use spl_network::Team;
+use types::{FilteredGameState, Formation, FormationPositions, FormationState, WorldState};
+
+use crate::framework::configuration::{Positioning as PositioningParameters, Tactic};
+
+const FORMATIONS_DIRECTORY: &str = "etc/configuration";
+
+/// Places the positioned roles according to the formation the tactic selects for the current
+/// situation
+///
+/// Switching the formation blends from the previously computed positions to the new formation
+/// over `formation_transition_duration` instead of jumping.
+pub struct Positioning {
+    formations: HashMap<String, Formation>,
+    current_formation_name: Option<String>,
+    transition_start: Option<(SystemTime, FormationPositions)>,
+    last_positions: Option<FormationPositions>,
+}
+
+impl Positioning {
+    pub fn load(tactic: &Tactic) -> anyhow::Result<Self> {
+        let formations = load_formations(FORMATIONS_DIRECTORY)?;
+        for name in tactic_formation_names(tactic) {
+            if !formations.contains_key(name) {
+                bail!("Tactic refers to unknown formation {name:?}");
+            }
+        }
+        Ok(Self {
+            formations,
+            current_formation_name: None,
+            transition_start: None,
+            last_positions: None,
+        })
+    }
+
+    pub fn update(
+        &mut self,
+        now: SystemTime,
+        world_state: &WorldState,
+        absolute_ball_position: Point2<f32>,
+        parameters: &PositioningParameters,
+    ) -> Option<FormationState> {
+        if let Some(selected_formation_name) = select_formation(world_state, &parameters.tactic) {
+            if self.current_formation_name.as_deref() != Some(selected_formation_name) {
+                self.current_formation_name = Some(selected_formation_name.to_string());
+                self.transition_start = self
+                    .last_positions
+                    .map(|last_positions| (now, last_positions));
+            }
+        }
+        let formation_name = self.current_formation_name.as_ref()?;
+        let formation = match self.formations.get(formation_name) {
+            Some(formation) => formation,
+            None => {
+                warn!("Tactic refers to unknown formation {formation_name:?}");
+                return None;
+            }
+        };
+        let formation_positions = formation.positions(absolute_ball_position)?;
+
+        let transition_progress = match self.transition_start {
+            Some((start_time, _)) if !parameters.formation_transition_duration.is_zero() => {
+                let elapsed = now.duration_since(start_time).unwrap_or_default();
+                (elapsed.as_secs_f32() / parameters.formation_transition_duration.as_secs_f32())
+                    .min(1.0)
+            }
+            _ => 1.0,
+        };
+        let positions = match self.transition_start {
+            Some((_, start_positions)) if transition_progress < 1.0 => {
+                start_positions.interpolate(&formation_positions, transition_progress)
+            }
+            _ => formation_positions,
+        };
+        let positions = if is_ball_free(world_state) {
+            positions
+        } else {
+            positions.map(|position| {
+                keep_distance_to_ball(
+                    position,
+                    absolute_ball_position,
+                    parameters.minimum_distance_to_non_free_ball,
+                )
+            })
+        };
+        self.last_positions = Some(positions);
+
+        Some(FormationState {
+            formation_name: formation_name.clone(),
+            ball_position: absolute_ball_position,
+            transition_progress,
+            anchors: formation.anchors.clone(),
+            positions,
+        })
+    }
+}
+
+fn load_formations(directory: impl AsRef<Path>) -> anyhow::Result<HashMap<String, Formation>> {
+    let directory = directory.as_ref();
+    let mut formations = HashMap::new();
+    for entry in read_dir(directory)
+        .with_context(|| format!("Failed to read directory {}", directory.display()))?
+    {
+        let path = entry?.path();
+        let name = match path
+            .file_name()
+            .and_then(|file_name| file_name.to_str())
+            .and_then(|file_name| file_name.strip_prefix("formation."))
+            .and_then(|file_name| file_name.strip_suffix(".json"))
+        {
+            Some(name) => name.to_string(),
+            None => continue,
+        };
+        let file =
+            File::open(&path).with_context(|| format!("Failed to open {}", path.display()))?;
+        let formation: Formation = from_reader(BufReader::new(file))
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        if formation.anchors.is_empty() {
+            bail!("Formation {name:?} has no anchors");
+        }
+        formations.insert(name, formation);
+    }
+    Ok(formations)
+}
+
+fn tactic_formation_names(tactic: &Tactic) -> [&str; 5] {
+    [
+        &tactic.own_kick_off,
+        &tactic.opponent_kick_off,
+        &tactic.playing,
+        &tactic.own_set_play,
+        &tactic.opponent_set_play,
+    ]
+}
+
+/// Returns `None` in situations without movement, the current formation is kept then
+fn select_formation<'tactic>(
+    world_state: &WorldState,
+    tactic: &'tactic Tactic,
+) -> Option<&'tactic str> {
+    let set_play_kicking_team =
+        world_state
+            .game_controller_state
+            .as_ref()
+            .and_then(|game_controller_state| {
+                game_controller_state
+                    .set_play
+                    .map(|_| game_controller_state.kicking_team)
+            });
+    match world_state.filtered_game_state {
+        Some(FilteredGameState::Ready {
+            kicking_team: Team::Hulks,
+        }) => Some(&tactic.own_kick_off),
+        Some(FilteredGameState::Ready { .. }) => Some(&tactic.opponent_kick_off),
+        None | Some(FilteredGameState::Playing { .. }) => match set_play_kicking_team {
+            Some(Team::Hulks) => Some(&tactic.own_set_play),
+            Some(_) => Some(&tactic.opponent_set_play),
+            None => Some(&tactic.playing),
+        },
+        Some(FilteredGameState::Initial | FilteredGameState::Set | FilteredGameState::Finished) => {
+            None
+        }
+    }
+}
+
+fn is_ball_free(world_state: &WorldState) -> bool {
+    matches!(
+        world_state.filtered_game_state,
+        None | Some(FilteredGameState::Playing { ball_is_free: true })
+    )
+}
+
+/// Moves the position away from the ball until it is at least `minimum_distance` away
+pub fn keep_distance_to_ball(
+    position: Point2<f32>,
+    ball_position: Point2<f32>,
+    minimum_distance: f32,
+) -> Point2<f32> {
+    if distance(&position, &ball_position) >= minimum_distance {
+        return position;
+    }
+    let ball_to_position = position - ball_position;
+    let direction = if ball_to_position.norm() > f32::EPSILON {
+        ball_to_position.normalize()
+    } else {
+        -Vector2::x()
+    };
+    ball_position + direction * minimum_distance
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use approx::assert_relative_eq;
+    use nalgebra::point;
+    use spl_network::{GamePhase, GameState, Half, SetPlay, TeamColor};
+    use types::{GameControllerState, Players};
+
+    use super::*;
+
+    fn tactic() -> Tactic {
+        Tactic {
+            own_kick_off: "own_kick_off".to_string(),
+            opponent_kick_off: "opponent_kick_off".to_string(),
+            playing: "balanced".to_string(),
+            own_set_play: "attacking".to_string(),
+            opponent_set_play: "defensive".to_string(),
+        }
+    }
+
+    #[test]
+    fn shipped_formations_cover_the_default_tactic() {
+        let formations = load_formations(FORMATIONS_DIRECTORY).unwrap();
+        for name in tactic_formation_names(&tactic()) {
+            assert!(formations.contains_key(name), "missing formation {name}");
+        }
+    }
+
+    #[test]
+    fn set_plays_select_formation_by_kicking_team() {
+        let tactic = tactic();
+        let mut world_state = WorldState {
+            filtered_game_state: Some(FilteredGameState::Playing { ball_is_free: true }),
+            ..Default::default()
+        };
+        assert_eq!(select_formation(&world_state, &tactic), Some("balanced"));
+
+        let game_controller_state = GameControllerState {
+            game_state: GameState::Playing,
+            game_phase: GamePhase::Normal,
+            kicking_team: Team::Opponent,
+            last_game_state_change: SystemTime::UNIX_EPOCH,
+            penalties: Players::default(),
+            remaining_amount_of_messages: 1200,
+            set_play: Some(SetPlay::CornerKick),
+            hulks_team_color: TeamColor::Blue,
+            opponent_team_color: TeamColor::Red,
+            half: Half::First,
+            remaining_time_in_half: Duration::from_secs(600),
+            secondary_time: Duration::ZERO,
+            hulks_score: 0,
+            opponent_score: 0,
+        };
+        world_state.game_controller_state = Some(game_controller_state);
+        assert_eq!(select_formation(&world_state, &tactic), Some("defensive"));
+
+        world_state.filtered_game_state = Some(FilteredGameState::Set);
+        assert_eq!(select_formation(&world_state, &tactic), None);
+    }
+
+    #[test]
+    fn formation_changes_are_blended() {
+        let mut positioning = Positioning::load(&tactic()).unwrap();
+        let parameters = PositioningParameters {
+            tactic: tactic(),
+            formation_transition_duration: Duration::from_secs(2),
+            minimum_distance_to_non_free_ball: 0.95,
+        };
+        let start = SystemTime::UNIX_EPOCH;
+        let mut world_state = WorldState {
+            filtered_game_state: Some(FilteredGameState::Ready {
+                kicking_team: Team::Hulks,
+            }),
+            ..Default::default()
+        };
+        let kick_off = positioning
+            .update(start, &world_state, Point2::origin(), &parameters)
+            .unwrap();
+        assert_eq!(kick_off.formation_name, "own_kick_off");
+        assert_relative_eq!(kick_off.transition_progress, 1.0);
+
+        world_state.filtered_game_state = Some(FilteredGameState::Playing { ball_is_free: true });
+        let switched = positioning
+            .update(
+                start + Duration::from_secs(1),
+                &world_state,
+                Point2::origin(),
+                &parameters,
+            )
+            .unwrap();
+        assert_eq!(switched.positions, kick_off.positions);
+        let halfway = positioning
+            .update(
+                start + Duration::from_secs(2),
+                &world_state,
+                Point2::origin(),
+                &parameters,
+            )
+            .unwrap();
+        let balanced = positioning
+            .update(
+                start + Duration::from_secs(4),
+                &world_state,
+                Point2::origin(),
+                &parameters,
+            )
+            .unwrap();
+        assert_eq!(halfway.formation_name, "balanced");
+        assert_relative_eq!(halfway.transition_progress, 0.5);
+        assert_eq!(
+            halfway.positions,
+            kick_off.positions.interpolate(&balanced.positions, 0.5)
+        );
+    }
+
+    #[test]
+    fn positions_keep_distance_to_non_free_ball() {
+        let ball = point![1.0, 0.0];
+        assert_relative_eq!(
+            keep_distance_to_ball(point![1.5, 0.0], ball, 0.95),
+            point![1.95, 0.0]
+        );
+        assert_relative_eq!(
+            keep_distance_to_ball(point![3.0, 0.0], ball, 0.95),
+            point![3.0, 0.0]
+        );
+    }
+}
@@ -0,0 +1,184 @@
+use std::time::SystemTime;
+
+use nalgebra::{vector, Isometry2, Point2};
+use types::{rotate_towards, Line, MotionCommand, PathObstacle, WorldState};
+
+use crate::framework::{
+    configuration::{InterceptBall, Passing},
+    AdditionalOutput,
+};
+
+use super::{
+    defend::{ball_in_field, intercept_rolling_ball},
+    head::LookAction,
+    walk_to_pose::WalkAndStand,
+};
+
+/// Pass announced to this robot by a teammate, positions in field coordinates
+#[derive(Clone, Copy, Debug)]
+pub struct PassReception {
+    pub passer_position: Point2<f32>,
+    /// Position of the robot when the announcement arrived, kept until the pass is over to give
+    /// the passer a stable target
+    pub position: Point2<f32>,
+}
+
+pub fn update_reception(
+    world_state: &WorldState,
+    now: SystemTime,
+    parameters: &Passing,
+    last_reception: Option<PassReception>,
+) -> Option<PassReception> {
+    let robot_to_field = world_state.robot.robot_to_field?;
+    let passer = world_state.teammates.iter().find(|teammate| {
+        teammate.pass_target == Some(world_state.robot.player_number)
+            && now
+                .duration_since(teammate.last_message_time)
+                .map_or(true, |age| age < parameters.pass_announcement_timeout)
+    })?;
+    Some(PassReception {
+        passer_position: passer.robot_to_field * Point2::origin(),
+        position: last_reception
+            .map(|reception| reception.position)
+            .unwrap_or_else(|| robot_to_field * Point2::origin()),
+    })
+}
+
+pub fn execute(
+    world_state: &WorldState,
+    pass_reception: Option<PassReception>,
+    intercept_ball: &InterceptBall,
+    walk_and_stand: &WalkAndStand,
+    look_action: &LookAction,
+    path_obstacles_output: &mut AdditionalOutput<Vec<PathObstacle>>,
+) -> Option<MotionCommand> {
+    let pose = receive_pass_pose(world_state, pass_reception?, intercept_ball)?;
+    walk_and_stand.execute(pose, look_action.execute(), path_obstacles_output)
+}
+
+/// Faces the ball at the reception position, a rolling ball is intercepted on the line through
+/// the reception position perpendicular to the direction the ball rolls in
+fn receive_pass_pose(
+    world_state: &WorldState,
+    pass_reception: PassReception,
+    intercept_ball: &InterceptBall,
+) -> Option<Isometry2<f32>> {
+    let robot_to_field = world_state.robot.robot_to_field?;
+    let ball = ball_in_field(world_state, robot_to_field);
+    let ball_position = ball.map_or(pass_reception.passer_position, |ball| ball.position);
+    let position = pass_reception.position;
+    let receive_pose = Isometry2::new(
+        position.coords,
+        rotate_towards(position, ball_position).angle(),
+    );
+
+    let interception_pose = ball.and_then(|ball| {
+        if ball.velocity.norm() < intercept_ball.minimum_ball_speed {
+            return None;
+        }
+        let direction = ball.velocity.normalize();
+        let along_line =
+            vector![-direction.y, direction.x] * intercept_ball.defender_interception_range;
+        let reception_line = Line(position - along_line, position + along_line);
+        intercept_rolling_ball(&ball, reception_line, intercept_ball)
+    });
+    Some(robot_to_field.inverse() * interception_pose.unwrap_or(receive_pose))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{f32::consts::PI, time::Duration};
+
+    use approx::assert_relative_eq;
+    use nalgebra::point;
+    use spl_network::PlayerNumber;
+    use types::{BallState, RobotState, Role, TeammateState};
+
+    use super::{super::defend::tests::intercept_ball, *};
+
+    fn parameters() -> Passing {
+        Passing {
+            pass_announcement_timeout: Duration::from_secs(3),
+            ..Default::default()
+        }
+    }
+
+    fn world_state(robot_position: Point2<f32>, last_message_time: SystemTime) -> WorldState {
+        WorldState {
+            robot: RobotState {
+                robot_to_field: Some(Isometry2::translation(robot_position.x, robot_position.y)),
+                player_number: PlayerNumber::Three,
+                ..Default::default()
+            },
+            teammates: vec![TeammateState {
+                player_number: PlayerNumber::Five,
+                robot_to_field: Isometry2::translation(-1.0, 1.0),
+                role: Role::Striker,
+                is_fallen: false,
+                pass_target: Some(PlayerNumber::Three),
+                last_message_time,
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn outdated_announcements_are_ignored() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(10);
+        let recent = world_state(point![1.0, 1.0], now - Duration::from_secs(1));
+        let reception = update_reception(&recent, now, &parameters(), None).unwrap();
+        assert_relative_eq!(reception.passer_position, point![-1.0, 1.0]);
+        assert_relative_eq!(reception.position, point![1.0, 1.0]);
+
+        let outdated = world_state(point![1.0, 1.0], now - Duration::from_secs(4));
+        assert!(update_reception(&outdated, now, &parameters(), Some(reception)).is_none());
+    }
+
+    #[test]
+    fn reception_position_is_kept_while_the_pass_is_announced() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(10);
+        let first = update_reception(
+            &world_state(point![1.0, 1.0], now),
+            now,
+            &parameters(),
+            None,
+        );
+        let moved = update_reception(
+            &world_state(point![1.5, 0.5], now),
+            now + Duration::from_millis(100),
+            &parameters(),
+            first,
+        )
+        .unwrap();
+        assert_relative_eq!(moved.position, point![1.0, 1.0]);
+    }
+
+    #[test]
+    fn receiver_faces_the_passer_at_the_reception_position() {
+        let world_state = world_state(point![1.0, 0.0], SystemTime::UNIX_EPOCH);
+        let reception = PassReception {
+            passer_position: point![-1.0, 1.0],
+            position: point![1.0, 1.0],
+        };
+        let pose = receive_pass_pose(&world_state, reception, &intercept_ball()).unwrap();
+        assert_relative_eq!(pose.translation.vector, vector![0.0, 1.0], epsilon = 1e-6);
+        assert_relative_eq!(pose.rotation.angle().abs(), PI, epsilon = 1e-6);
+    }
+
+    #[test]
+    fn rolling_ball_is_intercepted_next_to_the_reception_position() {
+        let mut world_state = world_state(point![0.0, 0.0], SystemTime::UNIX_EPOCH);
+        world_state.ball = Some(BallState {
+            position: point![0.0, 1.3],
+            velocity: vector![2.0, 0.0],
+            ..Default::default()
+        });
+        let reception = PassReception {
+            passer_position: point![-1.0, 1.0],
+            position: point![1.0, 1.0],
+        };
+        let pose = receive_pass_pose(&world_state, reception, &intercept_ball()).unwrap();
+        assert_relative_eq!(pose.translation.vector, vector![1.0, 1.3], epsilon = 1e-4);
+        assert_relative_eq!(pose.rotation.angle().abs(), PI, epsilon = 1e-4);
+    }
+}
@@ -0,0 +1,46 @@
+use nalgebra::{point, Isometry2, Vector2};
+use types::{rotate_towards, FieldDimensions, MotionCommand, PathObstacle, WorldState};
+
+use crate::framework::{configuration::Passing, AdditionalOutput};
+
+use super::{head::LookAction, walk_to_pose::WalkAndStand};
+
+pub fn execute(
+    world_state: &WorldState,
+    field_dimensions: &FieldDimensions,
+    parameters: &Passing,
+    walk_and_stand: &WalkAndStand,
+    look_action: &LookAction,
+    path_obstacles_output: &mut AdditionalOutput<Vec<PathObstacle>>,
+) -> Option<MotionCommand> {
+    let pose = support_set_play_pose(world_state, field_dimensions, parameters)?;
+    walk_and_stand.execute(pose, look_action.execute(), path_obstacles_output)
+}
+
+/// Offers a short pass during own set plays, between the minimum and maximum pass distance from
+/// the ball in the direction of the opponent penalty marker
+fn support_set_play_pose(
+    world_state: &WorldState,
+    field_dimensions: &FieldDimensions,
+    parameters: &Passing,
+) -> Option<Isometry2<f32>> {
+    let robot_to_field = world_state.robot.robot_to_field?;
+    let ball_position = robot_to_field * world_state.ball?.position;
+    let opponent_penalty_marker = point![
+        field_dimensions.length / 2.0 - field_dimensions.penalty_marker_distance,
+        0.0
+    ];
+    let ball_to_penalty_marker = opponent_penalty_marker - ball_position;
+    let direction = if ball_to_penalty_marker.norm() > f32::EPSILON {
+        ball_to_penalty_marker.normalize()
+    } else {
+        Vector2::x()
+    };
+    let pass_distance = (parameters.minimum_pass_distance + parameters.maximum_pass_distance) / 2.0;
+    let supporting_position = ball_position + direction * pass_distance;
+    let support_pose = Isometry2::new(
+        supporting_position.coords,
+        rotate_towards(supporting_position, ball_position).angle(),
+    );
+    Some(robot_to_field.inverse() * support_pose)
+}
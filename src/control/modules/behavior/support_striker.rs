@@ -1,68 +1,34 @@
-use std::f32::consts::FRAC_PI_4;
+use nalgebra::Isometry2;
+use types::{rotate_towards, FormationPositions, MotionCommand, PathObstacle, WorldState};
 
-use nalgebra::{point, Isometry2, UnitComplex, Vector2};
-use types::{
-    rotate_towards, BallState, FieldDimensions, FilteredGameState, MotionCommand, PathObstacle,
-    Side, WorldState,
-};
-
-use crate::framework::{configuration::RolePositions, AdditionalOutput};
+use crate::framework::AdditionalOutput;
 
 use super::{head::LookAction, walk_to_pose::WalkAndStand};
 
 pub fn execute(
     world_state: &WorldState,
-    field_dimensions: &FieldDimensions,
-    role_positions: &RolePositions,
+    formation_positions: Option<&FormationPositions>,
     walk_and_stand: &WalkAndStand,
     look_action: &LookAction,
     path_obstacles_output: &mut AdditionalOutput<Vec<PathObstacle>>,
 ) -> Option<MotionCommand> {
-    let pose = support_striker_pose(world_state, field_dimensions, role_positions)?;
+    let pose = support_striker_pose(world_state, formation_positions?)?;
     walk_and_stand.execute(pose, look_action.execute(), path_obstacles_output)
 }
 
 fn support_striker_pose(
     world_state: &WorldState,
-    field_dimensions: &FieldDimensions,
-    role_positions: &RolePositions,
+    formation_positions: &FormationPositions,
 ) -> Option<Isometry2<f32>> {
     let robot_to_field = world_state.robot.robot_to_field?;
-    let ball = world_state
+    let ball_position = world_state
         .ball
-        .map(|ball| BallState {
-            position: robot_to_field * ball.position,
-            field_side: ball.field_side,
-            penalty_shot_direction: Default::default(),
-        })
+        .map(|ball| robot_to_field * ball.position)
         .unwrap_or_default();
-    let side = ball.field_side.opposite();
-    let offset_vector = UnitComplex::new(match side {
-        Side::Left => -FRAC_PI_4,
-        Side::Right => FRAC_PI_4,
-    }) * -(Vector2::x() * role_positions.striker_supporter_distance_to_ball);
-    let supporting_position = ball.position + offset_vector;
-    let clamped_x = match world_state.filtered_game_state {
-        Some(FilteredGameState::Ready { .. })
-        | Some(FilteredGameState::Playing {
-            ball_is_free: false,
-        }) => supporting_position.x.clamp(
-            role_positions
-                .striker_supporter_minimum_x
-                .min(role_positions.striker_supporter_maximum_x_in_ready_and_when_ball_is_not_free),
-            role_positions
-                .striker_supporter_minimum_x
-                .max(role_positions.striker_supporter_maximum_x_in_ready_and_when_ball_is_not_free),
-        ),
-        _ => supporting_position.x.clamp(
-            role_positions.striker_supporter_minimum_x,
-            field_dimensions.length / 2.0,
-        ),
-    };
-    let clamped_position = point![clamped_x, supporting_position.y];
+    let supporting_position = formation_positions.striker_supporter;
     let support_pose = Isometry2::new(
-        clamped_position.coords,
-        rotate_towards(clamped_position, ball.position).angle(),
+        supporting_position.coords,
+        rotate_towards(supporting_position, ball_position).angle(),
     );
     Some(robot_to_field.inverse() * support_pose)
 }
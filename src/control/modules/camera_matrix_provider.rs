@@ -1,8 +1,8 @@
 use module_derive::{module, require_some};
-use nalgebra::{point, vector, Isometry3, UnitQuaternion, Vector3};
+use nalgebra::{point, vector, Isometry3};
 use types::{
-    CameraMatrices, CameraMatrix, CameraPosition, FieldDimensions, Line, Line2,
-    ProjectedFieldLines, RobotDimensions, RobotKinematics,
+    camera_to_head, CameraMatrices, CameraMatrix, CameraPosition, FieldDimensions, Line, Line2,
+    ProjectedFieldLines, RobotKinematics,
 };
 
 use crate::framework::configuration::CameraMatrixParameters;
@@ -74,29 +74,6 @@ impl CameraMatrixProvider {
     }
 }
 
-pub fn camera_to_head(
-    camera_position: CameraPosition,
-    extrinsic_rotation: Vector3<f32>,
-) -> Isometry3<f32> {
-    let extrinsic_angles_in_radians = extrinsic_rotation.map(|a: f32| a.to_radians());
-    let extrinsic_rotation = UnitQuaternion::from_euler_angles(
-        extrinsic_angles_in_radians.x,
-        extrinsic_angles_in_radians.y,
-        extrinsic_angles_in_radians.z,
-    );
-    let neck_to_camera = match camera_position {
-        CameraPosition::Top => RobotDimensions::NECK_TO_TOP_CAMERA,
-        CameraPosition::Bottom => RobotDimensions::NECK_TO_BOTTOM_CAMERA,
-    };
-    let camera_pitch = match camera_position {
-        CameraPosition::Top => 1.2f32.to_radians(),
-        CameraPosition::Bottom => 39.7f32.to_radians(),
-    };
-    Isometry3::from(neck_to_camera)
-        * Isometry3::rotation(Vector3::y() * camera_pitch)
-        * extrinsic_rotation
-}
-
 fn project_penalty_area_on_images(
     field_dimensions: &FieldDimensions,
     camera_matrix: &CameraMatrix,
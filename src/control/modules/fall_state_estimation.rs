@@ -19,6 +19,7 @@ pub struct FallStateEstimation {
 #[additional_output(path = filtered_linear_acceleration, data_type = Vector3<f32>)]
 #[additional_output(path = filtered_angular_velocity, data_type = Vector3<f32>)]
 #[additional_output(path = filtered_roll_pitch, data_type = Vector2<f32>)]
+#[additional_output(path = predicted_roll_pitch, data_type = Vector2<f32>)]
 #[additional_output(path = forward_gravitational_difference, data_type = f32)]
 #[additional_output(path = backward_gravitational_difference, data_type = f32)]
 #[main_output(data_type = FallState)]
@@ -122,27 +123,29 @@ impl FallStateEstimation {
                     .norm()
             });
 
-        let falling_direction = {
-            if self.roll_pitch_filter.state().x.abs()
-                > context.fall_state_estimation.falling_angle_threshold.x
-            {
-                if self.roll_pitch_filter.state().x > 0.0 {
-                    Some(FallDirection::Right)
-                } else {
-                    Some(FallDirection::Left)
-                }
-            } else if self.roll_pitch_filter.state().y.abs()
-                > context.fall_state_estimation.falling_angle_threshold.y
-            {
-                if self.roll_pitch_filter.state().y > 0.0 {
-                    Some(FallDirection::Forward)
-                } else {
-                    Some(FallDirection::Backward)
-                }
-            } else {
-                None
-            }
-        };
+        let roll_pitch = self.roll_pitch_filter.state();
+        let angular_velocity = self.angular_velocity_filter.state().xy();
+        let predicted_roll_pitch = roll_pitch
+            + angular_velocity
+                * context
+                    .fall_state_estimation
+                    .fall_prediction_horizon
+                    .as_secs_f32();
+        context
+            .predicted_roll_pitch
+            .fill_on_subscription(|| predicted_roll_pitch);
+
+        let falling_direction = falling_direction(
+            roll_pitch,
+            context.fall_state_estimation.falling_angle_threshold,
+        )
+        .or_else(|| {
+            predict_falling_direction(
+                predicted_roll_pitch,
+                angular_velocity,
+                context.fall_state_estimation,
+            )
+        });
         let fall_state = match (fallen_direction, falling_direction) {
             (Some(facing), _) => FallState::Fallen { facing },
             (None, Some(direction)) => FallState::Falling { direction },
@@ -155,6 +158,47 @@ impl FallStateEstimation {
     }
 }
 
+fn falling_direction(
+    roll_pitch: Vector2<f32>,
+    falling_angle_threshold: Vector2<f32>,
+) -> Option<FallDirection> {
+    if roll_pitch.x.abs() > falling_angle_threshold.x {
+        if roll_pitch.x > 0.0 {
+            Some(FallDirection::Right)
+        } else {
+            Some(FallDirection::Left)
+        }
+    } else if roll_pitch.y.abs() > falling_angle_threshold.y {
+        if roll_pitch.y > 0.0 {
+            Some(FallDirection::Forward)
+        } else {
+            Some(FallDirection::Backward)
+        }
+    } else {
+        None
+    }
+}
+
+/// Predicts a fall from the extrapolated roll and pitch. The prediction only counts if the robot
+/// rotates faster than the angular velocities occurring during regular motions along that axis.
+fn predict_falling_direction(
+    predicted_roll_pitch: Vector2<f32>,
+    angular_velocity: Vector2<f32>,
+    configuration: &crate::framework::configuration::FallStateEstimation,
+) -> Option<FallDirection> {
+    let is_fast = |axis: usize| {
+        angular_velocity[axis] < configuration.minimum_angular_velocity[axis]
+            || angular_velocity[axis] > configuration.maximum_angular_velocity[axis]
+    };
+    match falling_direction(predicted_roll_pitch, configuration.falling_angle_threshold)? {
+        direction @ (FallDirection::Left | FallDirection::Right) if is_fast(0) => Some(direction),
+        direction @ (FallDirection::Forward | FallDirection::Backward) if is_fast(1) => {
+            Some(direction)
+        }
+        _ => None,
+    }
+}
+
 fn convert_to_right_handed_coordinate_system(
     inertial_measurement_unit: InertialMeasurementUnitData,
 ) -> InertialMeasurementUnitData {
@@ -172,3 +216,52 @@ fn convert_to_right_handed_coordinate_system(
         roll_pitch: inertial_measurement_unit.roll_pitch,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn configuration() -> crate::framework::configuration::FallStateEstimation {
+        crate::framework::configuration::FallStateEstimation {
+            falling_angle_threshold: vector![0.5, 0.45],
+            minimum_angular_velocity: vector![-0.233, -0.61],
+            maximum_angular_velocity: vector![0.233, 0.317],
+            fall_prediction_horizon: Duration::from_millis(200),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn tilted_robot_is_falling() {
+        assert_eq!(
+            falling_direction(vector![0.0, 0.5], vector![0.5, 0.45]),
+            Some(FallDirection::Forward)
+        );
+        assert_eq!(
+            falling_direction(vector![-0.6, 0.0], vector![0.5, 0.45]),
+            Some(FallDirection::Left)
+        );
+        assert_eq!(
+            falling_direction(vector![0.1, -0.2], vector![0.5, 0.45]),
+            None
+        );
+    }
+
+    #[test]
+    fn fast_rotation_predicts_fall() {
+        assert_eq!(
+            predict_falling_direction(vector![0.0, -0.5], vector![0.0, -1.0], &configuration()),
+            Some(FallDirection::Backward)
+        );
+    }
+
+    #[test]
+    fn slow_rotation_does_not_predict_fall() {
+        assert_eq!(
+            predict_falling_direction(vector![0.0, 0.5], vector![0.0, 0.3], &configuration()),
+            None
+        );
+    }
+}
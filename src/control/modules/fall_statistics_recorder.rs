@@ -0,0 +1,225 @@
+use std::{
+    env::current_exe,
+    fs::{create_dir_all, rename, File},
+    io::{BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::mpsc::{channel, Sender},
+    thread,
+};
+
+use anyhow::Context;
+use log::warn;
+use module_derive::module;
+use serde_json::{from_reader, to_writer_pretty};
+use types::{
+    Facing, FallDirection, FallRecord, FallState, FallStatistics, MotionSelection, MotionType,
+    SensorData,
+};
+
+pub struct FallStatisticsRecorder {
+    statistics: FallStatistics,
+    statistics_sender: Sender<(PathBuf, FallStatistics)>,
+    last_upright_motion: MotionType,
+    last_falling_direction: Option<FallDirection>,
+    was_fallen: bool,
+}
+
+#[module(control)]
+#[input(path = sensor_data, data_type = SensorData, required)]
+#[input(path = fall_state, data_type = FallState, required)]
+#[input(path = motion_selection, data_type = MotionSelection)]
+#[parameter(path = control.fall_statistics_recorder, data_type = crate::framework::configuration::FallStatisticsRecorder)]
+#[parameter(path = log_directory, data_type = PathBuf)]
+#[additional_output(path = fall_statistics, data_type = FallStatistics)]
+impl FallStatisticsRecorder {}
+
+impl FallStatisticsRecorder {
+    fn new(context: NewContext) -> anyhow::Result<Self> {
+        let statistics_file_path = resolve_path(
+            &context
+                .log_directory
+                .join(&context.fall_statistics_recorder.statistics_file_name),
+        )?;
+        let statistics = if statistics_file_path.exists() {
+            load_statistics(&statistics_file_path).unwrap_or_else(|error| {
+                warn!("Failed to load fall statistics: {error:?}");
+                FallStatistics::default()
+            })
+        } else {
+            FallStatistics::default()
+        };
+        Ok(Self {
+            statistics,
+            statistics_sender: spawn_statistics_writer()?,
+            last_upright_motion: MotionType::default(),
+            last_falling_direction: None,
+            was_fallen: false,
+        })
+    }
+
+    fn cycle(&mut self, mut context: CycleContext) -> anyhow::Result<MainOutputs> {
+        match *context.fall_state {
+            FallState::Upright => {
+                self.last_falling_direction = None;
+                self.was_fallen = false;
+                if let Some(motion_selection) = context.motion_selection {
+                    if !matches!(
+                        motion_selection.current_motion,
+                        MotionType::FallProtection
+                            | MotionType::Dispatching
+                            | MotionType::StandUpBack
+                            | MotionType::StandUpFront
+                    ) {
+                        self.last_upright_motion = motion_selection.current_motion;
+                    }
+                }
+            }
+            FallState::Falling { direction } => {
+                self.last_falling_direction = Some(direction);
+            }
+            FallState::Fallen { facing } => {
+                if !self.was_fallen {
+                    self.was_fallen = true;
+                    let direction = self.last_falling_direction.unwrap_or(match facing {
+                        Facing::Down => FallDirection::Forward,
+                        Facing::Up => FallDirection::Backward,
+                    });
+                    self.statistics.record(
+                        FallRecord {
+                            direction,
+                            cause_motion: self.last_upright_motion,
+                            time: context.sensor_data.cycle_info.start_time,
+                        },
+                        context
+                            .fall_statistics_recorder
+                            .maximum_number_of_recorded_falls,
+                    );
+                    let statistics_file_path = context
+                        .log_directory
+                        .join(&context.fall_statistics_recorder.statistics_file_name);
+                    if self
+                        .statistics_sender
+                        .send((statistics_file_path, self.statistics.clone()))
+                        .is_err()
+                    {
+                        warn!("Fall statistics writer is not running, statistics are not stored");
+                    }
+                }
+            }
+        }
+
+        context
+            .fall_statistics
+            .fill_on_subscription(|| self.statistics.clone());
+
+        Ok(MainOutputs {})
+    }
+}
+
+/// Relative paths are resolved against the installation directory, the parent of the directory
+/// containing the executable, e.g. `/home/nao/hulk` for `/home/nao/hulk/bin/hulk`, instead of the
+/// working directory
+fn resolve_path(path: &Path) -> anyhow::Result<PathBuf> {
+    if path.is_absolute() {
+        return Ok(path.to_path_buf());
+    }
+    let executable = current_exe().context("Failed to get the path of the executable")?;
+    let installation_directory = executable
+        .parent()
+        .and_then(Path::parent)
+        .with_context(|| format!("{} has no installation directory", executable.display()))?;
+    Ok(installation_directory.join(path))
+}
+
+fn load_statistics(path: &Path) -> anyhow::Result<FallStatistics> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    from_reader(BufReader::new(file)).with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+/// Storing happens on a separate thread to keep file system latencies out of the control cycle
+fn spawn_statistics_writer() -> anyhow::Result<Sender<(PathBuf, FallStatistics)>> {
+    let (sender, receiver) = channel::<(PathBuf, FallStatistics)>();
+    thread::Builder::new()
+        .name("fall_statistics_writer".to_string())
+        .spawn(move || {
+            while let Ok((path, statistics)) = receiver.recv() {
+                if let Err(error) =
+                    resolve_path(&path).and_then(|path| store_statistics(&path, &statistics))
+                {
+                    warn!("Failed to store fall statistics: {error:?}");
+                }
+            }
+        })
+        .context("Failed to spawn fall statistics writer thread")?;
+    Ok(sender)
+}
+
+/// Writes to a temporary file next to the statistics file and replaces the statistics file
+/// afterwards, an interrupted write therefore never loses previously stored statistics
+fn store_statistics(path: &Path, statistics: &FallStatistics) -> anyhow::Result<()> {
+    if let Some(directory) = path.parent() {
+        create_dir_all(directory)
+            .with_context(|| format!("Failed to create {}", directory.display()))?;
+    }
+    let mut temporary_file_name = path
+        .file_name()
+        .with_context(|| format!("{} is not a file path", path.display()))?
+        .to_os_string();
+    temporary_file_name.push(".tmp");
+    let temporary_path = path.with_file_name(temporary_file_name);
+
+    let file = File::create(&temporary_path)
+        .with_context(|| format!("Failed to create {}", temporary_path.display()))?;
+    let mut writer = BufWriter::new(file);
+    to_writer_pretty(&mut writer, statistics)
+        .with_context(|| format!("Failed to write {}", temporary_path.display()))?;
+    writer
+        .flush()
+        .with_context(|| format!("Failed to write {}", temporary_path.display()))?;
+    writer
+        .get_ref()
+        .sync_all()
+        .with_context(|| format!("Failed to sync {}", temporary_path.display()))?;
+    rename(&temporary_path, path).with_context(|| {
+        format!(
+            "Failed to rename {} to {}",
+            temporary_path.display(),
+            path.display()
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stored_statistics_replace_the_previous_file() {
+        let path = std::env::temp_dir().join(format!(
+            "fall_statistics_{}/fall_statistics.json",
+            std::process::id()
+        ));
+        let mut statistics = FallStatistics::default();
+        store_statistics(&path, &statistics).unwrap();
+        statistics.number_of_falls = 2;
+        store_statistics(&path, &statistics).unwrap();
+
+        let loaded = load_statistics(&path).unwrap();
+        let directory = path.parent().unwrap();
+        let number_of_files = std::fs::read_dir(directory).unwrap().count();
+        std::fs::remove_dir_all(directory).unwrap();
+        assert_eq!(loaded.number_of_falls, 2);
+        assert_eq!(number_of_files, 1);
+    }
+
+    #[test]
+    fn relative_paths_do_not_depend_on_the_working_directory() {
+        let resolved = resolve_path(Path::new("logs/fall_statistics.json")).unwrap();
+        assert!(resolved.is_absolute());
+        assert!(resolved.ends_with("logs/fall_statistics.json"));
+        assert_eq!(
+            resolve_path(Path::new("/tmp/fall_statistics.json")).unwrap(),
+            Path::new("/tmp/fall_statistics.json")
+        );
+    }
+}
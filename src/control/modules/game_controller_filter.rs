@@ -43,20 +43,82 @@ impl GameControllerFilter {
             if game_state_changed {
                 self.last_game_state_change = Some(cycle_start_time);
             }
-            self.game_controller_state = Some(GameControllerState {
-                game_state: game_controller_state_message.game_state,
-                game_phase: game_controller_state_message.game_phase,
-                kicking_team: game_controller_state_message.kicking_team,
-                last_game_state_change: self.last_game_state_change.unwrap(),
-                penalties: game_controller_state_message.hulks_team.clone().into(),
-                remaining_amount_of_messages: game_controller_state_message
-                    .hulks_team
-                    .remaining_amount_of_messages,
-                set_play: game_controller_state_message.set_play,
-            });
+            self.game_controller_state = Some(filtered_game_controller_state(
+                game_controller_state_message,
+                self.last_game_state_change.unwrap(),
+            ));
         }
         Ok(MainOutputs {
             game_controller_state: self.game_controller_state,
         })
     }
 }
+
+fn filtered_game_controller_state(
+    message: &GameControllerStateMessage,
+    last_game_state_change: SystemTime,
+) -> GameControllerState {
+    GameControllerState {
+        game_state: message.game_state,
+        game_phase: message.game_phase,
+        kicking_team: message.kicking_team,
+        last_game_state_change,
+        penalties: message.hulks_team.clone().into(),
+        remaining_amount_of_messages: message.hulks_team.remaining_amount_of_messages,
+        set_play: message.set_play,
+        hulks_team_color: message.hulks_team.color,
+        opponent_team_color: message.opponent_team.color,
+        half: message.half,
+        remaining_time_in_half: message.remaining_time_in_half,
+        secondary_time: message.secondary_time,
+        hulks_score: message.hulks_team.score,
+        opponent_score: message.opponent_team.score,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use spl_network::{GamePhase, GameState, Half, Player, Team, TeamColor, TeamState};
+
+    use super::*;
+
+    fn team_state(team_number: u8, color: TeamColor, score: u8) -> TeamState {
+        TeamState {
+            team_number,
+            color,
+            score,
+            penalty_shoot_index: 0,
+            penalty_shoots: vec![],
+            remaining_amount_of_messages: 1200,
+            players: vec![Player { penalty: None }; 5],
+        }
+    }
+
+    #[test]
+    fn half_time_and_score_are_carried_into_the_filtered_state() {
+        let message = GameControllerStateMessage {
+            game_phase: GamePhase::Normal,
+            game_state: GameState::Playing,
+            set_play: None,
+            half: Half::Second,
+            remaining_time_in_half: Duration::from_secs(312),
+            secondary_time: Duration::from_secs(17),
+            hulks_team: team_state(24, TeamColor::Black, 1),
+            opponent_team: team_state(5, TeamColor::Yellow, 3),
+            kicking_team: Team::Hulks,
+        };
+
+        let state = filtered_game_controller_state(&message, SystemTime::UNIX_EPOCH);
+
+        assert_eq!(state.half, Half::Second);
+        assert_eq!(state.remaining_time_in_half, Duration::from_secs(312));
+        assert_eq!(state.secondary_time, Duration::from_secs(17));
+        assert_eq!(state.hulks_score, 1);
+        assert_eq!(state.opponent_score, 3);
+        assert_eq!(state.goal_difference(), -2);
+        assert_eq!(state.hulks_team_color, TeamColor::Black);
+        assert_eq!(state.opponent_team_color, TeamColor::Yellow);
+    }
+}
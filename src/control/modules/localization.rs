@@ -10,15 +10,19 @@ use nalgebra::{
 use ordered_float::NotNan;
 use spl_network::{GamePhase, PlayerNumber, Team};
 use types::{
-    field_marks_from_field_dimensions, CorrespondencePoints, Direction, FieldDimensions, FieldMark,
-    GameControllerState, InitialPose, Line, Line2, LineData, LocalizationUpdate, Players,
-    PrimaryState, Side,
+    field_marks_from_field_dimensions, line_intersections_from_field_dimensions,
+    CorrespondencePoints, Direction, FieldDimensions, FieldMark, GameControllerState, GoalPost,
+    InitialPose, IntersectionKind, Line, Line2, LineData, LineIntersection, LocalizationUpdate,
+    PenaltyMark, Players, PrimaryState, Side,
 };
 
 use crate::control::filtering::{PoseFilter, ScoredPoseFilter};
 
 pub struct Localization {
     field_marks: Vec<FieldMark>,
+    goal_posts: [Point2<f32>; 4],
+    penalty_marks: [Point2<f32>; 2],
+    line_intersections: Vec<LineIntersection>,
     last_primary_state: PrimaryState,
     hypotheses: Vec<ScoredPoseFilter>,
     hypotheses_when_entered_playing: Vec<ScoredPoseFilter>,
@@ -33,9 +37,15 @@ pub struct Localization {
 #[historic_input(path = current_odometry_to_last_odometry, data_type = Isometry2<f32>)]
 #[perception_input(name = line_data_top, path = line_data, data_type = LineData, cycler = vision_top)]
 #[perception_input(name = line_data_bottom, path = line_data, data_type = LineData, cycler = vision_bottom)]
+#[perception_input(name = goal_posts_top, path = goal_posts, data_type = Vec<GoalPost>, cycler = vision_top)]
+#[perception_input(name = goal_posts_bottom, path = goal_posts, data_type = Vec<GoalPost>, cycler = vision_bottom)]
+#[perception_input(name = penalty_marks_top, path = penalty_marks, data_type = Vec<PenaltyMark>, cycler = vision_top)]
+#[perception_input(name = penalty_marks_bottom, path = penalty_marks, data_type = Vec<PenaltyMark>, cycler = vision_bottom)]
 #[persistent_state(path = robot_to_field, data_type = Isometry2<f32>)]
 #[parameter(path = field_dimensions, data_type = FieldDimensions)]
 #[parameter(path = control.localization.circle_measurement_noise, data_type = Vector2<f32>)]
+#[parameter(path = control.localization.goal_post_matching_distance, data_type = f32)]
+#[parameter(path = control.localization.goal_post_measurement_noise, data_type = Vector2<f32>)]
 #[parameter(path = control.localization.gradient_convergence_threshold, data_type = f32)]
 #[parameter(path = control.localization.gradient_descent_step_size, data_type = f32)]
 #[parameter(path = control.localization.hypothesis_prediction_score_reduction_factor, data_type = f32)]
@@ -43,13 +53,21 @@ pub struct Localization {
 #[parameter(path = control.localization.initial_hypothesis_covariance, data_type = Matrix3<f32>)]
 #[parameter(path = control.localization.initial_hypothesis_score, data_type = f32)]
 #[parameter(path = control.localization.initial_poses, data_type = Players<InitialPose>)]
+#[parameter(path = control.localization.line_intersection_direction_tolerance, data_type = f32)]
+#[parameter(path = control.localization.line_intersection_matching_distance, data_type = f32)]
+#[parameter(path = control.localization.line_intersection_measurement_noise, data_type = Vector2<f32>)]
 #[parameter(path = control.localization.line_length_acceptance_factor, data_type = f32)]
 #[parameter(path = control.localization.line_measurement_noise, data_type = Vector2<f32>)]
 #[parameter(path = control.localization.maximum_amount_of_gradient_descent_iterations, data_type = usize)]
 #[parameter(path = control.localization.maximum_amount_of_outer_iterations, data_type = usize)]
 #[parameter(path = control.localization.minimum_fit_error, data_type = f32)]
 #[parameter(path = control.localization.odometry_noise, data_type = Vector3<f32>)]
+#[parameter(path = control.localization.penalty_mark_matching_distance, data_type = f32)]
+#[parameter(path = control.localization.penalty_mark_measurement_noise, data_type = Vector2<f32>)]
+#[parameter(path = control.localization.use_goal_post_measurements, data_type = bool)]
+#[parameter(path = control.localization.use_line_intersection_measurements, data_type = bool)]
 #[parameter(path = control.localization.use_line_measurements, data_type = bool)]
+#[parameter(path = control.localization.use_penalty_mark_measurements, data_type = bool)]
 #[parameter(path = control.localization.good_matching_threshold, data_type = f32)]
 #[parameter(path = control.localization.score_per_good_match, data_type = f32)]
 #[parameter(path = control.localization.hypothesis_score_base_increase, data_type = f32)]
@@ -60,6 +78,7 @@ pub struct Localization {
 #[additional_output(path = localization.updates, data_type = Vec<Vec<LocalizationUpdate>>)]
 #[additional_output(path = localization.fit_errors, data_type = Vec<Vec<Vec<Vec<f32>>>>)]
 #[main_output(name = robot_to_field, data_type = Isometry2<f32>)]
+#[main_output(name = robot_to_field_covariance, data_type = Matrix3<f32>)]
 impl Localization {}
 
 impl Localization {
@@ -71,6 +90,9 @@ impl Localization {
                     context.field_dimensions,
                 ))
                 .collect(),
+            goal_posts: context.field_dimensions.goal_post_positions(),
+            penalty_marks: context.field_dimensions.penalty_mark_positions(),
+            line_intersections: line_intersections_from_field_dimensions(context.field_dimensions),
             last_primary_state: PrimaryState::Unstiff,
             hypotheses: vec![],
             hypotheses_when_entered_playing: vec![],
@@ -234,6 +256,44 @@ impl Localization {
                 let current_odometry_to_last_odometry = context
                     .current_odometry_to_last_odometry
                     .get(*line_data_top_timestamp);
+                let measured_goal_posts_in_robot: Vec<_> = context
+                    .goal_posts_top
+                    .persistent
+                    .get(line_data_top_timestamp)
+                    .into_iter()
+                    .chain(
+                        context
+                            .goal_posts_bottom
+                            .persistent
+                            .get(line_data_top_timestamp),
+                    )
+                    .flatten()
+                    .filter_map(|data| data.as_ref())
+                    .flatten()
+                    .map(|goal_post| goal_post.position_in_robot)
+                    .collect();
+                let measured_penalty_marks_in_robot: Vec<_> = context
+                    .penalty_marks_top
+                    .persistent
+                    .get(line_data_top_timestamp)
+                    .into_iter()
+                    .chain(
+                        context
+                            .penalty_marks_bottom
+                            .persistent
+                            .get(line_data_top_timestamp),
+                    )
+                    .flatten()
+                    .filter_map(|data| data.as_ref())
+                    .flatten()
+                    .map(|penalty_mark| penalty_mark.position_in_robot)
+                    .collect();
+                let measured_line_intersections_in_robot: Vec<_> = line_data_top
+                    .iter()
+                    .chain(line_data_bottom.iter())
+                    .filter_map(|&data| data.as_ref())
+                    .flat_map(|line_data| line_data.intersections_in_robot.iter().copied())
+                    .collect();
 
                 let mut fit_errors_per_hypothesis = vec![];
                 for (hypothesis_index, scored_filter) in self.hypotheses.iter_mut().enumerate() {
@@ -249,6 +309,46 @@ impl Localization {
                         scored_filter.score *=
                             *context.hypothesis_prediction_score_reduction_factor;
                     }
+                    if *context.use_goal_post_measurements {
+                        let number_of_good_matches = update_with_point_landmarks(
+                            scored_filter,
+                            &measured_goal_posts_in_robot,
+                            &self.goal_posts,
+                            *context.goal_post_matching_distance,
+                            context.goal_post_measurement_noise,
+                            *context.good_matching_threshold,
+                        )
+                        .context("Failed to update pose filter with goal posts")?;
+                        scored_filter.score +=
+                            number_of_good_matches as f32 * *context.score_per_good_match;
+                    }
+                    if *context.use_penalty_mark_measurements {
+                        let number_of_good_matches = update_with_point_landmarks(
+                            scored_filter,
+                            &measured_penalty_marks_in_robot,
+                            &self.penalty_marks,
+                            *context.penalty_mark_matching_distance,
+                            context.penalty_mark_measurement_noise,
+                            *context.good_matching_threshold,
+                        )
+                        .context("Failed to update pose filter with penalty marks")?;
+                        scored_filter.score +=
+                            number_of_good_matches as f32 * *context.score_per_good_match;
+                    }
+                    if *context.use_line_intersection_measurements {
+                        let number_of_good_matches = update_with_line_intersections(
+                            scored_filter,
+                            &measured_line_intersections_in_robot,
+                            &self.line_intersections,
+                            *context.line_intersection_matching_distance,
+                            *context.line_intersection_direction_tolerance,
+                            context.line_intersection_measurement_noise,
+                            *context.good_matching_threshold,
+                        )
+                        .context("Failed to update pose filter with line intersections")?;
+                        scored_filter.score +=
+                            number_of_good_matches as f32 * *context.score_per_good_match;
+                    }
                     if *context.use_line_measurements {
                         let robot_to_field = scored_filter.pose_filter.isometry();
                         let current_measured_lines_in_field: Vec<_> = line_data_top
@@ -429,6 +529,7 @@ impl Localization {
                 .expect("Expected at least one hypothesis");
             let best_score = best_hypothesis.score;
             let robot_to_field = best_hypothesis.pose_filter.isometry();
+            let robot_to_field_covariance = best_hypothesis.pose_filter.covariance();
             self.hypotheses
                 .retain(|filter| filter.score >= *context.hypothesis_retain_factor * best_score);
 
@@ -442,11 +543,13 @@ impl Localization {
             *context.robot_to_field = robot_to_field;
             return Ok(MainOutputs {
                 robot_to_field: Some(robot_to_field),
+                robot_to_field_covariance: Some(robot_to_field_covariance),
             });
         }
 
         Ok(MainOutputs {
             robot_to_field: None,
+            robot_to_field_covariance: None,
         })
     }
 
@@ -570,6 +673,118 @@ fn predict(
     )
 }
 
+/// Updates the pose filter with point landmarks (e.g. goal posts or penalty marks) matched to
+/// their nearest reference position. Each landmark measures the robot position given the current
+/// orientation estimate. Returns the number of landmarks matching closer than the good matching
+/// threshold.
+fn update_with_point_landmarks(
+    scored_filter: &mut ScoredPoseFilter,
+    measured_landmarks_in_robot: &[Point2<f32>],
+    reference_landmarks: &[Point2<f32>],
+    matching_distance: f32,
+    measurement_noise: &Vector2<f32>,
+    good_matching_threshold: f32,
+) -> Result<usize> {
+    let mut number_of_good_matches = 0;
+    for measured_landmark_in_robot in measured_landmarks_in_robot {
+        let robot_to_field = scored_filter.pose_filter.isometry();
+        let measured_landmark_in_field = robot_to_field * measured_landmark_in_robot;
+        let closest_reference = reference_landmarks.iter().min_by_key(|reference| {
+            NotNan::new(distance(reference, &measured_landmark_in_field))
+                .expect("Landmark distance should not be NaN")
+        });
+        let reference = match closest_reference {
+            Some(reference)
+                if distance(reference, &measured_landmark_in_field) < matching_distance =>
+            {
+                reference
+            }
+            _ => continue,
+        };
+        update_with_landmark_position(
+            scored_filter,
+            measured_landmark_in_robot,
+            reference,
+            measurement_noise,
+        )?;
+        if distance(reference, &measured_landmark_in_field) < good_matching_threshold {
+            number_of_good_matches += 1;
+        }
+    }
+    Ok(number_of_good_matches)
+}
+
+/// Updates the pose filter with line intersections matched to the nearest reference intersection
+/// of the same kind. Lines alone leave the pose unconstrained along their direction, an
+/// intersection pins it down in both dimensions. Directions of L and T intersections have to agree
+/// with the reference after rotating them into the field, X intersections have no direction.
+/// Returns the number of intersections matching closer than the good matching threshold.
+fn update_with_line_intersections(
+    scored_filter: &mut ScoredPoseFilter,
+    measured_intersections_in_robot: &[LineIntersection],
+    reference_intersections: &[LineIntersection],
+    matching_distance: f32,
+    direction_tolerance: f32,
+    measurement_noise: &Vector2<f32>,
+    good_matching_threshold: f32,
+) -> Result<usize> {
+    let mut number_of_good_matches = 0;
+    for measured_intersection_in_robot in measured_intersections_in_robot {
+        let robot_to_field = scored_filter.pose_filter.isometry();
+        let measured_position_in_field = robot_to_field * measured_intersection_in_robot.position;
+        let measured_direction_in_field =
+            robot_to_field.rotation * measured_intersection_in_robot.direction;
+        let closest_reference = reference_intersections
+            .iter()
+            .filter(|reference| {
+                reference.kind == measured_intersection_in_robot.kind
+                    && (reference.kind == IntersectionKind::X
+                        || reference.direction.angle(&measured_direction_in_field)
+                            < direction_tolerance)
+            })
+            .min_by_key(|reference| {
+                NotNan::new(distance(&reference.position, &measured_position_in_field))
+                    .expect("Line intersection distance should not be NaN")
+            });
+        let reference = match closest_reference {
+            Some(reference)
+                if distance(&reference.position, &measured_position_in_field)
+                    < matching_distance =>
+            {
+                reference.position
+            }
+            _ => continue,
+        };
+        update_with_landmark_position(
+            scored_filter,
+            &measured_intersection_in_robot.position,
+            &reference,
+            measurement_noise,
+        )?;
+        if distance(&reference, &measured_position_in_field) < good_matching_threshold {
+            number_of_good_matches += 1;
+        }
+    }
+    Ok(number_of_good_matches)
+}
+
+fn update_with_landmark_position(
+    scored_filter: &mut ScoredPoseFilter,
+    measured_landmark_in_robot: &Point2<f32>,
+    reference: &Point2<f32>,
+    measurement_noise: &Vector2<f32>,
+) -> Result<()> {
+    let robot_to_field = scored_filter.pose_filter.isometry();
+    let measured_robot_position =
+        reference - robot_to_field.rotation * measured_landmark_in_robot.coords;
+    let distance_to_landmark = measured_landmark_in_robot.coords.norm();
+    scored_filter.pose_filter.update_with_2d_translation(
+        measured_robot_position.coords,
+        Matrix::from_diagonal(measurement_noise) * distance_to_landmark,
+        |state| vector![state.x, state.y],
+    )
+}
+
 fn get_fitted_field_mark_correspondence(
     measured_lines_in_field: &[Line2],
     field_marks: &[FieldMark],
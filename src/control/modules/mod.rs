@@ -4,6 +4,7 @@ pub mod button_filter;
 pub mod camera_matrix_provider;
 pub mod center_of_mass_provider;
 pub mod fall_state_estimation;
+pub mod fall_statistics_recorder;
 pub mod game_controller_filter;
 pub mod game_state_filter;
 pub mod ground_contact_detector;
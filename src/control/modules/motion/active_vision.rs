@@ -0,0 +1,443 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use module_derive::module;
+use nalgebra::{point, Isometry2, Isometry3, Matrix3, Point2, UnitComplex};
+use ordered_float::NotNan;
+use serde::{Deserialize, Serialize};
+use serialize_hierarchy::SerializeHierarchy;
+use types::{
+    CameraMatrices, FieldDimensions, HeadJoints, HeadMotion, MotionCommand, Obstacle, SensorData,
+};
+
+use crate::{
+    control::modules::{ball_filter::BallFilterHypothesis, behavior::head::generate_field_marks},
+    framework::configuration,
+    kinematics::{head_to_neck, neck_to_robot},
+};
+
+use super::look_at::look_at;
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
+pub struct GazeTarget {
+    pub position: Point2<f32>,
+    pub information_gain: f32,
+    pub score: f32,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct InformationSource {
+    position: Point2<f32>,
+    information_gain: f32,
+}
+
+pub struct ActiveVision {
+    field_marks: Vec<Point2<f32>>,
+    last_seen_in_sector: Vec<SystemTime>,
+    current_target: Option<Point2<f32>>,
+    last_target_switch: SystemTime,
+}
+
+#[module(control)]
+#[input(path = sensor_data, data_type = SensorData, required)]
+#[input(path = motion_command, data_type = MotionCommand, required)]
+#[input(path = camera_matrices, data_type = CameraMatrices)]
+#[input(path = ground_to_robot, data_type = Isometry3<f32>)]
+#[input(path = ball_filter_hypotheses, data_type = Vec<BallFilterHypothesis>)]
+#[input(path = robot_to_field, data_type = Isometry2<f32>)]
+#[input(path = robot_to_field_covariance, data_type = Matrix3<f32>)]
+#[input(path = obstacles, data_type = Vec<Obstacle>)]
+#[parameter(path = control.active_vision, data_type = configuration::ActiveVision, name = config)]
+#[parameter(path = control.head_motion.maximum_velocity, data_type = HeadJoints)]
+#[parameter(path = control.head_motion_limits.maximum_yaw, data_type = f32)]
+#[parameter(path = control.look_at.minimum_bottom_focus_pitch, data_type = f32)]
+#[parameter(path = field_dimensions, data_type = FieldDimensions)]
+#[additional_output(path = active_vision_targets, data_type = Vec<GazeTarget>)]
+#[main_output(name = active_vision, data_type = HeadJoints)]
+impl ActiveVision {}
+
+impl ActiveVision {
+    fn new(context: NewContext) -> anyhow::Result<Self> {
+        Ok(Self {
+            field_marks: generate_field_marks(context.field_dimensions),
+            last_seen_in_sector: vec![UNIX_EPOCH; context.config.number_of_exploration_sectors],
+            current_target: None,
+            last_target_switch: UNIX_EPOCH,
+        })
+    }
+
+    fn cycle(&mut self, mut context: CycleContext) -> anyhow::Result<MainOutputs> {
+        let sensor_data = context.sensor_data;
+        let now = sensor_data.cycle_info.start_time;
+        let current_head_angles = sensor_data.positions.head;
+
+        let default_output = Ok(MainOutputs {
+            active_vision: Some(current_head_angles),
+        });
+
+        let (camera_matrices, ground_to_robot) =
+            match (context.camera_matrices, context.ground_to_robot) {
+                (Some(camera_matrices), Some(ground_to_robot)) => {
+                    (camera_matrices, ground_to_robot)
+                }
+                _ => return default_output,
+            };
+
+        let field_of_view = camera_matrices.top.field_of_view.x;
+        let sector_range = *context.maximum_yaw + field_of_view / 2.0;
+        self.mark_seen_sectors(current_head_angles.yaw, field_of_view, sector_range, now);
+
+        if context.motion_command.head_motion() != Some(HeadMotion::ActiveVision) {
+            self.current_target = None;
+            return default_output;
+        }
+
+        let mut sources = Vec::new();
+        if let Some(ball_filter_hypotheses) = context.ball_filter_hypotheses {
+            sources.extend(ball_information(ball_filter_hypotheses, context.config));
+        }
+        if let (Some(robot_to_field), Some(robot_to_field_covariance)) =
+            (context.robot_to_field, context.robot_to_field_covariance)
+        {
+            sources.extend(field_mark_information(
+                &self.field_marks,
+                *robot_to_field,
+                *robot_to_field_covariance,
+                context.config,
+            ));
+        }
+        sources.extend(self.exploration_information(
+            context.obstacles.as_deref().unwrap_or_default(),
+            sector_range,
+            now,
+            context.config,
+        ));
+
+        let zero_head_to_robot =
+            neck_to_robot(&HeadJoints::default()) * head_to_neck(&HeadJoints::default());
+        let ground_to_zero_head = zero_head_to_robot.inverse() * ground_to_robot;
+        let candidates: Vec<_> = sources
+            .iter()
+            .filter_map(|candidate| {
+                let head_joints = look_at(
+                    sensor_data.positions,
+                    ground_to_zero_head,
+                    camera_matrices.top.camera_to_head.inverse(),
+                    camera_matrices.bottom.camera_to_head.inverse(),
+                    candidate.position,
+                    *context.minimum_bottom_focus_pitch,
+                );
+                if head_joints.yaw.abs() > *context.maximum_yaw {
+                    return None;
+                }
+                let information_gain =
+                    visible_information_gain(candidate.position, &sources, field_of_view);
+                let travel_time =
+                    travel_time(current_head_angles, head_joints, *context.maximum_velocity);
+                let score = information_gain - context.config.travel_time_cost * travel_time;
+                if !score.is_finite() {
+                    return None;
+                }
+                Some((
+                    GazeTarget {
+                        position: candidate.position,
+                        information_gain,
+                        score,
+                    },
+                    head_joints,
+                ))
+            })
+            .collect();
+
+        let followed_target = self.current_target.and_then(|current_target| {
+            candidates.iter().min_by_key(|(candidate, _)| {
+                NotNan::new(nalgebra::distance(&candidate.position, &current_target)).unwrap()
+            })
+        });
+        let is_fixating = now
+            .duration_since(self.last_target_switch)
+            .unwrap_or(Duration::ZERO)
+            < context.config.minimum_fixation_duration;
+        let best_target = candidates
+            .iter()
+            .max_by_key(|(candidate, _)| NotNan::new(candidate.score).unwrap());
+        let target = match (followed_target, is_fixating) {
+            (Some(followed_target), true) => Some(followed_target),
+            (followed_target, _) => {
+                let followed_position = followed_target.map(|(target, _)| target.position);
+                let best_position = best_target.map(|(target, _)| target.position);
+                if best_position != followed_position {
+                    self.last_target_switch = now;
+                }
+                best_target
+            }
+        };
+        self.current_target = target.map(|(target, _)| target.position);
+
+        context.active_vision_targets.fill_on_subscription(|| {
+            candidates
+                .iter()
+                .map(|(candidate, _)| candidate.clone())
+                .collect()
+        });
+
+        match target {
+            Some((_, head_joints)) => Ok(MainOutputs {
+                active_vision: Some(*head_joints),
+            }),
+            None => default_output,
+        }
+    }
+
+    fn mark_seen_sectors(
+        &mut self,
+        head_yaw: f32,
+        field_of_view: f32,
+        sector_range: f32,
+        now: SystemTime,
+    ) {
+        let number_of_sectors = self.last_seen_in_sector.len();
+        for (index, last_seen) in self.last_seen_in_sector.iter_mut().enumerate() {
+            let sector_center = sector_center(index, number_of_sectors, sector_range);
+            if (sector_center - head_yaw).abs() < field_of_view / 2.0 {
+                *last_seen = now;
+            }
+        }
+    }
+
+    fn exploration_information(
+        &self,
+        obstacles: &[Obstacle],
+        sector_range: f32,
+        now: SystemTime,
+        config: &configuration::ActiveVision,
+    ) -> Vec<InformationSource> {
+        let number_of_sectors = self.last_seen_in_sector.len();
+        let sector_width = 2.0 * sector_range / number_of_sectors as f32;
+        self.last_seen_in_sector
+            .iter()
+            .enumerate()
+            .map(|(index, last_seen)| {
+                let sector_center = sector_center(index, number_of_sectors, sector_range);
+                let time_since_seen = now.duration_since(*last_seen).unwrap_or(Duration::ZERO);
+                let staleness = if config.exploration_time_constant.is_zero() {
+                    1.0
+                } else {
+                    1.0 - (-time_since_seen.as_secs_f32()
+                        / config.exploration_time_constant.as_secs_f32())
+                    .exp()
+                };
+                let number_of_obstacles_in_sector = obstacles
+                    .iter()
+                    .filter(|obstacle| {
+                        angle_between(bearing(obstacle.position), sector_center)
+                            < sector_width / 2.0
+                    })
+                    .count();
+                InformationSource {
+                    position: point![
+                        config.exploration_distance * sector_center.cos(),
+                        config.exploration_distance * sector_center.sin()
+                    ],
+                    information_gain: config.exploration_weight
+                        * staleness
+                        * (1 + number_of_obstacles_in_sector) as f32,
+                }
+            })
+            .collect()
+    }
+}
+
+fn sector_center(index: usize, number_of_sectors: usize, sector_range: f32) -> f32 {
+    -sector_range + (index as f32 + 0.5) * 2.0 * sector_range / number_of_sectors as f32
+}
+
+fn bearing(position: Point2<f32>) -> f32 {
+    position.y.atan2(position.x)
+}
+
+fn angle_between(first: f32, second: f32) -> f32 {
+    UnitComplex::new(first - second).angle().abs()
+}
+
+/// The ball is more interesting the more uncertain its position is. Each hypothesis contributes
+/// according to its share of the total validity.
+fn ball_information(
+    hypotheses: &[BallFilterHypothesis],
+    config: &configuration::ActiveVision,
+) -> Vec<InformationSource> {
+    let valid_hypotheses: Vec<_> = hypotheses
+        .iter()
+        .filter(|hypothesis| hypothesis.validity() >= config.minimum_ball_validity)
+        .collect();
+    let total_validity: f32 = valid_hypotheses
+        .iter()
+        .map(|hypothesis| hypothesis.validity())
+        .sum();
+    if total_validity <= 0.0 {
+        return Vec::new();
+    }
+    valid_hypotheses
+        .into_iter()
+        .map(|hypothesis| InformationSource {
+            position: hypothesis.position(),
+            information_gain: config.ball_weight * hypothesis.validity() / total_validity
+                * (1.0 + hypothesis.position_covariance().trace() / config.ball_reference_variance)
+                    .ln(),
+        })
+        .collect()
+}
+
+/// Field marks are more interesting the more uncertain the pose is. The rotational uncertainty
+/// matters more for distant field marks.
+fn field_mark_information(
+    field_marks: &[Point2<f32>],
+    robot_to_field: Isometry2<f32>,
+    robot_to_field_covariance: Matrix3<f32>,
+    config: &configuration::ActiveVision,
+) -> Vec<InformationSource> {
+    let field_to_robot = robot_to_field.inverse();
+    field_marks
+        .iter()
+        .map(|field_mark| field_to_robot * field_mark)
+        .filter(|position| position.coords.norm() < config.maximum_field_mark_distance)
+        .map(|position| {
+            let pose_uncertainty = robot_to_field_covariance[(0, 0)]
+                + robot_to_field_covariance[(1, 1)]
+                + position.coords.norm_squared() * robot_to_field_covariance[(2, 2)];
+            InformationSource {
+                position,
+                information_gain: config.localization_weight
+                    * (1.0 + pose_uncertainty / config.localization_reference_variance).ln(),
+            }
+        })
+        .collect()
+}
+
+fn visible_information_gain(
+    gaze_target: Point2<f32>,
+    sources: &[InformationSource],
+    field_of_view: f32,
+) -> f32 {
+    let gaze_bearing = bearing(gaze_target);
+    sources
+        .iter()
+        .filter(|source| {
+            angle_between(bearing(source.position), gaze_bearing) < field_of_view / 2.0
+        })
+        .map(|source| source.information_gain)
+        .sum()
+}
+
+fn travel_time(from: HeadJoints, to: HeadJoints, maximum_velocity: HeadJoints) -> f32 {
+    let yaw_time = (to.yaw - from.yaw).abs() / maximum_velocity.yaw;
+    let pitch_time = (to.pitch - from.pitch).abs() / maximum_velocity.pitch;
+    yaw_time.max(pitch_time)
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+    use nalgebra::{matrix, Isometry2};
+
+    use super::*;
+
+    fn config() -> configuration::ActiveVision {
+        configuration::ActiveVision {
+            localization_reference_variance: 0.02,
+            localization_weight: 1.0,
+            maximum_field_mark_distance: 4.0,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn uncertain_pose_makes_field_marks_more_interesting() {
+        let field_marks = [point![1.0, 0.0], point![10.0, 0.0]];
+        let certain = field_mark_information(
+            &field_marks,
+            Isometry2::identity(),
+            Matrix3::from_diagonal_element(0.001),
+            &config(),
+        );
+        let uncertain = field_mark_information(
+            &field_marks,
+            Isometry2::identity(),
+            matrix![
+                0.1, 0.0, 0.0;
+                0.0, 0.1, 0.0;
+                0.0, 0.0, 0.1;
+            ],
+            &config(),
+        );
+
+        assert_eq!(certain.len(), 1);
+        assert_eq!(uncertain.len(), 1);
+        assert!(uncertain[0].information_gain > certain[0].information_gain);
+    }
+
+    #[test]
+    fn only_sources_in_field_of_view_contribute() {
+        let sources = [
+            InformationSource {
+                position: point![1.0, 0.1],
+                information_gain: 1.0,
+            },
+            InformationSource {
+                position: point![1.0, -0.1],
+                information_gain: 2.0,
+            },
+            InformationSource {
+                position: point![0.0, 1.0],
+                information_gain: 4.0,
+            },
+        ];
+
+        assert_relative_eq!(
+            visible_information_gain(point![1.0, 0.0], &sources, 1.0),
+            3.0
+        );
+        assert_relative_eq!(
+            visible_information_gain(point![0.0, 2.0], &sources, 1.0),
+            4.0
+        );
+    }
+
+    #[test]
+    fn travel_time_is_limited_by_slowest_joint() {
+        let from = HeadJoints {
+            yaw: 0.0,
+            pitch: 0.0,
+        };
+        let to = HeadJoints {
+            yaw: 1.0,
+            pitch: 0.3,
+        };
+        let maximum_velocity = HeadJoints {
+            yaw: 4.0,
+            pitch: 1.0,
+        };
+
+        assert_relative_eq!(travel_time(from, to, maximum_velocity), 0.3);
+    }
+
+    #[test]
+    fn zero_exploration_time_constant_keeps_information_finite() {
+        let active_vision = ActiveVision {
+            field_marks: vec![],
+            last_seen_in_sector: vec![UNIX_EPOCH; 4],
+            current_target: None,
+            last_target_switch: UNIX_EPOCH,
+        };
+        let config = configuration::ActiveVision {
+            exploration_distance: 2.0,
+            exploration_weight: 1.0,
+            ..Default::default()
+        };
+        let sources = active_vision.exploration_information(&[], 1.5, UNIX_EPOCH, &config);
+
+        assert_eq!(sources.len(), 4);
+        assert!(sources
+            .iter()
+            .all(|source| source.information_gain.is_finite()));
+    }
+}
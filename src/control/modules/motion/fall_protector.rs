@@ -49,25 +49,27 @@ impl FallProtector {
         if self.start_time.elapsed().unwrap() >= Duration::from_millis(500) {
             head_stiffness = 0.5;
         }
-        match motion_command {
-            MotionCommand::FallProtection {
-                direction: FallDirection::Forward,
-            } => {
-                if relative_eq!(current_positions.head.pitch, -0.672, epsilon = 0.05)
-                    && relative_eq!(current_positions.head.yaw.abs(), 0.0, epsilon = 0.05)
-                {
+        let positions = match motion_command {
+            MotionCommand::FallProtection { direction } => {
+                let positions = &context.fall_protection[*direction];
+                if relative_eq!(
+                    current_positions.head.pitch,
+                    positions.head.pitch,
+                    epsilon = 0.05
+                ) && relative_eq!(
+                    current_positions.head.yaw,
+                    positions.head.yaw,
+                    epsilon = 0.05
+                ) {
                     head_stiffness = context.fall_protection.ground_impact_head_stiffness;
                 }
+                positions
             }
-            MotionCommand::FallProtection { .. } => {
-                if relative_eq!(current_positions.head.pitch, 0.5149, epsilon = 0.05)
-                    && relative_eq!(current_positions.head.yaw.abs(), 0.0, epsilon = 0.05)
-                {
-                    head_stiffness = context.fall_protection.ground_impact_head_stiffness;
-                }
+            _ => {
+                head_stiffness = context.fall_protection.ground_impact_head_stiffness;
+                &context.fall_protection[FallDirection::Backward]
             }
-            _ => head_stiffness = context.fall_protection.ground_impact_head_stiffness,
-        }
+        };
 
         let stiffnesses = Joints::from_head_and_body(
             HeadJoints::fill(head_stiffness),
@@ -77,40 +79,18 @@ impl FallProtector {
             ),
         );
 
-        let fall_protection_command = match motion_command {
-            MotionCommand::FallProtection {
-                direction: FallDirection::Forward,
-            } => Some(JointsCommand {
-                positions: Joints::from_head_and_body(
-                    HeadJoints {
-                        yaw: 0.0,
-                        pitch: -0.672,
-                    },
-                    BodyJoints {
-                        left_arm: context.fall_protection.left_arm_positions,
-                        right_arm: context.fall_protection.right_arm_positions,
-                        left_leg: current_positions.left_leg,
-                        right_leg: current_positions.right_leg,
-                    },
-                ),
-                stiffnesses,
-            }),
-            _ => Some(JointsCommand {
-                positions: Joints::from_head_and_body(
-                    HeadJoints {
-                        yaw: 0.0,
-                        pitch: 0.5149,
-                    },
-                    BodyJoints {
-                        left_arm: context.fall_protection.left_arm_positions,
-                        right_arm: context.fall_protection.right_arm_positions,
-                        left_leg: current_positions.left_leg,
-                        right_leg: current_positions.right_leg,
-                    },
-                ),
-                stiffnesses,
-            }),
-        };
+        let fall_protection_command = Some(JointsCommand {
+            positions: Joints::from_head_and_body(
+                positions.head,
+                BodyJoints {
+                    left_arm: positions.left_arm,
+                    right_arm: positions.right_arm,
+                    left_leg: current_positions.left_leg,
+                    right_leg: current_positions.right_leg,
+                },
+            ),
+            stiffnesses,
+        });
 
         Ok(MainOutputs {
             fall_protection_command,
@@ -15,6 +15,7 @@ pub struct HeadMotion {
 #[input(path = sensor_data, data_type = SensorData, required)]
 #[input(path = look_around, data_type = HeadJoints, required)]
 #[input(path = look_at, data_type = HeadJoints, required)]
+#[input(path = active_vision, data_type = HeadJoints, required)]
 #[parameter(path = control.center_head_position, data_type = HeadJoints)]
 #[parameter(path = control.head_motion.maximum_velocity, data_type = HeadJoints)]
 #[parameter(path = control.head_motion.outer_maximum_pitch, data_type = f32)]
@@ -35,6 +36,7 @@ impl HeadMotion {
         let sensor_data = context.sensor_data;
         let look_around = context.look_around;
         let look_at = context.look_at;
+        let active_vision = context.active_vision;
         let current_head_angles = sensor_data.positions.head;
 
         let raw_request = match motion_command.head_motion() {
@@ -43,6 +45,7 @@ impl HeadMotion {
                 *look_around
             }
             Some(HeadMotionCommand::LookAt { .. }) => *look_at,
+            Some(HeadMotionCommand::ActiveVision) => *active_vision,
             Some(HeadMotionCommand::Unstiff) => current_head_angles,
             Some(HeadMotionCommand::ZeroAngles) => Default::default(),
             None => Default::default(),
@@ -74,7 +74,7 @@ impl LookAt {
     }
 }
 
-fn look_at(
+pub fn look_at(
     joint_angles: Joints,
     ground_to_zero_head: Isometry3<f32>,
     head_to_top_camera: Isometry3<f32>,
@@ -1,3 +1,4 @@
+pub mod active_vision;
 pub mod arms_up_squat;
 pub mod dispatching_interpolator;
 pub mod fall_protector;
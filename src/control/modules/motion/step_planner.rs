@@ -1,6 +1,9 @@
 use module_derive::{module, require_some};
-use nalgebra::{Isometry2, UnitComplex};
-use types::{MotionCommand, OrientationMode, PathSegment, SensorData, Side, Step, SupportFoot};
+use nalgebra::{point, Isometry2, Point2, UnitComplex, Vector2};
+use types::{
+    MotionCommand, Obstacle, ObstacleKind, Orientation, OrientationMode, PathSegment,
+    RobotDimensions, SensorData, Side, Step, SupportFoot,
+};
 
 pub struct StepPlanner;
 
@@ -8,6 +11,7 @@ pub struct StepPlanner;
 #[input(path = sensor_data, data_type = SensorData)]
 #[input(path = motion_command, data_type = MotionCommand)]
 #[input(path = support_foot, data_type = SupportFoot)]
+#[input(path = obstacles, data_type = Vec<Obstacle>)]
 #[persistent_state(path = walk_return_offset, data_type = Step)]
 #[parameter(path = control.step_planner.injected_step, data_type = Option<Step>)]
 #[parameter(path = control.step_planner.max_step_size, data_type = Step)]
@@ -15,6 +19,9 @@ pub struct StepPlanner;
 #[parameter(path = control.step_planner.translation_exponent, data_type = f32)]
 #[parameter(path = control.step_planner.rotation_exponent, data_type = f32)]
 #[parameter(path = control.step_planner.inside_turn_ratio, data_type = f32)]
+#[parameter(path = control.step_planner.planning_horizon, data_type = usize)]
+#[parameter(path = control.step_planner.foot_collision_radius, data_type = f32)]
+#[additional_output(path = planned_steps, data_type = Vec<Step>)]
 #[main_output(name = step_plan, data_type = Step)]
 impl StepPlanner {}
 
@@ -23,7 +30,7 @@ impl StepPlanner {
         Ok(Self)
     }
 
-    fn cycle(&mut self, context: CycleContext) -> anyhow::Result<MainOutputs> {
+    fn cycle(&mut self, mut context: CycleContext) -> anyhow::Result<MainOutputs> {
         let motion_command = require_some!(context.motion_command);
         let support_side = require_some!(require_some!(context.support_foot).support_side);
 
@@ -43,73 +50,226 @@ impl StepPlanner {
                 })
             }
         };
+        if path.is_empty() {
+            anyhow::bail!("Empty path provided");
+        }
+
+        if let Some(injected_step) = context.injected_step {
+            let step = compensate_with_return_offset(*injected_step, *context.walk_return_offset);
+            let step = clamp_step_to_walk_volume(
+                step,
+                context.max_step_size,
+                *context.max_step_size_backwards,
+                *context.translation_exponent,
+                *context.rotation_exponent,
+            );
+            let step =
+                clamp_to_anatomic_constraints(step, support_side, *context.inside_turn_ratio);
+            return Ok(MainOutputs {
+                step_plan: Some(step),
+            });
+        }
 
-        let segment = path
+        let obstacles: Vec<_> = context
+            .obstacles
             .iter()
-            .scan(0.0f32, |distance, segment| {
-                let result = if *distance < context.max_step_size.forward {
-                    Some(segment)
-                } else {
-                    None
-                };
-                *distance += segment.length();
-                result
-            })
-            .last()
-            .ok_or_else(|| anyhow::anyhow!("Empty path provided"))?;
-
-        let target_pose = match segment {
-            PathSegment::LineSegment(line_segment) => {
-                let direction = line_segment.1;
-                let rotation = if direction.coords.norm_squared() < f32::EPSILON {
-                    UnitComplex::identity()
-                } else {
-                    UnitComplex::from_cos_sin_unchecked(direction.x, direction.y)
-                };
-                Isometry2::<f32>::from_parts(line_segment.1.into(), rotation)
-            }
-            PathSegment::Arc(arc, orientation) => {
-                let direction = orientation
-                    .rotate_vector_90_degrees(arc.start - arc.circle.center)
-                    .normalize();
-                Isometry2::<f32>::from_parts(
-                    (arc.start + direction * context.max_step_size.forward).into(),
-                    UnitComplex::from_cos_sin_unchecked(direction.x, direction.y),
-                )
-            }
-        };
+            .flat_map(|obstacles| obstacles.iter())
+            .filter(|obstacle| !matches!(obstacle.kind, ObstacleKind::Ball))
+            .copied()
+            .collect();
 
-        let mut step = Step {
-            forward: target_pose.translation.x,
-            left: target_pose.translation.y,
-            turn: match orientation_mode {
-                OrientationMode::AlignWithPath => target_pose.rotation,
-                OrientationMode::Override(orientation) => *orientation,
-            }
-            .angle(),
-        };
+        let path_length: f32 = path.iter().map(PathSegment::length).sum();
+        let number_of_steps =
+            number_of_steps_to_target(path_length, orientation_mode, context.max_step_size);
+        let step_length = path_length / number_of_steps as f32;
 
-        if let Some(injected_step) = context.injected_step {
-            step = *injected_step;
+        let mut planned_steps = Vec::new();
+        let mut current_pose = Isometry2::identity();
+        let mut current_support_side = support_side;
+        for step_index in 1..=number_of_steps.min((*context.planning_horizon).max(1)) {
+            let path_pose = pose_along_path(path, step_length * step_index as f32);
+            let target_rotation = match orientation_mode {
+                OrientationMode::AlignWithPath => path_pose.rotation,
+                OrientationMode::Override(orientation) => UnitComplex::new(
+                    orientation.angle() * step_index as f32 / number_of_steps as f32,
+                ),
+            };
+            let target_pose = Isometry2::from_parts(path_pose.translation, target_rotation);
+            let step_pose = current_pose.inverse() * target_pose;
+            let step = Step {
+                forward: step_pose.translation.x,
+                left: step_pose.translation.y,
+                turn: step_pose.rotation.angle(),
+            };
+
+            let step = if step_index == 1 {
+                compensate_with_return_offset(step, *context.walk_return_offset)
+            } else {
+                step
+            };
+            let step = clamp_step_to_walk_volume(
+                step,
+                context.max_step_size,
+                *context.max_step_size_backwards,
+                *context.translation_exponent,
+                *context.rotation_exponent,
+            );
+            let step = clamp_to_anatomic_constraints(
+                step,
+                current_support_side,
+                *context.inside_turn_ratio,
+            );
+            let obstacles_in_step_frame = obstacles.iter().map(|obstacle| Obstacle {
+                position: current_pose.inverse() * obstacle.position,
+                ..*obstacle
+            });
+            let step = clip_step_to_obstacles(
+                step,
+                current_support_side.opposite(),
+                obstacles_in_step_frame,
+                *context.foot_collision_radius,
+            );
+
+            current_pose *= Isometry2::new(Vector2::new(step.forward, step.left), step.turn);
+            current_support_side = current_support_side.opposite();
+            planned_steps.push(step);
         }
 
-        let step = compensate_with_return_offset(step, *context.walk_return_offset);
+        let step_plan = planned_steps[0];
+        context.planned_steps.fill_on_subscription(|| planned_steps);
 
-        // clamp_step_to_walk_volume
-        let step = clamp_step_to_walk_volume(
-            step,
-            context.max_step_size,
-            *context.max_step_size_backwards,
-            *context.translation_exponent,
-            *context.rotation_exponent,
+        Ok(MainOutputs {
+            step_plan: Some(step_plan),
+        })
+    }
+}
+
+fn number_of_steps_to_target(
+    path_length: f32,
+    orientation_mode: &OrientationMode,
+    max_step_size: &Step,
+) -> usize {
+    let number_of_translation_steps = (path_length / max_step_size.forward).ceil();
+    let number_of_rotation_steps = match orientation_mode {
+        OrientationMode::AlignWithPath => 0.0,
+        OrientationMode::Override(orientation) => {
+            (orientation.angle().abs() / max_step_size.turn).ceil()
+        }
+    };
+    (number_of_translation_steps.max(number_of_rotation_steps) as usize).max(1)
+}
+
+fn pose_along_path(path: &[PathSegment], distance: f32) -> Isometry2<f32> {
+    let mut remaining_distance = distance;
+    let mut pose = Isometry2::identity();
+    for segment in path {
+        let segment_length = segment.length();
+        pose = pose_on_segment(
+            segment,
+            remaining_distance.min(segment_length),
+            pose.rotation,
         );
+        remaining_distance -= segment_length;
+        if remaining_distance <= 0.0 {
+            break;
+        }
+    }
+    pose
+}
 
-        // clamp_to_anatomic_constraints
-        let step = clamp_to_anatomic_constraints(step, support_side, *context.inside_turn_ratio);
+fn pose_on_segment(
+    segment: &PathSegment,
+    distance: f32,
+    fallback_rotation: UnitComplex<f32>,
+) -> Isometry2<f32> {
+    match segment {
+        PathSegment::LineSegment(line_segment) => {
+            let direction = line_segment.1 - line_segment.0;
+            if direction.norm_squared() < f32::EPSILON {
+                return Isometry2::from_parts(line_segment.1.into(), fallback_rotation);
+            }
+            let direction = direction.normalize();
+            Isometry2::from_parts(
+                (line_segment.0 + direction * distance).into(),
+                UnitComplex::from_cos_sin_unchecked(direction.x, direction.y),
+            )
+        }
+        PathSegment::Arc(arc, orientation) => {
+            let signed_angle = match orientation {
+                Orientation::Counterclockwise => distance / arc.circle.radius,
+                Orientation::Clockwise => -distance / arc.circle.radius,
+                Orientation::Colinear => 0.0,
+            };
+            let center_to_position =
+                UnitComplex::new(signed_angle) * (arc.start - arc.circle.center);
+            let direction = orientation
+                .rotate_vector_90_degrees(center_to_position)
+                .normalize();
+            Isometry2::from_parts(
+                (arc.circle.center + center_to_position).into(),
+                UnitComplex::from_cos_sin_unchecked(direction.x, direction.y),
+            )
+        }
+    }
+}
 
-        Ok(MainOutputs {
-            step_plan: Some(step),
+fn clip_step_to_obstacles(
+    step: Step,
+    swing_side: Side,
+    obstacles: impl IntoIterator<Item = Obstacle>,
+    foot_collision_radius: f32,
+) -> Step {
+    let foot_offset = match swing_side {
+        Side::Left => RobotDimensions::ROBOT_TO_LEFT_PELVIS.y,
+        Side::Right => RobotDimensions::ROBOT_TO_RIGHT_PELVIS.y,
+    };
+    let swing_foot_start = point![0.0, foot_offset];
+    let swing_foot_end =
+        Isometry2::new(Vector2::new(step.forward, step.left), step.turn) * swing_foot_start;
+    let scale = obstacles
+        .into_iter()
+        .map(|obstacle| {
+            free_fraction_of_movement(
+                swing_foot_start,
+                swing_foot_end,
+                obstacle.position,
+                obstacle.radius_at_foot_height + foot_collision_radius,
+            )
         })
+        .fold(1.0, f32::min);
+    Step {
+        forward: step.forward * scale,
+        left: step.left * scale,
+        turn: step.turn,
+    }
+}
+
+/// Fraction in [0, 1] of the movement from start to end before entering the circle around center.
+///
+/// Movements starting inside the circle are not restricted, otherwise the robot could not leave it.
+fn free_fraction_of_movement(
+    start: Point2<f32>,
+    end: Point2<f32>,
+    center: Point2<f32>,
+    radius: f32,
+) -> f32 {
+    let movement = end - start;
+    let center_to_start = start - center;
+    let a = movement.norm_squared();
+    let b = 2.0 * movement.dot(&center_to_start);
+    let c = center_to_start.norm_squared() - radius.powi(2);
+    if c <= 0.0 || a < f32::EPSILON {
+        return 1.0;
+    }
+    let discriminant = b.powi(2) - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return 1.0;
+    }
+    let entering_fraction = (-b - discriminant.sqrt()) / (2.0 * a);
+    if (0.0..=1.0).contains(&entering_fraction) {
+        entering_fraction
+    } else {
+        1.0
     }
 }
 
@@ -234,3 +394,83 @@ fn clamp_to_anatomic_constraints(
         turn: clamped_turn,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use approx::assert_relative_eq;
+    use nalgebra::point;
+    use spl_network::Team;
+    use types::{direct_path, Arc, Circle};
+
+    use super::*;
+
+    fn max_step_size() -> Step {
+        Step {
+            forward: 0.05,
+            left: 0.12,
+            turn: 0.9,
+        }
+    }
+
+    #[test]
+    fn steps_end_exactly_at_target() {
+        let path = direct_path(Point2::origin(), point![0.12, 0.0]);
+        let path_length: f32 = path.iter().map(PathSegment::length).sum();
+        let number_of_steps = number_of_steps_to_target(
+            path_length,
+            &OrientationMode::AlignWithPath,
+            &max_step_size(),
+        );
+        assert_eq!(number_of_steps, 3);
+
+        let step_length = path_length / number_of_steps as f32;
+        assert_relative_eq!(step_length, 0.04, epsilon = 0.0001);
+        let last_pose = pose_along_path(&path, step_length * number_of_steps as f32);
+        assert_relative_eq!(last_pose.translation.x, 0.12, epsilon = 0.0001);
+        assert_relative_eq!(last_pose.translation.y, 0.0, epsilon = 0.0001);
+    }
+
+    #[test]
+    fn override_orientation_requires_enough_steps_to_turn() {
+        let number_of_steps = number_of_steps_to_target(
+            0.0,
+            &OrientationMode::Override(UnitComplex::new(2.0)),
+            &max_step_size(),
+        );
+        assert_eq!(number_of_steps, 3);
+    }
+
+    #[test]
+    fn pose_along_arc_is_tangential() {
+        let path = vec![PathSegment::Arc(
+            Arc::new(
+                Circle::new(point![0.0, 1.0], 1.0),
+                point![0.0, 0.0],
+                point![1.0, 1.0],
+            ),
+            Orientation::Counterclockwise,
+        )];
+        let pose = pose_along_path(&path, FRAC_PI_2);
+        assert_relative_eq!(pose.translation.x, 1.0, epsilon = 0.0001);
+        assert_relative_eq!(pose.translation.y, 1.0, epsilon = 0.0001);
+        assert_relative_eq!(pose.rotation.angle(), FRAC_PI_2, epsilon = 0.0001);
+    }
+
+    #[test]
+    fn step_is_clipped_in_front_of_obstacle() {
+        let step = Step {
+            forward: 0.05,
+            left: 0.0,
+            turn: 0.0,
+        };
+        let obstacle = Obstacle::robot(point![0.2, 0.05], 0.1, 0.1, Team::Uncertain);
+        let clipped_step = clip_step_to_obstacles(step, Side::Left, [obstacle], 0.08);
+        assert_relative_eq!(clipped_step.forward, 0.02, epsilon = 0.0001);
+
+        let far_obstacle = Obstacle::robot(point![1.0, 0.05], 0.1, 0.1, Team::Uncertain);
+        let unclipped_step = clip_step_to_obstacles(step, Side::Left, [far_obstacle], 0.08);
+        assert_relative_eq!(unclipped_step.forward, 0.05, epsilon = 0.0001);
+    }
+}
@@ -3,17 +3,20 @@ use std::time::Duration;
 use anyhow::Result;
 use log::warn;
 use module_derive::module;
-use nalgebra::{geometry::Isometry3, Point3, Vector3};
+use nalgebra::{geometry::Isometry3, Point3, Vector2, Vector3};
 use serde::{Deserialize, Serialize};
 use serialize_hierarchy::SerializeHierarchy;
 use types::{
     ArmJoints, BodyJoints, BodyJointsCommand, InertialMeasurementUnitData, Joints, KickVariant,
-    LegJoints, MotionCommand, MotionSafeExits, MotionType, RobotKinematics, SensorData, Side, Step,
-    SupportFoot, WalkCommand,
+    LegJoints, MotionCommand, MotionSafeExits, MotionType, RobotKinematics, SensorData, Side,
+    SolePressure, Step, SupportFoot, WalkCommand,
 };
 
 use crate::{
-    control::{database::StepAdjustment, filtering::LowPassFilter},
+    control::{
+        database::{StepAdjustment, WalkStabilization},
+        filtering::LowPassFilter,
+    },
     framework::{configuration, AdditionalOutput},
     kinematics,
 };
@@ -24,6 +27,10 @@ use self::{
     engine::{calculate_foot_to_robot, parabolic_return, parabolic_step},
     foot_offsets::FootOffsets,
     kicking::apply_joint_overrides,
+    stabilization::{
+        capture_point_error, pendulum_frequency, predict_capture_point, robot_to_upright,
+        step_duration_reduction, step_placement_adjustment, zero_moment_point,
+    },
     walk_state::WalkState,
 };
 
@@ -32,6 +39,7 @@ mod balancing;
 mod engine;
 mod foot_offsets;
 mod kicking;
+mod stabilization;
 mod walk_state;
 
 /// # WalkingEngine
@@ -79,6 +87,8 @@ pub struct WalkingEngine {
     t_on_last_phase_end: Duration,
     /// The duration the currently executed step is planned to take
     step_duration: Duration,
+    /// The step duration before the stabilization shortened the step
+    planned_step_duration: Duration,
     /// Fix the side of the swing foot for an entire walk phase
     #[leaf]
     swing_side: Side,
@@ -88,6 +98,13 @@ pub struct WalkingEngine {
     #[leaf]
     /// Low pass filter the robot tilt for step adjustments
     filtered_robot_tilt_shift: LowPassFilter<f32>,
+    #[leaf]
+    /// Low pass filter the velocity of the center of mass relative to the support sole
+    filtered_center_of_mass_velocity: LowPassFilter<Vector2<f32>>,
+    /// center of mass relative to the support sole of the last cycle
+    last_center_of_mass_to_support_sole: Option<Vector2<f32>>,
+    /// adjustment of the current step placing the swing foot towards the capture point
+    stabilization_step_adjustment: Step,
     /// Foot offsets for the left foot the walking engine interpolation generated for the last cycle
     last_left_walk_request: FootOffsets,
     /// Foot offsets for the right foot the walking engine interpolation generated for the last cycle
@@ -116,6 +133,8 @@ pub struct WalkingEngine {
 #[input(path = walk_command, data_type = WalkCommand, required)]
 #[input(path = robot_kinematics, data_type = RobotKinematics, required)]
 #[input(path = motion_command, data_type = MotionCommand, required)]
+#[input(path = center_of_mass, data_type = Point3<f32>, required)]
+#[input(path = sole_pressure, data_type = SolePressure, required)]
 #[persistent_state(path = motion_safe_exits, data_type = MotionSafeExits)]
 #[persistent_state(path = walk_return_offset, data_type = Step)]
 #[parameter(path = control.walking_engine, data_type = configuration::WalkingEngine, name = config)]
@@ -123,6 +142,7 @@ pub struct WalkingEngine {
 #[parameter(path = control.ready_pose, data_type = Joints)]
 #[additional_output(path = walking_engine, data_type = WalkingEngine)]
 #[additional_output(path = step_adjustment, data_type = StepAdjustment)]
+#[additional_output(path = walk_stabilization, data_type = WalkStabilization)]
 #[main_output(name = walk_joints_command, data_type = BodyJointsCommand)]
 impl WalkingEngine {}
 
@@ -134,6 +154,13 @@ impl WalkingEngine {
                 0.0,
                 context.config.tilt_shift_low_pass_factor,
             ),
+            filtered_center_of_mass_velocity: LowPassFilter::with_alpha(
+                Vector2::zeros(),
+                context
+                    .config
+                    .stabilization
+                    .center_of_mass_velocity_low_pass_factor,
+            ),
             left_arm: SwingingArm::new(Side::Left),
             right_arm: SwingingArm::new(Side::Right),
             ..Default::default()
@@ -161,8 +188,19 @@ impl WalkingEngine {
                 context.config,
                 context.kick_steps,
             );
+            self.planned_step_duration = self.step_duration;
+            self.stabilization_step_adjustment = Step::zero();
         }
 
+        self.stabilize(
+            *context.center_of_mass,
+            context.robot_kinematics,
+            context.sensor_data,
+            context.sole_pressure,
+            context.config,
+            &mut context.walk_stabilization,
+        );
+
         match &self.walk_state {
             WalkState::Standing => self.reset(),
             WalkState::Starting(_) | WalkState::Walking(_) | WalkState::Stopping => {
@@ -327,6 +365,87 @@ impl WalkingEngine {
             .update(measured_robot_tilt_shift);
     }
 
+    fn stabilize(
+        &mut self,
+        center_of_mass: Point3<f32>,
+        robot_kinematics: &RobotKinematics,
+        sensor_data: &SensorData,
+        sole_pressure: &SolePressure,
+        config: &configuration::WalkingEngine,
+        walk_stabilization_output: &mut AdditionalOutput<WalkStabilization>,
+    ) {
+        let robot_to_upright = robot_to_upright(sensor_data.inertial_measurement_unit.roll_pitch);
+        let support_sole_to_robot = match self.swing_side.opposite() {
+            Side::Left => robot_kinematics.left_sole_to_robot,
+            Side::Right => robot_kinematics.right_sole_to_robot,
+        };
+        let support_sole = robot_to_upright * support_sole_to_robot * Point3::origin();
+        let center_of_mass = robot_to_upright * center_of_mass;
+        let center_of_mass_to_support_sole = center_of_mass.xy() - support_sole.xy();
+
+        let cycle_duration = sensor_data.cycle_info.last_cycle_duration.as_secs_f32();
+        if let Some(last_center_of_mass_to_support_sole) = self.last_center_of_mass_to_support_sole
+        {
+            if cycle_duration > 0.0 {
+                self.filtered_center_of_mass_velocity.update(
+                    (center_of_mass_to_support_sole - last_center_of_mass_to_support_sole)
+                        / cycle_duration,
+                );
+            }
+        }
+        self.last_center_of_mass_to_support_sole = Some(center_of_mass_to_support_sole);
+        let center_of_mass_velocity = self.filtered_center_of_mass_velocity.state();
+
+        let zero_moment_point = zero_moment_point(
+            robot_to_upright,
+            robot_kinematics,
+            &sensor_data.force_sensitive_resistors,
+            sole_pressure,
+            config.stabilization.minimum_sole_pressure,
+        );
+        let pendulum_frequency = pendulum_frequency(center_of_mass.z - support_sole.z);
+        let capture_point = center_of_mass.xy() + center_of_mass_velocity / pendulum_frequency;
+        let predicted_capture_point = predict_capture_point(
+            capture_point,
+            zero_moment_point.unwrap_or_else(|| center_of_mass.xy()),
+            pendulum_frequency,
+            self.step_duration.saturating_sub(self.t),
+        );
+
+        let is_stabilizing = config.stabilization.enable
+            && zero_moment_point.is_some()
+            && matches!(self.walk_state, WalkState::Walking(_));
+        let capture_point_error = if is_stabilizing {
+            capture_point_error(
+                predicted_capture_point - support_sole.xy(),
+                &config.stabilization,
+            )
+        } else {
+            Vector2::zeros()
+        };
+        self.stabilization_step_adjustment =
+            step_placement_adjustment(capture_point_error, self.swing_side, &config.stabilization);
+        let shortened_step_duration = self
+            .planned_step_duration
+            .saturating_sub(step_duration_reduction(
+                capture_point_error,
+                &config.stabilization,
+            ))
+            .max(config.minimal_step_duration);
+        self.step_duration = self.step_duration.min(shortened_step_duration);
+
+        walk_stabilization_output.fill_on_subscription(|| WalkStabilization {
+            center_of_mass: center_of_mass.xy(),
+            center_of_mass_velocity,
+            zero_moment_point,
+            capture_point,
+            predicted_capture_point,
+            capture_point_error,
+            step_adjustment: self.stabilization_step_adjustment,
+            step_duration: self.step_duration,
+        });
+    }
+
     fn initialize_step_states_from_request(
         &mut self,
         walk_command: WalkCommand,
@@ -443,9 +562,14 @@ impl WalkingEngine {
         self.t = Duration::ZERO;
         self.t_on_last_phase_end = Duration::ZERO;
         self.step_duration = Duration::ZERO;
+        self.planned_step_duration = Duration::ZERO;
         self.swing_side = Side::Left;
         self.filtered_gyro_y.reset(0.0);
         self.filtered_robot_tilt_shift.reset(0.0);
+        self.filtered_center_of_mass_velocity
+            .reset(Vector2::zeros());
+        self.last_center_of_mass_to_support_sole = None;
+        self.stabilization_step_adjustment = Step::zero();
         self.last_left_walk_request = FootOffsets::zero();
         self.last_right_walk_request = FootOffsets::zero();
         self.last_left_level_adjustment = 0.0;
@@ -536,6 +660,7 @@ impl WalkingEngine {
         self.t_on_last_phase_end = self.t;
         self.t = Duration::ZERO;
         self.max_foot_lift_last_step = self.max_swing_foot_lift;
+        self.last_center_of_mass_to_support_sole = None;
         self.last_left_walk_request = self.left_foot;
         self.last_right_walk_request = self.right_foot;
     }
@@ -553,7 +678,7 @@ impl WalkingEngine {
             next_turn,
             next_left_foot_lift,
             next_right_foot_lift,
-        ) = self.next_foot_offsets(self.current_step);
+        ) = self.next_foot_offsets(self.current_step + self.stabilization_step_adjustment);
         let (adjusted_left_foot, adjusted_right_foot) = step_adjustment(
             self.swing_side,
             self.filtered_robot_tilt_shift.state(),
@@ -0,0 +1,224 @@
+use std::time::Duration;
+
+use nalgebra::{vector, Isometry3, Point2, Point3, Vector2, Vector3};
+use types::{Foot, ForceSensitiveResistors, RobotKinematics, Side, SolePressure, Step};
+
+use crate::framework::configuration;
+
+const GRAVITATIONAL_ACCELERATION: f32 = 9.81;
+
+/// Positions of the force sensitive resistors in the left sole frame, ordered front left, front
+/// right, rear left, rear right (taken from the NAO documentation)
+const LEFT_FOOT_SENSOR_POSITIONS: [Vector2<f32>; 4] = [
+    vector![0.07025, 0.0299],
+    vector![0.07025, -0.0231],
+    vector![-0.03025, 0.0299],
+    vector![-0.02965, -0.0191],
+];
+
+/// Positions of the force sensitive resistors in the right sole frame, ordered front left, front
+/// right, rear left, rear right (taken from the NAO documentation)
+const RIGHT_FOOT_SENSOR_POSITIONS: [Vector2<f32>; 4] = [
+    vector![0.07025, 0.0231],
+    vector![0.07025, -0.0299],
+    vector![-0.03025, 0.0191],
+    vector![-0.02965, -0.0299],
+];
+
+/// Rotates vectors of the robot coordinate system into a frame that is aligned with the ground.
+pub fn robot_to_upright(roll_pitch: Vector2<f32>) -> Isometry3<f32> {
+    Isometry3::rotation(Vector3::y() * roll_pitch.y)
+        * Isometry3::rotation(Vector3::x() * roll_pitch.x)
+}
+
+fn center_of_pressure(foot: &Foot, sensor_positions: &[Vector2<f32>; 4]) -> Option<Point2<f32>> {
+    let total_pressure = foot.sum();
+    if total_pressure <= 0.0 {
+        return None;
+    }
+    let weighted_sum = sensor_positions[0] * foot.front_left
+        + sensor_positions[1] * foot.front_right
+        + sensor_positions[2] * foot.rear_left
+        + sensor_positions[3] * foot.rear_right;
+    Some(Point2::from(weighted_sum / total_pressure))
+}
+
+/// Estimates the zero moment point as the center of pressure of both feet, projected onto the
+/// ground in upright robot coordinates.
+pub fn zero_moment_point(
+    robot_to_upright: Isometry3<f32>,
+    robot_kinematics: &RobotKinematics,
+    force_sensitive_resistors: &ForceSensitiveResistors,
+    sole_pressure: &SolePressure,
+    minimum_sole_pressure: f32,
+) -> Option<Point2<f32>> {
+    if sole_pressure.total() < minimum_sole_pressure {
+        return None;
+    }
+    let left_center_of_pressure =
+        center_of_pressure(&force_sensitive_resistors.left, &LEFT_FOOT_SENSOR_POSITIONS).map(
+            |center| {
+                (robot_to_upright
+                    * robot_kinematics.left_sole_to_robot
+                    * Point3::new(center.x, center.y, 0.0))
+                .xy()
+            },
+        );
+    let right_center_of_pressure = center_of_pressure(
+        &force_sensitive_resistors.right,
+        &RIGHT_FOOT_SENSOR_POSITIONS,
+    )
+    .map(|center| {
+        (robot_to_upright
+            * robot_kinematics.right_sole_to_robot
+            * Point3::new(center.x, center.y, 0.0))
+        .xy()
+    });
+    match (left_center_of_pressure, right_center_of_pressure) {
+        (Some(left), Some(right)) => {
+            let left_weight = sole_pressure.left / sole_pressure.total();
+            Some(right + (left - right) * left_weight)
+        }
+        (Some(left), None) => Some(left),
+        (None, Some(right)) => Some(right),
+        (None, None) => None,
+    }
+}
+
+/// Natural frequency of the linear inverted pendulum with the given height.
+pub fn pendulum_frequency(center_of_mass_height: f32) -> f32 {
+    (GRAVITATIONAL_ACCELERATION / center_of_mass_height.max(f32::EPSILON)).sqrt()
+}
+
+/// Predicts where the capture point will be after `remaining_duration`. The capture point
+/// diverges exponentially away from the zero moment point.
+pub fn predict_capture_point(
+    capture_point: Point2<f32>,
+    zero_moment_point: Point2<f32>,
+    pendulum_frequency: f32,
+    remaining_duration: Duration,
+) -> Point2<f32> {
+    zero_moment_point
+        + (capture_point - zero_moment_point)
+            * (pendulum_frequency * remaining_duration.as_secs_f32()).exp()
+}
+
+/// Distance of the capture point (relative to the support sole) outside of the region nominal
+/// walking keeps it in.
+pub fn capture_point_error(
+    capture_point_to_support_sole: Vector2<f32>,
+    config: &configuration::WalkStabilization,
+) -> Vector2<f32> {
+    let excess = |value: f32, lower: f32, upper: f32| {
+        if value > upper {
+            value - upper
+        } else if value < lower {
+            value - lower
+        } else {
+            0.0
+        }
+    };
+    vector![
+        excess(
+            capture_point_to_support_sole.x,
+            config.capture_point_backward_limit,
+            config.capture_point_forward_limit,
+        ),
+        excess(
+            capture_point_to_support_sole.y,
+            -config.capture_point_sideways_limit,
+            config.capture_point_sideways_limit,
+        )
+    ]
+}
+
+/// Moves the swing foot towards the capture point. Sideways, the swing foot is only moved
+/// outwards since stepping inwards would collide with the support leg.
+pub fn step_placement_adjustment(
+    capture_point_error: Vector2<f32>,
+    swing_side: Side,
+    config: &configuration::WalkStabilization,
+) -> Step {
+    let forward = (config.step_placement_gain * capture_point_error.x).clamp(
+        -config.max_forward_step_adjustment,
+        config.max_forward_step_adjustment,
+    );
+    let sideways = config.step_placement_gain * capture_point_error.y;
+    let left = match swing_side {
+        Side::Left => sideways.clamp(0.0, config.max_sideways_step_adjustment),
+        Side::Right => sideways.clamp(-config.max_sideways_step_adjustment, 0.0),
+    };
+    Step {
+        forward,
+        left,
+        turn: 0.0,
+    }
+}
+
+/// Shortens the step proportionally to the capture point error to put the swing foot down earlier.
+pub fn step_duration_reduction(
+    capture_point_error: Vector2<f32>,
+    config: &configuration::WalkStabilization,
+) -> Duration {
+    let reduction = (config.step_duration_reduction_factor * capture_point_error.norm())
+        .clamp(0.0, config.max_step_duration_reduction.as_secs_f32());
+    Duration::from_secs_f32(reduction)
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+    use nalgebra::point;
+
+    use super::*;
+
+    fn config() -> configuration::WalkStabilization {
+        configuration::WalkStabilization {
+            capture_point_backward_limit: -0.03,
+            capture_point_forward_limit: 0.07,
+            capture_point_sideways_limit: 0.05,
+            max_forward_step_adjustment: 0.02,
+            max_sideways_step_adjustment: 0.02,
+            max_step_duration_reduction: Duration::from_millis(40),
+            step_duration_reduction_factor: 1.0,
+            step_placement_gain: 1.0,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn capture_point_inside_region_has_no_error() {
+        let error = capture_point_error(vector![0.02, -0.01], &config());
+        assert_relative_eq!(error, Vector2::zeros());
+    }
+
+    #[test]
+    fn capture_point_outside_region_has_error() {
+        let error = capture_point_error(vector![0.09, -0.06], &config());
+        assert_relative_eq!(error, vector![0.02, -0.01], epsilon = 1e-6);
+    }
+
+    #[test]
+    fn capture_point_diverges_from_zero_moment_point() {
+        let predicted = predict_capture_point(
+            point![0.01, 0.0],
+            point![0.0, 0.0],
+            pendulum_frequency(0.25),
+            Duration::from_millis(100),
+        );
+        assert!(predicted.x > 0.01);
+        assert_relative_eq!(predicted.y, 0.0);
+    }
+
+    #[test]
+    fn sideways_adjustment_only_steps_outwards() {
+        let inward_error = vector![0.0, -0.01];
+        let adjustment = step_placement_adjustment(inward_error, Side::Left, &config());
+        assert_relative_eq!(adjustment.left, 0.0);
+
+        let outward_error = vector![0.05, 0.03];
+        let adjustment = step_placement_adjustment(outward_error, Side::Left, &config());
+        assert_relative_eq!(adjustment.forward, 0.02);
+        assert_relative_eq!(adjustment.left, 0.02);
+    }
+}
@@ -1,10 +1,14 @@
 use std::time::{Duration, SystemTime};
 
-use itertools::{chain, iproduct};
+use itertools::chain;
 use module_derive::{module, require_some};
-use nalgebra::{distance, point, Isometry2, Matrix2, Point2};
+use nalgebra::{distance, Isometry2, Matrix2, Point2};
 use serde::{Deserialize, Serialize};
-use types::{DetectedRobots, FieldDimensions, Obstacle, ObstacleKind, SensorData, SonarObstacle};
+use spl_network::{Team, TeamColor};
+use types::{
+    DetectedRobots, FieldDimensions, GameControllerState, GoalPost, Obstacle, ObstacleKind,
+    SensorData, SonarObstacle,
+};
 
 use crate::control::filtering::KalmanFilter;
 
@@ -14,6 +18,7 @@ pub struct ObstacleFilterHypothesis {
     measurement_count: usize,
     last_update: SystemTime,
     obstacle_kind: ObstacleKind,
+    team: Team,
 }
 
 pub struct ObstacleFilter {
@@ -28,12 +33,15 @@ pub struct ObstacleFilter {
 #[parameter(path = control.obstacle_filter.unknown_obstacle_radius, data_type = f32)]
 #[parameter(path = control.obstacle_filter.goal_post_obstacle_radius, data_type = f32)]
 #[input(path = sensor_data, data_type = SensorData)]
+#[input(path = game_controller_state, data_type = GameControllerState)]
 #[historic_input(path = network_robot_obstacles, data_type = Vec<Point2<f32>>)]
 #[historic_input(path = sonar_obstacles, data_type = Vec<SonarObstacle>)]
 #[historic_input(path = robot_to_field, data_type = Isometry2<f32>)]
 #[historic_input(path = current_odometry_to_last_odometry, data_type = Isometry2<f32>)]
 #[perception_input(name = detected_robots_top, path = detected_robots, data_type = DetectedRobots, cycler = vision_top)]
 #[perception_input(name = detected_robots_bottom, path = detected_robots, data_type = DetectedRobots, cycler = vision_bottom)]
+#[perception_input(name = goal_posts_top, path = goal_posts, data_type = Vec<GoalPost>, cycler = vision_top)]
+#[perception_input(name = goal_posts_bottom, path = goal_posts, data_type = Vec<GoalPost>, cycler = vision_bottom)]
 #[additional_output(path = obstacle_filter_hypotheses, data_type = Vec<ObstacleFilterHypothesis>)]
 #[main_output(name = obstacles, data_type = Vec<Obstacle> )]
 impl ObstacleFilter {}
@@ -77,6 +85,7 @@ impl ObstacleFilter {
                 self.update_hypotheses_with_measurement(
                     *network_robot_obstacle,
                     ObstacleKind::Robot,
+                    Team::Hulks,
                     detection_time,
                     context
                         .obstacle_filter
@@ -93,11 +102,15 @@ impl ObstacleFilter {
                     .chain(robots_bottom.iter())
                     .filter_map(|data| data.as_ref());
 
-                for obstacles in measured_robots_in_control_cycle {
-                    for obstacle in obstacles.robot_positions.iter() {
+                for detected_robots in measured_robots_in_control_cycle {
+                    for robot in detected_robots.robots.iter() {
+                        let team = robot.jersey_color.map_or(Team::Uncertain, |jersey_color| {
+                            team_from_jersey_color(jersey_color, context.game_controller_state)
+                        });
                         self.update_hypotheses_with_measurement(
-                            obstacle.center,
+                            robot.position,
                             ObstacleKind::Robot,
+                            team,
                             detection_time,
                             context
                                 .obstacle_filter
@@ -110,6 +123,32 @@ impl ObstacleFilter {
                 }
             }
 
+            if context.obstacle_filter.use_goal_post_detection_measurements {
+                let measured_goal_posts = context
+                    .goal_posts_top
+                    .persistent
+                    .get(&detection_time)
+                    .into_iter()
+                    .chain(context.goal_posts_bottom.persistent.get(&detection_time))
+                    .flatten()
+                    .filter_map(|data| data.as_ref())
+                    .flatten();
+                for goal_post in measured_goal_posts {
+                    self.update_hypotheses_with_measurement(
+                        goal_post.position_in_robot,
+                        ObstacleKind::GoalPost,
+                        Team::Uncertain,
+                        detection_time,
+                        context
+                            .obstacle_filter
+                            .goal_post_measurement_matching_distance,
+                        Matrix2::from_diagonal(
+                            &context.obstacle_filter.goal_post_measurement_noise,
+                        ),
+                    );
+                }
+            }
+
             if let Some(sonar_obstacles) = context.sonar_obstacles.get(detection_time) {
                 for sonar_obstacle in sonar_obstacles.iter() {
                     // TODO: Use a clever more intelligent metric
@@ -125,6 +164,7 @@ impl ObstacleFilter {
                         self.update_hypotheses_with_measurement(
                             sonar_obstacle.position_in_robot,
                             ObstacleKind::Unknown,
+                            Team::Uncertain,
                             detection_time,
                             context.obstacle_filter.sonar_goal_post_matching_distance,
                             Matrix2::from_diagonal(
@@ -169,14 +209,28 @@ impl ObstacleFilter {
                     kind: hypothesis.obstacle_kind,
                     radius_at_hip_height,
                     radius_at_foot_height,
+                    team: hypothesis.team,
                 }
             })
             .collect::<Vec<_>>();
         let current_robot_to_field = context.robot_to_field.get(cycle_start_time);
         let goal_posts = calculate_goal_post_positions(current_robot_to_field, field_dimensions);
-        let goal_post_obstacles = goal_posts.into_iter().map(|goal_post| {
-            Obstacle::goal_post(goal_post, field_dimensions.goal_post_diameter / 2.0)
-        });
+        // goal posts which are tracked from detections replace the ones expected from localization
+        let goal_post_obstacles = goal_posts
+            .into_iter()
+            .filter(|goal_post| {
+                robot_obstacles.iter().all(|obstacle| {
+                    !matches!(obstacle.kind, ObstacleKind::GoalPost)
+                        || distance(&obstacle.position, goal_post)
+                            > context
+                                .obstacle_filter
+                                .goal_post_measurement_matching_distance
+                })
+            })
+            .map(|goal_post| {
+                Obstacle::goal_post(goal_post, field_dimensions.goal_post_diameter / 2.0)
+            })
+            .collect::<Vec<_>>();
         context
             .obstacle_filter_hypotheses
             .fill_on_subscription(|| self.hypotheses.clone());
@@ -209,6 +263,7 @@ impl ObstacleFilter {
         &mut self,
         detected_position: Point2<f32>,
         detected_obstacle_kind: ObstacleKind,
+        detected_team: Team,
         detection_time: SystemTime,
         matching_distance: f32,
         measurement_noise: Matrix2<f32>,
@@ -224,6 +279,7 @@ impl ObstacleFilter {
             self.spawn_hypothesis(
                 detected_position,
                 detected_obstacle_kind,
+                detected_team,
                 detection_time,
                 measurement_noise,
             );
@@ -236,10 +292,13 @@ impl ObstacleFilter {
                 measurement_noise * detected_position.coords.norm_squared(),
             );
             hypothesis.obstacle_kind = match hypothesis.obstacle_kind {
-                ObstacleKind::Robot => hypothesis.obstacle_kind,
+                ObstacleKind::Robot | ObstacleKind::GoalPost => hypothesis.obstacle_kind,
                 ObstacleKind::Unknown => detected_obstacle_kind,
                 _ => panic!("Unexpected obstacle kind"),
             };
+            if detected_team != Team::Uncertain {
+                hypothesis.team = detected_team;
+            }
             hypothesis.measurement_count += 1;
             hypothesis.last_update = detection_time;
         });
@@ -249,6 +308,7 @@ impl ObstacleFilter {
         &mut self,
         detected_position: Point2<f32>,
         obstacle_kind: ObstacleKind,
+        team: Team,
         detection_time: SystemTime,
         initial_covariance: Matrix2<f32>,
     ) {
@@ -256,6 +316,7 @@ impl ObstacleFilter {
         let new_hypothesis = ObstacleFilterHypothesis {
             filter: KalmanFilter::new(initial_state, initial_covariance),
             obstacle_kind,
+            team,
             measurement_count: 1,
             last_update: detection_time,
         };
@@ -290,10 +351,15 @@ impl ObstacleFilter {
                         hypothesis.filter.covariance(),
                     );
                     existing_hypothesis.obstacle_kind = match existing_hypothesis.obstacle_kind {
-                        ObstacleKind::Robot => existing_hypothesis.obstacle_kind,
+                        ObstacleKind::Robot | ObstacleKind::GoalPost => {
+                            existing_hypothesis.obstacle_kind
+                        }
                         ObstacleKind::Unknown => hypothesis.obstacle_kind,
                         _ => panic!("Unexpected obstacle kind"),
                     };
+                    if existing_hypothesis.team == Team::Uncertain {
+                        existing_hypothesis.team = hypothesis.team;
+                    }
                 }
                 None => deduplicated_hypotheses.push(hypothesis),
             }
@@ -302,6 +368,17 @@ impl ObstacleFilter {
     }
 }
 
+fn team_from_jersey_color(
+    jersey_color: TeamColor,
+    game_controller_state: &Option<GameControllerState>,
+) -> Team {
+    match game_controller_state {
+        Some(state) if state.hulks_team_color == jersey_color => Team::Hulks,
+        Some(state) if state.opponent_team_color == jersey_color => Team::Opponent,
+        _ => Team::Uncertain,
+    }
+}
+
 fn calculate_goal_post_positions(
     current_robot_to_field: &Option<Isometry2<f32>>,
     field_dimensions: &FieldDimensions,
@@ -309,17 +386,9 @@ fn calculate_goal_post_positions(
     current_robot_to_field
         .map(|robot_to_field| {
             let field_to_robot = robot_to_field.inverse();
-            iproduct!([-1.0, 1.0], [-1.0, 1.0]).map(move |(x_sign, y_sign)| {
-                let radius = field_dimensions.goal_post_diameter / 2.0;
-                let position_on_field = point![
-                    x_sign
-                        * (field_dimensions.length / 2.0
-                            + field_dimensions.goal_post_diameter / 2.0
-                            - field_dimensions.line_width / 2.0),
-                    y_sign * (field_dimensions.goal_inner_width / 2.0 + radius)
-                ];
-                field_to_robot * position_on_field
-            })
+            field_dimensions
+                .goal_post_positions()
+                .map(|position_on_field| field_to_robot * position_on_field)
         })
         .into_iter()
         .flatten()
@@ -6,31 +6,42 @@ use std::{
 use anyhow::Result;
 use log::debug;
 use module_derive::{module, require_some};
-use nalgebra::{Isometry2, Point2};
-use spl_network::{GameControllerReturnMessage, Penalty, PlayerNumber, SplMessage};
+use nalgebra::{Isometry2, Matrix3, Matrix4, Point2};
+use spl_network::{
+    GameControllerReturnMessage, Penalty, PlayerNumber, SplMessage, TeamCommunicationPayload,
+    MAXIMUM_NUMBER_OF_PAYLOAD_OBSTACLES,
+};
 use tokio::sync::{
     mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
     Mutex,
 };
 use types::{
-    BallPosition, FallState, FieldDimensions, GameControllerState, Players, PrimaryState, Role,
-    SensorData,
+    BallPosition, DetectedRobots, FallState, FieldDimensions, GameControllerState,
+    MessageBudgetUsage, MessageReason, Players, PrimaryState, Role, SensorData, TeammateState,
 };
 
-use crate::{framework::configuration::SplNetwork, spl_network::MessageReceivers};
+use crate::{
+    control::message_budget::MessageBudget, framework::configuration::SplNetwork,
+    spl_network::MessageReceivers,
+};
 
 pub struct RoleAssignment {
+    announced_pass_target: Option<PlayerNumber>,
     game_controller_return_message_receiver:
         Arc<Mutex<UnboundedReceiver<GameControllerReturnMessage>>>,
     game_controller_return_message_sender: UnboundedSender<GameControllerReturnMessage>,
     last_received_spl_striker_message: Option<SystemTime>,
     last_transmitted_game_controller_return_message: Option<SystemTime>,
     last_transmitted_spl_striker_message: Option<SystemTime>,
+    message_budget: MessageBudget,
     spl_message_receiver: Arc<Mutex<UnboundedReceiver<SplMessage>>>,
     spl_message_sender: UnboundedSender<SplMessage>,
     role: Role,
     role_initialized: bool,
     team_ball: Option<BallPosition>,
+    teammates: Vec<TeammateState>,
+    was_ball_seen: bool,
+    was_fallen: bool,
 }
 
 #[module(control)]
@@ -38,17 +49,26 @@ pub struct RoleAssignment {
 #[input(path = fall_state, data_type = FallState)]
 #[input(path = game_controller_state, data_type = GameControllerState)]
 #[input(path = robot_to_field, data_type = Isometry2<f32>)]
+#[input(path = robot_to_field_covariance, data_type = Matrix3<f32>)]
 #[input(path = sensor_data, data_type = SensorData)]
 #[input(path = primary_state, data_type = PrimaryState)]
+#[persistent_state(path = pass_target, data_type = Option<PlayerNumber>)]
 #[perception_input(path = spl_message, data_type = SplMessage, cycler = spl_network)]
+#[perception_input(name = detected_robots_top, path = detected_robots, data_type = DetectedRobots, cycler = vision_top)]
+#[perception_input(name = detected_robots_bottom, path = detected_robots, data_type = DetectedRobots, cycler = vision_bottom)]
 #[parameter(path = control.role_assignment.forced_role, data_type = Option<Role>)]
+#[parameter(path = control.role_assignment.teammate_timeout, data_type = Duration)]
+#[parameter(path = control.role_assignment.pass_receiver_claim_distance, data_type = f32)]
 #[parameter(path = field_dimensions, data_type = FieldDimensions)]
 #[parameter(path = player_number, data_type = PlayerNumber)]
+#[parameter(path = team_number, data_type = u8)]
 #[parameter(path = spl_network, data_type = SplNetwork)]
+#[additional_output(path = message_budget, data_type = MessageBudgetUsage)]
 #[main_output(data_type = BallPosition, name = team_ball)]
 #[main_output(data_type = MessageReceivers)]
 #[main_output(data_type = Vec<Point2<f32>>, name = network_robot_obstacles)]
 #[main_output(data_type = Role)]
+#[main_output(data_type = Vec<TeammateState>, name = teammates)]
 impl RoleAssignment {}
 
 impl RoleAssignment {
@@ -57,6 +77,7 @@ impl RoleAssignment {
             unbounded_channel();
         let (spl_message_sender, spl_message_receiver) = unbounded_channel();
         Ok(Self {
+            announced_pass_target: None,
             game_controller_return_message_receiver: Arc::new(Mutex::new(
                 game_controller_return_message_receiver,
             )),
@@ -64,15 +85,19 @@ impl RoleAssignment {
             last_received_spl_striker_message: None,
             last_transmitted_game_controller_return_message: None,
             last_transmitted_spl_striker_message: None,
+            message_budget: MessageBudget::default(),
             spl_message_receiver: Arc::new(Mutex::new(spl_message_receiver)),
             spl_message_sender,
             role: Role::default(),
             role_initialized: false,
             team_ball: None,
+            teammates: Vec::new(),
+            was_ball_seen: false,
+            was_fallen: false,
         })
     }
 
-    fn cycle(&mut self, context: CycleContext) -> Result<MainOutputs> {
+    fn cycle(&mut self, mut context: CycleContext) -> Result<MainOutputs> {
         let cycle_start_time = require_some!(context.sensor_data).cycle_info.start_time;
         let ball = context.ball_position;
         let fall_state = require_some!(context.fall_state);
@@ -104,17 +129,39 @@ impl RoleAssignment {
                     .unwrap(),
             )? > context.spl_network.game_controller_return_message_interval;
 
-        let mut send_spl_striker_message = self.last_transmitted_spl_striker_message.is_none()
-            || cycle_start_time
-                .duration_since(self.last_transmitted_spl_striker_message.unwrap())?
-                > context.spl_network.spl_striker_message_send_interval;
+        if let Some(game_controller_state) = context.game_controller_state {
+            self.message_budget.update(
+                cycle_start_time,
+                game_controller_state,
+                context.spl_network,
+            );
+        }
+        let is_fallen = matches!(fall_state, FallState::Fallen { .. });
+        let has_fallen = is_fallen && !self.was_fallen;
+        let has_found_ball = ball.is_some() && !self.was_ball_seen;
+
+        let pass_target = match role {
+            Role::Striker => *context.pass_target,
+            _ => None,
+        };
+        let has_new_pass_target =
+            pass_target.is_some() && pass_target != self.announced_pass_target;
+
+        let mut send_spl_striker_message = self
+            .message_budget
+            .is_periodic_message_due(cycle_start_time)
+            || (role == Role::Striker && (has_fallen || has_found_ball || has_new_pass_target));
 
-        let spl_striker_message_timeout = match self.last_received_spl_striker_message {
-            None => false,
-            Some(last_received_spl_striker_message) => {
+        let spl_striker_message_timeout = match (
+            self.last_received_spl_striker_message,
+            self.message_budget
+                .striker_message_timeout(context.spl_network),
+        ) {
+            (Some(last_received_spl_striker_message), Some(striker_message_timeout)) => {
                 cycle_start_time.duration_since(last_received_spl_striker_message)?
-                    > context.spl_network.spl_striker_message_receive_timeout
+                    > striker_message_timeout
             }
+            _ => false,
         };
 
         let silence_interval_has_passed = match self.last_transmitted_spl_striker_message {
@@ -129,8 +176,9 @@ impl RoleAssignment {
             self.last_transmitted_game_controller_return_message = Some(cycle_start_time);
             self.game_controller_return_message_sender
                 .send(GameControllerReturnMessage {
+                    team_number: *context.team_number,
                     player_number: *context.player_number,
-                    fallen: matches!(fall_state, FallState::Fallen { .. }),
+                    fallen: is_fallen,
                     robot_to_field,
                     ball_position: seen_ball_to_network_ball_position(ball, cycle_start_time),
                 })?;
@@ -187,6 +235,9 @@ impl RoleAssignment {
                     (robot_to_field.inverse() * spl_message.robot_to_field) * Point2::origin();
                 if spl_message.player_number != *context.player_number {
                     network_robot_obstacles.push(sender_position);
+                    update_teammates(&mut self.teammates, spl_message, cycle_start_time);
+                    self.message_budget
+                        .record_teammate_message(spl_message.player_number, cycle_start_time);
                 }
                 (role, send_spl_striker_message, team_ball) = process_role_state_machine(
                     role,
@@ -204,50 +255,107 @@ impl RoleAssignment {
             }
         }
 
+        self.teammates.retain(|teammate| {
+            cycle_start_time
+                .duration_since(teammate.last_message_time)
+                .map_or(true, |age| age < *context.teammate_timeout)
+        });
+        if claims_striker_as_pass_receiver(
+            role,
+            primary_state,
+            &self.teammates,
+            *context.player_number,
+            ball,
+            *context.pass_receiver_claim_distance,
+        ) {
+            role = Role::Striker;
+            send_spl_striker_message = true;
+            team_ball = team_ball_from_seen_ball(ball, &robot_to_field, cycle_start_time);
+        }
+
+        let message_reason = if context.forced_role.is_none() && role != self.role {
+            MessageReason::RoleChanged
+        } else if has_fallen {
+            MessageReason::Fallen
+        } else if has_found_ball {
+            MessageReason::BallFoundAgain
+        } else if has_new_pass_target {
+            MessageReason::PassAnnounced
+        } else {
+            MessageReason::Periodic
+        };
         if send_spl_striker_message
             && primary_state == PrimaryState::Playing
             && silence_interval_has_passed
+            && context.game_controller_state.is_some()
+            && self
+                .message_budget
+                .try_spend(cycle_start_time, message_reason, context.spl_network)
         {
             self.last_transmitted_spl_striker_message = Some(cycle_start_time);
             self.last_received_spl_striker_message = Some(cycle_start_time);
-            if let Some(game_controller_state) = *context.game_controller_state {
-                if game_controller_state.remaining_amount_of_messages
-                    > context
-                        .spl_network
-                        .remaining_amount_of_messages_to_stop_sending
-                {
-                    if ball.is_none() && team_ball.is_some() {
-                        self.spl_message_sender.send(SplMessage {
-                            player_number: *context.player_number,
-                            fallen: matches!(fall_state, FallState::Fallen { .. }),
-                            robot_to_field,
-                            ball_position: team_ball_to_network_ball_position(
-                                &team_ball,
-                                &robot_to_field,
-                                cycle_start_time,
-                            ),
-                        })?;
-                    } else {
-                        self.spl_message_sender.send(SplMessage {
-                            player_number: *context.player_number,
-                            fallen: matches!(fall_state, FallState::Fallen { .. }),
-                            robot_to_field,
-                            ball_position: seen_ball_to_network_ball_position(
-                                ball,
-                                cycle_start_time,
-                            ),
-                        })?;
-                    }
-                }
+            self.announced_pass_target = pass_target;
+            let mut payload = TeamCommunicationPayload {
+                role: context.forced_role.unwrap_or(role),
+                pass_target,
+                robot_to_field_covariance: context
+                    .robot_to_field_covariance
+                    .unwrap_or_else(Matrix3::zeros),
+                obstacles: closest_detected_robots(
+                    context
+                        .detected_robots_top
+                        .persistent
+                        .values()
+                        .last()
+                        .into_iter()
+                        .chain(context.detected_robots_bottom.persistent.values().last())
+                        .flatten(),
+                ),
+                ..Default::default()
+            };
+            if ball.is_none() && team_ball.is_some() {
+                payload.ball_velocity = team_ball
+                    .map(|team_ball| robot_to_field.inverse() * team_ball.velocity)
+                    .unwrap_or_default();
+                self.spl_message_sender.send(SplMessage {
+                    team_number: *context.team_number,
+                    player_number: *context.player_number,
+                    fallen: is_fallen,
+                    robot_to_field,
+                    ball_position: team_ball_to_network_ball_position(
+                        &team_ball,
+                        &robot_to_field,
+                        cycle_start_time,
+                    ),
+                    payload: Some(payload),
+                })?;
+            } else {
+                payload.ball_velocity = ball.map(|ball| ball.velocity).unwrap_or_default();
+                self.spl_message_sender.send(SplMessage {
+                    team_number: *context.team_number,
+                    player_number: *context.player_number,
+                    fallen: is_fallen,
+                    robot_to_field,
+                    ball_position: seen_ball_to_network_ball_position(ball, cycle_start_time),
+                    payload: Some(payload),
+                })?;
             }
         }
 
+        if pass_target.is_none() {
+            self.announced_pass_target = None;
+        }
         if let Some(forced_role) = context.forced_role {
             self.role = *forced_role;
         } else {
             self.role = role;
         }
         self.team_ball = team_ball;
+        self.was_ball_seen = ball.is_some();
+        self.was_fallen = is_fallen;
+        context
+            .message_budget
+            .fill_on_subscription(|| self.message_budget.usage().clone());
 
         Ok(MainOutputs {
             role: Some(self.role),
@@ -259,6 +367,7 @@ impl RoleAssignment {
                 spl_message_receiver: self.spl_message_receiver.clone(),
             }),
             network_robot_obstacles: Some(network_robot_obstacles),
+            teammates: Some(self.teammates.clone()),
         })
     }
 }
@@ -605,6 +714,57 @@ fn decide_if_claiming_striker_or_other_role(
     }
 }
 
+/// A robot a teammate passes to becomes striker as soon as the ball gets close
+fn claims_striker_as_pass_receiver(
+    role: Role,
+    primary_state: PrimaryState,
+    teammates: &[TeammateState],
+    player_number: PlayerNumber,
+    ball: &Option<BallPosition>,
+    pass_receiver_claim_distance: f32,
+) -> bool {
+    let is_pass_receiver = teammates
+        .iter()
+        .any(|teammate| teammate.pass_target == Some(player_number));
+    let is_ball_arriving = matches!(
+        ball,
+        Some(ball) if ball.position.coords.norm() < pass_receiver_claim_distance
+    );
+    is_pass_receiver
+        && is_ball_arriving
+        && primary_state == PrimaryState::Playing
+        && !matches!(role, Role::Striker | Role::Keeper | Role::ReplacementKeeper)
+}
+
+fn update_teammates(
+    teammates: &mut Vec<TeammateState>,
+    spl_message: &SplMessage,
+    cycle_start_time: SystemTime,
+) {
+    let teammate = TeammateState {
+        player_number: spl_message.player_number,
+        robot_to_field: spl_message.robot_to_field,
+        role: spl_message
+            .payload
+            .as_ref()
+            .map(|payload| payload.role)
+            .unwrap_or_default(),
+        is_fallen: spl_message.fallen,
+        pass_target: spl_message
+            .payload
+            .as_ref()
+            .and_then(|payload| payload.pass_target),
+        last_message_time: cycle_start_time,
+    };
+    match teammates
+        .iter_mut()
+        .find(|known_teammate| known_teammate.player_number == teammate.player_number)
+    {
+        Some(known_teammate) => *known_teammate = teammate,
+        None => teammates.push(teammate),
+    }
+}
+
 fn seen_ball_to_network_ball_position(
     ball: &Option<BallPosition>,
     cycle_start_time: SystemTime,
@@ -628,6 +788,20 @@ fn team_ball_to_network_ball_position(
     })
 }
 
+/// Positions of the detected robots in robot coordinates, closest first
+fn closest_detected_robots<'a>(
+    detected_robots: impl Iterator<Item = &'a &'a Option<DetectedRobots>>,
+) -> Vec<Point2<f32>> {
+    let mut positions: Vec<_> = detected_robots
+        .copied()
+        .flatten()
+        .flat_map(|detected_robots| detected_robots.robots.iter().map(|robot| robot.position))
+        .collect();
+    positions.sort_by(|left, right| left.coords.norm().total_cmp(&right.coords.norm()));
+    positions.truncate(MAXIMUM_NUMBER_OF_PAYLOAD_OBSTACLES);
+    positions
+}
+
 fn team_ball_from_spl_message(
     cycle_start_time: SystemTime,
     spl_message: &SplMessage,
@@ -637,7 +811,13 @@ fn team_ball_from_spl_message(
         .as_ref()
         .map(|ball_position| BallPosition {
             position: spl_message.robot_to_field * ball_position.relative_position,
+            velocity: spl_message
+                .payload
+                .as_ref()
+                .map(|payload| spl_message.robot_to_field * payload.ball_velocity)
+                .unwrap_or_default(),
             last_seen: cycle_start_time - ball_position.age,
+            ..Default::default()
         })
 }
 
@@ -646,9 +826,21 @@ fn team_ball_from_seen_ball(
     current_pose: &Isometry2<f32>,
     cycle_start_time: SystemTime,
 ) -> Option<BallPosition> {
-    ball.as_ref().map(|ball| BallPosition {
-        position: (current_pose * ball.position),
-        last_seen: cycle_start_time,
+    ball.as_ref().map(|ball| {
+        let rotation = current_pose.rotation.to_rotation_matrix();
+        let mut state_rotation = Matrix4::identity();
+        state_rotation
+            .fixed_slice_mut::<2, 2>(0, 0)
+            .copy_from(rotation.matrix());
+        state_rotation
+            .fixed_slice_mut::<2, 2>(2, 2)
+            .copy_from(rotation.matrix());
+        BallPosition {
+            position: (current_pose * ball.position),
+            velocity: current_pose * ball.velocity,
+            covariance: state_rotation * ball.covariance * state_rotation.transpose(),
+            last_seen: cycle_start_time,
+        }
     })
 }
 
@@ -865,3 +1057,95 @@ fn print_case(
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use approx::assert_relative_eq;
+    use nalgebra::{point, vector};
+
+    use super::*;
+
+    #[test]
+    fn seen_ball_is_rotated_into_field_with_its_covariance() {
+        let ball = BallPosition {
+            position: point![1.0, 0.0],
+            velocity: vector![0.5, 0.0],
+            covariance: Matrix4::from_diagonal(&vector![0.1, 0.2, 0.3, 0.4]),
+            last_seen: SystemTime::UNIX_EPOCH,
+        };
+        let robot_to_field = Isometry2::new(vector![1.0, 2.0], FRAC_PI_2);
+
+        let team_ball =
+            team_ball_from_seen_ball(&Some(ball), &robot_to_field, SystemTime::UNIX_EPOCH).unwrap();
+
+        assert_relative_eq!(team_ball.position, point![1.0, 3.0], epsilon = 1e-6);
+        assert_relative_eq!(team_ball.velocity, vector![0.0, 0.5], epsilon = 1e-6);
+        assert_relative_eq!(
+            team_ball.covariance,
+            Matrix4::from_diagonal(&vector![0.2, 0.1, 0.4, 0.3]),
+            epsilon = 1e-6
+        );
+    }
+
+    #[test]
+    fn pass_receiver_claims_striker_when_the_ball_arrives() {
+        let passer = TeammateState {
+            player_number: PlayerNumber::Five,
+            robot_to_field: Isometry2::identity(),
+            role: Role::Striker,
+            is_fallen: false,
+            pass_target: Some(PlayerNumber::Three),
+            last_message_time: SystemTime::UNIX_EPOCH,
+        };
+        let ball_at = |x: f32| {
+            Some(BallPosition {
+                position: point![x, 0.0],
+                velocity: vector![0.0, 0.0],
+                covariance: Matrix4::identity(),
+                last_seen: SystemTime::UNIX_EPOCH,
+            })
+        };
+        let claims = |role, primary_state, ball: &Option<BallPosition>| {
+            claims_striker_as_pass_receiver(
+                role,
+                primary_state,
+                &[passer],
+                PlayerNumber::Three,
+                ball,
+                1.0,
+            )
+        };
+
+        assert!(claims(
+            Role::StrikerSupporter,
+            PrimaryState::Playing,
+            &ball_at(0.5)
+        ));
+        assert!(!claims(
+            Role::StrikerSupporter,
+            PrimaryState::Playing,
+            &ball_at(2.0)
+        ));
+        assert!(!claims(
+            Role::StrikerSupporter,
+            PrimaryState::Playing,
+            &None
+        ));
+        assert!(!claims(
+            Role::StrikerSupporter,
+            PrimaryState::Set,
+            &ball_at(0.5)
+        ));
+        assert!(!claims(Role::Keeper, PrimaryState::Playing, &ball_at(0.5)));
+        assert!(!claims_striker_as_pass_receiver(
+            Role::StrikerSupporter,
+            PrimaryState::Playing,
+            &[passer],
+            PlayerNumber::Four,
+            &ball_at(0.5),
+            1.0,
+        ));
+    }
+}
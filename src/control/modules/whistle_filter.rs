@@ -2,19 +2,25 @@ use std::{collections::VecDeque, time::SystemTime};
 
 use anyhow::Result;
 use module_derive::{module, require_some};
-use types::{FilteredWhistle, SensorData, Whistle};
+use nalgebra::{vector, Isometry2, Point2, Vector2};
+use types::{FieldDimensions, FilteredWhistle, SensorData, Whistle};
 
 pub struct WhistleFilter {
     pub detection_buffer: VecDeque<bool>,
     pub was_detected_last_cycle: bool,
     pub last_detection: Option<SystemTime>,
+    pub bearing_hypotheses: Vec<BearingHypothesis>,
 }
 
 #[module(control)]
 #[input(path = sensor_data, data_type = SensorData)]
 #[perception_input(path = detected_whistle, data_type = Whistle, cycler = audio)]
+#[persistent_state(path = robot_to_field, data_type = Isometry2<f32>)]
 #[parameter(path = control.whistle_filter.buffer_length, data_type = usize)]
 #[parameter(path = control.whistle_filter.minimum_detections, data_type = usize)]
+#[parameter(path = control.whistle_filter.bearing_merge_angle, data_type = f32)]
+#[parameter(path = control.whistle_filter.neighboring_field_offsets, data_type = Vec<Vector2<f32>>)]
+#[parameter(path = field_dimensions, data_type = FieldDimensions)]
 #[main_output(data_type = FilteredWhistle)]
 impl WhistleFilter {}
 
@@ -24,23 +30,28 @@ impl WhistleFilter {
             detection_buffer: Default::default(),
             was_detected_last_cycle: false,
             last_detection: None,
+            bearing_hypotheses: Vec::new(),
         })
     }
 
     fn cycle(&mut self, context: CycleContext) -> Result<MainOutputs> {
-        let cycle_start_time = require_some!(context.sensor_data).cycle_info.start_time;
+        let sensor_data = require_some!(context.sensor_data);
+        let cycle_start_time = sensor_data.cycle_info.start_time;
+        let head_yaw = sensor_data.positions.head.yaw;
 
-        for is_detected in context
+        for whistle in context
             .detected_whistle
             .persistent
             .values()
             .flatten()
-            .filter_map(|&detected_whistle| {
-                detected_whistle.as_ref().map(|whistle| whistle.is_detected)
-            })
-            .flatten()
+            .filter_map(|detected_whistle| detected_whistle.as_ref())
         {
-            self.detection_buffer.push_front(is_detected);
+            for &is_detected in whistle.is_detected.iter() {
+                self.detection_buffer.push_front(is_detected);
+            }
+            if let Some(bearing) = whistle.bearing {
+                self.add_bearing(head_yaw + bearing, *context.bearing_merge_angle);
+            }
         }
         self.detection_buffer.truncate(*context.buffer_length);
         let number_of_detections = self
@@ -48,7 +59,25 @@ impl WhistleFilter {
             .iter()
             .filter(|&&was_detected| was_detected)
             .count();
-        let is_detected = number_of_detections > *context.minimum_detections;
+        if number_of_detections == 0 {
+            self.bearing_hypotheses.clear();
+        }
+
+        let bearing = self
+            .bearing_hypotheses
+            .iter()
+            .max_by_key(|hypothesis| hypothesis.number_of_measurements)
+            .map(BearingHypothesis::bearing);
+        let is_from_neighboring_field = bearing.is_some_and(|bearing| {
+            points_at_neighboring_field(
+                context.robot_to_field,
+                bearing,
+                context.field_dimensions,
+                context.neighboring_field_offsets,
+            )
+        });
+        let is_detected =
+            number_of_detections > *context.minimum_detections && !is_from_neighboring_field;
         let started_this_cycle = is_detected && !self.was_detected_last_cycle;
         if started_this_cycle {
             self.last_detection = Some(cycle_start_time);
@@ -60,7 +89,138 @@ impl WhistleFilter {
                 is_detected,
                 last_detection: self.last_detection,
                 started_this_cycle,
+                bearing,
             }),
         })
     }
+
+    fn add_bearing(&mut self, bearing: f32, merge_angle: f32) {
+        let direction = vector![bearing.cos(), bearing.sin()];
+        match self
+            .bearing_hypotheses
+            .iter_mut()
+            .find(|hypothesis| hypothesis.direction_sum.angle(&direction) < merge_angle)
+        {
+            Some(hypothesis) => {
+                hypothesis.direction_sum += direction;
+                hypothesis.number_of_measurements += 1;
+            }
+            None => self.bearing_hypotheses.push(BearingHypothesis {
+                direction_sum: direction,
+                number_of_measurements: 1,
+            }),
+        }
+    }
+}
+
+/// Bearings of the same whistle measured in consecutive audio cycles
+#[derive(Clone, Debug)]
+pub struct BearingHypothesis {
+    direction_sum: Vector2<f32>,
+    number_of_measurements: usize,
+}
+
+impl BearingHypothesis {
+    fn bearing(&self) -> f32 {
+        self.direction_sum.y.atan2(self.direction_sum.x)
+    }
+}
+
+fn points_at_neighboring_field(
+    robot_to_field: &Isometry2<f32>,
+    bearing: f32,
+    field_dimensions: &FieldDimensions,
+    neighboring_field_offsets: &[Vector2<f32>],
+) -> bool {
+    let origin = Point2::from(robot_to_field.translation.vector);
+    let direction = robot_to_field.rotation * vector![bearing.cos(), bearing.sin()];
+    let half_extents = vector![field_dimensions.length / 2.0, field_dimensions.width / 2.0];
+    neighboring_field_offsets.iter().any(|offset| {
+        ray_intersects_rectangle(
+            origin,
+            direction,
+            Point2::from(offset - half_extents),
+            Point2::from(offset + half_extents),
+        )
+    })
+}
+
+fn ray_intersects_rectangle(
+    origin: Point2<f32>,
+    direction: Vector2<f32>,
+    minimum: Point2<f32>,
+    maximum: Point2<f32>,
+) -> bool {
+    let mut entry: f32 = 0.0;
+    let mut exit = f32::INFINITY;
+    for axis in 0..2 {
+        if direction[axis].abs() < f32::EPSILON {
+            if origin[axis] < minimum[axis] || origin[axis] > maximum[axis] {
+                return false;
+            }
+            continue;
+        }
+        let first = (minimum[axis] - origin[axis]) / direction[axis];
+        let second = (maximum[axis] - origin[axis]) / direction[axis];
+        entry = entry.max(first.min(second));
+        exit = exit.min(first.max(second));
+    }
+    entry <= exit
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use nalgebra::point;
+
+    use super::*;
+
+    #[test]
+    fn rays_hit_rectangles_only_in_front_of_them() {
+        let minimum = point![-1.0, 5.0];
+        let maximum = point![1.0, 7.0];
+
+        assert!(ray_intersects_rectangle(
+            Point2::origin(),
+            vector![0.0, 1.0],
+            minimum,
+            maximum
+        ));
+        assert!(ray_intersects_rectangle(
+            Point2::origin(),
+            vector![0.1, 1.0],
+            minimum,
+            maximum
+        ));
+        assert!(!ray_intersects_rectangle(
+            Point2::origin(),
+            vector![0.0, -1.0],
+            minimum,
+            maximum
+        ));
+        assert!(!ray_intersects_rectangle(
+            Point2::origin(),
+            vector![1.0, 0.0],
+            minimum,
+            maximum
+        ));
+    }
+
+    #[test]
+    fn close_bearings_are_merged_into_one_hypothesis() {
+        let mut filter = WhistleFilter {
+            detection_buffer: Default::default(),
+            was_detected_last_cycle: false,
+            last_detection: None,
+            bearing_hypotheses: Vec::new(),
+        };
+        filter.add_bearing(FRAC_PI_2, 0.35);
+        filter.add_bearing(FRAC_PI_2 + 0.1, 0.35);
+        filter.add_bearing(-FRAC_PI_2, 0.35);
+
+        assert_eq!(filter.bearing_hypotheses.len(), 2);
+        assert_eq!(filter.bearing_hypotheses[0].number_of_measurements, 2);
+        assert!((filter.bearing_hypotheses[0].bearing() - (FRAC_PI_2 + 0.05)).abs() < 1e-3);
+    }
 }
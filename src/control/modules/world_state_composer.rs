@@ -1,12 +1,12 @@
 use module_derive::module;
 
 use anyhow::Result;
-use nalgebra::{Isometry2, Point2};
+use nalgebra::{Isometry2, Point2, Vector2};
 
 use spl_network::{GamePhase, PlayerNumber};
 use types::{
     BallPosition, BallState, FallState, FilteredGameState, GameControllerState, Obstacle,
-    PenaltyShotDirection, PrimaryState, RobotState, Role, Side, WorldState,
+    PenaltyShotDirection, PrimaryState, RobotState, Role, Side, TeammateState, WorldState,
 };
 
 use crate::control::filtering::greater_than_with_hysteresis;
@@ -27,6 +27,7 @@ pub struct WorldStateComposer {
 #[input(path = robot_to_field, data_type = Isometry2<f32>)]
 #[input(path = role, data_type = Role, required)]
 #[input(path = team_ball, data_type = BallPosition)]
+#[input(path = teammates, data_type = Vec<TeammateState>)]
 #[parameter(path = player_number, data_type = PlayerNumber)]
 #[main_output(data_type = WorldState)]
 impl WorldStateComposer {}
@@ -59,12 +60,14 @@ impl WorldStateComposer {
             (PrimaryState::Ready, ..) => None,
             (_, Some(ball_position), _, robot_to_field) => Some(create_ball_state(
                 ball_position.position,
+                ball_position.velocity,
                 *robot_to_field,
                 &mut self.last_ball_field_side,
                 *context.penalty_shot_direction,
             )),
             (_, None, Some(ball_position), Some(robot_to_field)) => Some(create_ball_state(
                 robot_to_field.inverse() * ball_position.position,
+                robot_to_field.inverse() * ball_position.velocity,
                 Some(*robot_to_field),
                 &mut self.last_ball_field_side,
                 *context.penalty_shot_direction,
@@ -88,6 +91,7 @@ impl WorldStateComposer {
             obstacles: obstacles.clone(),
             robot,
             game_controller_state,
+            teammates: context.teammates.clone().unwrap_or_default(),
         };
 
         Ok(MainOutputs {
@@ -98,6 +102,7 @@ impl WorldStateComposer {
 
 fn create_ball_state(
     position: Point2<f32>,
+    velocity: Vector2<f32>,
     robot_to_field: Option<Isometry2<f32>>,
     last_ball_field_side: &mut Side,
     penalty_shot_direction: Option<PenaltyShotDirection>,
@@ -120,6 +125,7 @@ fn create_ball_state(
     };
     BallState {
         position,
+        velocity,
         field_side,
         penalty_shot_direction,
     }
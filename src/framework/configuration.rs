@@ -7,8 +7,8 @@ use serialize_hierarchy::SerializeHierarchy;
 use spl_network::PlayerNumber;
 
 use types::{
-    ArmJoints, FieldDimensions, HeadJoints, InitialPose, Joints, KickStep, KickVariant,
-    MotionCommand, Players, Role, Step,
+    ArmJoints, FallDirection, FieldDimensions, HeadJoints, InitialPose, Joints, KickStep,
+    KickVariant, MotionCommand, Players, Role, Step,
 };
 
 #[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
@@ -17,9 +17,15 @@ pub struct Configuration {
     pub control: Control,
     pub disable_communication_acceptor: bool,
     pub field_dimensions: FieldDimensions,
+    /// Directory for files written while running, relative paths are relative to the installation
+    /// directory, e.g. `/home/nao/hulk`
+    pub log_directory: PathBuf,
     #[leaf]
     pub player_number: PlayerNumber,
     pub spl_network: SplNetwork,
+    /// Team number used in all GameController and SPL messages and for the SPL message port, the
+    /// SPL network reads it once at startup, so changes require a restart
+    pub team_number: u8,
     pub vision_top: Vision,
     pub vision_bottom: Vision,
 }
@@ -27,6 +33,22 @@ pub struct Configuration {
 #[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
 pub struct Audio {
     pub whistle_detection: WhistleDetection,
+    pub event_detectors: Vec<AudioEventDetector>,
+    /// Microphone buffers are appended to this WAV file if set
+    #[leaf]
+    pub recording_path: Option<PathBuf>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
+pub struct AudioEventDetector {
+    pub name: String,
+    pub frequency_band: Range<f32>,
+    /// Time the band has to stand out of the noise floor before the event becomes active
+    pub minimum_duration: Duration,
+    /// Threshold for the ratio of the mean band magnitude to the median magnitude of the spectrum
+    /// in dB
+    pub minimum_signal_to_noise_ratio: f32,
+    pub minimum_number_of_channels: usize,
 }
 
 #[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
@@ -35,10 +57,15 @@ pub struct WhistleDetection {
     pub background_noise_scaling: f32,
     pub whistle_scaling: f32,
     pub number_of_chunks: usize,
+    /// Microphone positions projected to the ground plane in head coordinates, in the order of
+    /// the recorded channels
+    pub microphone_positions: Vec<Point2<f32>>,
+    pub speed_of_sound: f32,
 }
 
 #[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
 pub struct Control {
+    pub active_vision: ActiveVision,
     pub ball_filter: BallFilter,
     pub button_filter: ButtonFilter,
     pub behavior: Behavior,
@@ -46,6 +73,7 @@ pub struct Control {
     pub dispatching_head_interpolator: DispatchingHeadInterpolator,
     pub fall_protection: FallProtection,
     pub fall_state_estimation: FallStateEstimation,
+    pub fall_statistics_recorder: FallStatisticsRecorder,
     pub game_state_filter: GameStateFilter,
     pub ground_contact_detector: HighDetector,
     pub head_motion: HeadMotion,
@@ -73,6 +101,8 @@ pub struct Control {
 #[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
 pub struct Localization {
     pub circle_measurement_noise: Vector2<f32>,
+    pub goal_post_matching_distance: f32,
+    pub goal_post_measurement_noise: Vector2<f32>,
     pub gradient_convergence_threshold: f32,
     pub gradient_descent_step_size: f32,
     pub hypothesis_prediction_score_reduction_factor: f32,
@@ -80,13 +110,21 @@ pub struct Localization {
     pub initial_hypothesis_covariance: Matrix3<f32>,
     pub initial_hypothesis_score: f32,
     pub initial_poses: Players<InitialPose>,
+    pub line_intersection_direction_tolerance: f32,
+    pub line_intersection_matching_distance: f32,
+    pub line_intersection_measurement_noise: Vector2<f32>,
     pub line_length_acceptance_factor: f32,
     pub line_measurement_noise: Vector2<f32>,
     pub maximum_amount_of_gradient_descent_iterations: usize,
     pub maximum_amount_of_outer_iterations: usize,
     pub minimum_fit_error: f32,
     pub odometry_noise: Vector3<f32>,
+    pub penalty_mark_matching_distance: f32,
+    pub penalty_mark_measurement_noise: Vector2<f32>,
+    pub use_goal_post_measurements: bool,
+    pub use_line_intersection_measurements: bool,
     pub use_line_measurements: bool,
+    pub use_penalty_mark_measurements: bool,
     pub good_matching_threshold: f32,
     pub score_per_good_match: f32,
     pub hypothesis_score_base_increase: f32,
@@ -100,6 +138,8 @@ pub struct StepPlanner {
     pub translation_exponent: f32,
     pub rotation_exponent: f32,
     pub inside_turn_ratio: f32,
+    pub planning_horizon: usize,
+    pub foot_collision_radius: f32,
 }
 
 #[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
@@ -118,6 +158,10 @@ pub struct HighDetector {
 pub struct RoleAssignment {
     #[leaf]
     pub forced_role: Option<Role>,
+    /// Teammates are forgotten if no message was received from them for this duration
+    pub teammate_timeout: Duration,
+    /// The receiver of an announced pass claims the striker role once the ball is this close
+    pub pass_receiver_claim_distance: f32,
 }
 
 #[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
@@ -126,8 +170,13 @@ pub struct Behavior {
     pub dribbling: Dribbling,
     #[leaf]
     pub injected_motion_command: Option<MotionCommand>,
+    pub intercept_ball: InterceptBall,
     pub lost_ball: LostBall,
+    pub passing: Passing,
+    /// Replaces `passing` during own set plays to allow short and backward passes
+    pub set_play_passing: Passing,
     pub path_planning: PathPlanning,
+    pub positioning: Positioning,
     pub role_positions: RolePositions,
     pub walk_and_stand: WalkAndStand,
     pub search: Search,
@@ -138,18 +187,75 @@ pub struct Behavior {
 pub struct LookAction {
     pub angle_threshold: f32,
     pub distance_threshold: f32,
+    pub use_active_vision: bool,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
+pub struct InterceptBall {
+    /// Deceleration of a rolling ball due to friction with the carpet in m/s²
+    pub rolling_deceleration: f32,
+    /// Slower balls are considered lying still to ignore velocity noise of the ball filter
+    pub minimum_ball_speed: f32,
+    pub maximum_time_to_interception: Duration,
+    /// Distance along the defense line the defenders walk to intercept a ball
+    pub defender_interception_range: f32,
+    /// Lateral distance the keeper covers with a jump
+    pub keeper_jump_reach: f32,
+    /// The keeper jumps when the ball crosses its position earlier than this
+    pub keeper_jump_time: Duration,
+    /// Balls crossing closer to the keeper are blocked in the squat instead of jumping
+    pub keeper_minimum_jump_offset: f32,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
+pub struct Passing {
+    pub enabled: bool,
+    pub minimum_pass_distance: f32,
+    /// Distance the forward in-walk kick reliably moves the ball
+    pub maximum_pass_distance: f32,
+    /// Receivers have to be at least this much closer to the opponent goal than the ball
+    pub minimum_forward_gain: f32,
+    /// Minimum distance of obstacles to the line from the ball to the receiver
+    pub lane_clearance: f32,
+    /// Minimum distance of obstacles to the receiver
+    pub receiver_clearance: f32,
+    /// A pass announcement is ignored by the receiver after this duration
+    pub pass_announcement_timeout: Duration,
+    /// Weight of the obstacle clearance of the lane relative to the forward gain when ranking
+    /// receivers
+    pub clearance_weight: f32,
+    /// The striker does not pass while a free target in the opponent goal is at most this far from
+    /// the ball
+    pub maximum_shot_distance: f32,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
+pub struct Positioning {
+    pub tactic: Tactic,
+    /// Duration of the blend from the positions of the previous formation to the new one
+    pub formation_transition_duration: Duration,
+    /// Formation positions are pushed out of this radius around the ball while it is not free
+    pub minimum_distance_to_non_free_ball: f32,
+}
+
+/// Names of the formations used in each situation, loaded from
+/// `etc/configuration/formation.<name>.json`
+#[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
+pub struct Tactic {
+    pub own_kick_off: String,
+    pub opponent_kick_off: String,
+    pub playing: String,
+    pub own_set_play: String,
+    pub opponent_set_play: String,
 }
 
 #[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
 pub struct RolePositions {
-    pub defender_aggressive_ring_radius: f32,
-    pub defender_passive_ring_radius: f32,
-    pub defender_y_offset: f32,
-    pub striker_supporter_distance_to_ball: f32,
-    pub striker_supporter_maximum_x_in_ready_and_when_ball_is_not_free: f32,
-    pub striker_supporter_minimum_x: f32,
     pub keeper_x_offset: f32,
     pub striker_distance_to_non_free_ball: f32,
+    /// Distance of all field players to the ball while the opponent takes a set play, the rules
+    /// require 0.75 m
+    pub distance_to_opponent_set_play_ball: f32,
     pub striker_set_position: Vector2<f32>,
 }
 
@@ -165,6 +271,7 @@ pub struct Dribbling {
     pub kick_pose_obstacle_radius: f32,
     pub emergency_kick_target_angles: Vec<f32>,
     pub ball_radius_for_kick_target_selection: f32,
+    pub goal_post_matching_distance: f32,
 }
 
 #[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
@@ -253,6 +360,7 @@ pub struct WalkingEngine {
     pub minimal_step_duration: Duration,
     pub number_of_stabilizing_steps: usize,
     pub sideways_step_duration_increase: f32,
+    pub stabilization: WalkStabilization,
     pub stable_step_deviation: Duration,
     pub starting_step_duration: Duration,
     pub starting_step_foot_lift: f32,
@@ -265,6 +373,21 @@ pub struct WalkingEngine {
     pub walk_hip_height: f32,
 }
 
+#[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
+pub struct WalkStabilization {
+    pub capture_point_backward_limit: f32,
+    pub capture_point_forward_limit: f32,
+    pub capture_point_sideways_limit: f32,
+    pub center_of_mass_velocity_low_pass_factor: f32,
+    pub enable: bool,
+    pub max_forward_step_adjustment: f32,
+    pub max_sideways_step_adjustment: f32,
+    pub max_step_duration_reduction: Duration,
+    pub minimum_sole_pressure: f32,
+    pub step_duration_reduction_factor: f32,
+    pub step_placement_gain: f32,
+}
+
 #[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
 pub struct SwingingArms {
     pub debug_pull_back: bool,
@@ -314,6 +437,22 @@ pub struct HeadMotion {
     pub maximum_velocity: HeadJoints,
 }
 
+#[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
+pub struct ActiveVision {
+    pub ball_reference_variance: f32,
+    pub ball_weight: f32,
+    pub exploration_distance: f32,
+    pub exploration_time_constant: Duration,
+    pub exploration_weight: f32,
+    pub localization_reference_variance: f32,
+    pub localization_weight: f32,
+    pub maximum_field_mark_distance: f32,
+    pub minimum_ball_validity: f32,
+    pub minimum_fixation_duration: Duration,
+    pub number_of_exploration_sectors: usize,
+    pub travel_time_cost: f32,
+}
+
 #[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
 pub struct LookAround {
     pub look_around_timeout: Duration,
@@ -329,6 +468,11 @@ pub struct LookAround {
 pub struct WhistleFilter {
     pub buffer_length: usize,
     pub minimum_detections: usize,
+    /// Bearings closer than this angle are merged into the same hypothesis
+    pub bearing_merge_angle: f32,
+    /// Centers of the neighboring fields in field coordinates, whistles pointing into them are
+    /// ignored
+    pub neighboring_field_offsets: Vec<Vector2<f32>>,
 }
 
 #[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
@@ -348,7 +492,13 @@ pub struct SonarObstacle {
 #[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
 pub struct SplNetwork {
     pub game_controller_return_message_interval: Duration,
+    pub half_duration: Duration,
+    /// Logs all sent and received packets as JSONL if the path ends with `.jsonl` and as pcap
+    /// otherwise
+    pub packet_capture_path: Option<PathBuf>,
     pub remaining_amount_of_messages_to_stop_sending: u16,
+    /// Messages of the team budget only spent on event messages, e.g. after finding the ball
+    pub reserved_amount_of_event_messages: u16,
     pub silence_interval_between_messages: Duration,
     pub spl_striker_message_receive_timeout: Duration,
     pub spl_striker_message_send_interval: Duration,
@@ -358,11 +508,14 @@ pub struct SplNetwork {
 #[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
 pub struct Vision {
     pub ball_detection: BallDetection,
+    pub extrinsic_calibrator: ExtrinsicCalibrator,
     pub image_segmenter: ImageSegmenter,
     pub image_receiver: ImageReceiver,
     pub line_detection: LineDetection,
     pub field_border_detection: FieldBorderDetection,
     pub field_color_detection: FieldColorDetection,
+    pub goal_post_detection: GoalPostDetection,
+    pub penalty_mark_detection: PenaltyMarkDetection,
     pub perspective_grid_candidates_provider: PerspectiveGridCandidatesProvider,
     pub robot_detection: RobotDetection,
     pub camera_matrix_parameters: CameraMatrixParameters,
@@ -408,6 +561,25 @@ pub struct FieldColorDetection {
     pub lower_green_chromaticity_threshold: f32,
     pub upper_green_chromaticity_threshold: f32,
     pub green_luminance_threshold: u8,
+    pub estimation: FieldColorEstimation,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
+pub struct FieldColorEstimation {
+    pub enable: bool,
+    pub horizon_margin: f32,
+    pub horizontal_sample_stride: usize,
+    pub vertical_sample_stride: usize,
+    pub number_of_histogram_bins: usize,
+    pub minimum_number_of_samples: usize,
+    pub minimum_peak_green_chromaticity: f32,
+    pub field_cluster_width: f32,
+    pub minimum_field_fraction: f32,
+    pub lower_green_chromaticity_deviation_factor: f32,
+    pub upper_green_chromaticity_deviation_factor: f32,
+    pub red_blue_chromaticity_deviation_factor: f32,
+    pub green_luminance_deviation_factor: f32,
+    pub smoothing_factor: f32,
 }
 
 #[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
@@ -438,6 +610,8 @@ pub struct LineDetection {
     pub check_line_length: bool,
     pub check_line_segments_projection: bool,
     pub gradient_alignment: f32,
+    pub intersection_angle_tolerance: f32,
+    pub intersection_endpoint_tolerance: f32,
     pub maximum_distance_to_robot: f32,
     pub maximum_fit_distance_in_pixels: f32,
     pub maximum_gap_on_line: f32,
@@ -497,6 +671,8 @@ pub struct ObstacleFilter {
     pub measurement_count_threshold: usize,
     pub use_robot_detection_measurements: bool,
     pub use_sonar_measurements: bool,
+    pub use_goal_post_detection_measurements: bool,
+    pub goal_post_measurement_noise: Vector2<f32>,
     pub robot_obstacle_radius_at_hip_height: f32,
     pub robot_obstacle_radius_at_foot_height: f32,
     pub unknown_obstacle_radius: f32,
@@ -512,9 +688,48 @@ pub struct FallStateEstimation {
     pub falling_angle_threshold: Vector2<f32>,
     pub minimum_angular_velocity: Vector2<f32>,
     pub maximum_angular_velocity: Vector2<f32>,
+    pub fall_prediction_horizon: Duration,
     pub fallen_timeout: Duration,
 }
 
+#[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
+pub struct GoalPostDetection {
+    pub enable: bool,
+    pub minimum_luminance: u8,
+    pub minimum_segment_length: u16,
+    pub maximum_scan_line_gap: f32,
+    pub maximum_base_height_difference: f32,
+    pub minimum_number_of_scan_lines: usize,
+    pub maximum_post_width: f32,
+    pub maximum_distance: f32,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
+pub struct ExtrinsicCalibrator {
+    pub enable: bool,
+    /// Known position of the robot on the field while calibrating
+    pub robot_position: Point2<f32>,
+    /// Known orientation of the robot on the field while calibrating
+    pub robot_orientation: f32,
+    pub number_of_frames: usize,
+    pub line_sampling_distance: f32,
+    pub maximum_correspondence_distance: f32,
+    pub maximum_number_of_iterations: usize,
+    /// Stops optimizing when the rotation step (in degrees) gets smaller than this
+    pub convergence_threshold: f32,
+    pub damping: f32,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
+pub struct PenaltyMarkDetection {
+    pub enable: bool,
+    pub maximum_projected_segment_length: f32,
+    pub minimum_distance_to_lines: f32,
+    pub maximum_cluster_radius: f32,
+    pub minimum_number_of_points: usize,
+    pub maximum_distance_to_robot: f32,
+}
+
 #[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
 pub struct PerspectiveGridCandidatesProvider {
     pub minimum_radius: f32,
@@ -532,9 +747,38 @@ pub struct CameraMatrixParameters {
 pub struct FallProtection {
     pub ground_impact_head_stiffness: f32,
     pub arm_stiffness: f32,
-    pub left_arm_positions: ArmJoints,
-    pub right_arm_positions: ArmJoints,
     pub leg_stiffness: f32,
+    pub forward: FallProtectionPositions,
+    pub backward: FallProtectionPositions,
+    pub left: FallProtectionPositions,
+    pub right: FallProtectionPositions,
+}
+
+impl Index<FallDirection> for FallProtection {
+    type Output = FallProtectionPositions;
+
+    fn index(&self, direction: FallDirection) -> &Self::Output {
+        match direction {
+            FallDirection::Forward => &self.forward,
+            FallDirection::Backward => &self.backward,
+            FallDirection::Left => &self.left,
+            FallDirection::Right => &self.right,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
+pub struct FallProtectionPositions {
+    pub head: HeadJoints,
+    pub left_arm: ArmJoints,
+    pub right_arm: ArmJoints,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
+pub struct FallStatisticsRecorder {
+    pub maximum_number_of_recorded_falls: usize,
+    /// File in the `log_directory` the statistics are loaded from and stored to
+    pub statistics_file_name: PathBuf,
 }
 
 #[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
@@ -549,6 +793,39 @@ pub struct ProjectedLimbs {
 #[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
 pub struct RobotDetection {
     pub enable: bool,
+    #[leaf]
+    pub method: RobotDetectionMethod,
+    pub heuristic: RobotDetectionHeuristic,
+    /// Network mapping a 32x32 grayscale sample to robot confidence and feet position in the
+    /// sample, it is not part of the repository, see the robot detection in the vision docs
+    pub neural_network: PathBuf,
+    pub maximum_number_of_candidate_evaluations: usize,
+    pub candidate_radius_enlargement_factor: f32,
+    pub confidence_threshold: f32,
+    pub cluster_merge_distance: f32,
+    pub maximum_distance_to_robot: f32,
+    pub jersey_height: Range<f32>,
+    pub jersey_width: f32,
+    pub jersey_samples_per_axis: usize,
+    pub maximum_jersey_color_distance: f32,
+    pub minimum_jersey_vote_ratio: f32,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
+pub struct PenaltyShotDirectionEstimation {
+    pub moving_distance_threshold: f32,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
+pub enum RobotDetectionMethod {
+    /// Clusters the lowest segments of vertical scan lines, works without a trained network
+    #[default]
+    Heuristic,
+    NeuralNetwork,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
+pub struct RobotDetectionHeuristic {
     pub amount_of_segments_factor: f32,
     pub amount_score_exponent: f32,
     pub cluster_cone_radius: f32,
@@ -561,8 +838,3 @@ pub struct RobotDetection {
     pub minimum_cluster_score: f32,
     pub minimum_consecutive_segments: usize,
 }
-
-#[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
-pub struct PenaltyShotDirectionEstimation {
-    pub moving_distance_threshold: f32,
-}
@@ -12,6 +12,7 @@ mod spl_network;
 mod statistics;
 mod vision;
 
+pub use audio::evaluation as audio_evaluation;
 pub use logging::setup_logger;
 use ransac::{Ransac, RansacResult};
 pub use runtime::Runtime;
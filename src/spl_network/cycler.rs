@@ -6,7 +6,6 @@ use std::{
 
 use anyhow::Result;
 use log::error;
-use spl_network::HULKS_TEAM_NUMBER;
 use tokio::{net::UdpSocket, runtime, sync::Notify};
 use tokio_util::sync::CancellationToken;
 use types::MessageEvent;
@@ -25,8 +24,11 @@ use super::{
     database::MainOutputs,
     game_controller_return_message_sender::send_game_controller_return_message,
     game_controller_state_message_parser::parse_game_controller_state_message,
-    message_receiver::receive_message, spl_message_parser::parse_spl_message,
-    spl_message_sender::spl_message_sender, Database,
+    message_receiver::receive_message,
+    packet_recorder::{PacketDirection, PacketRecorder},
+    spl_message_parser::parse_spl_message,
+    spl_message_sender::spl_message_sender,
+    Database,
 };
 
 #[allow(dead_code)]
@@ -41,7 +43,10 @@ where
     communication_channels: CommunicationChannelsForCycler<Database>,
     control_database_changed: Arc<Notify>,
 
+    /// Read once at startup since the SPL message port is derived from it
+    team_number: u8,
     last_game_controller_address: Option<SocketAddr>,
+    packet_recorder: PacketRecorder,
 }
 
 impl<Hardware> SplNetwork<Hardware>
@@ -56,6 +61,8 @@ where
         communication_channels: CommunicationChannelsForCycler<Database>,
         control_database_changed: Arc<Notify>,
     ) -> anyhow::Result<Self> {
+        let team_number = communication_channels.configuration.next().team_number;
+        let packet_recorder = PacketRecorder::new()?;
         Ok(Self {
             hardware_interface,
             control_reader,
@@ -64,7 +71,9 @@ where
             communication_channels,
             control_database_changed,
 
+            team_number,
             last_game_controller_address: Default::default(),
+            packet_recorder,
         })
     }
 
@@ -92,7 +101,7 @@ where
                     };
                     let spl_messages = match UdpSocket::bind(format!(
                         "0.0.0.0:{}",
-                        10000 + (HULKS_TEAM_NUMBER as u16)
+                        10000 + (self.team_number as u16)
                     ))
                     .await
                     {
@@ -163,25 +172,62 @@ where
             };
 
             self.spl_network_producer.announce();
+            let team_number = self.team_number;
+            let packet_capture_path = self
+                .communication_channels
+                .configuration
+                .next()
+                .spl_network
+                .packet_capture_path
+                .clone();
 
             // process
             match message_event {
                 MessageEvent::GameControllerReturnMessageToBeSent { message } => {
-                    send_game_controller_return_message(
+                    if let Some((destination, payload)) = send_game_controller_return_message(
                         game_controller_state_messages,
                         &self.last_game_controller_address,
                         message,
                     )
-                    .await;
+                    .await
+                    {
+                        self.packet_recorder.record(
+                            &packet_capture_path,
+                            team_number,
+                            game_controller_state_messages,
+                            destination,
+                            PacketDirection::Outgoing,
+                            &payload,
+                        );
+                    }
                 }
                 MessageEvent::SplMessageToBeSent { message } => {
-                    spl_message_sender(spl_messages, message).await;
+                    if let Some((destination, payload)) =
+                        spl_message_sender(spl_messages, message).await
+                    {
+                        self.packet_recorder.record(
+                            &packet_capture_path,
+                            team_number,
+                            spl_messages,
+                            destination,
+                            PacketDirection::Outgoing,
+                            &payload,
+                        );
+                    }
                 }
                 MessageEvent::IncomingGameControllerStateMessage { message, sender } => {
+                    self.packet_recorder.record(
+                        &packet_capture_path,
+                        team_number,
+                        game_controller_state_messages,
+                        sender,
+                        PacketDirection::Incoming,
+                        message,
+                    );
                     spl_network_database
                         .main_outputs
                         .game_controller_state_message =
-                        parse_game_controller_state_message(message);
+                        parse_game_controller_state_message(message, team_number);
                     if spl_network_database
                         .main_outputs
                         .game_controller_state_message
@@ -190,8 +236,17 @@ where
                         self.last_game_controller_address = Some(sender);
                     }
                 }
-                MessageEvent::IncomingSplMessage { message, sender: _ } => {
-                    spl_network_database.main_outputs.spl_message = parse_spl_message(message);
+                MessageEvent::IncomingSplMessage { message, sender } => {
+                    self.packet_recorder.record(
+                        &packet_capture_path,
+                        team_number,
+                        spl_messages,
+                        sender,
+                        PacketDirection::Incoming,
+                        message,
+                    );
+                    spl_network_database.main_outputs.spl_message =
+                        parse_spl_message(message, team_number);
                 }
             }
 
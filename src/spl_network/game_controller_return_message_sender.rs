@@ -4,27 +4,29 @@ use log::warn;
 use spl_network::GameControllerReturnMessage;
 use tokio::net::UdpSocket;
 
+/// Returns the destination and the payload if the message was sent
 pub async fn send_game_controller_return_message(
     game_controller_state_messages: &UdpSocket,
     last_game_controller_address: &Option<SocketAddr>,
     message: GameControllerReturnMessage,
-) {
+) -> Option<(SocketAddr, Vec<u8>)> {
     let game_controller_address = match last_game_controller_address {
         Some(game_controller_address) => game_controller_address,
         None => {
             // Unknown GameController address, silently skipping return message sending
-            return;
+            return None;
         }
     };
+    let destination = SocketAddr::new(game_controller_address.ip(), 3939);
     let message: Vec<u8> = message.into();
     match game_controller_state_messages
-        .send_to(
-            message.as_slice(),
-            SocketAddr::new(game_controller_address.ip(), 3939),
-        )
+        .send_to(message.as_slice(), destination)
         .await
     {
-        Ok(_) => {}
-        Err(error) => warn!("Failed to send GameController return message: {:?}", error),
+        Ok(_) => Some((destination, message)),
+        Err(error) => {
+            warn!("Failed to send GameController return message: {:?}", error);
+            None
+        }
     }
 }
@@ -1,10 +1,11 @@
-use std::convert::TryInto;
-
 use log::warn;
 use spl_network::GameControllerStateMessage;
 
-pub fn parse_game_controller_state_message(message: &[u8]) -> Option<GameControllerStateMessage> {
-    match message.try_into() {
+pub fn parse_game_controller_state_message(
+    message: &[u8],
+    team_number: u8,
+) -> Option<GameControllerStateMessage> {
+    match GameControllerStateMessage::try_from_bytes(message, team_number) {
         Ok(message) => Some(message),
         Err(error) => {
             warn!(
@@ -3,6 +3,7 @@ mod database;
 mod game_controller_return_message_sender;
 mod game_controller_state_message_parser;
 mod message_receiver;
+mod packet_recorder;
 mod spl_message_parser;
 mod spl_message_sender;
 
@@ -0,0 +1,211 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufWriter, Write},
+    net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket},
+    path::{Path, PathBuf},
+    sync::mpsc::{channel, Sender},
+    thread,
+    time::SystemTime,
+};
+
+use anyhow::Context;
+use log::warn;
+use spl_network::{CaptureRecord, CapturedPacket, PcapWriter};
+
+enum CaptureWriter {
+    Pcap(PcapWriter<BufWriter<File>>),
+    JsonLines(BufWriter<File>),
+}
+
+impl CaptureWriter {
+    /// Writes JSONL if the path ends with `.jsonl` and pcap otherwise
+    fn create(path: &Path) -> anyhow::Result<Self> {
+        let file = BufWriter::new(
+            File::create(path)
+                .with_context(|| format!("Failed to create packet capture {}", path.display()))?,
+        );
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("jsonl") => Ok(Self::JsonLines(file)),
+            _ => Ok(Self::Pcap(PcapWriter::new(file)?)),
+        }
+    }
+
+    fn write(&mut self, packet: &CapturedPacket, team_number: u8) -> anyhow::Result<()> {
+        match self {
+            CaptureWriter::Pcap(writer) => {
+                writer.write(packet)?;
+                writer.flush()
+            }
+            CaptureWriter::JsonLines(writer) => {
+                serde_json::to_writer(&mut *writer, &CaptureRecord::new(packet, team_number))?;
+                writer.write_all(b"\n")?;
+                Ok(writer.flush()?)
+            }
+        }
+    }
+}
+
+enum CaptureCommand {
+    Record {
+        capture_path: PathBuf,
+        team_number: u8,
+        packet: CapturedPacket,
+    },
+    Stop,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum PacketDirection {
+    Incoming,
+    Outgoing,
+}
+
+/// Logs all sent and received packets while `spl_network.packet_capture_path` is set
+///
+/// Files are written by a separate thread to not block the executor of the SPL network cycler.
+pub struct PacketRecorder {
+    command_sender: Sender<CaptureCommand>,
+    is_recording: bool,
+    /// Interface addresses by peer address, the sockets are bound to the unspecified address
+    interface_addresses: HashMap<IpAddr, IpAddr>,
+}
+
+impl PacketRecorder {
+    pub fn new() -> anyhow::Result<Self> {
+        let (command_sender, command_receiver) = channel();
+        thread::Builder::new()
+            .name("packet_recorder".to_string())
+            .spawn(move || {
+                let mut writer: Option<(PathBuf, CaptureWriter)> = None;
+                while let Ok(command) = command_receiver.recv() {
+                    let (capture_path, team_number, packet) = match command {
+                        CaptureCommand::Record {
+                            capture_path,
+                            team_number,
+                            packet,
+                        } => (capture_path, team_number, packet),
+                        CaptureCommand::Stop => {
+                            writer = None;
+                            continue;
+                        }
+                    };
+                    if let Err(error) =
+                        write_packet(&mut writer, capture_path, team_number, &packet)
+                    {
+                        warn!("Failed to record packet: {error:?}");
+                    }
+                }
+            })
+            .context("Failed to spawn packet recorder thread")?;
+        Ok(Self {
+            command_sender,
+            is_recording: false,
+            interface_addresses: HashMap::new(),
+        })
+    }
+
+    /// Records a packet sent to or received from `peer` via `socket` if a capture path is set
+    pub fn record(
+        &mut self,
+        capture_path: &Option<PathBuf>,
+        team_number: u8,
+        socket: &tokio::net::UdpSocket,
+        peer: SocketAddr,
+        direction: PacketDirection,
+        payload: &[u8],
+    ) {
+        let command = match capture_path {
+            Some(capture_path) => {
+                let local_address = match self.local_address(socket, peer) {
+                    Ok(local_address) => local_address,
+                    Err(error) => {
+                        warn!("Failed to determine local address of packet: {error:?}");
+                        return;
+                    }
+                };
+                let (source, destination) = match direction {
+                    PacketDirection::Incoming => (peer, local_address),
+                    PacketDirection::Outgoing => (local_address, peer),
+                };
+                CaptureCommand::Record {
+                    capture_path: capture_path.clone(),
+                    team_number,
+                    packet: CapturedPacket {
+                        time: SystemTime::now(),
+                        source,
+                        destination,
+                        payload: payload.to_vec(),
+                    },
+                }
+            }
+            None if self.is_recording => CaptureCommand::Stop,
+            None => return,
+        };
+        self.is_recording = capture_path.is_some();
+        if self.command_sender.send(command).is_err() {
+            warn!("Packet recorder thread has stopped");
+        }
+    }
+
+    /// Address of the interface communicating with the peer and the port of the socket
+    fn local_address(
+        &mut self,
+        socket: &tokio::net::UdpSocket,
+        peer: SocketAddr,
+    ) -> anyhow::Result<SocketAddr> {
+        let port = socket.local_addr()?.port();
+        let interface_address = match self.interface_addresses.get(&peer.ip()) {
+            Some(interface_address) => *interface_address,
+            None => {
+                let interface_address = interface_address_towards(peer)?;
+                self.interface_addresses
+                    .insert(peer.ip(), interface_address);
+                interface_address
+            }
+        };
+        Ok(SocketAddr::new(interface_address, port))
+    }
+}
+
+/// Lets the kernel choose the route to the peer without sending anything
+fn interface_address_towards(peer: SocketAddr) -> anyhow::Result<IpAddr> {
+    let probe = UdpSocket::bind(SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), 0))?;
+    probe.set_broadcast(true)?;
+    probe
+        .connect(peer)
+        .with_context(|| format!("No route to {peer}"))?;
+    Ok(probe.local_addr()?.ip())
+}
+
+fn write_packet(
+    writer: &mut Option<(PathBuf, CaptureWriter)>,
+    capture_path: PathBuf,
+    team_number: u8,
+    packet: &CapturedPacket,
+) -> anyhow::Result<()> {
+    let is_recording_to_path = matches!(writer, Some((path, _)) if *path == capture_path);
+    if !is_recording_to_path {
+        *writer = None;
+        let capture_writer = CaptureWriter::create(&capture_path)?;
+        *writer = Some((capture_path, capture_writer));
+    }
+    if let Some((_, writer)) = writer.as_mut() {
+        writer.write(packet, team_number)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loopback_peers_are_reached_via_loopback_interface() {
+        let peer = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 3939);
+        assert_eq!(
+            interface_address_towards(peer).unwrap(),
+            IpAddr::from(Ipv4Addr::LOCALHOST)
+        );
+    }
+}
@@ -1,11 +1,16 @@
-use std::convert::TryInto;
-
 use log::warn;
 use spl_network::SplMessage;
 
-pub fn parse_spl_message(message: &[u8]) -> Option<SplMessage> {
-    match message.try_into() {
-        Ok(message) => Some(message),
+pub fn parse_spl_message(message: &[u8], team_number: u8) -> Option<SplMessage> {
+    match SplMessage::try_from(message) {
+        Ok(message) if message.team_number == team_number => Some(message),
+        Ok(message) => {
+            warn!(
+                "Discarding SPL message of team {} != {}",
+                message.team_number, team_number
+            );
+            None
+        }
         Err(error) => {
             warn!(
                 "Failed to parse SPL message (will be discarded): {:?}",
@@ -1,22 +1,24 @@
 use std::net::{Ipv4Addr, SocketAddr};
 
 use log::warn;
-use spl_network::{SplMessage, HULKS_TEAM_NUMBER};
+use spl_network::SplMessage;
 use tokio::net::UdpSocket;
 
-pub async fn spl_message_sender(spl_messages: &UdpSocket, message: SplMessage) {
+/// Returns the destination and the payload if the message was sent
+pub async fn spl_message_sender(
+    spl_messages: &UdpSocket,
+    message: SplMessage,
+) -> Option<(SocketAddr, Vec<u8>)> {
+    let destination = SocketAddr::new(
+        Ipv4Addr::BROADCAST.into(),
+        10000 + (message.team_number as u16),
+    );
     let message: Vec<u8> = message.into();
-    match spl_messages
-        .send_to(
-            message.as_slice(),
-            SocketAddr::new(
-                Ipv4Addr::BROADCAST.into(),
-                10000 + (HULKS_TEAM_NUMBER as u16),
-            ),
-        )
-        .await
-    {
-        Ok(_) => {}
-        Err(error) => warn!("Failed to send SPL message: {:?}", error),
+    match spl_messages.send_to(message.as_slice(), destination).await {
+        Ok(_) => Some((destination, message)),
+        Err(error) => {
+            warn!("Failed to send SPL message: {:?}", error);
+            None
+        }
     }
 }
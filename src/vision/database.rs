@@ -5,9 +5,10 @@ use serde::{Deserialize, Serialize};
 use serialize_hierarchy::SerializeHierarchy;
 
 use types::{
-    Ball, CameraMatrix, CandidateEvaluation, ClusterCone, CycleInfo, DetectedRobots, FieldBorder,
-    FieldColor, FilteredSegments, Image422, ImageLines, ImageSegments, LineData,
-    PerspectiveGridCandidates, ScoredClusterPoint,
+    Ball, CameraMatrix, CandidateEvaluation, ClusterCone, CycleInfo, DetectedRobot, DetectedRobots,
+    ExtrinsicCalibration, FieldBorder, FieldColor, FieldColorHistogram, FilteredSegments, GoalPost,
+    Image422, ImageLines, ImageSegments, LineData, PenaltyMark, PerspectiveGridCandidates,
+    ScoredClusterPoint,
 };
 
 #[derive(Clone, Debug, Default, SerializeHierarchy)]
@@ -16,11 +17,14 @@ pub struct MainOutputs {
     pub camera_matrix: Option<CameraMatrix>,
     pub cycle_info: Option<CycleInfo>,
     pub detected_robots: Option<DetectedRobots>,
+    pub extrinsic_calibration: Option<ExtrinsicCalibration>,
     pub field_border: Option<FieldBorder>,
     pub field_color: Option<FieldColor>,
     pub filtered_segments: Option<FilteredSegments>,
+    pub goal_posts: Option<Vec<GoalPost>>,
     pub image_segments: Option<ImageSegments>,
     pub line_data: Option<LineData>,
+    pub penalty_marks: Option<Vec<PenaltyMark>>,
     pub perspective_grid_candidates: Option<PerspectiveGridCandidates>,
 }
 
@@ -29,8 +33,11 @@ pub struct AdditionalOutputs {
     pub ball_candidates: Option<Vec<CandidateEvaluation>>,
     pub lines_in_image: Option<ImageLines>,
     pub field_border_points: Option<Vec<Point2<f32>>>,
+    pub field_color_histogram: Option<FieldColorHistogram>,
+    pub goal_post_base_points: Option<Vec<Point2<f32>>>,
     pub image_segmenter_cycle_time: Option<Duration>,
     pub robot_detection: RobotDetection,
+    pub robot_detection_candidates: Option<Vec<DetectedRobot>>,
 }
 
 #[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
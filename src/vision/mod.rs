@@ -2,6 +2,7 @@ mod cycler;
 mod database;
 mod image_receiver;
 mod modules;
+mod neural_network;
 
 pub use cycler::Vision;
 pub use database::{AdditionalOutputs, Database, MainOutputs};
@@ -1,21 +1,24 @@
-use compiled_nn::CompiledNN;
 use module_derive::{module, require_some};
 use nalgebra::{point, vector};
 use types::{
     Ball, CameraMatrix, CandidateEvaluation, Circle, PerspectiveGridCandidates, Rectangle,
 };
 
-use crate::framework::configuration::BallDetection as BallDetectionConfiguration;
+use crate::{
+    framework::configuration::BallDetection as BallDetectionConfiguration,
+    vision::neural_network::{Network, NeuralNetwork},
+};
 
 pub const SAMPLE_SIZE: usize = 32;
 pub type Sample = [[f32; SAMPLE_SIZE]; SAMPLE_SIZE];
 
 struct NeuralNetworks {
-    preclassifier: CompiledNN,
-    classifier: CompiledNN,
-    positioner: CompiledNN,
+    preclassifier: Network,
+    classifier: Network,
+    positioner: Network,
 }
 
+#[cfg(feature = "compiled-nn")]
 unsafe impl Send for NeuralNetworks {}
 
 #[derive(Debug)]
@@ -47,7 +50,7 @@ impl BallDetection {
     fn cycle(&mut self, mut context: CycleContext) -> anyhow::Result<MainOutputs> {
         let candidates = &require_some!(context.perspective_grid_candidates).candidates;
         let camera_matrix = require_some!(context.camera_matrix);
-        let networks = self.get_neural_networks(&context);
+        let networks = self.get_neural_networks(&context)?;
 
         let evaluations = Self::evaluate_candidates(
             candidates,
@@ -59,7 +62,7 @@ impl BallDetection {
             context.ball_detection.ball_radius_enlargement_factor,
             context.ball_detection.preclassifier_confidence_threshold,
             context.ball_detection.classifier_confidence_threshold,
-        );
+        )?;
         context
             .ball_candidates
             .fill_on_subscription(|| evaluations.clone());
@@ -90,16 +93,15 @@ impl BallDetection {
         Ok(MainOutputs { balls: Some(balls) })
     }
 
-    fn get_neural_networks(&mut self, context: &CycleContext) -> &mut NeuralNetworks {
+    fn get_neural_networks(
+        &mut self,
+        context: &CycleContext,
+    ) -> anyhow::Result<&mut NeuralNetworks> {
         if self.neural_networks.is_none() {
-            let mut preclassifier = CompiledNN::default();
-            preclassifier.compile(context.ball_detection.preclassifier_neural_network.clone());
-
-            let mut classifier = CompiledNN::default();
-            classifier.compile(context.ball_detection.classifier_neural_network.clone());
-
-            let mut positioner = CompiledNN::default();
-            positioner.compile(context.ball_detection.positioner_neural_network.clone());
+            let preclassifier =
+                Network::from_file(&context.ball_detection.preclassifier_neural_network)?;
+            let classifier = Network::from_file(&context.ball_detection.classifier_neural_network)?;
+            let positioner = Network::from_file(&context.ball_detection.positioner_neural_network)?;
 
             self.neural_networks = Some(NeuralNetworks {
                 preclassifier,
@@ -108,64 +110,50 @@ impl BallDetection {
             });
         }
 
-        self.neural_networks.as_mut().unwrap()
+        Ok(self.neural_networks.as_mut().unwrap())
     }
 
-    fn preclassify_sample(network: &mut CompiledNN, sample: &Sample) -> f32 {
-        let input = network.input(0);
+    fn preclassify_sample(
+        network: &mut impl NeuralNetwork,
+        sample: &Sample,
+    ) -> anyhow::Result<f32> {
+        let input = network.input();
         for y in 0..SAMPLE_SIZE {
             for x in 0..SAMPLE_SIZE {
                 input[x + y * SAMPLE_SIZE] = sample[y][x];
             }
         }
-        network.apply();
-        network.output(0)[0]
+        network.apply()?;
+        Ok(network.output()[0])
     }
 
-    fn classify_sample(network: &mut CompiledNN, sample: &Sample) -> f32 {
-        let input = network.input(0);
+    fn classify_sample(network: &mut impl NeuralNetwork, sample: &Sample) -> anyhow::Result<f32> {
+        let input = network.input();
         for y in 0..SAMPLE_SIZE {
             for x in 0..SAMPLE_SIZE {
                 input[x + y * SAMPLE_SIZE] = sample[y][x];
             }
         }
-        network.apply();
-        network.output(0)[0]
+        network.apply()?;
+        Ok(network.output()[0])
     }
 
-    fn position_sample(network: &mut CompiledNN, sample: &Sample) -> Circle {
-        let input = network.input(0);
+    fn position_sample(
+        network: &mut impl NeuralNetwork,
+        sample: &Sample,
+    ) -> anyhow::Result<Circle> {
+        let input = network.input();
         for y in 0..SAMPLE_SIZE {
             for x in 0..SAMPLE_SIZE {
                 input[x + y * SAMPLE_SIZE] = sample[y][x];
             }
         }
-        network.apply();
-        Circle {
-            center: point![network.output(0)[0], network.output(0)[1]],
-            radius: network.output(0)[2],
-        }
-    }
-
-    fn sample_grayscale(image: &Image422, candidate: Circle) -> Sample {
-        let top_left = candidate.center - vector![candidate.radius, candidate.radius];
-        let image_pixels_per_sample_pixel = candidate.radius * 2.0 / SAMPLE_SIZE as f32;
-
-        let mut sample = Sample::default();
-        for (y, column) in sample.iter_mut().enumerate() {
-            for (x, pixel) in column.iter_mut().enumerate() {
-                let sample_point = point![
-                    (top_left.x + x as f32 * image_pixels_per_sample_pixel) * 0.5,
-                    top_left.y + y as f32 * image_pixels_per_sample_pixel
-                ];
-                *pixel = image.try_at(sample_point).map_or(
-                    128.0,
-                    |color| if x % 2 == 0 { color.y1 } else { color.y2 } as f32,
-                );
-            }
-        }
-
-        sample
+        network.apply()?;
+        let output = network.output();
+        Ok(Circle {
+            center: point![output[0], output[1]],
+            radius: output[2],
+        })
     }
 
     fn evaluate_candidates(
@@ -176,7 +164,7 @@ impl BallDetection {
         ball_radius_enlargement_factor: f32,
         classifier_confidence_threshold: f32,
         preclassifier_confidence_threshold: f32,
-    ) -> Vec<CandidateEvaluation> {
+    ) -> anyhow::Result<Vec<CandidateEvaluation>> {
         let preclassifier = &mut networks.preclassifier;
         let classifier = &mut networks.classifier;
         let positioner = &mut networks.positioner;
@@ -189,17 +177,17 @@ impl BallDetection {
                     center: candidate.center,
                     radius: candidate.radius * ball_radius_enlargement_factor,
                 };
-                let sample = Self::sample_grayscale(image, enlarged_candidate);
-                let preclassifier_confidence = Self::preclassify_sample(preclassifier, &sample);
+                let sample = sample_grayscale(image, enlarged_candidate);
+                let preclassifier_confidence = Self::preclassify_sample(preclassifier, &sample)?;
 
                 let mut classifier_confidence = None;
                 if preclassifier_confidence > preclassifier_confidence_threshold {
-                    classifier_confidence = Some(Self::classify_sample(classifier, &sample))
+                    classifier_confidence = Some(Self::classify_sample(classifier, &sample)?)
                 };
 
                 let mut corrected_circle = None;
                 if classifier_confidence > Some(classifier_confidence_threshold) {
-                    let raw_corrected_circle = Self::position_sample(positioner, &sample);
+                    let raw_corrected_circle = Self::position_sample(positioner, &sample)?;
 
                     corrected_circle = Some(Circle {
                         center: candidate.center
@@ -212,13 +200,13 @@ impl BallDetection {
                     });
                 }
 
-                CandidateEvaluation {
+                Ok(CandidateEvaluation {
                     candidate_circle: *candidate,
                     preclassifier_confidence,
                     classifier_confidence,
                     corrected_circle,
                     merge_weight: None,
-                }
+                })
             })
             .collect()
     }
@@ -322,6 +310,28 @@ impl BallDetection {
     }
 }
 
+/// Samples the luminance of the square around a candidate circle at network input resolution
+pub fn sample_grayscale(image: &Image422, candidate: Circle) -> Sample {
+    let top_left = candidate.center - vector![candidate.radius, candidate.radius];
+    let image_pixels_per_sample_pixel = candidate.radius * 2.0 / SAMPLE_SIZE as f32;
+
+    let mut sample = Sample::default();
+    for (y, column) in sample.iter_mut().enumerate() {
+        for (x, pixel) in column.iter_mut().enumerate() {
+            let sample_point = point![
+                (top_left.x + x as f32 * image_pixels_per_sample_pixel) * 0.5,
+                top_left.y + y as f32 * image_pixels_per_sample_pixel
+            ];
+            *pixel = image.try_at(sample_point).map_or(
+                128.0,
+                |color| if x % 2 == 0 { color.y1 } else { color.y2 } as f32,
+            );
+        }
+    }
+
+    sample
+}
+
 #[cfg(test)]
 mod tests {
     use std::path::{Path, PathBuf};
@@ -343,16 +353,15 @@ mod tests {
 
     #[test]
     fn preclassify_ball() {
-        let mut network = CompiledNN::default();
-        network.compile(CLASSIFIER_PATH);
-        let sample = BallDetection::sample_grayscale(
+        let mut network = Network::from_file(CLASSIFIER_PATH).unwrap();
+        let sample = sample_grayscale(
             &Image422::load_from_ycbcr_444_file(Path::new(BALL_SAMPLE_PATH)).unwrap(),
             Circle {
                 center: point![16.0, 16.0],
                 radius: 16.0,
             },
         );
-        let confidence = BallDetection::preclassify_sample(&mut network, &sample);
+        let confidence = BallDetection::preclassify_sample(&mut network, &sample).unwrap();
 
         println!("{:?}", confidence);
         assert_relative_eq!(confidence, 1.0, epsilon = 0.01);
@@ -360,16 +369,15 @@ mod tests {
 
     #[test]
     fn classify_ball() {
-        let mut network = CompiledNN::default();
-        network.compile(PRECLASSIFIER_PATH);
-        let sample = BallDetection::sample_grayscale(
+        let mut network = Network::from_file(PRECLASSIFIER_PATH).unwrap();
+        let sample = sample_grayscale(
             &Image422::load_from_ycbcr_444_file(Path::new(BALL_SAMPLE_PATH)).unwrap(),
             Circle {
                 center: point![16.0, 16.0],
                 radius: 16.0,
             },
         );
-        let confidence = BallDetection::classify_sample(&mut network, &sample);
+        let confidence = BallDetection::classify_sample(&mut network, &sample).unwrap();
 
         println!("{:?}", confidence);
         assert_relative_eq!(confidence, 1.0, epsilon = 0.01);
@@ -377,16 +385,15 @@ mod tests {
 
     #[test]
     fn position_ball() {
-        let mut network = CompiledNN::default();
-        network.compile(POSITIONER_PATH);
-        let sample = BallDetection::sample_grayscale(
+        let mut network = Network::from_file(POSITIONER_PATH).unwrap();
+        let sample = sample_grayscale(
             &Image422::load_from_ycbcr_444_file(Path::new(BALL_SAMPLE_PATH)).unwrap(),
             Circle {
                 center: point![16.0, 16.0],
                 radius: 16.0,
             },
         );
-        let circle = BallDetection::position_sample(&mut network, &sample);
+        let circle = BallDetection::position_sample(&mut network, &sample).unwrap();
 
         assert_relative_eq!(
             circle,
@@ -499,17 +506,25 @@ mod tests {
             .balls
             .ok_or_else(|| anyhow!("No result returned"))?;
 
-        assert_relative_eq!(
-            balls[0],
-            Ball {
-                position: point![0.376, -0.22],
-                image_location: Circle {
-                    center: point![307.7, 175.16],
-                    radius: 43.12,
-                }
+        // CompiledNN approximates exp in its elu and tanh activations, the portable interpreter
+        // evaluates them exactly, which moves the positioned ball by about a sixth of a pixel
+        #[cfg(feature = "compiled-nn")]
+        let expected_ball = Ball {
+            position: point![0.376, -0.22],
+            image_location: Circle {
+                center: point![307.7, 175.16],
+                radius: 43.12,
             },
-            epsilon = 0.01,
-        );
+        };
+        #[cfg(not(feature = "compiled-nn"))]
+        let expected_ball = Ball {
+            position: point![0.376, -0.218],
+            image_location: Circle {
+                center: point![307.84, 175.32],
+                radius: 43.13,
+            },
+        };
+        assert_relative_eq!(balls[0], expected_ball, epsilon = 0.01);
         Ok(())
     }
 }
@@ -0,0 +1,445 @@
+use log::{info, warn};
+use module_derive::{module, require_some};
+use nalgebra::{distance, vector, Isometry2, Matrix3, Point2, Vector3};
+use types::{
+    camera_to_head, field_marks_from_field_dimensions, CameraMatrix, CameraPosition,
+    ExtrinsicCalibration, FieldDimensions, FieldMark, Line2, LineData,
+};
+
+use crate::framework::configuration;
+
+/// Step (in degrees) used to numerically differentiate the residuals w.r.t. the extrinsic rotations
+const DIFFERENTIATION_STEP: f32 = 0.01;
+
+struct Frame {
+    camera_matrix: CameraMatrix,
+    line_points_in_image: Vec<Point2<f32>>,
+}
+
+struct Correspondence {
+    frame_index: usize,
+    point_index: usize,
+    field_mark: FieldMark,
+}
+
+/// Calibrates the extrinsic camera rotations while the robot stands at a known pose. Detected line
+/// points of several frames are projected onto the field and the rotations are optimized such
+/// that the points lie on their closest field marks. Distances are divided by the distance of the
+/// point to the robot which approximates the angular reprojection error and keeps far away points
+/// from dominating.
+pub struct ExtrinsicCalibrator {
+    field_marks: Vec<FieldMark>,
+    frames: Vec<Frame>,
+    calibration: ExtrinsicCalibration,
+}
+
+#[module(vision)]
+#[input(path = camera_matrix, data_type = CameraMatrix)]
+#[input(path = line_data, data_type = LineData)]
+#[parameter(path = field_dimensions, data_type = FieldDimensions)]
+#[parameter(path = $this_cycler.camera_matrix_parameters.extrinsic_rotations, data_type = Vector3<f32>)]
+#[parameter(path = $this_cycler.extrinsic_calibrator, data_type = configuration::ExtrinsicCalibrator)]
+#[main_output(data_type = ExtrinsicCalibration)]
+impl ExtrinsicCalibrator {}
+
+impl ExtrinsicCalibrator {
+    fn new(context: NewContext) -> anyhow::Result<Self> {
+        Ok(Self {
+            field_marks: field_marks_from_field_dimensions(context.field_dimensions),
+            frames: vec![],
+            calibration: Default::default(),
+        })
+    }
+
+    fn cycle(&mut self, context: CycleContext) -> anyhow::Result<MainOutputs> {
+        let parameters = context.extrinsic_calibrator;
+        if !parameters.enable {
+            self.frames.clear();
+            self.calibration = Default::default();
+            return Ok(MainOutputs {
+                extrinsic_calibration: None,
+            });
+        }
+        if self.calibration.is_finished {
+            return Ok(MainOutputs {
+                extrinsic_calibration: Some(self.calibration.clone()),
+            });
+        }
+
+        let camera_matrix = require_some!(context.camera_matrix);
+        let line_data = require_some!(context.line_data);
+        self.frames.push(Frame {
+            camera_matrix: camera_matrix.clone(),
+            line_points_in_image: sample_line_points_in_image(
+                &line_data.lines_in_robot,
+                camera_matrix,
+                parameters.line_sampling_distance,
+            ),
+        });
+        self.calibration.number_of_collected_frames = self.frames.len();
+        self.calibration.extrinsic_rotations = *context.extrinsic_rotations;
+
+        if self.frames.len() >= parameters.number_of_frames {
+            let robot_to_field = Isometry2::new(
+                parameters.robot_position.coords,
+                parameters.robot_orientation,
+            );
+            self.calibration = calibrate(
+                &self.frames,
+                &self.field_marks,
+                robot_to_field,
+                context.camera_position,
+                *context.extrinsic_rotations,
+                parameters,
+            );
+            if self.calibration.number_of_used_points == 0 {
+                warn!("No line points matched any field mark, extrinsic calibration failed");
+            } else {
+                info!(
+                    "Calibrated extrinsic rotations of {:?} camera: {:?} (error {} -> {})",
+                    context.camera_position,
+                    self.calibration.extrinsic_rotations,
+                    self.calibration.initial_error,
+                    self.calibration.error,
+                );
+            }
+        }
+
+        Ok(MainOutputs {
+            extrinsic_calibration: Some(self.calibration.clone()),
+        })
+    }
+}
+
+/// Samples points along the detected lines and projects them back into the image with the camera
+/// matrix they were detected with. This recovers the image positions of the line points
+/// independent of the extrinsic calibration.
+fn sample_line_points_in_image(
+    lines_in_robot: &[Line2],
+    camera_matrix: &CameraMatrix,
+    sampling_distance: f32,
+) -> Vec<Point2<f32>> {
+    lines_in_robot
+        .iter()
+        .flat_map(|line| {
+            let number_of_segments = (line.length() / sampling_distance).ceil().max(1.0) as usize;
+            (0..=number_of_segments).map(move |index| {
+                line.0 + (line.1 - line.0) * (index as f32 / number_of_segments as f32)
+            })
+        })
+        .filter_map(|point_in_robot| camera_matrix.ground_to_pixel(&point_in_robot).ok())
+        .collect()
+}
+
+fn calibrate(
+    frames: &[Frame],
+    field_marks: &[FieldMark],
+    robot_to_field: Isometry2<f32>,
+    camera_position: CameraPosition,
+    initial_extrinsic_rotations: Vector3<f32>,
+    parameters: &configuration::ExtrinsicCalibrator,
+) -> ExtrinsicCalibration {
+    let robot_position = Point2::from(robot_to_field.translation.vector);
+    let project = |extrinsic_rotations: Vector3<f32>| {
+        project_line_points_to_field(frames, robot_to_field, camera_position, extrinsic_rotations)
+    };
+    let initial_points_in_field = project(initial_extrinsic_rotations);
+    let initial_correspondences = associate(
+        &initial_points_in_field,
+        field_marks,
+        parameters.maximum_correspondence_distance,
+    );
+    let initial_error = root_mean_square(&residuals(
+        &initial_correspondences,
+        &initial_points_in_field,
+        robot_position,
+        parameters.maximum_correspondence_distance,
+    ));
+
+    let mut extrinsic_rotations = initial_extrinsic_rotations;
+    for _ in 0..parameters.maximum_number_of_iterations {
+        let points_in_field = project(extrinsic_rotations);
+        let correspondences = associate(
+            &points_in_field,
+            field_marks,
+            parameters.maximum_correspondence_distance,
+        );
+        if correspondences.len() < 3 {
+            break;
+        }
+        let current_residuals = residuals(
+            &correspondences,
+            &points_in_field,
+            robot_position,
+            parameters.maximum_correspondence_distance,
+        );
+        let jacobian_columns: Vec<Vec<f32>> = (0..3)
+            .map(|axis| {
+                let mut shifted_rotations = extrinsic_rotations;
+                shifted_rotations[axis] += DIFFERENTIATION_STEP;
+                residuals(
+                    &correspondences,
+                    &project(shifted_rotations),
+                    robot_position,
+                    parameters.maximum_correspondence_distance,
+                )
+                .iter()
+                .zip(current_residuals.iter())
+                .map(|(shifted, current)| (shifted - current) / DIFFERENTIATION_STEP)
+                .collect()
+            })
+            .collect();
+        let dot = |first: &[f32], second: &[f32]| -> f32 {
+            first.iter().zip(second).map(|(a, b)| a * b).sum()
+        };
+        let normal_matrix =
+            Matrix3::from_fn(|row, column| dot(&jacobian_columns[row], &jacobian_columns[column]))
+                + Matrix3::identity() * parameters.damping;
+        let gradient = Vector3::from_fn(|row, _| dot(&jacobian_columns[row], &current_residuals));
+        let step = match normal_matrix.try_inverse() {
+            Some(inverse) => -(inverse * gradient),
+            None => break,
+        };
+        extrinsic_rotations += step;
+        if step.norm() < parameters.convergence_threshold {
+            break;
+        }
+    }
+
+    let points_in_field = project(extrinsic_rotations);
+    let correspondences = associate(
+        &points_in_field,
+        field_marks,
+        parameters.maximum_correspondence_distance,
+    );
+    let error = root_mean_square(&residuals(
+        &correspondences,
+        &points_in_field,
+        robot_position,
+        parameters.maximum_correspondence_distance,
+    ));
+    ExtrinsicCalibration {
+        number_of_collected_frames: frames.len(),
+        number_of_used_points: correspondences.len(),
+        initial_error,
+        error,
+        extrinsic_rotations,
+        is_finished: true,
+    }
+}
+
+/// Projects the line points of all frames onto the field using the given extrinsic rotations.
+/// Points which cannot be projected (e.g. above the horizon) are `None`.
+fn project_line_points_to_field(
+    frames: &[Frame],
+    robot_to_field: Isometry2<f32>,
+    camera_position: CameraPosition,
+    extrinsic_rotations: Vector3<f32>,
+) -> Vec<Vec<Option<Point2<f32>>>> {
+    let camera_to_head = camera_to_head(camera_position, extrinsic_rotations);
+    frames
+        .iter()
+        .map(|frame| {
+            let camera_matrix = frame.camera_matrix.with_camera_to_head(camera_to_head);
+            frame
+                .line_points_in_image
+                .iter()
+                .map(|point_in_image| {
+                    camera_matrix
+                        .pixel_to_ground(point_in_image)
+                        .ok()
+                        .map(|point_in_robot| robot_to_field * point_in_robot)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn associate(
+    points_in_field: &[Vec<Option<Point2<f32>>>],
+    field_marks: &[FieldMark],
+    maximum_correspondence_distance: f32,
+) -> Vec<Correspondence> {
+    points_in_field
+        .iter()
+        .enumerate()
+        .flat_map(|(frame_index, points_in_field)| {
+            points_in_field
+                .iter()
+                .enumerate()
+                .filter_map(move |(point_index, point_in_field)| {
+                    let point_in_field = (*point_in_field)?;
+                    let (field_mark, closest_distance) = field_marks
+                        .iter()
+                        .map(|field_mark| {
+                            (
+                                field_mark,
+                                distance_to_field_mark(point_in_field, field_mark),
+                            )
+                        })
+                        .min_by(|(_, first), (_, second)| first.total_cmp(second))?;
+                    (closest_distance <= maximum_correspondence_distance).then_some(
+                        Correspondence {
+                            frame_index,
+                            point_index,
+                            field_mark: *field_mark,
+                        },
+                    )
+                })
+        })
+        .collect()
+}
+
+fn residuals(
+    correspondences: &[Correspondence],
+    points_in_field: &[Vec<Option<Point2<f32>>>],
+    robot_position: Point2<f32>,
+    maximum_correspondence_distance: f32,
+) -> Vec<f32> {
+    correspondences
+        .iter()
+        .map(|correspondence| {
+            match points_in_field[correspondence.frame_index][correspondence.point_index] {
+                Some(point_in_field) => {
+                    signed_distance_to_field_mark(point_in_field, &correspondence.field_mark)
+                        / distance(&point_in_field, &robot_position)
+                }
+                None => maximum_correspondence_distance,
+            }
+        })
+        .collect()
+}
+
+/// Distance used for associating points, lines are treated as segments
+fn distance_to_field_mark(point: Point2<f32>, field_mark: &FieldMark) -> f32 {
+    match field_mark {
+        FieldMark::Line { line, .. } => line.squared_distance_to_segment(point).sqrt(),
+        FieldMark::Circle { center, radius } => (distance(center, &point) - radius).abs(),
+    }
+}
+
+/// Differentiable distance used for optimizing, lines are treated as infinite lines
+fn signed_distance_to_field_mark(point: Point2<f32>, field_mark: &FieldMark) -> f32 {
+    match field_mark {
+        FieldMark::Line { line, .. } => {
+            let direction = (line.1 - line.0).normalize();
+            let normal = vector![-direction.y, direction.x];
+            normal.dot(&(point - line.0))
+        }
+        FieldMark::Circle { center, radius } => distance(center, &point) - radius,
+    }
+}
+
+fn root_mean_square(values: &[f32]) -> f32 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    (values.iter().map(|value| value.powi(2)).sum::<f32>() / values.len() as f32).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::{point, Isometry3};
+    use types::Line;
+
+    use super::*;
+
+    fn camera_matrix(extrinsic_rotations: Vector3<f32>) -> CameraMatrix {
+        CameraMatrix::from_normalized_focal_and_center(
+            vector![0.95, 1.27],
+            point![0.5, 0.5],
+            vector![640.0, 480.0],
+            camera_to_head(CameraPosition::Top, extrinsic_rotations),
+            Isometry3::new(vector![0.0, 0.0, 0.2], vector![0.0, 0.35, 0.0]),
+            Isometry3::new(vector![0.0, 0.0, 0.33], Vector3::zeros()),
+        )
+    }
+
+    fn parameters() -> configuration::ExtrinsicCalibrator {
+        configuration::ExtrinsicCalibrator {
+            enable: true,
+            robot_position: point![-1.0, 0.5],
+            robot_orientation: 0.2,
+            number_of_frames: 1,
+            line_sampling_distance: 0.1,
+            maximum_correspondence_distance: 0.3,
+            maximum_number_of_iterations: 20,
+            convergence_threshold: 0.001,
+            damping: 0.001,
+        }
+    }
+
+    #[test]
+    fn miscalibrated_rotations_are_recovered() {
+        let field_marks = field_marks_from_field_dimensions(&FieldDimensions {
+            ball_radius: 0.05,
+            length: 9.0,
+            width: 6.0,
+            line_width: 0.05,
+            penalty_marker_size: 0.1,
+            goal_box_area_length: 0.6,
+            goal_box_area_width: 2.2,
+            penalty_area_length: 1.65,
+            penalty_area_width: 4.0,
+            penalty_marker_distance: 1.3,
+            center_circle_diameter: 1.5,
+            border_strip_width: 0.7,
+            goal_inner_width: 1.5,
+            goal_post_diameter: 0.1,
+            goal_depth: 0.5,
+        });
+        let parameters = parameters();
+        let robot_to_field = Isometry2::new(
+            parameters.robot_position.coords,
+            parameters.robot_orientation,
+        );
+        let true_rotations = vector![1.0, -2.0, 1.5];
+        let true_camera_matrix = camera_matrix(true_rotations);
+        let line_points_in_image = field_marks
+            .iter()
+            .flat_map(|field_mark| match *field_mark {
+                FieldMark::Line { line, .. } => (0..=100)
+                    .map(|index| line.0 + (line.1 - line.0) * (index as f32 / 100.0))
+                    .collect::<Vec<_>>(),
+                FieldMark::Circle { center, radius } => (0..100)
+                    .map(|index| {
+                        let angle = index as f32 / 100.0 * std::f32::consts::TAU;
+                        center + vector![angle.cos(), angle.sin()] * radius
+                    })
+                    .collect(),
+            })
+            .map(|point_in_field| robot_to_field.inverse() * point_in_field)
+            // line detection only reports lines close to the robot
+            .filter(|point_in_robot| point_in_robot.coords.norm() < 3.0)
+            .filter_map(|point_in_robot| true_camera_matrix.ground_to_pixel(&point_in_robot).ok())
+            .filter(|point| (0.0..640.0).contains(&point.x) && (0.0..480.0).contains(&point.y))
+            .collect();
+        let frames = [Frame {
+            camera_matrix: camera_matrix(Vector3::zeros()),
+            line_points_in_image,
+        }];
+
+        let calibration = calibrate(
+            &frames,
+            &field_marks,
+            robot_to_field,
+            CameraPosition::Top,
+            Vector3::zeros(),
+            &parameters,
+        );
+
+        assert!(calibration.number_of_used_points > 20);
+        assert!(calibration.error < calibration.initial_error);
+        assert!((calibration.extrinsic_rotations - true_rotations).norm() < 0.1);
+    }
+
+    #[test]
+    fn sampled_line_points_include_end_points() {
+        let camera_matrix = camera_matrix(Vector3::zeros());
+        let line = Line(point![1.0, -0.2], point![1.0, 0.2]);
+        let points = sample_line_points_in_image(&[line], &camera_matrix, 0.1);
+        assert_eq!(points.len(), 5);
+        let end_in_robot = camera_matrix.pixel_to_ground(&points[4]).unwrap();
+        assert!(distance(&end_in_robot, &line.1) < 1e-3);
+    }
+}
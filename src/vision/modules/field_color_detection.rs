@@ -1,33 +1,222 @@
 use module_derive::module;
-use types::FieldColor;
+use nalgebra::Vector5;
+use types::{CameraMatrix, FieldColor, FieldColorHistogram, Rgb, RgbChannel};
 
-pub struct FieldColorDetection;
+use crate::framework::configuration::FieldColorEstimation;
+
+pub struct FieldColorDetection {
+    filtered_thresholds: Vector5<f32>,
+}
 
 #[module(vision)]
+#[input(path = camera_matrix, data_type = CameraMatrix)]
 #[parameter(path = $this_cycler.field_color_detection.red_chromaticity_threshold, data_type = f32)]
 #[parameter(path = $this_cycler.field_color_detection.blue_chromaticity_threshold, data_type = f32)]
 #[parameter(path = $this_cycler.field_color_detection.lower_green_chromaticity_threshold, data_type = f32)]
 #[parameter(path = $this_cycler.field_color_detection.upper_green_chromaticity_threshold, data_type = f32)]
 #[parameter(path = $this_cycler.field_color_detection.green_luminance_threshold, data_type = u8)]
+#[parameter(path = $this_cycler.field_color_detection.estimation, data_type = FieldColorEstimation)]
+#[additional_output(path = field_color_histogram, data_type = FieldColorHistogram)]
 #[main_output(data_type = FieldColor)]
 impl FieldColorDetection {}
 
 impl FieldColorDetection {
-    fn new(_context: NewContext) -> anyhow::Result<Self> {
-        Ok(Self)
+    fn new(context: NewContext) -> anyhow::Result<Self> {
+        let configured_field_color = FieldColor {
+            red_chromaticity_threshold: *context.red_chromaticity_threshold,
+            blue_chromaticity_threshold: *context.blue_chromaticity_threshold,
+            lower_green_chromaticity_threshold: *context.lower_green_chromaticity_threshold,
+            upper_green_chromaticity_threshold: *context.upper_green_chromaticity_threshold,
+            green_luminance_threshold: *context.green_luminance_threshold,
+        };
+        Ok(Self {
+            filtered_thresholds: to_thresholds(&configured_field_color),
+        })
     }
 
-    fn cycle(&mut self, context: CycleContext) -> anyhow::Result<MainOutputs> {
+    fn cycle(&mut self, mut context: CycleContext) -> anyhow::Result<MainOutputs> {
+        let configured_field_color = FieldColor {
+            red_chromaticity_threshold: *context.red_chromaticity_threshold,
+            blue_chromaticity_threshold: *context.blue_chromaticity_threshold,
+            lower_green_chromaticity_threshold: *context.lower_green_chromaticity_threshold,
+            upper_green_chromaticity_threshold: *context.upper_green_chromaticity_threshold,
+            green_luminance_threshold: *context.green_luminance_threshold,
+        };
+        if !context.estimation.enable {
+            self.filtered_thresholds = to_thresholds(&configured_field_color);
+            return Ok(MainOutputs {
+                field_color: Some(configured_field_color),
+            });
+        }
+
+        let estimated_field_color = match context.camera_matrix {
+            Some(camera_matrix) => {
+                let samples =
+                    sample_below_horizon(context.image, camera_matrix, context.estimation);
+                let (estimated_field_color, histogram) =
+                    estimate_field_color(&samples, context.estimation);
+                context
+                    .field_color_histogram
+                    .fill_on_subscription(|| histogram);
+                estimated_field_color
+            }
+            None => None,
+        };
+
+        // without a camera matrix or a plausible estimate, the thresholds fade back to the
+        // configured values
+        self.filtered_thresholds = low_pass_thresholds(
+            self.filtered_thresholds,
+            estimated_field_color
+                .as_ref()
+                .unwrap_or(&configured_field_color),
+            context.estimation.smoothing_factor,
+        );
+
         Ok(MainOutputs {
-            field_color: Some(FieldColor {
-                red_chromaticity_threshold: *context.red_chromaticity_threshold,
-                blue_chromaticity_threshold: *context.blue_chromaticity_threshold,
-                lower_green_chromaticity_threshold: *context.lower_green_chromaticity_threshold,
-                upper_green_chromaticity_threshold: *context.upper_green_chromaticity_threshold,
-                green_luminance_threshold: *context.green_luminance_threshold,
-            }),
+            field_color: Some(from_thresholds(self.filtered_thresholds)),
+        })
+    }
+}
+
+fn low_pass_thresholds(
+    filtered_thresholds: Vector5<f32>,
+    target: &FieldColor,
+    smoothing_factor: f32,
+) -> Vector5<f32> {
+    let target = to_thresholds(target);
+    if target.iter().all(|threshold| threshold.is_finite()) {
+        filtered_thresholds + (target - filtered_thresholds) * smoothing_factor
+    } else {
+        filtered_thresholds
+    }
+}
+
+fn to_thresholds(field_color: &FieldColor) -> Vector5<f32> {
+    Vector5::new(
+        field_color.red_chromaticity_threshold,
+        field_color.blue_chromaticity_threshold,
+        field_color.lower_green_chromaticity_threshold,
+        field_color.upper_green_chromaticity_threshold,
+        field_color.green_luminance_threshold as f32,
+    )
+}
+
+fn from_thresholds(thresholds: Vector5<f32>) -> FieldColor {
+    FieldColor {
+        red_chromaticity_threshold: thresholds[0],
+        blue_chromaticity_threshold: thresholds[1],
+        lower_green_chromaticity_threshold: thresholds[2],
+        upper_green_chromaticity_threshold: thresholds[3],
+        green_luminance_threshold: thresholds[4].round().clamp(0.0, 255.0) as u8,
+    }
+}
+
+fn sample_below_horizon(
+    image: &Image422,
+    camera_matrix: &CameraMatrix,
+    estimation: &FieldColorEstimation,
+) -> Vec<Rgb> {
+    let width_444 = (image.width() * 2) as f32;
+    (0..image.width())
+        .step_by(estimation.horizontal_sample_stride.max(1))
+        .flat_map(|x_422| {
+            let horizon_y = camera_matrix.horizon.y_at_x((x_422 * 2) as f32, width_444)
+                + estimation.horizon_margin;
+            let start_y = horizon_y.clamp(0.0, image.height() as f32) as usize;
+            (start_y..image.height())
+                .step_by(estimation.vertical_sample_stride.max(1))
+                .map(move |y| Rgb::from(image[(x_422, y)]))
+        })
+        .collect()
+}
+
+fn histogram_bin(value: f32, number_of_bins: usize) -> usize {
+    ((value * number_of_bins as f32) as usize).min(number_of_bins - 1)
+}
+
+fn mean_and_standard_deviation(values: impl Iterator<Item = f32> + Clone) -> (f32, f32) {
+    let count = values.clone().count() as f32;
+    let mean = values.clone().sum::<f32>() / count;
+    let variance = values.map(|value| (value - mean).powi(2)).sum::<f32>() / count;
+    (mean, variance.sqrt())
+}
+
+/// Estimates thresholds from the dominant green chromaticity below the horizon. The field is
+/// assumed to cover most of the sampled pixels, so the thresholds are derived from the statistics
+/// of the samples around the histogram peak.
+fn estimate_field_color(
+    samples: &[Rgb],
+    estimation: &FieldColorEstimation,
+) -> (Option<FieldColor>, FieldColorHistogram) {
+    let number_of_bins = estimation.number_of_histogram_bins.max(1);
+    let mut histogram = FieldColorHistogram {
+        number_of_samples: samples.len(),
+        number_of_field_samples: 0,
+        green_chromaticity: vec![0; number_of_bins],
+        green_luminance: vec![0; number_of_bins],
+    };
+    for sample in samples {
+        histogram.green_chromaticity
+            [histogram_bin(sample.get_chromaticity(RgbChannel::Green), number_of_bins)] += 1;
+        histogram.green_luminance[histogram_bin(sample.g as f32 / 256.0, number_of_bins)] += 1;
+    }
+    if samples.len() < estimation.minimum_number_of_samples.max(1) {
+        return (None, histogram);
+    }
+
+    let peak_bin = histogram
+        .green_chromaticity
+        .iter()
+        .enumerate()
+        .max_by_key(|(_bin, count)| **count)
+        .map(|(bin, _count)| bin)
+        .unwrap_or_default();
+    let peak_green_chromaticity = (peak_bin as f32 + 0.5) / number_of_bins as f32;
+    if peak_green_chromaticity < estimation.minimum_peak_green_chromaticity {
+        return (None, histogram);
+    }
+
+    let field_samples: Vec<_> = samples
+        .iter()
+        .filter(|sample| {
+            (sample.get_chromaticity(RgbChannel::Green) - peak_green_chromaticity).abs()
+                <= estimation.field_cluster_width
         })
+        .collect();
+    histogram.number_of_field_samples = field_samples.len();
+    if field_samples.is_empty()
+        || (field_samples.len() as f32) < estimation.minimum_field_fraction * samples.len() as f32
+    {
+        return (None, histogram);
     }
+
+    let chromaticities = |channel| {
+        field_samples
+            .iter()
+            .map(move |sample| sample.get_chromaticity(channel))
+    };
+    let (red_mean, red_deviation) = mean_and_standard_deviation(chromaticities(RgbChannel::Red));
+    let (blue_mean, blue_deviation) = mean_and_standard_deviation(chromaticities(RgbChannel::Blue));
+    let (green_mean, green_deviation) =
+        mean_and_standard_deviation(chromaticities(RgbChannel::Green));
+    let (luminance_mean, luminance_deviation) =
+        mean_and_standard_deviation(field_samples.iter().map(|sample| sample.g as f32));
+
+    let field_color = FieldColor {
+        red_chromaticity_threshold: red_mean
+            + estimation.red_blue_chromaticity_deviation_factor * red_deviation,
+        blue_chromaticity_threshold: blue_mean
+            + estimation.red_blue_chromaticity_deviation_factor * blue_deviation,
+        lower_green_chromaticity_threshold: green_mean
+            - estimation.lower_green_chromaticity_deviation_factor * green_deviation,
+        upper_green_chromaticity_threshold: green_mean
+            - estimation.upper_green_chromaticity_deviation_factor * green_deviation,
+        green_luminance_threshold: (luminance_mean
+            - estimation.green_luminance_deviation_factor * luminance_deviation)
+            .clamp(0.0, 255.0) as u8,
+    };
+    (Some(field_color), histogram)
 }
 
 #[cfg(test)]
@@ -60,4 +249,86 @@ mod test {
         let field_color_intensity = field_color.get_intensity(ycbcr);
         assert_eq!(field_color_intensity, Intensity::Low);
     }
+
+    fn estimation() -> FieldColorEstimation {
+        FieldColorEstimation {
+            enable: true,
+            number_of_histogram_bins: 64,
+            minimum_number_of_samples: 10,
+            minimum_peak_green_chromaticity: 0.36,
+            field_cluster_width: 0.04,
+            minimum_field_fraction: 0.3,
+            lower_green_chromaticity_deviation_factor: 3.0,
+            upper_green_chromaticity_deviation_factor: 1.5,
+            red_blue_chromaticity_deviation_factor: 3.0,
+            green_luminance_deviation_factor: 3.0,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn estimate_thresholds_from_green_samples() {
+        let samples: Vec<_> = (0..80)
+            .map(|index| Rgb::new(40 + index % 5, 110 + index % 7, 45 + index % 3))
+            .chain((0..20).map(|_| Rgb::WHITE))
+            .collect();
+        let (field_color, histogram) = estimate_field_color(&samples, &estimation());
+        let field_color = field_color.expect("Expected field color estimate");
+        assert_eq!(histogram.number_of_samples, 100);
+        assert_eq!(histogram.number_of_field_samples, 80);
+        assert!(field_color.lower_green_chromaticity_threshold < 0.55);
+        assert!(
+            field_color.lower_green_chromaticity_threshold
+                < field_color.upper_green_chromaticity_threshold
+        );
+        let green = YCbCr444 {
+            y: 84,
+            cb: 106,
+            cr: 98,
+        };
+        assert_eq!(field_color.get_intensity(green), Intensity::High);
+        let white = YCbCr444 {
+            y: 255,
+            cb: 128,
+            cr: 128,
+        };
+        assert_eq!(field_color.get_intensity(white), Intensity::Low);
+    }
+
+    #[test]
+    fn no_estimate_without_dominant_green() {
+        let samples: Vec<_> = (0..100).map(|_| Rgb::WHITE).collect();
+        let (field_color, _histogram) = estimate_field_color(&samples, &estimation());
+        assert!(field_color.is_none());
+    }
+
+    #[test]
+    fn thresholds_decay_toward_configured_field_color() {
+        let estimated = FieldColor {
+            red_chromaticity_threshold: 0.3,
+            blue_chromaticity_threshold: 0.3,
+            lower_green_chromaticity_threshold: 0.45,
+            upper_green_chromaticity_threshold: 0.5,
+            green_luminance_threshold: 100,
+        };
+        let configured = FieldColor {
+            red_chromaticity_threshold: 0.37,
+            blue_chromaticity_threshold: 0.38,
+            lower_green_chromaticity_threshold: 0.4,
+            upper_green_chromaticity_threshold: 0.43,
+            green_luminance_threshold: 255,
+        };
+        let filtered = to_thresholds(&estimated);
+
+        let decayed = low_pass_thresholds(filtered, &configured, 0.1);
+
+        assert!(decayed[0] > estimated.red_chromaticity_threshold);
+        assert!(decayed[0] < configured.red_chromaticity_threshold);
+        assert!(decayed[4] > 100.0 && decayed[4] < 255.0);
+        let not_finite = FieldColor {
+            red_chromaticity_threshold: f32::NAN,
+            ..configured
+        };
+        assert_eq!(low_pass_thresholds(filtered, &not_finite, 0.1), filtered);
+    }
 }
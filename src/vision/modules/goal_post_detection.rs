@@ -0,0 +1,226 @@
+use module_derive::module;
+use nalgebra::{distance, point, Point2};
+use types::{CameraMatrix, FieldBorder, GoalPost, ImageSegments, Intensity, ScanLine};
+
+pub struct GoalPostDetection;
+
+#[module(vision)]
+#[input(path = camera_matrix, data_type = CameraMatrix, required)]
+#[input(path = field_border, data_type = FieldBorder, required)]
+#[input(path = image_segments, data_type = ImageSegments, required)]
+#[parameter(path = $this_cycler.goal_post_detection.enable, data_type = bool)]
+#[parameter(path = $this_cycler.goal_post_detection.minimum_luminance, data_type = u8)]
+#[parameter(path = $this_cycler.goal_post_detection.minimum_segment_length, data_type = u16)]
+#[parameter(path = $this_cycler.goal_post_detection.maximum_scan_line_gap, data_type = f32)]
+#[parameter(path = $this_cycler.goal_post_detection.maximum_base_height_difference, data_type = f32)]
+#[parameter(path = $this_cycler.goal_post_detection.minimum_number_of_scan_lines, data_type = usize)]
+#[parameter(path = $this_cycler.goal_post_detection.maximum_post_width, data_type = f32)]
+#[parameter(path = $this_cycler.goal_post_detection.maximum_distance, data_type = f32)]
+#[additional_output(path = goal_post_base_points, data_type = Vec<Point2<f32>>)]
+#[main_output(name = goal_posts, data_type = Vec<GoalPost>)]
+impl GoalPostDetection {}
+
+impl GoalPostDetection {
+    fn new(_context: NewContext) -> anyhow::Result<Self> {
+        Ok(Self)
+    }
+
+    fn cycle(&mut self, mut context: CycleContext) -> anyhow::Result<MainOutputs> {
+        // without a field border (e.g. in the bottom camera) nothing can stand above the field
+        if !*context.enable || context.field_border.border_lines.is_empty() {
+            return Ok(MainOutputs {
+                goal_posts: Some(vec![]),
+            });
+        }
+
+        let base_points: Vec<_> = context
+            .image_segments
+            .scan_grid
+            .vertical_scan_lines
+            .iter()
+            .filter_map(|scan_line| {
+                find_post_base(
+                    scan_line,
+                    context.field_border,
+                    *context.minimum_luminance,
+                    *context.minimum_segment_length,
+                )
+            })
+            .collect();
+        context
+            .goal_post_base_points
+            .fill_on_subscription(|| base_points.clone());
+
+        let goal_posts = cluster_base_points(
+            &base_points,
+            *context.maximum_scan_line_gap,
+            *context.maximum_base_height_difference,
+        )
+        .into_iter()
+        .filter(|cluster| cluster.len() >= *context.minimum_number_of_scan_lines)
+        .filter_map(|cluster| {
+            goal_post_from_cluster(
+                &cluster,
+                context.camera_matrix,
+                *context.maximum_post_width,
+                *context.maximum_distance,
+            )
+        })
+        .collect();
+
+        Ok(MainOutputs {
+            goal_posts: Some(goal_posts),
+        })
+    }
+}
+
+/// Finds the lower end of a bright, non-field segment which starts above the field border and
+/// ends on the field. Scan line positions are in 422 pixel coordinates.
+fn find_post_base(
+    scan_line: &ScanLine,
+    field_border: &FieldBorder,
+    minimum_luminance: u8,
+    minimum_segment_length: u16,
+) -> Option<Point2<f32>> {
+    let position = scan_line.position as f32;
+    scan_line
+        .segments
+        .iter()
+        .zip(scan_line.segments.iter().skip(1))
+        .find_map(|(segment, next_segment)| {
+            let is_post_colored = segment.field_color == Intensity::Low
+                && segment.color.y >= minimum_luminance
+                && segment.length() >= minimum_segment_length;
+            let starts_above_field_border =
+                !field_border.is_inside_field(point![position, segment.start as f32]);
+            let ends_on_field = field_border.is_inside_field(point![position, segment.end as f32])
+                && next_segment.field_color == Intensity::High;
+            (is_post_colored && starts_above_field_border && ends_on_field)
+                .then(|| point![position, segment.end as f32])
+        })
+}
+
+/// Groups base points of neighboring scan lines. The points are expected to be ordered by their
+/// scan line position.
+fn cluster_base_points(
+    base_points: &[Point2<f32>],
+    maximum_scan_line_gap: f32,
+    maximum_base_height_difference: f32,
+) -> Vec<Vec<Point2<f32>>> {
+    let mut clusters: Vec<Vec<Point2<f32>>> = vec![];
+    for &point in base_points {
+        match clusters.last_mut() {
+            Some(cluster)
+                if cluster.last().is_some_and(|last_point| {
+                    point.x - last_point.x <= maximum_scan_line_gap
+                        && (point.y - last_point.y).abs() <= maximum_base_height_difference
+                }) =>
+            {
+                cluster.push(point)
+            }
+            _ => clusters.push(vec![point]),
+        }
+    }
+    clusters
+}
+
+fn goal_post_from_cluster(
+    cluster: &[Point2<f32>],
+    camera_matrix: &CameraMatrix,
+    maximum_post_width: f32,
+    maximum_distance: f32,
+) -> Option<GoalPost> {
+    let leftmost_point = cluster.first()?;
+    let rightmost_point = cluster.last()?;
+    let lowest_y = cluster
+        .iter()
+        .map(|point| point.y)
+        .fold(f32::NEG_INFINITY, f32::max);
+    let base_in_image = point![(leftmost_point.x + rightmost_point.x) / 2.0, lowest_y];
+
+    let to_ground =
+        |point: Point2<f32>| camera_matrix.pixel_to_ground(&point![point.x * 2.0, point.y]);
+    let left_in_robot = to_ground(point![leftmost_point.x, lowest_y]).ok()?;
+    let right_in_robot = to_ground(point![rightmost_point.x, lowest_y]).ok()?;
+    if distance(&left_in_robot, &right_in_robot) > maximum_post_width {
+        return None;
+    }
+    let position_in_robot = to_ground(base_in_image).ok()?;
+    if position_in_robot.coords.norm() > maximum_distance {
+        return None;
+    }
+    Some(GoalPost {
+        position_in_robot,
+        base_in_image,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use types::{EdgeType, Line, Segment, YCbCr444};
+
+    use super::*;
+
+    fn segment(start: u16, end: u16, luminance: u8, field_color: Intensity) -> Segment {
+        Segment {
+            start,
+            end,
+            start_edge_type: EdgeType::Rising,
+            end_edge_type: EdgeType::Falling,
+            color: YCbCr444 {
+                y: luminance,
+                cb: 128,
+                cr: 128,
+            },
+            field_color,
+        }
+    }
+
+    fn horizontal_field_border(y: f32) -> FieldBorder {
+        FieldBorder {
+            border_lines: vec![Line(point![0.0, y], point![320.0, y])],
+        }
+    }
+
+    #[test]
+    fn white_segment_crossing_field_border_is_post_base() {
+        let scan_line = ScanLine {
+            position: 100,
+            segments: vec![
+                segment(0, 50, 80, Intensity::Low),
+                segment(50, 130, 200, Intensity::Low),
+                segment(130, 480, 90, Intensity::High),
+            ],
+        };
+        let base = find_post_base(&scan_line, &horizontal_field_border(100.0), 150, 20);
+        assert_eq!(base, Some(point![100.0, 130.0]));
+    }
+
+    #[test]
+    fn white_segment_on_field_is_no_post_base() {
+        let scan_line = ScanLine {
+            position: 100,
+            segments: vec![
+                segment(0, 110, 80, Intensity::Low),
+                segment(110, 140, 200, Intensity::Low),
+                segment(140, 480, 90, Intensity::High),
+            ],
+        };
+        let base = find_post_base(&scan_line, &horizontal_field_border(100.0), 150, 20);
+        assert_eq!(base, None);
+    }
+
+    #[test]
+    fn base_points_of_distant_scan_lines_are_separate_clusters() {
+        let base_points = [
+            point![10.0, 130.0],
+            point![14.0, 131.0],
+            point![18.0, 130.0],
+            point![60.0, 130.0],
+        ];
+        let clusters = cluster_base_points(&base_points, 8.0, 10.0);
+        assert_eq!(clusters.len(), 2);
+        assert_eq!(clusters[0].len(), 3);
+        assert_eq!(clusters[1].len(), 1);
+    }
+}
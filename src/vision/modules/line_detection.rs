@@ -3,7 +3,10 @@ use std::{collections::HashSet, ops::Range};
 use module_derive::{module, require_some};
 use nalgebra::{distance, point, vector, Point2, Vector2};
 use ordered_float::NotNan;
-use types::{CameraMatrix, EdgeType, FilteredSegments, ImageLines, Line, LineData, Segment};
+use types::{
+    line_intersections, CameraMatrix, EdgeType, FilteredSegments, ImageLines, Line, LineData,
+    Segment,
+};
 
 use crate::{Ransac, RansacResult};
 
@@ -17,6 +20,8 @@ pub struct LineDetection;
 #[parameter(path = $this_cycler.line_detection.check_line_length, data_type = bool)]
 #[parameter(path = $this_cycler.line_detection.check_line_distance, data_type = bool)]
 #[parameter(path = $this_cycler.line_detection.gradient_alignment, data_type = f32)]
+#[parameter(path = $this_cycler.line_detection.intersection_angle_tolerance, data_type = f32)]
+#[parameter(path = $this_cycler.line_detection.intersection_endpoint_tolerance, data_type = f32)]
 #[parameter(path = $this_cycler.line_detection.maximum_distance_to_robot, data_type = f32)]
 #[parameter(path = $this_cycler.line_detection.maximum_fit_distance_in_pixels, data_type = f32)]
 #[parameter(path = $this_cycler.line_detection.maximum_gap_on_line, data_type = f32)]
@@ -135,9 +140,15 @@ impl LineDetection {
                     .push(Line(start_point_in_image, end_point_in_image));
             }
         }
+        let intersections_in_robot = line_intersections(
+            &lines_in_robot,
+            *context.intersection_angle_tolerance,
+            *context.intersection_endpoint_tolerance,
+        );
         let line_data = LineData {
             lines_in_robot,
             used_vertical_filtered_segments,
+            intersections_in_robot,
         };
         context
             .lines_in_image
@@ -366,6 +377,8 @@ mod tests {
             check_line_length: &false,
             check_line_segments_projection: &false,
             gradient_alignment: &-0.95,
+            intersection_angle_tolerance: &0.3,
+            intersection_endpoint_tolerance: &0.2,
             maximum_distance_to_robot: &0.3,
             maximum_fit_distance_in_pixels: &3.0,
             maximum_gap_on_line: &30.0,
@@ -1,9 +1,12 @@
 pub mod ball_detection;
 pub mod camera_matrix_provider;
+pub mod extrinsic_calibrator;
 pub mod field_border_detection;
 pub mod field_color_detection;
+pub mod goal_post_detection;
 pub mod image_segmenter;
 pub mod line_detection;
+pub mod penalty_mark_detection;
 pub mod perspective_grid_candidates_provider;
 pub mod robot_detection;
 pub mod segment_filter;
@@ -0,0 +1,169 @@
+use module_derive::{module, require_some};
+use nalgebra::{distance, point, Point2};
+use types::{CameraMatrix, EdgeType, FilteredSegments, LineData, PenaltyMark, Segment};
+
+pub struct PenaltyMarkDetection;
+
+#[module(vision)]
+#[input(path = camera_matrix, data_type = CameraMatrix)]
+#[input(path = filtered_segments, data_type = FilteredSegments)]
+#[input(path = line_data, data_type = LineData)]
+#[parameter(path = $this_cycler.penalty_mark_detection.enable, data_type = bool)]
+#[parameter(path = $this_cycler.penalty_mark_detection.maximum_projected_segment_length, data_type = f32)]
+#[parameter(path = $this_cycler.penalty_mark_detection.minimum_distance_to_lines, data_type = f32)]
+#[parameter(path = $this_cycler.penalty_mark_detection.maximum_cluster_radius, data_type = f32)]
+#[parameter(path = $this_cycler.penalty_mark_detection.minimum_number_of_points, data_type = usize)]
+#[parameter(path = $this_cycler.penalty_mark_detection.maximum_distance_to_robot, data_type = f32)]
+#[main_output(name = penalty_marks, data_type = Vec<PenaltyMark>)]
+impl PenaltyMarkDetection {}
+
+impl PenaltyMarkDetection {
+    fn new(_context: NewContext) -> anyhow::Result<Self> {
+        Ok(Self)
+    }
+
+    fn cycle(&mut self, context: CycleContext) -> anyhow::Result<MainOutputs> {
+        if !*context.enable {
+            return Ok(MainOutputs {
+                penalty_marks: Some(vec![]),
+            });
+        }
+        let camera_matrix = require_some!(context.camera_matrix);
+        let filtered_segments = require_some!(context.filtered_segments);
+        let line_data = require_some!(context.line_data);
+
+        let candidates: Vec<_> = filtered_segments
+            .scan_grid
+            .vertical_scan_lines
+            .iter()
+            .flat_map(|scan_line| {
+                scan_line.segments.iter().filter_map(|segment| {
+                    project_candidate(
+                        segment,
+                        scan_line.position,
+                        camera_matrix,
+                        *context.maximum_projected_segment_length,
+                    )
+                })
+            })
+            .filter(|candidate| {
+                candidate.position_in_robot.coords.norm() <= *context.maximum_distance_to_robot
+                    && line_data.lines_in_robot.iter().all(|line| {
+                        line.squared_distance_to_segment(candidate.position_in_robot)
+                            >= context.minimum_distance_to_lines.powi(2)
+                    })
+            })
+            .collect();
+
+        let penalty_marks = cluster_candidates(&candidates, *context.maximum_cluster_radius)
+            .into_iter()
+            .filter(|cluster| cluster.len() >= *context.minimum_number_of_points)
+            .map(|cluster| mean_of_cluster(&cluster))
+            .collect();
+
+        Ok(MainOutputs {
+            penalty_marks: Some(penalty_marks),
+        })
+    }
+}
+
+/// Projects a bright segment to the ground if it is short enough to be part of a penalty mark.
+/// Scan line positions are in 422 pixel coordinates.
+fn project_candidate(
+    segment: &Segment,
+    scan_line_position: u16,
+    camera_matrix: &CameraMatrix,
+    maximum_projected_segment_length: f32,
+) -> Option<PenaltyMark> {
+    if segment.start_edge_type != EdgeType::Rising || segment.end_edge_type != EdgeType::Falling {
+        return None;
+    }
+    let x = scan_line_position as f32 * 2.0;
+    let start_in_robot = camera_matrix
+        .pixel_to_ground(&point![x, segment.start as f32])
+        .ok()?;
+    let end_in_robot = camera_matrix
+        .pixel_to_ground(&point![x, segment.end as f32])
+        .ok()?;
+    if distance(&start_in_robot, &end_in_robot) > maximum_projected_segment_length {
+        return None;
+    }
+    let center_in_image = point![scan_line_position as f32, segment.center() as f32];
+    let position_in_robot = camera_matrix
+        .pixel_to_ground(&point![x, center_in_image.y])
+        .ok()?;
+    Some(PenaltyMark {
+        position_in_robot,
+        center_in_image,
+    })
+}
+
+/// Greedily assigns each candidate to the first cluster whose mean is within the radius.
+fn cluster_candidates(candidates: &[PenaltyMark], maximum_radius: f32) -> Vec<Vec<PenaltyMark>> {
+    let mut clusters: Vec<Vec<PenaltyMark>> = vec![];
+    for &candidate in candidates {
+        let matching_cluster = clusters.iter_mut().find(|cluster| {
+            distance(
+                &mean_of_cluster(cluster).position_in_robot,
+                &candidate.position_in_robot,
+            ) <= maximum_radius
+        });
+        match matching_cluster {
+            Some(cluster) => cluster.push(candidate),
+            None => clusters.push(vec![candidate]),
+        }
+    }
+    clusters
+}
+
+fn mean_of_cluster(cluster: &[PenaltyMark]) -> PenaltyMark {
+    let number_of_points = cluster.len() as f32;
+    let (position_sum, center_sum) = cluster.iter().fold(
+        (Point2::origin(), Point2::origin()),
+        |(position_sum, center_sum): (Point2<f32>, Point2<f32>), candidate| {
+            (
+                position_sum + candidate.position_in_robot.coords,
+                center_sum + candidate.center_in_image.coords,
+            )
+        },
+    );
+    PenaltyMark {
+        position_in_robot: position_sum / number_of_points,
+        center_in_image: center_sum / number_of_points,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+
+    use super::*;
+
+    fn candidate(x: f32, y: f32) -> PenaltyMark {
+        PenaltyMark {
+            position_in_robot: point![x, y],
+            center_in_image: point![x * 10.0, y * 10.0],
+        }
+    }
+
+    #[test]
+    fn nearby_candidates_form_one_cluster() {
+        let candidates = [
+            candidate(2.0, 0.0),
+            candidate(2.03, 0.02),
+            candidate(1.98, -0.01),
+            candidate(3.0, 1.0),
+        ];
+        let clusters = cluster_candidates(&candidates, 0.1);
+        assert_eq!(clusters.len(), 2);
+        assert_eq!(clusters[0].len(), 3);
+        assert_eq!(clusters[1].len(), 1);
+    }
+
+    #[test]
+    fn cluster_mean_is_average_of_candidates() {
+        let mean = mean_of_cluster(&[candidate(1.0, 0.0), candidate(2.0, 1.0)]);
+        assert_relative_eq!(mean.position_in_robot, point![1.5, 0.5]);
+        assert_relative_eq!(mean.center_in_image, point![15.0, 5.0]);
+    }
+}
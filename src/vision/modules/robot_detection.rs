@@ -1,96 +1,236 @@
 use std::ops::Range;
 
-use module_derive::module;
-use nalgebra::{distance, point};
+use anyhow::bail;
+use module_derive::{module, require_some};
+use nalgebra::{distance, point, vector, Point2};
+use spl_network::TeamColor;
 use types::{
-    Ball, CameraMatrix, ClusterCone, DetectedRobots, EdgeType, FieldDimensions, FilteredSegments,
-    LineData, ScoredCluster, ScoredClusterPoint,
+    Ball, CameraMatrix, Circle, ClusterCone, DetectedRobot, DetectedRobots, EdgeType,
+    FilteredSegments, GameControllerState, LineData, PerspectiveGridCandidates, Rgb, ScoredCluster,
+    ScoredClusterPoint,
 };
 
-use crate::statistics::{mean, standard_deviation};
+use crate::{
+    framework::configuration::{
+        RobotDetection as RobotDetectionConfiguration, RobotDetectionHeuristic,
+        RobotDetectionMethod,
+    },
+    statistics::{mean, standard_deviation},
+    vision::neural_network::{Network, NeuralNetwork},
+};
+
+use super::ball_detection::{sample_grayscale, Sample, SAMPLE_SIZE};
+
+pub struct RobotDetection {
+    neural_network: Option<Network>,
+}
 
-pub struct RobotDetection;
+#[cfg(feature = "compiled-nn")]
+unsafe impl Send for RobotDetection {}
 
 #[module(vision)]
-#[input(path = balls, data_type = Vec<Ball>, required)]
-#[input(path = camera_matrix, data_type = CameraMatrix, required)]
-#[input(path = filtered_segments, data_type = FilteredSegments, required)]
-#[input(path = line_data, data_type = LineData, required)]
-#[parameter(path = $this_cycler.robot_detection.enable, data_type = bool)]
-#[parameter(path = $this_cycler.robot_detection.amount_of_segments_factor, data_type = f32)]
-#[parameter(path = $this_cycler.robot_detection.amount_score_exponent, data_type = f32)]
-#[parameter(path = $this_cycler.robot_detection.cluster_cone_radius, data_type = f32)]
-#[parameter(path = $this_cycler.robot_detection.cluster_distance_score_range, data_type = Range<f32>)]
-#[parameter(path = $this_cycler.robot_detection.detection_box_width, data_type = f32)]
-#[parameter(path = $this_cycler.robot_detection.ignore_ball_segments, data_type = bool)]
-#[parameter(path = $this_cycler.robot_detection.ignore_line_segments, data_type = bool)]
-#[parameter(path = $this_cycler.robot_detection.luminance_score_exponent, data_type = f32)]
-#[parameter(path = $this_cycler.robot_detection.maximum_cluster_distance, data_type = f32)]
-#[parameter(path = $this_cycler.robot_detection.minimum_cluster_score, data_type = f32)]
-#[parameter(path = $this_cycler.robot_detection.minimum_consecutive_segments, data_type = usize)]
-#[parameter(path = field_dimensions, data_type = FieldDimensions)]
+#[input(path = balls, data_type = Vec<Ball>)]
+#[input(path = filtered_segments, data_type = FilteredSegments)]
+#[input(path = line_data, data_type = LineData)]
+#[input(path = perspective_grid_candidates, data_type = PerspectiveGridCandidates)]
+#[input(path = camera_matrix, data_type = CameraMatrix)]
+#[input(path = game_controller_state, data_type = GameControllerState, cycler = control)]
+#[parameter(path = $this_cycler.robot_detection, data_type = RobotDetectionConfiguration)]
 #[additional_output(path = robot_detection.cluster_points_in_pixel, data_type = Vec<ScoredClusterPoint>)]
 #[additional_output(path = robot_detection.clustered_cluster_points_in_ground, data_type = Vec<Vec<ScoredClusterPoint>>)]
 #[additional_output(path = robot_detection.cluster_cones, data_type = Vec<ClusterCone>)]
+#[additional_output(path = robot_detection_candidates, data_type = Vec<DetectedRobot>)]
 #[main_output(data_type = DetectedRobots)]
 impl RobotDetection {}
 
 impl RobotDetection {
     fn new(_context: NewContext) -> anyhow::Result<Self> {
-        Ok(Self)
+        Ok(Self {
+            neural_network: None,
+        })
     }
 
     fn cycle(&mut self, mut context: CycleContext) -> anyhow::Result<MainOutputs> {
-        if !*context.enable {
-            return Ok(MainOutputs::none());
+        if !context.robot_detection.enable {
+            return Ok(MainOutputs {
+                detected_robots: Some(Default::default()),
+            });
         }
+        let camera_matrix = require_some!(context.camera_matrix);
 
-        let mut scored_cluster_points_in_pixel = extract_segment_cluster_points(
-            context.filtered_segments,
-            *context.minimum_consecutive_segments,
-            *context.amount_of_segments_factor,
-            *context.ignore_ball_segments,
-            *context.ignore_line_segments,
-            context.balls,
-            context.line_data,
-        );
-        scored_cluster_points_in_pixel.sort_unstable_by(|left_point, right_point| {
-            right_point.point.y.total_cmp(&left_point.point.y)
-        });
-        context
-            .cluster_points_in_pixel
-            .fill_on_subscription(|| scored_cluster_points_in_pixel.clone());
-
-        let scored_cluster_points_in_ground =
-            project_to_ground(scored_cluster_points_in_pixel, context.camera_matrix);
-
-        let clustered_cluster_points_in_ground = cluster_scored_cluster_points(
-            scored_cluster_points_in_ground,
-            *context.maximum_cluster_distance,
-            context.cluster_distance_score_range,
-            *context.amount_score_exponent,
-            *context.luminance_score_exponent,
-        );
-        context
-            .clustered_cluster_points_in_ground
-            .fill_on_subscription(|| clustered_cluster_points_in_ground.clone());
-
-        let clusters_in_ground =
-            map_clustered_cluster_points_to_scored_clusters(clustered_cluster_points_in_ground);
-        let clusters_in_ground =
-            filter_clusters_via_scores(clusters_in_ground, *context.minimum_cluster_score);
-        let (clusters_in_ground, cluster_cones) =
-            filter_clusters_via_cones(clusters_in_ground, *context.cluster_cone_radius);
-        context.cluster_cones.fill_on_subscription(|| cluster_cones);
+        let mut robots = match context.robot_detection.method {
+            RobotDetectionMethod::Heuristic => {
+                let parameters = &context.robot_detection.heuristic;
+                let mut scored_cluster_points_in_pixel = extract_segment_cluster_points(
+                    require_some!(context.filtered_segments),
+                    parameters.minimum_consecutive_segments,
+                    parameters.amount_of_segments_factor,
+                    parameters.ignore_line_segments,
+                    parameters.ignore_ball_segments,
+                    require_some!(context.balls),
+                    require_some!(context.line_data),
+                );
+                scored_cluster_points_in_pixel.sort_unstable_by(|left_point, right_point| {
+                    right_point.point.y.total_cmp(&left_point.point.y)
+                });
+                context
+                    .cluster_points_in_pixel
+                    .fill_on_subscription(|| scored_cluster_points_in_pixel.clone());
+                let clusters = detect_with_heuristic(
+                    scored_cluster_points_in_pixel,
+                    camera_matrix,
+                    parameters,
+                );
+                context
+                    .clustered_cluster_points_in_ground
+                    .fill_on_subscription(|| clusters.clustered_cluster_points_in_ground);
+                context
+                    .cluster_cones
+                    .fill_on_subscription(|| clusters.cluster_cones);
+                clusters
+                    .clusters_in_ground
+                    .iter()
+                    .filter_map(|cluster| {
+                        scored_cluster_to_robot(cluster, camera_matrix, parameters)
+                    })
+                    .collect()
+            }
+            RobotDetectionMethod::NeuralNetwork => {
+                let candidates = &require_some!(context.perspective_grid_candidates).candidates;
+                if self.neural_network.is_none() {
+                    self.neural_network =
+                        Some(Network::from_file(&context.robot_detection.neural_network)?);
+                }
+                let network = self.neural_network.as_mut().unwrap();
+
+                let mut detections = vec![];
+                for candidate in candidates.iter().take(
+                    context
+                        .robot_detection
+                        .maximum_number_of_candidate_evaluations,
+                ) {
+                    let enlarged_candidate = Circle {
+                        center: candidate.center,
+                        radius: candidate.radius
+                            * context.robot_detection.candidate_radius_enlargement_factor,
+                    };
+                    let sample = sample_grayscale(context.image, enlarged_candidate);
+                    let (confidence, feet_in_sample) = classify_sample(network, &sample)?;
+                    if confidence < context.robot_detection.confidence_threshold {
+                        continue;
+                    }
+                    let feet_in_image = sample_to_image(feet_in_sample, enlarged_candidate);
+                    let position = match camera_matrix.pixel_to_ground(&feet_in_image) {
+                        Ok(position) => position,
+                        Err(_) => continue,
+                    };
+                    if position.coords.norm() > context.robot_detection.maximum_distance_to_robot {
+                        continue;
+                    }
+                    detections.push(DetectedRobot {
+                        position,
+                        image_location: Circle {
+                            center: feet_in_image,
+                            radius: enlarged_candidate.radius,
+                        },
+                        confidence,
+                        jersey_color: None,
+                    });
+                }
+                context
+                    .robot_detection_candidates
+                    .fill_on_subscription(|| detections.clone());
+
+                merge_detections(&detections, context.robot_detection.cluster_merge_distance)
+            }
+        };
+
+        if let Some(game_controller_state) = context.game_controller_state {
+            let team_colors = [
+                game_controller_state.hulks_team_color,
+                game_controller_state.opponent_team_color,
+            ];
+            for robot in robots.iter_mut() {
+                let jersey_colors = sample_jersey(
+                    context.image,
+                    camera_matrix,
+                    robot.position,
+                    &context.robot_detection.jersey_height,
+                    context.robot_detection.jersey_width,
+                    context.robot_detection.jersey_samples_per_axis,
+                );
+                robot.jersey_color = classify_jersey_color(
+                    &jersey_colors,
+                    team_colors,
+                    context.robot_detection.maximum_jersey_color_distance,
+                    context.robot_detection.minimum_jersey_vote_ratio,
+                );
+            }
+        }
 
         Ok(MainOutputs {
-            detected_robots: Some(DetectedRobots {
-                robot_positions: clusters_in_ground,
-            }),
+            detected_robots: Some(DetectedRobots { robots }),
         })
     }
 }
 
+struct HeuristicClusters {
+    clustered_cluster_points_in_ground: Vec<Vec<ScoredClusterPoint>>,
+    clusters_in_ground: Vec<ScoredCluster>,
+    cluster_cones: Vec<ClusterCone>,
+}
+
+fn detect_with_heuristic(
+    scored_cluster_points_in_pixel: Vec<ScoredClusterPoint>,
+    camera_matrix: &CameraMatrix,
+    parameters: &RobotDetectionHeuristic,
+) -> HeuristicClusters {
+    let scored_cluster_points_in_ground =
+        project_to_ground(scored_cluster_points_in_pixel, camera_matrix);
+
+    let clustered_cluster_points_in_ground = cluster_scored_cluster_points(
+        scored_cluster_points_in_ground,
+        parameters.maximum_cluster_distance,
+        &parameters.cluster_distance_score_range,
+        parameters.amount_score_exponent,
+        parameters.luminance_score_exponent,
+    );
+
+    let clusters_in_ground =
+        map_clustered_cluster_points_to_scored_clusters(clustered_cluster_points_in_ground.clone());
+    let clusters_in_ground =
+        filter_clusters_via_scores(clusters_in_ground, parameters.minimum_cluster_score);
+    let (clusters_in_ground, cluster_cones) =
+        filter_clusters_via_cones(clusters_in_ground, parameters.cluster_cone_radius);
+    HeuristicClusters {
+        clustered_cluster_points_in_ground,
+        clusters_in_ground,
+        cluster_cones,
+    }
+}
+
+/// The image location spans the cluster cone radius around the projected cluster center
+fn scored_cluster_to_robot(
+    cluster: &ScoredCluster,
+    camera_matrix: &CameraMatrix,
+    parameters: &RobotDetectionHeuristic,
+) -> Option<DetectedRobot> {
+    let center_in_image = camera_matrix.ground_to_pixel(&cluster.center).ok()?;
+    let cone = ClusterCone::from_cluster(cluster, parameters.cluster_cone_radius);
+    let left_in_image = camera_matrix
+        .ground_to_pixel(&Point2::from(cone.left))
+        .ok()?;
+    Some(DetectedRobot {
+        position: cluster.center,
+        image_location: Circle {
+            center: center_in_image,
+            radius: distance(&center_in_image, &left_in_image),
+        },
+        confidence: 1.0,
+        jersey_color: None,
+    })
+}
+
 fn extract_segment_cluster_points(
     filtered_segments: &FilteredSegments,
     minimum_consecutive_segments: usize,
@@ -144,13 +284,13 @@ fn extract_segment_cluster_points(
             }
             None => vec![],
         };
-        let last_cluster_too_short = clusters.last().map_or(false, |last_cluster| {
-            last_cluster.len() < minimum_consecutive_segments
-        });
+        let last_cluster_too_short = clusters
+            .last()
+            .is_some_and(|last_cluster| last_cluster.len() < minimum_consecutive_segments);
         if last_cluster_too_short {
             clusters.pop();
         }
-        let last_cluster_reaches_border = clusters.last().map_or(false, |last_cluster| {
+        let last_cluster_reaches_border = clusters.last().is_some_and(|last_cluster| {
             let edge_type = last_cluster.last().unwrap().end_edge_type;
             edge_type == EdgeType::ImageBorder || edge_type == EdgeType::LimbBorder
         });
@@ -304,3 +444,261 @@ fn filter_clusters_via_cones(
             (clusters, cones)
         })
 }
+
+/// Returns the robot confidence and the position of the feet in normalized sample coordinates
+fn classify_sample(
+    network: &mut impl NeuralNetwork,
+    sample: &Sample,
+) -> anyhow::Result<(f32, Point2<f32>)> {
+    let input = network.input();
+    if input.len() != SAMPLE_SIZE * SAMPLE_SIZE {
+        bail!(
+            "Robot detection network has {} inputs instead of {}",
+            input.len(),
+            SAMPLE_SIZE * SAMPLE_SIZE
+        );
+    }
+    for y in 0..SAMPLE_SIZE {
+        for x in 0..SAMPLE_SIZE {
+            input[x + y * SAMPLE_SIZE] = sample[y][x];
+        }
+    }
+    network.apply()?;
+    match *network.output() {
+        [confidence, feet_x, feet_y] => Ok((confidence, point![feet_x, feet_y])),
+        ref output => bail!(
+            "Robot detection network has {} outputs instead of 3",
+            output.len()
+        ),
+    }
+}
+
+fn sample_to_image(point_in_sample: Point2<f32>, sampled_circle: Circle) -> Point2<f32> {
+    sampled_circle.center
+        + (point_in_sample.coords - vector![0.5, 0.5]) * (sampled_circle.radius * 2.0)
+}
+
+/// Merges detections closer than the merge distance on the ground into one robot, weighted by
+/// confidence. Several candidates usually fire on the feet and legs of the same robot.
+fn merge_detections(detections: &[DetectedRobot], merge_distance: f32) -> Vec<DetectedRobot> {
+    let mut sorted_detections = detections.to_vec();
+    sorted_detections.sort_by(|left, right| right.confidence.total_cmp(&left.confidence));
+
+    let mut clusters: Vec<Vec<DetectedRobot>> = vec![];
+    for detection in sorted_detections {
+        let matching_cluster = clusters
+            .iter_mut()
+            .find(|cluster| distance(&cluster[0].position, &detection.position) <= merge_distance);
+        match matching_cluster {
+            Some(cluster) => cluster.push(detection),
+            None => clusters.push(vec![detection]),
+        }
+    }
+
+    clusters
+        .into_iter()
+        .map(|cluster| {
+            let confidence_sum: f32 = cluster.iter().map(|detection| detection.confidence).sum();
+            let position = cluster.iter().fold(Point2::origin(), |sum, detection| {
+                sum + detection.position.coords * detection.confidence / confidence_sum
+            });
+            DetectedRobot {
+                position,
+                ..cluster[0]
+            }
+        })
+        .collect()
+}
+
+/// Samples colors on a grid at jersey height above the detected feet, spanning the jersey width
+/// perpendicular to the viewing direction
+fn sample_jersey(
+    image: &Image422,
+    camera_matrix: &CameraMatrix,
+    position: Point2<f32>,
+    jersey_height: &Range<f32>,
+    jersey_width: f32,
+    samples_per_axis: usize,
+) -> Vec<Rgb> {
+    let lateral_direction = vector![-position.y, position.x]
+        .try_normalize(f32::EPSILON)
+        .unwrap_or_else(|| vector![0.0, 1.0]);
+    let steps: Vec<f32> = (0..samples_per_axis)
+        .map(|index| {
+            if samples_per_axis > 1 {
+                index as f32 / (samples_per_axis - 1) as f32
+            } else {
+                0.5
+            }
+        })
+        .collect();
+    steps
+        .iter()
+        .flat_map(|height_step| {
+            steps
+                .iter()
+                .map(move |lateral_step| (height_step, lateral_step))
+        })
+        .filter_map(|(height_step, lateral_step)| {
+            let height =
+                jersey_height.start + height_step * (jersey_height.end - jersey_height.start);
+            let ground_position =
+                position + lateral_direction * (lateral_step - 0.5) * jersey_width;
+            let pixel = camera_matrix
+                .ground_with_z_to_pixel(&ground_position, height)
+                .ok()?;
+            if pixel.x < 0.0 || pixel.y < 0.0 {
+                return None;
+            }
+            image.try_at(point![pixel.x / 2.0, pixel.y]).map(Rgb::from)
+        })
+        .collect()
+}
+
+/// Lets every sampled color vote for the closer of both team colors and returns the winner if
+/// it received enough of all votes
+fn classify_jersey_color(
+    colors: &[Rgb],
+    team_colors: [TeamColor; 2],
+    maximum_color_distance: f32,
+    minimum_vote_ratio: f32,
+) -> Option<TeamColor> {
+    if colors.is_empty() {
+        return None;
+    }
+    let mut votes = [0; 2];
+    for &color in colors {
+        let distances = team_colors.map(|team_color| color_distance(color, team_color.into()));
+        let closest_team = if distances[0] <= distances[1] { 0 } else { 1 };
+        if distances[closest_team] <= maximum_color_distance {
+            votes[closest_team] += 1;
+        }
+    }
+    let winning_team = if votes[0] >= votes[1] { 0 } else { 1 };
+    (votes[winning_team] as f32 / colors.len() as f32 >= minimum_vote_ratio)
+        .then_some(team_colors[winning_team])
+}
+
+fn color_distance(left: Rgb, right: Rgb) -> f32 {
+    let difference = vector![
+        left.r as f32 - right.r as f32,
+        left.g as f32 - right.g as f32,
+        left.b as f32 - right.b as f32
+    ];
+    difference.norm()
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+
+    use super::*;
+
+    fn detection(x: f32, y: f32, confidence: f32) -> DetectedRobot {
+        DetectedRobot {
+            position: point![x, y],
+            image_location: Circle {
+                center: point![x * 100.0, y * 100.0],
+                radius: 10.0,
+            },
+            confidence,
+            jersey_color: None,
+        }
+    }
+
+    struct FixedOutputNetwork {
+        input: Vec<f32>,
+        output: Vec<f32>,
+    }
+
+    impl NeuralNetwork for FixedOutputNetwork {
+        fn from_file(path: impl AsRef<std::path::Path>) -> anyhow::Result<Self> {
+            bail!(
+                "Fixed output network cannot be loaded from {}",
+                path.as_ref().display()
+            )
+        }
+
+        fn input(&mut self) -> &mut [f32] {
+            &mut self.input
+        }
+
+        fn apply(&mut self) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        fn output(&mut self) -> &[f32] {
+            &self.output
+        }
+    }
+
+    #[test]
+    fn networks_of_wrong_shape_are_rejected() {
+        let sample = [[0.0; SAMPLE_SIZE]; SAMPLE_SIZE];
+        let mut network = FixedOutputNetwork {
+            input: vec![0.0; SAMPLE_SIZE * SAMPLE_SIZE],
+            output: vec![0.9, 0.5, 0.75],
+        };
+        let (confidence, feet_in_sample) = classify_sample(&mut network, &sample).unwrap();
+        assert_relative_eq!(confidence, 0.9);
+        assert_relative_eq!(feet_in_sample, point![0.5, 0.75]);
+
+        network.output = vec![0.9];
+        assert!(classify_sample(&mut network, &sample).is_err());
+        network.output = vec![0.9, 0.5, 0.75];
+        network.input = vec![0.0; 16];
+        assert!(classify_sample(&mut network, &sample).is_err());
+    }
+
+    #[test]
+    fn sample_center_maps_to_circle_center() {
+        let circle = Circle {
+            center: point![100.0, 50.0],
+            radius: 20.0,
+        };
+        assert_relative_eq!(sample_to_image(point![0.5, 0.5], circle), circle.center);
+        assert_relative_eq!(
+            sample_to_image(point![0.0, 1.0], circle),
+            point![80.0, 70.0]
+        );
+    }
+
+    #[test]
+    fn close_detections_are_merged_weighted_by_confidence() {
+        let robots = merge_detections(
+            &[
+                detection(2.0, 0.0, 0.5),
+                detection(2.2, 0.0, 1.0),
+                detection(3.0, 1.0, 0.9),
+            ],
+            0.3,
+        );
+
+        assert_eq!(robots.len(), 2);
+        assert_relative_eq!(robots[0].confidence, 1.0);
+        assert_relative_eq!(robots[0].position, point![2.0 + 0.2 * 2.0 / 3.0, 0.0]);
+        assert_relative_eq!(robots[1].position, point![3.0, 1.0]);
+    }
+
+    #[test]
+    fn jersey_color_is_classified_by_majority_of_close_colors() {
+        let blue = Rgb::new(10, 70, 160);
+        let red = Rgb::new(180, 30, 40);
+        let green = Rgb::new(0, 255, 0);
+        let team_colors = [TeamColor::Blue, TeamColor::Red];
+
+        assert_eq!(
+            classify_jersey_color(&[blue, blue, blue, red], team_colors, 90.0, 0.6),
+            Some(TeamColor::Blue)
+        );
+        assert_eq!(
+            classify_jersey_color(&[red, red, blue, green], team_colors, 90.0, 0.6),
+            None
+        );
+        assert_eq!(
+            classify_jersey_color(&[red, red, red, green], team_colors, 90.0, 0.6),
+            Some(TeamColor::Red)
+        );
+        assert_eq!(classify_jersey_color(&[], team_colors, 90.0, 0.6), None);
+    }
+}
@@ -0,0 +1,60 @@
+use std::path::Path;
+
+use anyhow::Result;
+
+/// Inference backend for the networks stored in `etc/neural_networks`
+///
+/// With the `compiled-nn` feature (enabled for NAO and Webots builds) networks are JIT-compiled
+/// via `compiled_nn`, which only supports x86. Otherwise the portable CPU interpreter from the
+/// `neural_network` crate is used, e.g. for tests and the behavior simulator.
+pub trait NeuralNetwork: Sized {
+    fn from_file(path: impl AsRef<Path>) -> Result<Self>;
+    fn input(&mut self) -> &mut [f32];
+    fn apply(&mut self) -> Result<()>;
+    fn output(&mut self) -> &[f32];
+}
+
+#[cfg(feature = "compiled-nn")]
+pub type Network = compiled_nn::CompiledNN;
+#[cfg(not(feature = "compiled-nn"))]
+pub type Network = neural_network::Network;
+
+#[cfg(feature = "compiled-nn")]
+impl NeuralNetwork for compiled_nn::CompiledNN {
+    fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let mut network = Self::default();
+        network.compile(path);
+        Ok(network)
+    }
+
+    fn input(&mut self) -> &mut [f32] {
+        compiled_nn::CompiledNN::input(self, 0)
+    }
+
+    fn apply(&mut self) -> Result<()> {
+        compiled_nn::CompiledNN::apply(self);
+        Ok(())
+    }
+
+    fn output(&mut self) -> &[f32] {
+        compiled_nn::CompiledNN::output(self, 0)
+    }
+}
+
+impl NeuralNetwork for neural_network::Network {
+    fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        neural_network::Network::load(path)
+    }
+
+    fn input(&mut self) -> &mut [f32] {
+        neural_network::Network::input(self)
+    }
+
+    fn apply(&mut self) -> Result<()> {
+        neural_network::Network::apply(self)
+    }
+
+    fn output(&mut self) -> &[f32] {
+        neural_network::Network::output(self)
+    }
+}
@@ -32,7 +32,7 @@ export default function BallFilter({
     }
     const unsubscribeBallFilter = connection.subscribeOutput(
       Cycler.Control,
-      OutputType.Additional,
+      OutputType.Main,
       "ball_filter_hypotheses",
       (ball_filter_hypotheses) => {
         setBallFilterHypotheses(ball_filter_hypotheses);
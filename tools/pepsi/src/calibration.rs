@@ -0,0 +1,133 @@
+use std::{str::FromStr, time::Duration};
+
+use anyhow::{anyhow, bail, Context};
+use clap::{Args, ValueEnum};
+use communication::{Communication, CyclerOutput, SubscriberMessage};
+use serde_json::{from_value, Value};
+use tokio::time::timeout;
+
+use repository::Repository;
+use types::ExtrinsicCalibration;
+
+use crate::parsers::{NaoAddress, NaoNumber};
+
+#[derive(Args)]
+pub struct Arguments {
+    /// The NAO to calibrate e.g. 20w or 10.1.24.22 (has to stand at the configured calibration pose)
+    #[arg(required = true)]
+    pub nao: NaoAddress,
+    /// The cameras to calibrate
+    #[arg(long, value_enum, default_values_t = [Camera::Top, Camera::Bottom])]
+    pub cameras: Vec<Camera>,
+    /// Seconds to wait for the calibration of each camera
+    #[arg(long, default_value_t = 60)]
+    pub timeout: u64,
+    /// Only print the calibrated extrinsic rotations without writing them into the head configuration
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum Camera {
+    Top,
+    Bottom,
+}
+
+impl Camera {
+    fn cycler(self) -> &'static str {
+        match self {
+            Camera::Top => "vision_top",
+            Camera::Bottom => "vision_bottom",
+        }
+    }
+}
+
+pub async fn calibration(arguments: Arguments, repository: &Repository) -> anyhow::Result<()> {
+    let hardware_ids = repository
+        .get_hardware_ids()
+        .await
+        .context("Failed to get hardware IDs")?;
+    let nao_number = NaoNumber::try_from(arguments.nao)?;
+    let head_id = &hardware_ids
+        .get(&nao_number.number)
+        .ok_or_else(|| anyhow!("No hardware IDs known for NAO {nao_number}"))?
+        .head_id;
+
+    let communication = Communication::new(Some(format!("ws://{}:1337", arguments.nao)), true);
+    for camera in arguments.cameras {
+        let calibration = calibrate_camera(
+            &communication,
+            camera.cycler(),
+            Duration::from_secs(arguments.timeout),
+        )
+        .await
+        .with_context(|| format!("Failed to calibrate {camera:?} camera"))?;
+        if calibration.number_of_used_points == 0 {
+            bail!("No line points matched any field mark in {camera:?} camera");
+        }
+        let extrinsic_rotations = calibration.extrinsic_rotations;
+        println!(
+            "{camera:?} camera: extrinsic rotations {:?} from {} line points (error {} -> {})",
+            extrinsic_rotations.as_slice(),
+            calibration.number_of_used_points,
+            calibration.initial_error,
+            calibration.error,
+        );
+        if !arguments.dry_run {
+            repository
+                .set_extrinsic_rotations(
+                    head_id,
+                    camera.cycler(),
+                    [
+                        extrinsic_rotations.x,
+                        extrinsic_rotations.y,
+                        extrinsic_rotations.z,
+                    ],
+                )
+                .await
+                .with_context(|| format!("Failed to set extrinsic rotations for {head_id}"))?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn calibrate_camera(
+    communication: &Communication,
+    cycler: &str,
+    maximum_duration: Duration,
+) -> anyhow::Result<ExtrinsicCalibration> {
+    let output = CyclerOutput::from_str(&format!("{cycler}.main.extrinsic_calibration"))?;
+    let enable_path = format!("{cycler}.extrinsic_calibrator.enable");
+    let (uuid, mut receiver) = communication.subscribe_output(output.clone()).await;
+    communication
+        .update_parameter_value(&enable_path, Value::Bool(true))
+        .await;
+
+    let result = timeout(maximum_duration, async {
+        while let Some(message) = receiver.recv().await {
+            match message {
+                SubscriberMessage::Update { value } if !value.is_null() => {
+                    let calibration: ExtrinsicCalibration =
+                        from_value(value).context("Failed to parse extrinsic calibration")?;
+                    if calibration.is_finished {
+                        return Ok(calibration);
+                    }
+                }
+                SubscriberMessage::SubscriptionFailure { info } => {
+                    bail!("Failed to subscribe to extrinsic calibration: {info}")
+                }
+                _ => {}
+            }
+        }
+        bail!("Subscription of extrinsic calibration closed unexpectedly")
+    })
+    .await;
+
+    communication
+        .update_parameter_value(&enable_path, Value::Bool(false))
+        .await;
+    communication.unsubscribe_output(output, uuid).await;
+
+    result.context("Timed out waiting for extrinsic calibration")?
+}
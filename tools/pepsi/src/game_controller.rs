@@ -0,0 +1,455 @@
+use std::{
+    convert::TryFrom,
+    net::{Ipv4Addr, SocketAddr},
+    path::PathBuf,
+    str::FromStr,
+    time::Duration,
+};
+
+use anyhow::{bail, Context};
+use clap::Args;
+use spl_network::{
+    GameControllerReturnMessage, GameControllerStateMessage, GamePhase, GameState, Half, Penalty,
+    Player, SetPlay, Team, TeamColor, TeamState, GAMECONTROLLER_DATA_PORT,
+    GAMECONTROLLER_RETURN_PORT,
+};
+use tokio::{
+    fs::read_to_string,
+    io::{stdin, AsyncBufReadExt, BufReader},
+    net::UdpSocket,
+    time::{interval, Instant},
+};
+
+const SEND_INTERVAL: Duration = Duration::from_millis(500);
+const HALF_DURATION: Duration = Duration::from_secs(600);
+const READY_DURATION: Duration = Duration::from_secs(45);
+const SET_PLAY_DURATION: Duration = Duration::from_secs(30);
+const PENALTY_DURATION: Duration = Duration::from_secs(45);
+const MESSAGE_BUDGET: u16 = 1200;
+
+#[derive(Args)]
+pub struct Arguments {
+    /// Team number of the first team, it is listed first in the packets and kicks off
+    #[arg(long, default_value_t = 24)]
+    first_team_number: u8,
+    /// Team number of the second team
+    #[arg(long, default_value_t = 5)]
+    second_team_number: u8,
+    #[arg(long, default_value_t = 5)]
+    players_per_team: u8,
+    /// Address the GameController packets are broadcasted to
+    #[arg(long, default_value_t = Ipv4Addr::BROADCAST)]
+    broadcast_address: Ipv4Addr,
+    /// File with one "<seconds since start> <command>" per line, commands are read from stdin if
+    /// not given
+    #[arg(long)]
+    script: Option<PathBuf>,
+    /// Print received return messages of the robots
+    #[arg(long)]
+    print_return_messages: bool,
+}
+
+/// Broadcasts GameController packets of a scripted or interactively controlled game
+///
+/// Commands: initial, ready, set, playing, finished, second_half, goal <team>,
+/// kick_off <team>, set_play <team> <set play>, penalty_shootout <team>,
+/// penalize <team> <player> [<penalty>], unpenalize <team> <player>, quit.
+/// Teams are 1 or 2, players start from 1.
+pub async fn game_controller(arguments: Arguments) -> anyhow::Result<()> {
+    let mut game_controller = GameController::new(
+        arguments.first_team_number,
+        arguments.second_team_number,
+        arguments.players_per_team,
+    );
+    let mut script = match &arguments.script {
+        Some(path) => parse_script(
+            &read_to_string(path)
+                .await
+                .with_context(|| format!("Failed to read script {path:?}"))?,
+        )?,
+        None => Vec::new(),
+    };
+    script.reverse();
+    let is_interactive = arguments.script.is_none();
+
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, GAMECONTROLLER_RETURN_PORT))
+        .await
+        .context("Failed to bind GameController return message socket")?;
+    socket
+        .set_broadcast(true)
+        .context("Failed to enable broadcast support")?;
+    let destination = SocketAddr::new(arguments.broadcast_address.into(), GAMECONTROLLER_DATA_PORT);
+
+    let mut commands = BufReader::new(stdin()).lines();
+    let mut send_interval = interval(SEND_INTERVAL);
+    let mut return_message_buffer = [0; 1024];
+    let start = Instant::now();
+    let mut last_tick = start;
+    let mut packet_number: u8 = 0;
+    loop {
+        tokio::select! {
+            _ = send_interval.tick() => {
+                let now = Instant::now();
+                game_controller.advance(now - last_tick);
+                last_tick = now;
+                while let Some((time, command)) = script.last() {
+                    if now - start < *time {
+                        break;
+                    }
+                    println!("{:>6.1}s {command:?}", time.as_secs_f32());
+                    if !game_controller.apply(command) {
+                        return Ok(());
+                    }
+                    script.pop();
+                }
+                if !is_interactive && script.is_empty() && game_controller.is_finished() {
+                    return Ok(());
+                }
+                socket
+                    .send_to(&game_controller.message.to_bytes(packet_number), destination)
+                    .await
+                    .context("Failed to send GameController packet")?;
+                packet_number = packet_number.wrapping_add(1);
+            }
+            line = commands.next_line(), if is_interactive => {
+                let line = match line.context("Failed to read command")? {
+                    Some(line) => line,
+                    None => return Ok(()),
+                };
+                if line.trim().is_empty() {
+                    continue;
+                }
+                match line.parse() {
+                    Ok(command) => {
+                        if !game_controller.apply(&command) {
+                            return Ok(());
+                        }
+                        game_controller.print_state();
+                    }
+                    Err(error) => eprintln!("{error:#}"),
+                }
+            }
+            received = socket.recv_from(&mut return_message_buffer) => {
+                let (number_of_bytes, sender) =
+                    received.context("Failed to receive return message")?;
+                if !arguments.print_return_messages {
+                    continue;
+                }
+                match GameControllerReturnMessage::try_from(&return_message_buffer[..number_of_bytes]) {
+                    Ok(message) => println!(
+                        "{sender}: team {} {:?}{} at {:?}",
+                        message.team_number,
+                        message.player_number,
+                        if message.fallen { " (fallen)" } else { "" },
+                        message.robot_to_field.translation.vector.as_slice(),
+                    ),
+                    Err(error) => eprintln!("Discarding message from {sender}: {error:#}"),
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Command {
+    State(GameState),
+    SecondHalf,
+    Goal(TeamIndex),
+    KickOff(TeamIndex),
+    SetPlay(TeamIndex, SetPlay),
+    PenaltyShootout(TeamIndex),
+    Penalize(TeamIndex, usize, Penalty),
+    Unpenalize(TeamIndex, usize),
+    Quit,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum TeamIndex {
+    First,
+    Second,
+}
+
+impl FromStr for TeamIndex {
+    type Err = anyhow::Error;
+
+    fn from_str(team: &str) -> anyhow::Result<Self> {
+        match team {
+            "1" => Ok(TeamIndex::First),
+            "2" => Ok(TeamIndex::Second),
+            _ => bail!("Unexpected team {team:?}, expected 1 or 2"),
+        }
+    }
+}
+
+impl FromStr for Command {
+    type Err = anyhow::Error;
+
+    fn from_str(line: &str) -> anyhow::Result<Self> {
+        let words: Vec<_> = line.split_whitespace().collect();
+        let team = |index: usize| -> anyhow::Result<TeamIndex> {
+            words
+                .get(index)
+                .context("Missing team")?
+                .parse::<TeamIndex>()
+        };
+        let player = |index: usize| -> anyhow::Result<usize> {
+            match words.get(index).context("Missing player")?.parse()? {
+                0 => bail!("Player numbers start from 1"),
+                player => Ok(player),
+            }
+        };
+        let command = match words.first().copied() {
+            Some("initial") => Command::State(GameState::Initial),
+            Some("ready") => Command::State(GameState::Ready),
+            Some("set") => Command::State(GameState::Set),
+            Some("playing") => Command::State(GameState::Playing),
+            Some("finished") => Command::State(GameState::Finished),
+            Some("second_half") => Command::SecondHalf,
+            Some("goal") => Command::Goal(team(1)?),
+            Some("kick_off") => Command::KickOff(team(1)?),
+            Some("set_play") => Command::SetPlay(
+                team(1)?,
+                parse_set_play(words.get(2).context("Missing set play")?)?,
+            ),
+            Some("penalty_shootout") => Command::PenaltyShootout(team(1)?),
+            Some("penalize") => Command::Penalize(
+                team(1)?,
+                player(2)?,
+                parse_penalty(words.get(3).copied().unwrap_or("pushing"))?,
+            ),
+            Some("unpenalize") => Command::Unpenalize(team(1)?, player(2)?),
+            Some("quit") => Command::Quit,
+            _ => bail!("Unexpected command {line:?}"),
+        };
+        Ok(command)
+    }
+}
+
+fn parse_set_play(set_play: &str) -> anyhow::Result<SetPlay> {
+    match set_play {
+        "goal_kick" => Ok(SetPlay::GoalKick),
+        "pushing_free_kick" => Ok(SetPlay::PushingFreeKick),
+        "corner_kick" => Ok(SetPlay::CornerKick),
+        "kick_in" => Ok(SetPlay::KickIn),
+        "penalty_kick" => Ok(SetPlay::PenaltyKick),
+        _ => bail!("Unexpected set play {set_play:?}"),
+    }
+}
+
+fn parse_penalty(penalty: &str) -> anyhow::Result<Penalty> {
+    let remaining = PENALTY_DURATION;
+    match penalty {
+        "illegal_ball_contact" => Ok(Penalty::IllegalBallContact { remaining }),
+        "pushing" => Ok(Penalty::PlayerPushing { remaining }),
+        "illegal_motion_in_set" => Ok(Penalty::IllegalMotionInSet { remaining }),
+        "inactive" => Ok(Penalty::InactivePlayer { remaining }),
+        "illegal_position" => Ok(Penalty::IllegalPosition { remaining }),
+        "leaving_the_field" => Ok(Penalty::LeavingTheField { remaining }),
+        "pickup" => Ok(Penalty::RequestForPickup { remaining }),
+        "local_game_stuck" => Ok(Penalty::LocalGameStuck { remaining }),
+        "illegal_position_in_set" => Ok(Penalty::IllegalPositionInSet { remaining }),
+        "substitute" => Ok(Penalty::Substitute {
+            remaining: Duration::ZERO,
+        }),
+        "manual" => Ok(Penalty::Manual {
+            remaining: Duration::ZERO,
+        }),
+        _ => bail!("Unexpected penalty {penalty:?}"),
+    }
+}
+
+fn parse_script(script: &str) -> anyhow::Result<Vec<(Duration, Command)>> {
+    let mut commands = script
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index, line.split('#').next().unwrap().trim()))
+        .filter(|(_, line)| !line.is_empty())
+        .map(|(index, line)| {
+            let (time, command) = line
+                .split_once(char::is_whitespace)
+                .with_context(|| format!("Line {}: expected \"<seconds> <command>\"", index + 1))?;
+            let time = time
+                .parse()
+                .with_context(|| format!("Line {}: failed to parse time", index + 1))?;
+            let command = command
+                .parse()
+                .with_context(|| format!("Line {}: failed to parse command", index + 1))?;
+            Ok((Duration::from_secs_f32(time), command))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    commands.sort_by_key(|(time, _)| *time);
+    Ok(commands)
+}
+
+/// Game state as the GameController would track it, with the first team as HULKs team
+struct GameController {
+    message: GameControllerStateMessage,
+}
+
+impl GameController {
+    fn new(first_team_number: u8, second_team_number: u8, players_per_team: u8) -> Self {
+        let team = |team_number, color| TeamState {
+            team_number,
+            color,
+            score: 0,
+            penalty_shoot_index: 0,
+            penalty_shoots: Vec::new(),
+            remaining_amount_of_messages: MESSAGE_BUDGET,
+            players: vec![Player { penalty: None }; players_per_team as usize],
+        };
+        Self {
+            message: GameControllerStateMessage {
+                game_phase: GamePhase::Normal,
+                game_state: GameState::Initial,
+                set_play: None,
+                half: Half::First,
+                remaining_time_in_half: HALF_DURATION,
+                secondary_time: Duration::ZERO,
+                hulks_team: team(first_team_number, TeamColor::Blue),
+                opponent_team: team(second_team_number, TeamColor::Red),
+                kicking_team: Team::Hulks,
+            },
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        self.message.game_state == GameState::Finished
+    }
+
+    fn team_mut(&mut self, team: TeamIndex) -> &mut TeamState {
+        match team {
+            TeamIndex::First => &mut self.message.hulks_team,
+            TeamIndex::Second => &mut self.message.opponent_team,
+        }
+    }
+
+    /// Returns false if the game controller should quit
+    fn apply(&mut self, command: &Command) -> bool {
+        match *command {
+            Command::State(game_state) => self.set_game_state(game_state),
+            Command::SecondHalf => {
+                self.message.half = Half::Second;
+                self.message.remaining_time_in_half = HALF_DURATION;
+                self.message.kicking_team = Team::Opponent;
+                self.set_game_state(GameState::Initial);
+            }
+            Command::Goal(team) => {
+                self.team_mut(team).score += 1;
+                self.message.kicking_team = team_of(other_team(team));
+                self.set_game_state(GameState::Ready);
+            }
+            Command::KickOff(team) => self.message.kicking_team = team_of(team),
+            Command::SetPlay(team, set_play) => {
+                if self.message.game_state == GameState::Playing {
+                    self.message.set_play = Some(set_play);
+                    self.message.kicking_team = team_of(team);
+                    self.message.secondary_time = SET_PLAY_DURATION;
+                }
+            }
+            Command::PenaltyShootout(team) => {
+                self.message.game_phase = GamePhase::PenaltyShootout {
+                    kicking_team: team_of(team),
+                };
+                self.message.kicking_team = team_of(team);
+                self.set_game_state(GameState::Set);
+            }
+            Command::Penalize(team, player, penalty) => {
+                if let Some(player) = self.team_mut(team).players.get_mut(player - 1) {
+                    player.penalty = Some(penalty);
+                }
+            }
+            Command::Unpenalize(team, player) => {
+                if let Some(player) = self.team_mut(team).players.get_mut(player - 1) {
+                    player.penalty = None;
+                }
+            }
+            Command::Quit => return false,
+        }
+        true
+    }
+
+    fn set_game_state(&mut self, game_state: GameState) {
+        self.message.game_state = game_state;
+        self.message.set_play = None;
+        self.message.secondary_time = match game_state {
+            GameState::Ready => READY_DURATION,
+            _ => Duration::ZERO,
+        };
+    }
+
+    /// Runs the clocks and the automatic transitions of the GameController
+    fn advance(&mut self, elapsed: Duration) {
+        let message = &mut self.message;
+        message.secondary_time = message.secondary_time.saturating_sub(elapsed);
+        if message.game_state == GameState::Playing {
+            message.remaining_time_in_half = message.remaining_time_in_half.saturating_sub(elapsed);
+        }
+        for player in message
+            .hulks_team
+            .players
+            .iter_mut()
+            .chain(message.opponent_team.players.iter_mut())
+        {
+            if let Some(penalty) = &mut player.penalty {
+                let remaining = match penalty {
+                    Penalty::IllegalBallContact { remaining }
+                    | Penalty::PlayerPushing { remaining }
+                    | Penalty::IllegalMotionInSet { remaining }
+                    | Penalty::InactivePlayer { remaining }
+                    | Penalty::IllegalPosition { remaining }
+                    | Penalty::LeavingTheField { remaining }
+                    | Penalty::RequestForPickup { remaining }
+                    | Penalty::LocalGameStuck { remaining }
+                    | Penalty::IllegalPositionInSet { remaining }
+                    | Penalty::Substitute { remaining }
+                    | Penalty::Manual { remaining } => remaining,
+                };
+                *remaining = remaining.saturating_sub(elapsed);
+            }
+        }
+
+        match message.game_state {
+            GameState::Ready if message.secondary_time.is_zero() => {
+                self.set_game_state(GameState::Set);
+            }
+            GameState::Playing if message.remaining_time_in_half.is_zero() => {
+                self.set_game_state(GameState::Finished);
+            }
+            GameState::Playing
+                if message.set_play.is_some() && message.secondary_time.is_zero() =>
+            {
+                message.set_play = None;
+            }
+            _ => {}
+        }
+    }
+
+    fn print_state(&self) {
+        let message = &self.message;
+        println!(
+            "{:?} {:?} {:?}, set play {:?}, kicking {:?}, score {}:{}, {}s remaining",
+            message.half,
+            message.game_phase,
+            message.game_state,
+            message.set_play,
+            message.kicking_team,
+            message.hulks_team.score,
+            message.opponent_team.score,
+            message.remaining_time_in_half.as_secs(),
+        );
+    }
+}
+
+fn team_of(team: TeamIndex) -> Team {
+    match team {
+        TeamIndex::First => Team::Hulks,
+        TeamIndex::Second => Team::Opponent,
+    }
+}
+
+fn other_team(team: TeamIndex) -> TeamIndex {
+    match team {
+        TeamIndex::First => TeamIndex::Second,
+        TeamIndex::Second => TeamIndex::First,
+    }
+}
@@ -6,8 +6,10 @@ use clap_complete::generate;
 use tokio::fs::read_dir;
 
 use aliveness::{aliveness, Arguments as AlivenessArguments};
+use calibration::{calibration, Arguments as CalibrationArguments};
 use cargo::{cargo, Arguments as CargoArguments, Command as CargoCommand};
 use communication::{communication, Arguments as CommunicationArguments};
+use game_controller::{game_controller, Arguments as GameControllerArguments};
 use hulk::{hulk, Arguments as HulkArguments};
 use location::{location, Arguments as LocationArguments};
 use logs::{logs, Arguments as LogsArguments};
@@ -23,8 +25,10 @@ use upload::{upload, Arguments as UploadArguments};
 use wireless::{wireless, Arguments as WirelessArguments};
 
 mod aliveness;
+mod calibration;
 mod cargo;
 mod communication;
+mod game_controller;
 mod hulk;
 mod location;
 mod logs;
@@ -63,6 +67,9 @@ async fn main() -> anyhow::Result<()> {
         Command::Build(arguments) => cargo(arguments, &repository, CargoCommand::Build)
             .await
             .context("Failed to execute build command")?,
+        Command::Calibration(arguments) => calibration(arguments, &repository)
+            .await
+            .context("Failed to execute calibration command")?,
         Command::Check(arguments) => cargo(arguments, &repository, CargoCommand::Check)
             .await
             .context("Failed to execute check command")?,
@@ -78,6 +85,9 @@ async fn main() -> anyhow::Result<()> {
             "pepsi",
             &mut std::io::stdout(),
         ),
+        Command::Gamecontroller(arguments) => game_controller(arguments)
+            .await
+            .context("Failed to execute gamecontroller command")?,
         Command::Hulk(arguments) => hulk(arguments, &repository)
             .await
             .context("Failed to execute hulk command")?,
@@ -139,6 +149,9 @@ enum Command {
     Aliveness(AlivenessArguments),
     /// Builds the code for a target
     Build(CargoArguments),
+    /// Calibrate the extrinsic camera rotations of a NAO from field lines and store them in its
+    /// head configuration
+    Calibration(CalibrationArguments),
     /// Checks the code with cargo check
     Check(CargoArguments),
     /// Checks the code with cargo clippy
@@ -151,6 +164,8 @@ enum Command {
         #[clap(name = "shell")]
         shell: clap_complete::shells::Shell,
     },
+    /// Run a local GameController stand-in broadcasting a scripted or interactively controlled game
+    Gamecontroller(GameControllerArguments),
     /// Control the HULK service
     Hulk(HulkArguments),
     /// Control the configured location
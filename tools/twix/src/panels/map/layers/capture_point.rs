@@ -0,0 +1,80 @@
+use std::{str::FromStr, sync::Arc};
+
+use anyhow::Result;
+use communication::CyclerOutput;
+use eframe::epaint::{Color32, Stroke};
+use nalgebra::{Isometry2, Point2};
+use types::FieldDimensions;
+
+use crate::{
+    nao::Nao, panels::map::layer::Layer, twix_painter::TwixPainter, value_buffer::ValueBuffer,
+};
+
+pub struct CapturePoint {
+    robot_to_field: ValueBuffer,
+    center_of_mass: ValueBuffer,
+    zero_moment_point: ValueBuffer,
+    capture_point: ValueBuffer,
+    predicted_capture_point: ValueBuffer,
+}
+
+impl Layer for CapturePoint {
+    const NAME: &'static str = "Capture Point";
+
+    fn new(nao: Arc<Nao>) -> Self {
+        let robot_to_field =
+            nao.subscribe_output(CyclerOutput::from_str("control.main.robot_to_field").unwrap());
+        let center_of_mass = nao.subscribe_output(
+            CyclerOutput::from_str("control.additional.walk_stabilization.center_of_mass").unwrap(),
+        );
+        let zero_moment_point = nao.subscribe_output(
+            CyclerOutput::from_str("control.additional.walk_stabilization.zero_moment_point")
+                .unwrap(),
+        );
+        let capture_point = nao.subscribe_output(
+            CyclerOutput::from_str("control.additional.walk_stabilization.capture_point").unwrap(),
+        );
+        let predicted_capture_point = nao.subscribe_output(
+            CyclerOutput::from_str("control.additional.walk_stabilization.predicted_capture_point")
+                .unwrap(),
+        );
+        Self {
+            robot_to_field,
+            center_of_mass,
+            zero_moment_point,
+            capture_point,
+            predicted_capture_point,
+        }
+    }
+
+    fn paint(&self, painter: &TwixPainter, _field_dimensions: &FieldDimensions) -> Result<()> {
+        let robot_to_field: Isometry2<f32> = self.robot_to_field.require_latest()?;
+        let center_of_mass: Point2<f32> = self.center_of_mass.require_latest()?;
+        let zero_moment_point: Option<Point2<f32>> = self.zero_moment_point.require_latest()?;
+        let capture_point: Point2<f32> = self.capture_point.require_latest()?;
+        let predicted_capture_point: Point2<f32> = self.predicted_capture_point.require_latest()?;
+
+        painter.circle_filled(robot_to_field * center_of_mass, 0.01, Color32::BLACK);
+        if let Some(zero_moment_point) = zero_moment_point {
+            painter.circle_filled(robot_to_field * zero_moment_point, 0.01, Color32::GREEN);
+        }
+        painter.line_segment(
+            robot_to_field * capture_point,
+            robot_to_field * predicted_capture_point,
+            Stroke {
+                width: 0.005,
+                color: Color32::RED,
+            },
+        );
+        painter.circle_filled(robot_to_field * capture_point, 0.01, Color32::RED);
+        painter.circle_stroke(
+            robot_to_field * predicted_capture_point,
+            0.01,
+            Stroke {
+                width: 0.005,
+                color: Color32::RED,
+            },
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,65 @@
+use std::{str::FromStr, sync::Arc};
+
+use anyhow::Result;
+use communication::CyclerOutput;
+use eframe::epaint::{Color32, Stroke};
+use types::{FieldDimensions, FormationState, Role};
+
+use crate::{
+    nao::Nao, panels::map::layer::Layer, twix_painter::TwixPainter, value_buffer::ValueBuffer,
+};
+
+pub struct Formation {
+    formation: ValueBuffer,
+}
+
+impl Layer for Formation {
+    const NAME: &'static str = "Formation";
+
+    fn new(nao: Arc<Nao>) -> Self {
+        let formation =
+            nao.subscribe_output(CyclerOutput::from_str("control.additional.formation").unwrap());
+        Self { formation }
+    }
+
+    fn paint(&self, painter: &TwixPainter, _field_dimensions: &FieldDimensions) -> Result<()> {
+        let formation: FormationState = self.formation.require_latest()?;
+
+        for anchor in formation.anchors {
+            painter.circle_filled(anchor.ball_position, 0.05, Color32::from_white_alpha(80));
+        }
+        painter.circle_stroke(
+            formation.ball_position,
+            0.1,
+            Stroke {
+                width: 0.02,
+                color: Color32::WHITE,
+            },
+        );
+        for (role, position) in formation.positions.positions() {
+            let color = match role {
+                Role::DefenderLeft => Color32::LIGHT_BLUE,
+                Role::DefenderRight => Color32::BLUE,
+                _ => Color32::YELLOW,
+            };
+            painter.line_segment(
+                formation.ball_position,
+                position,
+                Stroke {
+                    width: 0.01,
+                    color: Color32::from_white_alpha(60),
+                },
+            );
+            painter.circle(
+                position,
+                0.15,
+                color,
+                Stroke {
+                    width: 0.02,
+                    color: Color32::BLACK,
+                },
+            );
+        }
+        Ok(())
+    }
+}
@@ -1,5 +1,7 @@
 mod ball_position;
+mod capture_point;
 mod field;
+mod formation;
 mod image_segments;
 mod kick_decisions;
 mod obstacles;
@@ -8,7 +10,9 @@ mod path_obstacles;
 mod robot_pose;
 
 pub use ball_position::BallPosition;
+pub use capture_point::CapturePoint;
 pub use field::Field;
+pub use formation::Formation;
 pub use image_segments::ImageSegments;
 pub use kick_decisions::KickDecisions;
 pub use obstacles::Obstacles;
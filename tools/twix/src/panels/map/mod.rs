@@ -25,6 +25,8 @@ pub struct MapPanel {
     path_obstacles: EnabledLayer<layers::PathObstacles>,
     path: EnabledLayer<layers::Path>,
     kick_decisions: EnabledLayer<layers::KickDecisions>,
+    capture_point: EnabledLayer<layers::CapturePoint>,
+    formation: EnabledLayer<layers::Formation>,
     transformation: Similarity2<f32>,
 }
 
@@ -40,6 +42,8 @@ impl Panel for MapPanel {
         let path_obstacles = EnabledLayer::new(nao.clone(), storage, false);
         let path = EnabledLayer::new(nao.clone(), storage, false);
         let kick_decisions = EnabledLayer::new(nao.clone(), storage, false);
+        let capture_point = EnabledLayer::new(nao.clone(), storage, false);
+        let formation = EnabledLayer::new(nao.clone(), storage, false);
 
         let field_dimensions = nao.subscribe_parameter("field_dimensions");
         let transformation = Similarity2::identity();
@@ -53,6 +57,8 @@ impl Panel for MapPanel {
             path_obstacles,
             path,
             kick_decisions,
+            capture_point,
+            formation,
             transformation,
         }
     }
@@ -66,6 +72,8 @@ impl Panel for MapPanel {
         self.path_obstacles.save(storage);
         self.path.save(storage);
         self.kick_decisions.save(storage);
+        self.capture_point.save(storage);
+        self.formation.save(storage);
     }
 }
 
@@ -82,6 +90,8 @@ impl Widget for &mut MapPanel {
                 self.path_obstacles.checkbox(ui);
                 self.path.checkbox(ui);
                 self.kick_decisions.checkbox(ui);
+                self.capture_point.checkbox(ui);
+                self.formation.checkbox(ui);
             });
 
         let field_dimensions: FieldDimensions = match self.field_dimensions.get_latest() {
@@ -100,6 +110,8 @@ impl Widget for &mut MapPanel {
         let _ = self.path_obstacles.paint(&painter, &field_dimensions);
         let _ = self.path.paint(&painter, &field_dimensions);
         let _ = self.kick_decisions.paint(&painter, &field_dimensions);
+        let _ = self.capture_point.paint(&painter, &field_dimensions);
+        let _ = self.formation.paint(&painter, &field_dimensions);
 
         if let Some(pointer_position) = ui.input().pointer.interact_pos() {
             let pointer_in_world_before_zoom = painter.transform_pixel_to_world(pointer_position);